@@ -0,0 +1,55 @@
+//! Compares interpreter dispatch strategies on a tight arithmetic loop.
+//!
+//! Run with `cargo bench --bench dispatch`.
+
+use std::time::{Duration, Instant};
+use wasm_engine::interpreter::{Dispatch, Interpreter, InterpreterConfig, JumpTableDispatch, MatchDispatch, Value};
+use wasm_engine::{WasmEngine, WasmModule};
+
+const ITERATIONS: i32 = 1_000_000;
+const RUNS: u32 = 5;
+
+/// `sum(n)`: adds n, n-1, ..., 1 in a loop.
+fn sum_module() -> WasmModule {
+    let bytes = [
+        0x00, 0x61, 0x73, 0x6d, // magic
+        0x01, 0x00, 0x00, 0x00, // version
+        0x01, 0x06, 0x01, 0x60, 0x01, 0x7F, 0x01, 0x7F, // type: (i32) -> i32
+        0x03, 0x02, 0x01, 0x00, // function 0 has type 0
+        0x0A, 0x1B, 0x01, 0x19, 0x01, 0x01, 0x7F, // code: one i32 local
+        0x03, 0x40, // loop
+        0x20, 0x01, 0x20, 0x00, 0x6A, 0x21, 0x01, // acc += n
+        0x20, 0x00, 0x41, 0x01, 0x6B, 0x22, 0x00, // n -= 1
+        0x0D, 0x00, 0x0B, // br_if 0, end
+        0x20, 0x01, 0x0B, // local.get acc, end
+    ];
+    WasmEngine::new().unwrap().parse_module(&bytes).unwrap()
+}
+
+/// Fastest of `RUNS` runs, and the instructions executed per run.
+fn bench(module: &WasmModule, dispatch: &'static dyn Dispatch) -> (Duration, u64) {
+    let mut best = Duration::MAX;
+    let mut instructions = 0;
+    for _ in 0..RUNS {
+        let config = InterpreterConfig { dispatch, ..Default::default() };
+        let mut interpreter = Interpreter::with_config(module.clone(), config).unwrap();
+        let started = Instant::now();
+        let result = interpreter.invoke(0, &[Value::I32(ITERATIONS)], None).unwrap();
+        best = best.min(started.elapsed());
+        instructions = interpreter.instruction_count();
+        assert_eq!(result, vec![Value::I32((1..=ITERATIONS).fold(0i32, |acc, n| acc.wrapping_add(n)))]);
+    }
+    (best, instructions)
+}
+
+fn main() {
+    let module = sum_module();
+    let (matched, instructions) = bench(&module, &MatchDispatch);
+    let (table, _) = bench(&module, &JumpTableDispatch);
+
+    let per_instruction = |elapsed: Duration| elapsed.as_nanos() as f64 / instructions as f64;
+    println!("{} instructions per run, best of {}", instructions, RUNS);
+    println!("match:      {:>10.2?} ({:.2} ns/instruction)", matched, per_instruction(matched));
+    println!("jump table: {:>10.2?} ({:.2} ns/instruction)", table, per_instruction(table));
+    println!("speedup:    {:.2}x", matched.as_secs_f64() / table.as_secs_f64());
+}
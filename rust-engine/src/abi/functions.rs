@@ -30,6 +30,40 @@ impl ValueType {
     }
 }
 
+/// A typed argument to or result from a host function.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum AbiValue {
+    I32(i32),
+    I64(i64),
+    F32(f32),
+    F64(f64),
+}
+
+impl AbiValue {
+    pub fn value_type(&self) -> ValueType {
+        match self {
+            AbiValue::I32(_) => ValueType::I32,
+            AbiValue::I64(_) => ValueType::I64,
+            AbiValue::F32(_) => ValueType::F32,
+            AbiValue::F64(_) => ValueType::F64,
+        }
+    }
+    
+    /// The value as an unsigned 32-bit pointer, length or id.
+    pub fn as_u32(&self) -> Result<u32> {
+        match self {
+            AbiValue::I32(value) => Ok(*value as u32),
+            _ => Err(WasmError::TypeMismatch),
+        }
+    }
+}
+
+impl From<u32> for AbiValue {
+    fn from(value: u32) -> Self {
+        AbiValue::I32(value as i32)
+    }
+}
+
 /// Get all available ABI functions
 pub fn get_abi_functions() -> Vec<FunctionSignature> {
     vec![
@@ -45,6 +79,12 @@ pub fn get_abi_functions() -> Vec<FunctionSignature> {
             results: vec![ValueType::I32], // sensor_value
             description: "Read a sensor value. Args: (sensor_id). Sensor IDs: 0=temp, 1=humidity, 2=pressure, 3=motion, 4=light, 5=sound".to_string(),
         },
+        FunctionSignature {
+            name: "wasm_read_sensor_ex".to_string(),
+            params: vec![ValueType::I32, ValueType::I32], // sensor_id, out_ptr
+            results: vec![ValueType::I32], // 0 or -1
+            description: "Read a sensor with metadata. Args: (sensor_id, out_ptr). Writes 14 packed bytes: value i32, timestamp u64 (0 without WallClock), unit u8, valid u8".to_string(),
+        },
         FunctionSignature {
             name: "wasm_send_alert".to_string(),
             params: vec![ValueType::I32, ValueType::I32, ValueType::I32], // level, message_ptr, message_len
@@ -54,15 +94,57 @@ pub fn get_abi_functions() -> Vec<FunctionSignature> {
         FunctionSignature {
             name: "wasm_get_time".to_string(),
             params: vec![], // no parameters
-            results: vec![ValueType::I32], // timestamp
+            results: vec![ValueType::I64], // timestamp
             description: "Get current Unix timestamp in seconds".to_string(),
         },
+        FunctionSignature {
+            name: "wasm_monotonic_ms".to_string(),
+            params: vec![], // no parameters
+            results: vec![ValueType::I32], // elapsed_ms
+            description: "Get milliseconds elapsed on a monotonic clock".to_string(),
+        },
+        FunctionSignature {
+            name: "wasm_last_error".to_string(),
+            params: vec![ValueType::I32, ValueType::I32], // buf_ptr, cap
+            results: vec![ValueType::I32], // message_len
+            description: "Copy the last failed host call's error message into buf_ptr if it fits in cap. Returns its length, or 0 if none".to_string(),
+        },
         FunctionSignature {
             name: "wasm_random".to_string(),
             params: vec![], // no parameters
             results: vec![ValueType::I32], // random_value
             description: "Get a pseudo-random 32-bit integer".to_string(),
         },
+        FunctionSignature {
+            name: "wasm_sensor_subscribe".to_string(),
+            params: vec![ValueType::I32, ValueType::I32, ValueType::I32], // sensor_id, threshold, direction
+            results: vec![ValueType::I32], // subscription_id
+            description: "Subscribe to a sensor threshold crossing. Args: (sensor_id, threshold, direction). Directions: 0=above, 1=below".to_string(),
+        },
+        FunctionSignature {
+            name: "wasm_poll_events".to_string(),
+            params: vec![ValueType::I32, ValueType::I32], // buf_ptr, cap
+            results: vec![ValueType::I32], // event_count
+            description: "Copy up to cap pending sensor events to buf_ptr as (subscription_id, sensor_id, value) u32 triples. Returns the number written".to_string(),
+        },
+        FunctionSignature {
+            name: "wasm_kv_get".to_string(),
+            params: vec![ValueType::I32, ValueType::I32, ValueType::I32, ValueType::I32], // key_ptr, key_len, val_ptr, val_cap
+            results: vec![ValueType::I32], // value_len or -1 if unset
+            description: "Read a stored value. Args: (key_ptr, key_len, val_ptr, val_cap). Copies the value if it fits in val_cap; returns its length or -1 if the key is unset".to_string(),
+        },
+        FunctionSignature {
+            name: "wasm_kv_set".to_string(),
+            params: vec![ValueType::I32, ValueType::I32, ValueType::I32, ValueType::I32], // key_ptr, key_len, val_ptr, val_len
+            results: vec![ValueType::I32], // status code
+            description: "Store a value under a key. Args: (key_ptr, key_len, val_ptr, val_len). Fails if the instance's storage quota would be exceeded".to_string(),
+        },
+        FunctionSignature {
+            name: "wasm_http_post".to_string(),
+            params: vec![ValueType::I32, ValueType::I32, ValueType::I32, ValueType::I32], // url_ptr, url_len, body_ptr, body_len
+            results: vec![ValueType::I32], // status_code
+            description: "POST body to a URL allowed by the network policy. Returns the HTTP status code".to_string(),
+        },
         FunctionSignature {
             name: "wasm_memory_size".to_string(),
             params: vec![], // no parameters
@@ -231,10 +313,14 @@ pub fn generate_rust_bindings() -> String {
     bindings.push_str("        }\n");
     bindings.push_str("    }\n\n");
     
-    bindings.push_str("    pub fn get_time() -> i32 {\n");
+    bindings.push_str("    pub fn get_time() -> i64 {\n");
     bindings.push_str("        unsafe { wasm_get_time() }\n");
     bindings.push_str("    }\n\n");
     
+    bindings.push_str("    pub fn monotonic_ms() -> i32 {\n");
+    bindings.push_str("        unsafe { wasm_monotonic_ms() }\n");
+    bindings.push_str("    }\n\n");
+    
     bindings.push_str("    pub fn random() -> i32 {\n");
     bindings.push_str("        unsafe { wasm_random() }\n");
     bindings.push_str("    }\n");
@@ -261,6 +347,56 @@ pub fn generate_rust_bindings() -> String {
     bindings
 }
 
+/// Generate AssemblyScript/TypeScript declarations for the ABI
+pub fn generate_typescript_bindings() -> String {
+    let mut bindings = String::new();
+    bindings.push_str("// WASM-as-OS ABI Declarations for AssemblyScript/TypeScript\n\n");
+    
+    // Function declarations
+    for func in get_abi_functions() {
+        bindings.push_str(&format!("/** {} */\n", func.description));
+        
+        let return_type = match func.results.first() {
+            Some(ValueType::I32) => "i32",
+            Some(ValueType::I64) => "i64",
+            Some(ValueType::F32) => "f32",
+            Some(ValueType::F64) => "f64",
+            None => "void",
+        };
+        
+        let params: Vec<String> = func.params.iter().enumerate().map(|(i, param_type)| {
+            let type_str = match param_type {
+                ValueType::I32 => "i32",
+                ValueType::I64 => "i64",
+                ValueType::F32 => "f32",
+                ValueType::F64 => "f64",
+            };
+            format!("arg{}: {}", i, type_str)
+        }).collect();
+        
+        bindings.push_str(&format!("declare function {}({}): {};\n\n", func.name, params.join(", "), return_type));
+    }
+    
+    // Constants
+    bindings.push_str("export const enum Sensor {\n");
+    bindings.push_str("  Temperature = 0,\n");
+    bindings.push_str("  Humidity = 1,\n");
+    bindings.push_str("  Pressure = 2,\n");
+    bindings.push_str("  Motion = 3,\n");
+    bindings.push_str("  Light = 4,\n");
+    bindings.push_str("  Sound = 5,\n");
+    bindings.push_str("}\n\n");
+    
+    bindings.push_str("export const enum AlertLevel {\n");
+    bindings.push_str("  Info = 0,\n");
+    bindings.push_str("  Warning = 1,\n");
+    bindings.push_str("  Error = 2,\n");
+    bindings.push_str("  Critical = 3,\n");
+    bindings.push_str("}\n");
+    
+    bindings
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -277,6 +413,14 @@ mod tests {
         assert!(result.is_err());
     }
 
+    #[test]
+    fn test_abi_value_types() {
+        assert_eq!(AbiValue::I64(1).value_type(), ValueType::I64);
+        assert_eq!(AbiValue::from(u32::MAX), AbiValue::I32(-1));
+        assert_eq!(AbiValue::I32(-1).as_u32().unwrap(), u32::MAX);
+        assert!(AbiValue::F64(0.5).as_u32().is_err());
+    }
+
     #[test]
     fn test_function_docs() {
         let docs = get_function_docs("wasm_log");
@@ -302,4 +446,13 @@ mod tests {
         assert!(bindings.contains("pub fn wasm_log"));
         assert!(bindings.contains("pub mod safe"));
     }
+
+    #[test]
+    fn test_typescript_bindings_generation() {
+        let bindings = generate_typescript_bindings();
+        assert!(bindings.contains("declare function wasm_read_sensor(arg0: i32): i32;"));
+        assert!(bindings.contains("declare function wasm_get_time(): i64;"));
+        assert!(bindings.contains("export const enum Sensor {"));
+        assert!(bindings.contains("  Temperature = 0,"));
+    }
 }
\ No newline at end of file
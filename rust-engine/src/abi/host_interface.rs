@@ -1,10 +1,45 @@
 use crate::error::{WasmError, Result};
-use std::collections::HashMap;
-use std::time::{SystemTime, UNIX_EPOCH};
+pub use crate::rng::SeededRng;
+use crate::sandbox::faults::{Fault, FaultInjector};
+use std::cell::{Cell, OnceCell, RefCell};
+use std::collections::{HashMap, VecDeque};
+use std::fs::OpenOptions;
+use std::io::{BufRead, BufReader, BufWriter, Write};
+use std::net::{TcpStream, ToSocketAddrs};
+use std::sync::mpsc::{self, SyncSender, TrySendError};
+use std::thread;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+/// Default bytes of keys plus values an instance may keep in its `KvStore`.
+pub const DEFAULT_KV_QUOTA: usize = 64 * 1024;
+
+/// Threshold subscriptions a guest may register.
+pub const MAX_SUBSCRIPTIONS: usize = 64;
+
+/// Undelivered sensor events kept; the oldest are dropped beyond this.
+pub const MAX_PENDING_EVENTS: usize = 1024;
 
 #[derive(Debug)]
 pub struct HostInterface {
     sensor_data: HashMap<u32, u32>,
+    sensor_source: Option<Box<dyn SensorSource>>,
+    sensor_rate_limits: HashMap<u32, SensorRateLimit>,
+    sensor_throttles: RefCell<HashMap<u32, SensorThrottle>>,
+    sensor_series: RefCell<HashMap<u32, SensorSeries>>,
+    rng: Option<SeededRng>,
+    faults: Option<FaultInjector>,
+    /// Seconds added to the clock by injected `ClockJump` faults.
+    clock_offset: Cell<i64>,
+    subscriptions: Vec<SensorSubscription>,
+    pending_events: VecDeque<SensorEvent>,
+    /// Events dropped because `pending_events` was full.
+    dropped_events: u64,
+    kv_store: Box<dyn KvStore>,
+    kv_quota: usize,
+    /// Unset by default, so no request leaves the host until one is configured.
+    http_client: Option<Box<dyn HttpClient>>,
+    /// Origin for `monotonic_ms`.
+    started: Instant,
     alert_handlers: Vec<Box<dyn AlertHandler>>,
     log_handlers: Vec<Box<dyn LogHandler>>,
     initialized: bool,
@@ -18,6 +53,97 @@ pub trait LogHandler: std::fmt::Debug {
     fn handle_log(&self, message: &str);
 }
 
+/// Backing source for sensor readings, e.g. real hardware. Errors are
+/// treated as transient by the ABI's retry policy.
+pub trait SensorSource: std::fmt::Debug {
+    fn read_sensor(&self, sensor_id: u32) -> Result<u32>;
+}
+
+/// Transport behind `wasm_http_post`; returns the response status code.
+pub trait HttpClient: std::fmt::Debug {
+    fn post(&self, url: &str, body: &[u8]) -> Result<u16>;
+}
+
+/// Persistent key-value storage behind `wasm_kv_get`/`wasm_kv_set`.
+pub trait KvStore: std::fmt::Debug {
+    fn get(&self, key: &[u8]) -> Result<Option<Vec<u8>>>;
+    fn set(&mut self, key: &[u8], value: &[u8]) -> Result<()>;
+    /// Total bytes of keys plus values held, for quota checks.
+    fn size_bytes(&self) -> usize;
+}
+
+/// Default store; contents are lost when the instance is dropped.
+#[derive(Debug, Default)]
+pub struct InMemoryKvStore {
+    entries: HashMap<Vec<u8>, Vec<u8>>,
+}
+
+impl KvStore for InMemoryKvStore {
+    fn get(&self, key: &[u8]) -> Result<Option<Vec<u8>>> {
+        Ok(self.entries.get(key).cloned())
+    }
+
+    fn set(&mut self, key: &[u8], value: &[u8]) -> Result<()> {
+        self.entries.insert(key.to_vec(), value.to_vec());
+        Ok(())
+    }
+
+    fn size_bytes(&self) -> usize {
+        self.entries.iter().map(|(key, value)| key.len() + value.len()).sum()
+    }
+}
+
+/// What a rate-limited sensor read returns once the limit is hit.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ThrottleBehavior {
+    /// The last value actually read from the sensor.
+    CachedValue,
+    Error,
+}
+
+#[derive(Debug, Clone)]
+pub struct SensorRateLimit {
+    pub max_reads_per_sec: u32,
+    pub on_throttle: ThrottleBehavior,
+}
+
+/// Reads of one sensor in the current one-second window.
+#[derive(Debug)]
+struct SensorThrottle {
+    window_start: Instant,
+    reads: u32,
+    throttled: u32,
+    last_value: Option<u32>,
+}
+
+/// Scripted readings for one sensor and the index of the next read.
+#[derive(Debug)]
+struct SensorSeries {
+    values: Vec<u32>,
+    next: usize,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ThresholdDirection {
+    Above,
+    Below,
+}
+
+#[derive(Debug, Clone)]
+pub struct SensorSubscription {
+    pub sensor_id: u32,
+    pub threshold: u32,
+    pub direction: ThresholdDirection,
+}
+
+/// A threshold crossing waiting to be delivered to the guest.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SensorEvent {
+    pub subscription_id: u32,
+    pub sensor_id: u32,
+    pub value: u32,
+}
+
 #[derive(Debug)]
 struct DefaultAlertHandler;
 
@@ -56,6 +182,20 @@ impl HostInterface {
     pub fn new() -> Self {
         let mut interface = Self {
             sensor_data: HashMap::new(),
+            sensor_source: None,
+            sensor_rate_limits: HashMap::new(),
+            sensor_throttles: RefCell::new(HashMap::new()),
+            sensor_series: RefCell::new(HashMap::new()),
+            rng: None,
+            faults: None,
+            clock_offset: Cell::new(0),
+            subscriptions: Vec::new(),
+            pending_events: VecDeque::new(),
+            dropped_events: 0,
+            kv_store: Box::new(InMemoryKvStore::default()),
+            kv_quota: DEFAULT_KV_QUOTA,
+            http_client: None,
+            started: Instant::now(),
             alert_handlers: Vec::new(),
             log_handlers: Vec::new(),
             initialized: false,
@@ -65,6 +205,13 @@ impl HostInterface {
         interface
     }
     
+    /// An interface whose `random` replays the same sequence for the same seed.
+    pub fn with_seed(seed: u64) -> Self {
+        let mut interface = Self::new();
+        interface.set_rng(SeededRng::new(seed));
+        interface
+    }
+    
     fn initialize(&mut self) {
         // Initialize sensor data with default values
         self.sensor_data.insert(0, 25);    // Temperature: 25°C
@@ -92,6 +239,77 @@ impl HostInterface {
     }
     
     pub fn read_sensor(&self, sensor_id: u32) -> Result<u32> {
+        if let Some(result) = self.injected_fault("wasm_read_sensor") {
+            return result;
+        }
+        
+        let limit = match self.sensor_rate_limits.get(&sensor_id) {
+            Some(limit) => limit,
+            None => return self.read_sensor_uncached(sensor_id),
+        };
+        
+        let mut throttles = self.sensor_throttles.borrow_mut();
+        let throttle = throttles.entry(sensor_id).or_insert_with(|| SensorThrottle {
+            window_start: Instant::now(),
+            reads: 0,
+            throttled: 0,
+            last_value: None,
+        });
+        if throttle.window_start.elapsed() >= Duration::from_secs(1) {
+            if throttle.throttled > 0 {
+                log::warn!("Throttled {} reads of sensor {}", throttle.throttled, sensor_id);
+            }
+            throttle.window_start = Instant::now();
+            throttle.reads = 0;
+            throttle.throttled = 0;
+        }
+        
+        if throttle.reads >= limit.max_reads_per_sec {
+            if throttle.throttled == 0 {
+                log::warn!("Sensor {} exceeded {} reads/sec, throttling", sensor_id, limit.max_reads_per_sec);
+            }
+            throttle.throttled += 1;
+            return match (limit.on_throttle, throttle.last_value) {
+                (ThrottleBehavior::CachedValue, Some(value)) => Ok(value),
+                _ => Err(WasmError::Runtime(format!("Sensor {} read rate exceeded", sensor_id))),
+            };
+        }
+        
+        throttle.reads += 1;
+        let value = self.read_sensor_uncached(sensor_id)?;
+        throttle.last_value = Some(value);
+        Ok(value)
+    }
+    
+    /// Limit reads of `sensor_id`; reads over the limit never reach the sensor.
+    pub fn set_sensor_rate_limit(&mut self, sensor_id: u32, limit: SensorRateLimit) {
+        self.sensor_rate_limits.insert(sensor_id, limit);
+    }
+    
+    /// Replay `values` on successive reads of `sensor_id`, holding the last
+    /// one once the series runs out. An empty series restores the default.
+    pub fn set_sensor_series(&mut self, sensor_id: u32, values: Vec<u32>) {
+        let mut series = self.sensor_series.borrow_mut();
+        if values.is_empty() {
+            series.remove(&sensor_id);
+        } else {
+            series.insert(sensor_id, SensorSeries { values, next: 0 });
+        }
+    }
+    
+    fn read_sensor_uncached(&self, sensor_id: u32) -> Result<u32> {
+        if let Some(series) = self.sensor_series.borrow_mut().get_mut(&sensor_id) {
+            let value = series.values[series.next];
+            if series.next + 1 < series.values.len() {
+                series.next += 1;
+            }
+            return Ok(value);
+        }
+        
+        if let Some(source) = &self.sensor_source {
+            return source.read_sensor(sensor_id);
+        }
+        
         match self.sensor_data.get(&sensor_id) {
             Some(&value) => {
                 // Simulate some variation in sensor readings
@@ -103,25 +321,63 @@ impl HostInterface {
     }
     
     pub fn send_alert(&self, level: u32, message: &str) -> Result<()> {
+        self.send_alert_with(level, |handler| handler.handle_alert(level, message))
+    }
+    
+    /// Like `send_alert`, but each handler is invoked through `deliver`, so
+    /// a caller can retry just the handler that failed.
+    pub fn send_alert_with(&self, level: u32, mut deliver: impl FnMut(&dyn AlertHandler) -> Result<()>) -> Result<()> {
         if level > 3 {
             return Err(WasmError::Runtime("Invalid alert level".to_string()));
         }
+        if let Some(result) = self.injected_fault("wasm_send_alert") {
+            return result.map(|_| ());
+        }
         
+        // Every handler sees the alert even if an earlier one fails
+        let mut first_error = None;
         for handler in &self.alert_handlers {
-            handler.handle_alert(level, message)?;
+            if let Err(e) = deliver(handler.as_ref()) {
+                log::warn!("Alert handler {:?} failed: {}", handler, e);
+                first_error.get_or_insert(e);
+            }
         }
         
-        Ok(())
+        match first_error {
+            Some(e) => Err(e),
+            None => Ok(()),
+        }
     }
     
-    pub fn get_time(&self) -> Result<u32> {
+    pub fn get_time(&self) -> Result<i64> {
+        if let Some(result) = self.injected_fault("wasm_get_time") {
+            return result.map(i64::from);
+        }
+        
         SystemTime::now()
             .duration_since(UNIX_EPOCH)
-            .map(|d| d.as_secs() as u32)
+            .map(|d| (d.as_secs() as i64).saturating_add(self.clock_offset.get()))
             .map_err(|_| WasmError::Runtime("Time error".to_string()))
     }
     
+    /// Milliseconds since the interface was created; unaffected by clock jumps.
+    pub fn monotonic_ms(&self) -> Result<u32> {
+        if let Some(result) = self.injected_fault("wasm_monotonic_ms") {
+            return result;
+        }
+        
+        Ok(self.started.elapsed().as_millis() as u32)
+    }
+    
     pub fn random(&self) -> Result<u32> {
+        if let Some(result) = self.injected_fault("wasm_random") {
+            return result;
+        }
+        
+        if let Some(rng) = &self.rng {
+            return Ok(rng.next_u32());
+        }
+        
         // Simple pseudo-random number generator
         use std::collections::hash_map::DefaultHasher;
         use std::hash::{Hash, Hasher};
@@ -132,7 +388,119 @@ impl HostInterface {
     }
     
     pub fn update_sensor(&mut self, sensor_id: u32, value: u32) {
-        self.sensor_data.insert(sensor_id, value);
+        let previous = self.sensor_data.insert(sensor_id, value);
+        
+        for (id, sub) in self.subscriptions.iter().enumerate() {
+            if sub.sensor_id != sensor_id {
+                continue;
+            }
+            
+            let crossed = match sub.direction {
+                ThresholdDirection::Above => value > sub.threshold && previous.is_none_or(|p| p <= sub.threshold),
+                ThresholdDirection::Below => value < sub.threshold && previous.is_none_or(|p| p >= sub.threshold),
+            };
+            if crossed {
+                if self.pending_events.len() >= MAX_PENDING_EVENTS {
+                    self.pending_events.pop_front();
+                    self.dropped_events += 1;
+                }
+                self.pending_events.push_back(SensorEvent {
+                    subscription_id: id as u32,
+                    sensor_id,
+                    value,
+                });
+            }
+        }
+    }
+    
+    /// Register a threshold subscription and return its id.
+    pub fn subscribe(&mut self, sensor_id: u32, threshold: u32, direction: ThresholdDirection) -> Result<u32> {
+        if self.subscriptions.len() >= MAX_SUBSCRIPTIONS {
+            return Err(WasmError::Runtime(format!(
+                "Subscription limit of {} reached", MAX_SUBSCRIPTIONS)));
+        }
+        self.subscriptions.push(SensorSubscription { sensor_id, threshold, direction });
+        Ok((self.subscriptions.len() - 1) as u32)
+    }
+    
+    /// Remove and return up to `max` pending events, oldest first.
+    pub fn take_events(&mut self, max: usize) -> Vec<SensorEvent> {
+        let count = max.min(self.pending_events.len());
+        self.pending_events.drain(..count).collect()
+    }
+    
+    pub fn pending_event_count(&self) -> usize {
+        self.pending_events.len()
+    }
+    
+    /// Events discarded because the guest did not poll often enough.
+    pub fn dropped_event_count(&self) -> u64 {
+        self.dropped_events
+    }
+    
+    pub fn kv_get(&self, key: &[u8]) -> Result<Option<Vec<u8>>> {
+        self.kv_store.get(key)
+    }
+    
+    /// Store `value` under `key`, rejecting writes that would take the store
+    /// past the quota.
+    pub fn kv_set(&mut self, key: &[u8], value: &[u8]) -> Result<()> {
+        let replaced = self.kv_store.get(key)?.map_or(0, |old| key.len() + old.len());
+        let projected = self.kv_store.size_bytes() - replaced + key.len() + value.len();
+        if projected > self.kv_quota {
+            return Err(WasmError::Runtime(format!(
+                "Storage quota exceeded: {} bytes, limit is {}", projected, self.kv_quota)));
+        }
+        self.kv_store.set(key, value)
+    }
+    
+    pub fn http_post(&self, url: &str, body: &[u8]) -> Result<u16> {
+        match &self.http_client {
+            Some(client) => client.post(url, body),
+            None => Err(WasmError::Runtime("No HTTP client configured".to_string())),
+        }
+    }
+    
+    pub fn set_http_client(&mut self, client: Box<dyn HttpClient>) {
+        self.http_client = Some(client);
+    }
+    
+    pub fn set_kv_store(&mut self, store: Box<dyn KvStore>) {
+        self.kv_store = store;
+    }
+    
+    pub fn set_kv_quota(&mut self, bytes: usize) {
+        self.kv_quota = bytes;
+    }
+    
+    pub fn set_rng(&mut self, rng: SeededRng) {
+        self.rng = Some(rng);
+    }
+    
+    /// Seed of the installed generator, for recording in execution traces.
+    pub fn current_seed(&self) -> Option<u64> {
+        self.rng.as_ref().map(|rng| rng.seed())
+    }
+    
+    pub fn set_fault_injector(&mut self, injector: FaultInjector) {
+        self.faults = Some(injector);
+    }
+    
+    /// Outcome of a fault scripted for this call of `name`. Clock jumps are
+    /// applied here and let the call proceed.
+    fn injected_fault(&self, name: &str) -> Option<Result<u32>> {
+        match self.faults.as_ref()?.on_call(name)? {
+            Fault::Fail(message) => Some(Err(WasmError::Runtime(message))),
+            Fault::Return(value) => Some(Ok(value)),
+            Fault::ClockJump(seconds) => {
+                self.clock_offset.set(self.clock_offset.get() + seconds);
+                None
+            }
+        }
+    }
+    
+    pub fn set_sensor_source(&mut self, source: Box<dyn SensorSource>) {
+        self.sensor_source = Some(source);
     }
     
     pub fn add_alert_handler(&mut self, handler: Box<dyn AlertHandler>) {
@@ -204,35 +572,126 @@ impl AlertHandler for FileAlertHandler {
             .map_err(|_| WasmError::Runtime("Time error".to_string()))?
             .as_secs();
         
-        let log_entry = format!("[{}] {} - {}\n", timestamp, level_str, message);
+        let log_entry = format!("[{}] {} - {}\n", timestamp, level_str, escape_control(message));
         
-        // In a real implementation, this would write to a file
-        log::info!("Would write to {}: {}", self.file_path, log_entry.trim());
+        let file = OpenOptions::new().create(true).append(true).open(&self.file_path)?;
+        let mut writer = BufWriter::new(file);
+        writer.write_all(log_entry.as_bytes())?;
+        writer.flush()?;
         
         Ok(())
     }
 }
 
+/// Escape control characters so a guest message stays on one log line.
+fn escape_control(message: &str) -> String {
+    let mut escaped = String::with_capacity(message.len());
+    for c in message.chars() {
+        if c.is_control() {
+            escaped.extend(c.escape_default());
+        } else {
+            escaped.push(c);
+        }
+    }
+    escaped
+}
+
+/// Connect, write and read timeout for `NetworkAlertHandler`.
+pub const DEFAULT_ALERT_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Alerts a `NetworkAlertHandler` holds while its sender is busy; more are rejected.
+pub const ALERT_QUEUE_CAPACITY: usize = 64;
+
 // Network alert handler for remote notifications
 #[derive(Debug)]
 pub struct NetworkAlertHandler {
     endpoint: String,
+    timeout: Duration,
+    /// Queue to the sender thread, started by the first alert.
+    queue: OnceCell<SyncSender<(u32, String)>>,
 }
 
 impl NetworkAlertHandler {
+    /// `endpoint` is a plain `http://host[:port][/path]` URL.
     pub fn new(endpoint: &str) -> Self {
         Self {
             endpoint: endpoint.to_string(),
+            timeout: DEFAULT_ALERT_TIMEOUT,
+            queue: OnceCell::new(),
+        }
+    }
+    
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+    
+    fn split_endpoint(endpoint: &str) -> Result<(&str, &str)> {
+        let rest = endpoint.strip_prefix("http://")
+            .ok_or_else(|| WasmError::Runtime(format!("Unsupported alert endpoint: {}", endpoint)))?;
+        Ok(match rest.find('/') {
+            Some(slash) => (&rest[..slash], &rest[slash..]),
+            None => (rest, "/"),
+        })
+    }
+    
+    fn spawn_sender(&self) -> SyncSender<(u32, String)> {
+        let (sender, receiver) = mpsc::sync_channel::<(u32, String)>(ALERT_QUEUE_CAPACITY);
+        let endpoint = self.endpoint.clone();
+        let timeout = self.timeout;
+        thread::spawn(move || {
+            for (level, message) in receiver {
+                if let Err(e) = Self::post(&endpoint, timeout, level, &message) {
+                    log::warn!("Failed to deliver alert to {}: {}", endpoint, e);
+                }
+            }
+        });
+        sender
+    }
+    
+    /// POSTs `level=<n>` and the message as a plain-text body; any status
+    /// other than 2xx is an error.
+    fn post(endpoint: &str, timeout: Duration, level: u32, message: &str) -> Result<()> {
+        let (authority, path) = Self::split_endpoint(endpoint)?;
+        let authority = if authority.contains(':') { authority.to_string() } else { format!("{}:80", authority) };
+        let address = authority.to_socket_addrs()?.next()
+            .ok_or_else(|| WasmError::Runtime(format!("Cannot resolve alert endpoint: {}", endpoint)))?;
+        
+        let mut stream = TcpStream::connect_timeout(&address, timeout)?;
+        stream.set_read_timeout(Some(timeout))?;
+        stream.set_write_timeout(Some(timeout))?;
+        
+        let body = format!("level={}\n{}", level, message);
+        let request = format!(
+            "POST {} HTTP/1.1\r\nHost: {}\r\nContent-Type: text/plain\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+            path, authority, body.len(), body
+        );
+        stream.write_all(request.as_bytes())?;
+        
+        let mut status_line = String::new();
+        BufReader::new(stream).read_line(&mut status_line)?;
+        match status_line.split_whitespace().nth(1) {
+            Some(status) if status.starts_with('2') => Ok(()),
+            _ => Err(WasmError::Runtime(format!(
+                "Alert endpoint {} responded: {}", endpoint, status_line.trim()))),
         }
     }
 }
 
 impl AlertHandler for NetworkAlertHandler {
+    /// Queues the alert for a background sender so the host call never
+    /// waits on the network. Delivery failures are logged, not returned;
+    /// a malformed endpoint or a full queue fails the call.
     fn handle_alert(&self, level: u32, message: &str) -> Result<()> {
-        // In a real implementation, this would send HTTP requests
-        log::info!("Would send alert to {}: level={}, message={}", 
-                  self.endpoint, level, message);
-        Ok(())
+        Self::split_endpoint(&self.endpoint)?;
+        let queue = self.queue.get_or_init(|| self.spawn_sender());
+        match queue.try_send((level, message.to_string())) {
+            Ok(()) => Ok(()),
+            Err(TrySendError::Full(_)) => Err(WasmError::Runtime(format!(
+                "Alert queue for {} is full", self.endpoint))),
+            Err(TrySendError::Disconnected(_)) => Err(WasmError::Runtime(format!(
+                "Alert sender for {} has stopped", self.endpoint))),
+        }
     }
 }
 
@@ -268,6 +727,65 @@ mod tests {
         assert!(invalid.is_err());
     }
 
+    #[test]
+    fn test_file_alert_handler_appends() {
+        let path = std::env::temp_dir().join(format!("wasm_alerts_{}.log", std::process::id()));
+        let _ = std::fs::remove_file(&path);
+        
+        let mut interface = HostInterface::new();
+        interface.add_alert_handler(Box::new(FileAlertHandler::new(path.to_str().unwrap())));
+        interface.send_alert(1, "Temperature high").unwrap();
+        interface.send_alert(3, "Sensor offline").unwrap();
+        
+        // A handler that cannot write fails the call, but the others still run
+        interface.add_alert_handler(Box::new(FileAlertHandler::new(std::env::temp_dir().to_str().unwrap())));
+        assert!(interface.send_alert(0, "Recovered").is_err());
+        
+        let contents = std::fs::read_to_string(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        let lines: Vec<&str> = contents.lines().collect();
+        assert_eq!(lines.len(), 3);
+        assert!(lines[0].ends_with("WARNING - Temperature high"));
+        assert!(lines[1].ends_with("CRITICAL - Sensor offline"));
+        assert!(lines[2].ends_with("INFO - Recovered"));
+    }
+
+    #[test]
+    fn test_file_alert_handler_escapes_control_characters() {
+        let path = std::env::temp_dir().join(format!("wasm_alerts_escape_{}.log", std::process::id()));
+        let _ = std::fs::remove_file(&path);
+        
+        let handler = FileAlertHandler::new(path.to_str().unwrap());
+        handler.handle_alert(2, "disk full\n[0] CRITICAL - forged\x1b[2J").unwrap();
+        
+        let contents = std::fs::read_to_string(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(contents.lines().count(), 1);
+        assert!(contents.ends_with("ERROR - disk full\\n[0] CRITICAL - forged\\u{1b}[2J\n"));
+    }
+
+    #[test]
+    fn test_network_alert_handler_sends_in_background() {
+        use std::io::Read;
+        use std::net::TcpListener;
+        
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let endpoint = format!("http://{}/alerts", listener.local_addr().unwrap());
+        let handler = NetworkAlertHandler::new(&endpoint);
+        
+        // Returns before the endpoint has even accepted the connection
+        handler.handle_alert(3, "Sensor offline").unwrap();
+        
+        let (mut stream, _) = listener.accept().unwrap();
+        stream.write_all(b"HTTP/1.1 204 No Content\r\n\r\n").unwrap();
+        let mut request = String::new();
+        stream.read_to_string(&mut request).unwrap();
+        assert!(request.starts_with("POST /alerts HTTP/1.1\r\n"));
+        assert!(request.ends_with("level=3\nSensor offline"));
+        
+        assert!(NetworkAlertHandler::new("https://example.com").handle_alert(0, "x").is_err());
+    }
+
     #[test]
     fn test_time_and_random() {
         let interface = HostInterface::new();
@@ -282,6 +800,18 @@ mod tests {
         assert_ne!(rand1, rand2);
     }
 
+    #[test]
+    fn test_seeded_interfaces_match() {
+        let first = HostInterface::with_seed(7);
+        let second = HostInterface::with_seed(7);
+        let sequence = |interface: &HostInterface| -> Vec<u32> {
+            (0..8).map(|_| interface.random().unwrap()).collect()
+        };
+        
+        assert_eq!(sequence(&first), sequence(&second));
+        assert_ne!(sequence(&HostInterface::with_seed(8)), sequence(&HostInterface::with_seed(7)));
+    }
+
     #[test]
     fn test_sensor_update() {
         let mut interface = HostInterface::new();
@@ -291,4 +821,88 @@ mod tests {
         // Should be around 30 (with variation)
         assert!(temp >= 25 && temp <= 35);
     }
+
+    #[test]
+    fn test_sensor_series() {
+        let mut interface = HostInterface::new();
+        interface.set_sensor_series(0, vec![10, 20, 30]);
+        
+        let reads: Vec<u32> = (0..4).map(|_| interface.read_sensor(0).unwrap()).collect();
+        assert_eq!(reads, vec![10, 20, 30, 30]);
+        
+        interface.set_sensor_series(0, Vec::new());
+        let temp = interface.read_sensor(0).unwrap();
+        assert!((20..=30).contains(&temp));
+    }
+
+    #[derive(Debug)]
+    struct CountingSensor {
+        reads: std::rc::Rc<Cell<u32>>,
+    }
+
+    impl SensorSource for CountingSensor {
+        fn read_sensor(&self, _sensor_id: u32) -> Result<u32> {
+            self.reads.set(self.reads.get() + 1);
+            Ok(22)
+        }
+    }
+
+    #[test]
+    fn test_sensor_rate_limit() {
+        let mut interface = HostInterface::new();
+        let reads = std::rc::Rc::new(Cell::new(0));
+        interface.set_sensor_source(Box::new(CountingSensor { reads: reads.clone() }));
+        interface.set_sensor_rate_limit(0, SensorRateLimit {
+            max_reads_per_sec: 10,
+            on_throttle: ThrottleBehavior::CachedValue,
+        });
+        
+        for _ in 0..100 {
+            assert_eq!(interface.read_sensor(0).unwrap(), 22);
+        }
+        assert!(reads.get() <= 20);
+        
+        // Other sensors are unaffected; an erroring limit surfaces the throttle
+        interface.set_sensor_rate_limit(1, SensorRateLimit {
+            max_reads_per_sec: 1,
+            on_throttle: ThrottleBehavior::Error,
+        });
+        assert!(interface.read_sensor(1).is_ok());
+        assert!(interface.read_sensor(1).is_err());
+    }
+
+    #[test]
+    fn test_threshold_events() {
+        let mut interface = HostInterface::new();
+        let id = interface.subscribe(0, 30, ThresholdDirection::Above).unwrap();
+        
+        interface.update_sensor(0, 28);
+        assert_eq!(interface.pending_event_count(), 0);
+        
+        interface.update_sensor(0, 35);
+        interface.update_sensor(0, 36); // still above, no new crossing
+        let events = interface.take_events(10);
+        assert_eq!(events, vec![SensorEvent { subscription_id: id, sensor_id: 0, value: 35 }]);
+        assert_eq!(interface.pending_event_count(), 0);
+    }
+
+    #[test]
+    fn test_subscriptions_and_events_are_bounded() {
+        let mut interface = HostInterface::new();
+        for _ in 0..MAX_SUBSCRIPTIONS {
+            interface.subscribe(0, 30, ThresholdDirection::Above).unwrap();
+        }
+        assert!(interface.subscribe(0, 30, ThresholdDirection::Above).is_err());
+        
+        // Every subscription fires on each crossing; only the newest events are kept
+        let crossings = MAX_PENDING_EVENTS / MAX_SUBSCRIPTIONS + 1;
+        for value in 0..crossings as u32 {
+            interface.update_sensor(0, 0);
+            interface.update_sensor(0, 100 + value);
+        }
+        assert_eq!(interface.pending_event_count(), MAX_PENDING_EVENTS);
+        assert_eq!(interface.dropped_event_count(), MAX_SUBSCRIPTIONS as u64);
+        let oldest = interface.take_events(1);
+        assert_eq!(oldest[0].value, 101);
+    }
 }
\ No newline at end of file
@@ -4,35 +4,232 @@ pub mod host_interface;
 use crate::error::{WasmError, Result};
 use crate::memory::LinearMemory;
 use crate::sandbox::capabilities::{Capability, SensorType, AlertLevel};
+use crate::sandbox::policy::NetworkPolicy;
 use crate::sandbox::Sandbox;
+use functions::{AbiValue, ValueType};
+use host_interface::ThresholdDirection;
+use std::borrow::Cow;
+use std::collections::{HashMap, HashSet};
+use std::time::Duration;
+
+/// Bytes per event written by `wasm_poll_events`.
+const EVENT_SIZE: u32 = 12;
+
+/// Bytes written by `wasm_read_sensor_ex`: value i32, timestamp u64, unit u8, valid u8.
+const SENSOR_READING_SIZE: u32 = 14;
+
+/// Default cap on the `len` a guest may pass for a log or alert message.
+pub const DEFAULT_MAX_MESSAGE_LEN: u32 = 64 * 1024;
+
+/// Default cap on the time one host call may spend sleeping between retries.
+pub const DEFAULT_MAX_RETRY_DELAY: Duration = Duration::from_millis(250);
+
+/// Retry behaviour for host functions backed by fallible I/O.
+#[derive(Debug, Clone)]
+pub struct RetryPolicy {
+    pub max_retries: u32,
+    /// Delay before the first retry; doubled for each further retry.
+    pub backoff: Duration,
+    /// Total sleep allowed per host call; retries that would exceed it are
+    /// skipped, since the guest is blocked while the host sleeps.
+    pub max_total_delay: Duration,
+}
+
+impl RetryPolicy {
+    pub fn new(max_retries: u32, backoff: Duration) -> Self {
+        Self { max_retries, backoff, max_total_delay: DEFAULT_MAX_RETRY_DELAY }
+    }
+    
+    pub fn with_max_total_delay(mut self, max_total_delay: Duration) -> Self {
+        self.max_total_delay = max_total_delay;
+        self
+    }
+    
+    fn delay_for(&self, retry: u32) -> Duration {
+        self.backoff.saturating_mul(1u32 << retry.min(16))
+    }
+}
+
+/// An embedder-provided host function, registered with
+/// `WasmABI::register_host_function`.
+pub trait HostFn: std::fmt::Debug {
+    fn call(&self, args: &[AbiValue], memory: &mut LinearMemory, sandbox: &mut Sandbox) -> Result<AbiValue>;
+    
+    /// Checked against the sandbox before every call.
+    fn required_capability(&self) -> Option<Capability> {
+        None
+    }
+}
+
+/// Built-in host functions check their own capabilities, which can depend on
+/// their arguments.
+type BuiltinFn = fn(&mut WasmABI, &[u32], &mut LinearMemory) -> Result<AbiValue>;
+
+#[derive(Debug)]
+enum HostFunction {
+    Builtin(BuiltinFn),
+    Custom(Box<dyn HostFn>),
+}
 
 #[derive(Debug)]
 pub struct WasmABI {
     sandbox: Sandbox,
     host_interface: host_interface::HostInterface,
+    retry_policy: Option<RetryPolicy>,
+    /// Message of the most recent failed host call, read by `wasm_last_error`.
+    last_error: Option<String>,
+    /// Host functions guests may call, regardless of capabilities; `None` allows all.
+    enabled_functions: Option<HashSet<String>>,
+    /// Destinations `wasm_http_post` may reach; denies everything by default.
+    network_policy: NetworkPolicy,
+    /// Longest log or alert message accepted, checked before guest memory is read.
+    max_message_len: u32,
+    host_functions: HashMap<String, HostFunction>,
 }
 
 impl WasmABI {
     pub fn new(sandbox: Sandbox) -> Self {
-        Self {
+        let mut abi = Self {
             sandbox,
             host_interface: host_interface::HostInterface::new(),
+            retry_policy: None,
+            last_error: None,
+            enabled_functions: None,
+            network_policy: NetworkPolicy::default(),
+            max_message_len: DEFAULT_MAX_MESSAGE_LEN,
+            host_functions: HashMap::new(),
+        };
+        abi.register_builtins();
+        abi
+    }
+    
+    fn register_builtins(&mut self) {
+        let builtins: [(&str, BuiltinFn); 15] = [
+            ("wasm_log", |abi, args, memory| abi.wasm_log(args, memory).map(AbiValue::from)),
+            ("wasm_read_sensor", |abi, args, _| abi.wasm_read_sensor(args).map(AbiValue::from)),
+            ("wasm_read_sensor_ex", |abi, args, memory| abi.wasm_read_sensor_ex(args, memory).map(AbiValue::from)),
+            ("wasm_send_alert", |abi, args, memory| abi.wasm_send_alert(args, memory).map(AbiValue::from)),
+            ("wasm_get_time", |abi, _, _| abi.wasm_get_time().map(AbiValue::I64)),
+            ("wasm_monotonic_ms", |abi, _, _| abi.wasm_monotonic_ms().map(AbiValue::from)),
+            ("wasm_random", |abi, _, _| abi.wasm_random().map(AbiValue::from)),
+            ("wasm_sensor_subscribe", |abi, args, _| abi.wasm_sensor_subscribe(args).map(AbiValue::from)),
+            ("wasm_poll_events", |abi, args, memory| abi.wasm_poll_events(args, memory).map(AbiValue::from)),
+            ("wasm_kv_get", |abi, args, memory| abi.wasm_kv_get(args, memory).map(AbiValue::from)),
+            ("wasm_kv_set", |abi, args, memory| abi.wasm_kv_set(args, memory).map(AbiValue::from)),
+            ("wasm_last_error", |abi, args, memory| abi.wasm_last_error(args, memory).map(AbiValue::from)),
+            ("wasm_memory_size", |_, _, memory| Ok(AbiValue::from(memory.size()))),
+            ("wasm_memory_grow", |abi, args, memory| abi.wasm_memory_grow(args, memory).map(AbiValue::from)),
+            ("wasm_http_post", |abi, args, memory| abi.wasm_http_post(args, memory).map(AbiValue::from)),
+        ];
+        for (name, builtin) in builtins {
+            self.host_functions.insert(name.to_string(), HostFunction::Builtin(builtin));
         }
     }
     
+    /// Adds a host function guests can call by `name`, replacing any existing
+    /// function of that name, built-ins included.
+    pub fn register_host_function(&mut self, name: &str, handler: Box<dyn HostFn>) {
+        self.host_functions.insert(name.to_string(), HostFunction::Custom(handler));
+    }
+    
+    pub fn with_retry_policy(mut self, policy: RetryPolicy) -> Self {
+        self.retry_policy = Some(policy);
+        self
+    }
+    
+    pub fn with_network_policy(mut self, policy: NetworkPolicy) -> Self {
+        self.network_policy = policy;
+        self
+    }
+    
+    pub fn with_max_message_len(mut self, max_message_len: u32) -> Self {
+        self.max_message_len = max_message_len;
+        self
+    }
+    
+    /// Restricts guests to the named host functions. Checked before
+    /// capabilities, so a disabled function stays unavailable even when its
+    /// capability is granted.
+    pub fn set_enabled_functions(&mut self, names: &[&str]) {
+        self.enabled_functions = Some(names.iter().map(|name| name.to_string()).collect());
+    }
+    
+    fn is_function_enabled(&self, name: &str) -> bool {
+        self.enabled_functions.as_ref().is_none_or(|enabled| enabled.contains(name))
+    }
+    
+    /// Host functions whose failures may be transient and are worth retrying.
+    pub fn is_retryable(name: &str) -> bool {
+        matches!(name, "wasm_read_sensor" | "wasm_send_alert")
+    }
+    
+    /// Call a host function. Arguments to built-ins are checked against
+    /// their signatures in `functions`.
     pub fn call_host_function(
+        &mut self,
+        name: &str,
+        args: &[AbiValue],
+        memory: &mut LinearMemory,
+    ) -> Result<AbiValue> {
+        let result = self.dispatch(name, args, memory);
+        if let Err(e) = &result {
+            self.last_error = Some(e.to_string());
+        }
+        result
+    }
+    
+    /// `call_host_function` for callers passing and expecting plain i32 values.
+    pub fn call_host_function_u32(
         &mut self,
         name: &str,
         args: &[u32],
         memory: &mut LinearMemory,
     ) -> Result<u32> {
-        match name {
-            "wasm_log" => self.wasm_log(args, memory),
-            "wasm_read_sensor" => self.wasm_read_sensor(args),
-            "wasm_send_alert" => self.wasm_send_alert(args, memory),
-            "wasm_get_time" => self.wasm_get_time(),
-            "wasm_random" => self.wasm_random(),
-            _ => Err(WasmError::Runtime(format!("Unknown host function: {}", name))),
+        let args: Vec<AbiValue> = args.iter().map(|&arg| AbiValue::from(arg)).collect();
+        self.call_host_function(name, &args, memory)?.as_u32()
+    }
+    
+    fn dispatch(&mut self, name: &str, args: &[AbiValue], memory: &mut LinearMemory) -> Result<AbiValue> {
+        if !self.is_function_enabled(name) {
+            return Err(WasmError::Runtime("host function disabled".to_string()));
+        }
+        
+        match self.host_functions.get(name) {
+            Some(HostFunction::Builtin(builtin)) => {
+                let builtin = *builtin;
+                let arg_types: Vec<ValueType> = args.iter().map(AbiValue::value_type).collect();
+                functions::validate_function_call(name, &arg_types)?;
+                
+                // Every built-in parameter is an i32
+                let args = args.iter().map(AbiValue::as_u32).collect::<Result<Vec<u32>>>()?;
+                builtin(self, &args, memory)
+            }
+            Some(HostFunction::Custom(handler)) => {
+                if let Some(capability) = handler.required_capability() {
+                    self.sandbox.check_capability(&capability)?;
+                }
+                handler.call(args, memory, &mut self.sandbox)
+            }
+            None => Err(WasmError::Runtime(format!("Unknown host function: {}", name))),
+        }
+    }
+    
+    /// Copies the last host call error message into `buf_ptr` if it fits in
+    /// `cap` and returns its length, or 0 if no call has failed.
+    fn wasm_last_error(&mut self, args: &[u32], memory: &mut LinearMemory) -> Result<u32> {
+        if args.len() < 2 {
+            return Err(WasmError::Runtime("wasm_last_error requires 2 arguments".to_string()));
+        }
+        
+        let (buf_ptr, cap) = (args[0], args[1]);
+        match &self.last_error {
+            Some(message) => {
+                if message.len() <= cap as usize {
+                    memory.write_bytes(buf_ptr, message.as_bytes())?;
+                }
+                Ok(message.len() as u32)
+            }
+            None => Ok(0),
         }
     }
     
@@ -46,35 +243,190 @@ impl WasmABI {
         let message_ptr = args[0];
         let message_len = args[1];
         
-        let message_bytes = memory.read_bytes(message_ptr, message_len)?;
-        let message = String::from_utf8_lossy(message_bytes);
+        let message = self.read_message(memory, message_ptr, message_len)?;
         
         self.host_interface.log(&message);
         Ok(0)
     }
     
+    /// Guest message text, rejecting lengths over `max_message_len` before
+    /// anything is read or allocated.
+    fn read_message<'m>(&self, memory: &'m LinearMemory, ptr: u32, len: u32) -> Result<Cow<'m, str>> {
+        if len > self.max_message_len {
+            return Err(WasmError::Runtime(format!(
+                "Message of {} bytes exceeds the {} byte limit", len, self.max_message_len)));
+        }
+        Ok(String::from_utf8_lossy(memory.read_bytes(ptr, len)?))
+    }
+    
     fn wasm_read_sensor(&mut self, args: &[u32]) -> Result<u32> {
         if args.is_empty() {
             return Err(WasmError::Runtime("wasm_read_sensor requires 1 argument".to_string()));
         }
         
         let sensor_id = args[0];
-        let sensor_type = match sensor_id {
-            0 => SensorType::Temperature,
-            1 => SensorType::Humidity,
-            2 => SensorType::Pressure,
-            3 => SensorType::Motion,
-            4 => SensorType::Light,
-            5 => SensorType::Sound,
-            _ => return Err(WasmError::Runtime("Invalid sensor ID".to_string())),
-        };
+        let sensor_type = Self::sensor_type(sensor_id)?;
         
-        self.sandbox.check_capability(&Capability::ReadSensor(sensor_type.clone()))?;
+        self.sandbox.check_capability(&Capability::ReadSensor(sensor_type))?;
         
-        let value = self.host_interface.read_sensor(sensor_id)?;
+        let value = self.with_retries("wasm_read_sensor", |host| host.read_sensor(sensor_id))?;
         Ok(value)
     }
     
+    /// Writes a packed reading with unit and timestamp to `out_ptr`; returns 0,
+    /// or -1 with the valid flag cleared if the sensor could not be read. The
+    /// timestamp is 0 unless the module also holds `WallClock`.
+    fn wasm_read_sensor_ex(&mut self, args: &[u32], memory: &mut LinearMemory) -> Result<u32> {
+        if args.len() < 2 {
+            return Err(WasmError::Runtime("wasm_read_sensor_ex requires 2 arguments".to_string()));
+        }
+        
+        let sensor_id = args[0];
+        let out_ptr = args[1];
+        let sensor_type = Self::sensor_type(sensor_id)?;
+        
+        self.sandbox.check_capability(&Capability::ReadSensor(sensor_type.clone()))?;
+        
+        // Check the buffer before reading so a bad pointer doesn't consume a reading
+        memory.read_bytes(out_ptr, SENSOR_READING_SIZE)?;
+        
+        let reading = self.with_retries("wasm_read_sensor", |host| host.read_sensor(sensor_id));
+        let timestamp = if self.sandbox.has_capability(&Capability::WallClock) {
+            self.host_interface.get_time().unwrap_or(0)
+        } else {
+            0
+        };
+        let (value, valid) = match &reading {
+            Ok(value) => (*value, 1),
+            Err(e) => {
+                log::warn!("Extended read of sensor {} failed: {}", sensor_id, e);
+                (0, 0)
+            }
+        };
+        
+        memory.write_u32(out_ptr, value)?;
+        memory.write_u64(out_ptr + 4, timestamp as u64)?;
+        memory.write_u8(out_ptr + 12, Self::sensor_unit_code(&sensor_type))?;
+        memory.write_u8(out_ptr + 13, valid)?;
+        
+        Ok(if reading.is_ok() { 0 } else { u32::MAX })
+    }
+    
+    /// Unit of a sensor's readings: 0=°C, 1=%RH, 2=hPa, 3=none, 4=lux, 5=dB.
+    fn sensor_unit_code(sensor_type: &SensorType) -> u8 {
+        match sensor_type {
+            SensorType::Temperature => 0,
+            SensorType::Humidity => 1,
+            SensorType::Pressure => 2,
+            SensorType::Motion | SensorType::Any => 3,
+            SensorType::Light => 4,
+            SensorType::Sound => 5,
+        }
+    }
+    
+    fn wasm_sensor_subscribe(&mut self, args: &[u32]) -> Result<u32> {
+        if args.len() < 3 {
+            return Err(WasmError::Runtime("wasm_sensor_subscribe requires 3 arguments".to_string()));
+        }
+        
+        let sensor_id = args[0];
+        let threshold = args[1];
+        let direction = match args[2] {
+            0 => ThresholdDirection::Above,
+            1 => ThresholdDirection::Below,
+            _ => return Err(WasmError::Runtime("Invalid threshold direction".to_string())),
+        };
+        
+        self.sandbox.check_capability(&Capability::ReadSensor(Self::sensor_type(sensor_id)?))?;
+        
+        self.host_interface.subscribe(sensor_id, threshold, direction)
+    }
+    
+    /// Writes up to `cap` pending events to guest memory as
+    /// `(subscription_id, sensor_id, value)` u32 triples; returns the count.
+    fn wasm_poll_events(&mut self, args: &[u32], memory: &mut LinearMemory) -> Result<u32> {
+        if args.len() < 2 {
+            return Err(WasmError::Runtime("wasm_poll_events requires 2 arguments".to_string()));
+        }
+        
+        let buf_ptr = args[0];
+        let cap = args[1];
+        
+        // Check the whole buffer up front so no events are lost on a bad pointer
+        let buf_len = cap.checked_mul(EVENT_SIZE)
+            .ok_or(WasmError::MemoryOutOfBounds { address: buf_ptr, size: u32::MAX })?;
+        memory.read_bytes(buf_ptr, buf_len)?;
+        
+        let events = self.host_interface.take_events(cap as usize);
+        for (i, event) in events.iter().enumerate() {
+            let addr = buf_ptr + i as u32 * EVENT_SIZE;
+            memory.write_u32(addr, event.subscription_id)?;
+            memory.write_u32(addr + 4, event.sensor_id)?;
+            memory.write_u32(addr + 8, event.value)?;
+        }
+        
+        Ok(events.len() as u32)
+    }
+    
+    /// Copies the value for a key into `val_ptr` if it fits in `val_cap` and
+    /// returns its length, or -1 if the key is unset.
+    fn wasm_kv_get(&mut self, args: &[u32], memory: &mut LinearMemory) -> Result<u32> {
+        self.sandbox.check_capability(&Capability::Storage)?;
+        
+        if args.len() < 4 {
+            return Err(WasmError::Runtime("wasm_kv_get requires 4 arguments".to_string()));
+        }
+        
+        let key = memory.read_bytes(args[0], args[1])?.to_vec();
+        let (val_ptr, val_cap) = (args[2], args[3]);
+        
+        match self.host_interface.kv_get(&key)? {
+            Some(value) => {
+                if value.len() <= val_cap as usize {
+                    memory.write_bytes(val_ptr, &value)?;
+                }
+                Ok(value.len() as u32)
+            }
+            None => Ok(u32::MAX),
+        }
+    }
+    
+    fn wasm_kv_set(&mut self, args: &[u32], memory: &mut LinearMemory) -> Result<u32> {
+        self.sandbox.check_capability(&Capability::Storage)?;
+        
+        if args.len() < 4 {
+            return Err(WasmError::Runtime("wasm_kv_set requires 4 arguments".to_string()));
+        }
+        
+        let key = memory.read_bytes(args[0], args[1])?.to_vec();
+        let value = memory.read_bytes(args[2], args[3])?;
+        
+        self.host_interface.kv_set(&key, value)?;
+        Ok(0)
+    }
+    
+    fn wasm_http_post(&mut self, args: &[u32], memory: &LinearMemory) -> Result<u32> {
+        self.sandbox.check_capability(&Capability::NetworkAccess)?;
+        
+        if args.len() < 4 {
+            return Err(WasmError::Runtime("wasm_http_post requires 4 arguments".to_string()));
+        }
+        
+        let url = std::str::from_utf8(memory.read_bytes(args[0], args[1])?)
+            .map_err(|_| WasmError::Runtime("URL is not valid UTF-8".to_string()))?;
+        if !self.network_policy.allows_url(url) {
+            return Err(WasmError::Runtime(format!("URL not allowed by network policy: {}", url)));
+        }
+        let body = memory.read_bytes(args[2], args[3])?;
+        
+        let status = self.host_interface.http_post(url, body)?;
+        Ok(status as u32)
+    }
+    
+    fn sensor_type(sensor_id: u32) -> Result<SensorType> {
+        SensorType::from_id(sensor_id).ok_or_else(|| WasmError::Runtime("Invalid sensor ID".to_string()))
+    }
+    
     fn wasm_send_alert(&mut self, args: &[u32], memory: &LinearMemory) -> Result<u32> {
         if args.len() < 3 {
             return Err(WasmError::Runtime("wasm_send_alert requires 3 arguments".to_string()));
@@ -94,20 +446,54 @@ impl WasmABI {
         
         self.sandbox.check_capability(&Capability::SendAlert(alert_level.clone()))?;
         
-        let message_bytes = memory.read_bytes(message_ptr, message_len)?;
-        let message = String::from_utf8_lossy(message_bytes);
+        let message = self.read_message(memory, message_ptr, message_len)?;
         
-        self.host_interface.send_alert(level, &message)?;
+        // Only the handler that failed is retried; the delay budget covers all of them
+        let mut slept = Duration::ZERO;
+        self.host_interface.send_alert_with(level, |handler| {
+            self.retry_within("wasm_send_alert", &mut slept, || handler.handle_alert(level, &message))
+        })?;
         Ok(0)
     }
     
-    fn wasm_get_time(&mut self) -> Result<u32> {
-        self.sandbox.check_capability(&Capability::GetTime)?;
+    fn wasm_get_time(&mut self) -> Result<i64> {
+        self.sandbox.check_capability(&Capability::WallClock)?;
         
         let timestamp = self.host_interface.get_time()?;
         Ok(timestamp)
     }
     
+    fn wasm_monotonic_ms(&mut self) -> Result<u32> {
+        self.sandbox.check_capability(&Capability::MonotonicTime)?;
+        
+        self.host_interface.monotonic_ms()
+    }
+    
+    /// Returns the previous size in pages, or `u32::MAX` (-1) if memory
+    /// cannot grow by `delta` pages.
+    fn wasm_memory_grow(&mut self, args: &[u32], memory: &mut LinearMemory) -> Result<u32> {
+        if args.is_empty() {
+            return Err(WasmError::Runtime("wasm_memory_grow requires 1 argument".to_string()));
+        }
+        
+        self.sandbox.check_capability(&Capability::MemoryGrow)?;
+        
+        let max_pages = self.sandbox.limits().max_memory_pages;
+        let requested = memory.size() as u64 + args[0] as u64;
+        if requested > max_pages as u64 && !self.sandbox.is_observe_only() {
+            log::warn!("wasm_memory_grow to {} pages exceeds the sandbox limit of {}", requested, max_pages);
+            return Ok(u32::MAX);
+        }
+        
+        match memory.grow(args[0]) {
+            Ok(previous) => {
+                self.sandbox.update_memory_usage(memory.size());
+                Ok(previous)
+            }
+            Err(_) => Ok(u32::MAX),
+        }
+    }
+    
     fn wasm_random(&mut self) -> Result<u32> {
         self.sandbox.check_capability(&Capability::Random)?;
         
@@ -115,6 +501,42 @@ impl WasmABI {
         Ok(value)
     }
     
+    fn with_retries<T>(
+        &self,
+        name: &str,
+        mut call: impl FnMut(&host_interface::HostInterface) -> Result<T>,
+    ) -> Result<T> {
+        let mut slept = Duration::ZERO;
+        self.retry_within(name, &mut slept, || call(&self.host_interface))
+    }
+    
+    /// Run `call` under the retry policy, adding time spent sleeping to `slept`.
+    fn retry_within<T>(&self, name: &str, slept: &mut Duration, mut call: impl FnMut() -> Result<T>) -> Result<T> {
+        let policy = match &self.retry_policy {
+            Some(policy) if Self::is_retryable(name) => policy,
+            _ => return call(),
+        };
+        
+        let mut retry = 0;
+        loop {
+            match call() {
+                Ok(value) => return Ok(value),
+                Err(e) if retry < policy.max_retries => {
+                    let delay = policy.delay_for(retry);
+                    if *slept + delay > policy.max_total_delay {
+                        log::warn!("Host function {} failed ({}), retry delay budget exhausted", name, e);
+                        return Err(e);
+                    }
+                    log::warn!("Host function {} failed ({}), retry {}/{}", name, e, retry + 1, policy.max_retries);
+                    std::thread::sleep(delay);
+                    *slept += delay;
+                    retry += 1;
+                }
+                Err(e) => return Err(e),
+            }
+        }
+    }
+    
     pub fn get_sandbox_mut(&mut self) -> &mut Sandbox {
         &mut self.sandbox
     }
@@ -122,6 +544,10 @@ impl WasmABI {
     pub fn get_host_interface(&self) -> &host_interface::HostInterface {
         &self.host_interface
     }
+    
+    pub fn get_host_interface_mut(&mut self) -> &mut host_interface::HostInterface {
+        &mut self.host_interface
+    }
 }
 
 #[cfg(test)]
@@ -129,6 +555,44 @@ mod tests {
     use super::*;
     use crate::sandbox::{ResourceLimits, Sandbox};
     use crate::sandbox::capabilities::CapabilitySet;
+    use super::host_interface::AlertHandler;
+    use super::host_interface::SensorSource;
+    use super::host_interface::HttpClient;
+    use std::cell::{Cell, RefCell};
+    use std::rc::Rc;
+
+    #[derive(Debug)]
+    struct FlakySensor {
+        failures_left: Cell<u32>,
+        attempts: Rc<Cell<u32>>,
+    }
+
+    impl SensorSource for FlakySensor {
+        fn read_sensor(&self, _sensor_id: u32) -> Result<u32> {
+            self.attempts.set(self.attempts.get() + 1);
+            if self.failures_left.get() > 0 {
+                self.failures_left.set(self.failures_left.get() - 1);
+                return Err(WasmError::Runtime("Sensor bus busy".to_string()));
+            }
+            Ok(21)
+        }
+    }
+
+    fn flaky_abi(failures: u32, policy: Option<RetryPolicy>) -> (WasmABI, Rc<Cell<u32>>) {
+        let mut sandbox = Sandbox::new(ResourceLimits::default());
+        sandbox.grant_capability(Capability::ReadSensor(SensorType::Temperature));
+        let mut abi = WasmABI::new(sandbox);
+        if let Some(policy) = policy {
+            abi = abi.with_retry_policy(policy);
+        }
+        
+        let attempts = Rc::new(Cell::new(0));
+        abi.get_host_interface_mut().set_sensor_source(Box::new(FlakySensor {
+            failures_left: Cell::new(failures),
+            attempts: attempts.clone(),
+        }));
+        (abi, attempts)
+    }
 
     #[test]
     fn test_abi_creation() {
@@ -140,7 +604,7 @@ mod tests {
     #[test]
     fn test_wasm_get_time() {
         let mut sandbox = Sandbox::new(ResourceLimits::default());
-        sandbox.grant_capability(Capability::GetTime);
+        sandbox.grant_capability(Capability::WallClock);
         let mut abi = WasmABI::new(sandbox);
         
         let result = abi.wasm_get_time();
@@ -148,6 +612,69 @@ mod tests {
         assert!(result.unwrap() > 0);
     }
 
+    #[test]
+    fn test_get_time_returns_i64() {
+        use crate::sandbox::faults::{Fault, FaultInjector};
+        
+        let mut sandbox = Sandbox::new(ResourceLimits::default());
+        sandbox.grant_capability(Capability::WallClock);
+        let mut abi = WasmABI::new(sandbox);
+        let mut memory = LinearMemory::new(1, None).unwrap();
+        
+        // Past 2106, when a u32 of seconds since the epoch overflows
+        abi.get_host_interface_mut().set_fault_injector(FaultInjector::new()
+            .inject("wasm_get_time", 0, Fault::ClockJump(1 << 32)));
+        match abi.call_host_function("wasm_get_time", &[], &mut memory).unwrap() {
+            AbiValue::I64(seconds) => assert!(seconds > u32::MAX as i64),
+            other => panic!("expected an i64 timestamp, got {:?}", other),
+        }
+        
+        assert!(matches!(abi.call_host_function_u32("wasm_get_time", &[], &mut memory), Err(WasmError::TypeMismatch)));
+        assert!(abi.call_host_function("wasm_random", &[AbiValue::I64(0)], &mut memory).is_err());
+    }
+
+    #[test]
+    fn test_monotonic_without_wall_clock() {
+        let mut sandbox = Sandbox::new(ResourceLimits::default());
+        sandbox.grant_capability(Capability::MonotonicTime);
+        let mut abi = WasmABI::new(sandbox);
+        
+        assert!(abi.wasm_monotonic_ms().is_ok());
+        assert!(abi.wasm_get_time().is_err());
+    }
+
+    #[test]
+    fn test_disabled_host_function() {
+        let mut sandbox = Sandbox::new(ResourceLimits::default());
+        sandbox.grant_capability(Capability::Random);
+        sandbox.grant_capability(Capability::WallClock);
+        let mut abi = WasmABI::new(sandbox);
+        abi.set_enabled_functions(&["wasm_get_time", "wasm_log"]);
+        let mut memory = LinearMemory::new(1, None).unwrap();
+        
+        let err = abi.call_host_function_u32("wasm_random", &[], &mut memory).unwrap_err();
+        assert!(err.to_string().contains("host function disabled"));
+        assert!(abi.call_host_function("wasm_get_time", &[], &mut memory).is_ok());
+    }
+
+    #[test]
+    fn test_message_length_cap() {
+        let mut sandbox = Sandbox::new(ResourceLimits::default());
+        sandbox.grant_capability(Capability::Log);
+        sandbox.grant_capability(Capability::SendAlert(AlertLevel::Any));
+        let mut abi = WasmABI::new(sandbox);
+        let mut memory = LinearMemory::new(2, None).unwrap();
+        
+        let cap = DEFAULT_MAX_MESSAGE_LEN;
+        assert!(abi.call_host_function_u32("wasm_log", &[0, cap], &mut memory).is_ok());
+        let err = abi.call_host_function_u32("wasm_log", &[0, cap + 1], &mut memory).unwrap_err();
+        assert!(err.to_string().contains("exceeds"));
+        
+        let mut abi = abi.with_max_message_len(8);
+        assert!(abi.call_host_function_u32("wasm_send_alert", &[0, 0, 8], &mut memory).is_ok());
+        assert!(abi.call_host_function_u32("wasm_send_alert", &[0, 0, 9], &mut memory).is_err());
+    }
+
     #[test]
     fn test_capability_check() {
         let sandbox = Sandbox::new(ResourceLimits::default());
@@ -157,4 +684,381 @@ mod tests {
         let result = abi.wasm_get_time();
         assert!(result.is_err());
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn test_retry_transient_sensor_failure() {
+        // Three attempts in total: the initial call plus two retries
+        let policy = RetryPolicy::new(2, Duration::from_millis(1));
+        let (mut abi, attempts) = flaky_abi(2, Some(policy));
+        
+        assert_eq!(abi.wasm_read_sensor(&[0]).unwrap(), 21);
+        assert_eq!(attempts.get(), 3);
+    }
+
+    #[test]
+    fn test_retry_exhausted_or_disabled() {
+        let (mut abi, attempts) = flaky_abi(3, Some(RetryPolicy::new(2, Duration::ZERO)));
+        assert!(abi.wasm_read_sensor(&[0]).is_err());
+        assert_eq!(attempts.get(), 3);
+        
+        let (mut abi, attempts) = flaky_abi(1, None);
+        assert!(abi.wasm_read_sensor(&[0]).is_err());
+        assert_eq!(attempts.get(), 1);
+        assert!(!WasmABI::is_retryable("wasm_log"));
+    }
+
+    #[derive(Debug)]
+    struct FlakyAlertHandler {
+        failures_left: Cell<u32>,
+        calls: Rc<Cell<u32>>,
+    }
+
+    impl AlertHandler for FlakyAlertHandler {
+        fn handle_alert(&self, _level: u32, _message: &str) -> Result<()> {
+            self.calls.set(self.calls.get() + 1);
+            if self.failures_left.get() > 0 {
+                self.failures_left.set(self.failures_left.get() - 1);
+                return Err(WasmError::Runtime("Alert sink busy".to_string()));
+            }
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_alert_retry_repeats_only_failed_handler() {
+        let alert_abi = |policy: RetryPolicy| {
+            let mut sandbox = Sandbox::new(ResourceLimits::default());
+            sandbox.grant_capability(Capability::SendAlert(AlertLevel::Any));
+            let mut abi = WasmABI::new(sandbox).with_retry_policy(policy);
+            let steady = Rc::new(Cell::new(0));
+            let flaky = Rc::new(Cell::new(0));
+            abi.get_host_interface_mut().add_alert_handler(Box::new(FlakyAlertHandler {
+                failures_left: Cell::new(0),
+                calls: steady.clone(),
+            }));
+            abi.get_host_interface_mut().add_alert_handler(Box::new(FlakyAlertHandler {
+                failures_left: Cell::new(1),
+                calls: flaky.clone(),
+            }));
+            (abi, steady, flaky)
+        };
+        let mut memory = LinearMemory::new(1, None).unwrap();
+        
+        let (mut abi, steady, flaky) = alert_abi(RetryPolicy::new(2, Duration::ZERO));
+        assert!(abi.call_host_function_u32("wasm_send_alert", &[1, 0, 4], &mut memory).is_ok());
+        assert_eq!((steady.get(), flaky.get()), (1, 2));
+        
+        // A retry that would sleep past the budget is not attempted
+        let policy = RetryPolicy::new(2, Duration::from_secs(60)).with_max_total_delay(Duration::from_millis(10));
+        let (mut abi, steady, flaky) = alert_abi(policy);
+        let started = std::time::Instant::now();
+        assert!(abi.call_host_function_u32("wasm_send_alert", &[1, 0, 4], &mut memory).is_err());
+        assert!(started.elapsed() < Duration::from_secs(1));
+        assert_eq!((steady.get(), flaky.get()), (1, 1));
+    }
+
+    #[test]
+    fn test_injected_sensor_failure() {
+        use crate::sandbox::faults::{Fault, FaultInjector};
+        
+        let (mut abi, attempts) = flaky_abi(0, None);
+        abi.get_host_interface_mut().set_fault_injector(FaultInjector::new()
+            .inject("wasm_read_sensor", 1, Fault::Fail("injected bus fault".to_string())));
+        
+        assert_eq!(abi.wasm_read_sensor(&[0]).unwrap(), 21);
+        match abi.wasm_read_sensor(&[0]) {
+            Err(WasmError::Runtime(msg)) => assert_eq!(msg, "injected bus fault"),
+            other => panic!("expected injected fault, got {:?}", other),
+        }
+        assert_eq!(abi.wasm_read_sensor(&[0]).unwrap(), 21);
+        assert_eq!(attempts.get(), 2);
+    }
+
+    #[test]
+    fn test_last_error_after_failed_sensor_read() {
+        let (mut abi, _) = flaky_abi(1, None);
+        let mut memory = LinearMemory::new(1, None).unwrap();
+        assert_eq!(abi.call_host_function_u32("wasm_last_error", &[0, 64], &mut memory).unwrap(), 0);
+        
+        assert!(abi.call_host_function_u32("wasm_read_sensor", &[0], &mut memory).is_err());
+        let len = abi.call_host_function_u32("wasm_last_error", &[0, 64], &mut memory).unwrap();
+        let message = String::from_utf8(memory.read_bytes(0, len).unwrap().to_vec()).unwrap();
+        assert!(message.contains("Sensor bus busy"));
+        
+        // Too small a buffer reports the length without writing
+        assert_eq!(abi.call_host_function_u32("wasm_last_error", &[128, 4], &mut memory).unwrap(), len);
+        assert_eq!(memory.read_u32(128).unwrap(), 0);
+    }
+
+    #[test]
+    fn test_memory_grow_respects_sandbox_limit() {
+        let mut sandbox = Sandbox::new(ResourceLimits::default().with_memory_limit(2));
+        sandbox.grant_capability(Capability::MemoryGrow);
+        let mut abi = WasmABI::new(sandbox);
+        let mut memory = LinearMemory::new(1, None).unwrap();
+        
+        assert_eq!(abi.call_host_function_u32("wasm_memory_grow", &[2], &mut memory).unwrap(), u32::MAX);
+        assert_eq!(memory.size(), 1);
+        assert_eq!(abi.call_host_function_u32("wasm_memory_grow", &[1], &mut memory).unwrap(), 1);
+        assert_eq!(abi.call_host_function_u32("wasm_memory_grow", &[1], &mut memory).unwrap(), u32::MAX);
+        assert_eq!(abi.get_sandbox_mut().get_usage().memory_pages, 2);
+    }
+
+    #[test]
+    fn test_memory_grow_requires_capability() {
+        let mut abi = WasmABI::new(Sandbox::new(ResourceLimits::default()));
+        let mut memory = LinearMemory::new(1, Some(3)).unwrap();
+        assert!(abi.call_host_function_u32("wasm_memory_grow", &[1], &mut memory).is_err());
+        assert_eq!(abi.call_host_function_u32("wasm_memory_size", &[], &mut memory).unwrap(), 1);
+        
+        let mut sandbox = Sandbox::new(ResourceLimits::default());
+        sandbox.grant_capability(Capability::MemoryGrow);
+        let mut abi = WasmABI::new(sandbox);
+        assert_eq!(abi.call_host_function_u32("wasm_memory_grow", &[1], &mut memory).unwrap(), 1);
+        assert_eq!(abi.call_host_function_u32("wasm_memory_size", &[], &mut memory).unwrap(), 2);
+        assert_eq!(abi.get_sandbox_mut().get_usage().memory_pages, 2);
+        
+        // Growing past the maximum reports -1 and leaves memory unchanged
+        assert_eq!(abi.call_host_function_u32("wasm_memory_grow", &[5], &mut memory).unwrap(), u32::MAX);
+        assert_eq!(memory.size(), 2);
+    }
+
+    #[test]
+    fn test_read_sensor_ex() {
+        let mut sandbox = Sandbox::new(ResourceLimits::default());
+        sandbox.grant_capability(Capability::ReadSensor(SensorType::Humidity));
+        sandbox.grant_capability(Capability::WallClock);
+        let mut abi = WasmABI::new(sandbox);
+        abi.get_host_interface_mut().set_sensor_series(1, vec![55]);
+        let mut memory = LinearMemory::new(1, None).unwrap();
+        
+        let before = abi.get_host_interface().get_time().unwrap() as u64;
+        assert_eq!(abi.call_host_function_u32("wasm_read_sensor_ex", &[1, 16], &mut memory).unwrap(), 0);
+        let after = abi.get_host_interface().get_time().unwrap() as u64;
+        
+        assert_eq!(memory.read_u32(16).unwrap(), 55);
+        let timestamp = memory.read_u64(20).unwrap();
+        assert!((before..=after).contains(&timestamp));
+        assert_eq!(memory.read_u8(28).unwrap(), 1); // %RH
+        assert_eq!(memory.read_u8(29).unwrap(), 1);
+        
+        // Other sensors stay gated by their own capability
+        assert!(abi.call_host_function_u32("wasm_read_sensor_ex", &[0, 16], &mut memory).is_err());
+    }
+
+    #[test]
+    fn test_read_sensor_ex_without_wall_clock() {
+        let mut sandbox = Sandbox::new(ResourceLimits::default());
+        sandbox.grant_capability(Capability::ReadSensor(SensorType::Humidity));
+        let mut abi = WasmABI::new(sandbox);
+        let mut memory = LinearMemory::new(1, None).unwrap();
+        memory.write_u64(20, u64::MAX).unwrap();
+        
+        assert_eq!(abi.call_host_function_u32("wasm_read_sensor_ex", &[1, 16], &mut memory).unwrap(), 0);
+        assert_eq!(memory.read_u64(20).unwrap(), 0);
+        assert_eq!(memory.read_u8(29).unwrap(), 1);
+    }
+
+    /// Records the URL and body length of each request instead of sending it.
+    #[derive(Debug)]
+    struct MockHttpClient {
+        requests: Rc<RefCell<Vec<String>>>,
+    }
+
+    impl HttpClient for MockHttpClient {
+        fn post(&self, url: &str, body: &[u8]) -> Result<u16> {
+            self.requests.borrow_mut().push(format!("{} ({} bytes)", url, body.len()));
+            Ok(202)
+        }
+    }
+
+    #[test]
+    fn test_http_post_network_policy() {
+        let mut sandbox = Sandbox::new(ResourceLimits::default());
+        sandbox.grant_capability(Capability::NetworkAccess);
+        let mut abi = WasmABI::new(sandbox).with_network_policy(NetworkPolicy {
+            allow_outbound: true,
+            allowed_hosts: vec!["telemetry.example.com".to_string()],
+            ..NetworkPolicy::default()
+        });
+        let requests = Rc::new(RefCell::new(Vec::new()));
+        abi.get_host_interface_mut().set_http_client(Box::new(MockHttpClient { requests: requests.clone() }));
+        
+        let mut memory = LinearMemory::new(1, None).unwrap();
+        let allowed = b"https://telemetry.example.com/ingest";
+        let denied = b"https://attacker.example.net/ingest";
+        memory.write_bytes(0, allowed).unwrap();
+        memory.write_bytes(64, denied).unwrap();
+        memory.write_bytes(128, b"42").unwrap();
+        
+        let status = abi.call_host_function_u32("wasm_http_post", &[0, allowed.len() as u32, 128, 2], &mut memory);
+        assert_eq!(status.unwrap(), 202);
+        let err = abi.call_host_function_u32("wasm_http_post", &[64, denied.len() as u32, 128, 2], &mut memory).unwrap_err();
+        assert!(err.to_string().contains("not allowed by network policy"));
+        
+        assert_eq!(*requests.borrow(), vec!["https://telemetry.example.com/ingest (2 bytes)".to_string()]);
+    }
+
+    /// Returns the length of the string at (ptr, len).
+    #[derive(Debug)]
+    struct EchoFn;
+
+    impl HostFn for EchoFn {
+        fn call(&self, args: &[AbiValue], memory: &mut LinearMemory, _sandbox: &mut Sandbox) -> Result<AbiValue> {
+            let (ptr, len) = match args {
+                [ptr, len] => (ptr.as_u32()?, len.as_u32()?),
+                _ => return Err(WasmError::Runtime("wasm_echo requires 2 arguments".to_string())),
+            };
+            let message = std::str::from_utf8(memory.read_bytes(ptr, len)?)
+                .map_err(|_| WasmError::Runtime("invalid UTF-8".to_string()))?;
+            Ok(AbiValue::from(message.chars().count() as u32))
+        }
+        
+        fn required_capability(&self) -> Option<Capability> {
+            Some(Capability::Log)
+        }
+    }
+
+    #[test]
+    fn test_custom_host_function() {
+        let mut memory = LinearMemory::new(1, None).unwrap();
+        memory.write_bytes(16, "héllo".as_bytes()).unwrap();
+        let args = [16, "héllo".len() as u32];
+        
+        let mut abi = WasmABI::new(Sandbox::new(ResourceLimits::default()));
+        abi.register_host_function("wasm_echo", Box::new(EchoFn));
+        assert!(abi.call_host_function_u32("wasm_echo", &args, &mut memory).is_err());
+        
+        let mut sandbox = Sandbox::new(ResourceLimits::default());
+        sandbox.grant_capability(Capability::Log);
+        let mut abi = WasmABI::new(sandbox);
+        abi.register_host_function("wasm_echo", Box::new(EchoFn));
+        assert_eq!(abi.call_host_function_u32("wasm_echo", &args, &mut memory).unwrap(), 5);
+        assert!(abi.call_host_function_u32("wasm_missing", &[], &mut memory).is_err());
+    }
+
+    #[test]
+    fn test_kv_storage() {
+        let mut sandbox = Sandbox::new(ResourceLimits::default());
+        sandbox.grant_capability(Capability::Storage);
+        let mut abi = WasmABI::new(sandbox);
+        abi.get_host_interface_mut().set_kv_quota(16);
+        let mut memory = LinearMemory::new(1, None).unwrap();
+        memory.write_bytes(0, b"mode").unwrap();
+        memory.write_bytes(16, b"heat").unwrap();
+        
+        assert_eq!(abi.call_host_function_u32("wasm_kv_get", &[0, 4, 32, 8], &mut memory).unwrap(), u32::MAX);
+        assert_eq!(abi.call_host_function_u32("wasm_kv_set", &[0, 4, 16, 4], &mut memory).unwrap(), 0);
+        assert_eq!(abi.call_host_function_u32("wasm_kv_get", &[0, 4, 32, 8], &mut memory).unwrap(), 4);
+        assert_eq!(memory.read_bytes(32, 4).unwrap(), b"heat");
+        
+        // 4 key bytes + 13 value bytes is over the 16 byte quota
+        match abi.call_host_function_u32("wasm_kv_set", &[0, 4, 16, 13], &mut memory) {
+            Err(WasmError::Runtime(msg)) => assert!(msg.contains("quota")),
+            other => panic!("expected quota rejection, got {:?}", other),
+        }
+        assert_eq!(abi.call_host_function_u32("wasm_kv_get", &[0, 4, 32, 8], &mut memory).unwrap(), 4);
+    }
+
+    #[test]
+    fn test_sensor_subscribe_and_poll() {
+        let mut sandbox = Sandbox::new(ResourceLimits::default());
+        sandbox.grant_capability(Capability::ReadSensor(SensorType::Temperature));
+        let mut abi = WasmABI::new(sandbox);
+        let mut memory = LinearMemory::new(1, None).unwrap();
+        
+        let id = abi.call_host_function_u32("wasm_sensor_subscribe", &[0, 30, 0], &mut memory).unwrap();
+        assert!(abi.call_host_function_u32("wasm_sensor_subscribe", &[1, 50, 0], &mut memory).is_err());
+        
+        abi.get_host_interface_mut().update_sensor(0, 35);
+        let count = abi.call_host_function_u32("wasm_poll_events", &[64, 4], &mut memory).unwrap();
+        assert_eq!(count, 1);
+        assert_eq!(memory.read_u32(64).unwrap(), id);
+        assert_eq!(memory.read_u32(68).unwrap(), 0);
+        assert_eq!(memory.read_u32(72).unwrap(), 35);
+        
+        assert_eq!(abi.call_host_function_u32("wasm_poll_events", &[64, 4], &mut memory).unwrap(), 0);
+    }
+
+    /// Backs a 4-byte register at 0x1000 with `wasm_random`.
+    struct RandomRegister {
+        abi: Rc<RefCell<WasmABI>>,
+        scratch: LinearMemory,
+    }
+
+    impl crate::interpreter::MemoryMediator for RandomRegister {
+        fn on_read(&mut self, address: u32, _size: u32) -> Option<Vec<u8>> {
+            if address != 0x1000 {
+                return None;
+            }
+            let value = self.abi.borrow_mut().call_host_function_u32("wasm_random", &[], &mut self.scratch).unwrap();
+            Some(value.to_le_bytes().to_vec())
+        }
+
+        fn on_write(&mut self, _address: u32, _bytes: &[u8]) -> bool {
+            false
+        }
+    }
+
+    #[test]
+    fn test_seeded_random_replay() {
+        use crate::debugger::tracer::{ExecutionTracer, TraceFormat};
+        use crate::interpreter::{Interpreter, Value};
+        use crate::parser::{CodeSection, Function, FunctionType, ValueType, WasmModule};
+        use super::host_interface::SeededRng;
+        
+        // Runs a module that loads the random register once per call
+        let run = |seed: u64, tracer: &mut ExecutionTracer| -> Vec<Value> {
+            let mut sandbox = Sandbox::new(ResourceLimits::default());
+            sandbox.grant_capability(Capability::Random);
+            let mut abi = WasmABI::new(sandbox);
+            abi.get_host_interface_mut().set_rng(SeededRng::new(seed));
+            tracer.start_with_host(abi.get_host_interface());
+            
+            let module = WasmModule {
+                types: vec![FunctionType { params: vec![], results: vec![ValueType::I32] }],
+                functions: vec![Function { type_idx: 0 }],
+                code: vec![CodeSection { locals: vec![], body: vec![0x41, 0x80, 0x20, 0x28, 0x02, 0x00, 0x0B] }],
+                ..Default::default()
+            };
+            let register = RandomRegister {
+                abi: Rc::new(RefCell::new(abi)),
+                scratch: LinearMemory::new(1, None).unwrap(),
+            };
+            let mut interpreter = Interpreter::new(module).unwrap().with_memory_mediator(register);
+            let mut memory = LinearMemory::new(1, None).unwrap();
+            (0..4).map(|_| interpreter.invoke(0, &[], Some(&mut memory)).unwrap()[0]).collect()
+        };
+        
+        let mut tracer = ExecutionTracer::new();
+        let first = run(0xC0FFEE, &mut tracer);
+        assert_eq!(tracer.get_trace().metadata.rng_seed, Some(0xC0FFEE));
+        assert!(tracer.export_trace(TraceFormat::Json).contains("\"rng_seed\":12648430"));
+        
+        let seed = tracer.get_trace().metadata.rng_seed.unwrap();
+        let replayed = run(seed, &mut ExecutionTracer::new());
+        assert_eq!(first, replayed);
+        assert_ne!(first[0], first[1]);
+    }
+
+    #[test]
+    fn test_observe_only_records_activity() {
+        use crate::sandbox::Activity;
+        
+        let mut abi = WasmABI::new(Sandbox::observe_only(ResourceLimits::default()));
+        let mut memory = LinearMemory::new(1, None).unwrap();
+        memory.write_bytes(0, b"overheat").unwrap();
+        
+        abi.call_host_function_u32("wasm_read_sensor", &[0], &mut memory).unwrap();
+        abi.call_host_function_u32("wasm_send_alert", &[2, 0, 8], &mut memory).unwrap();
+        abi.get_sandbox_mut().update_memory_usage(4);
+        
+        let sandbox = abi.get_sandbox_mut();
+        let log = sandbox.activity_log().unwrap();
+        assert_eq!(log.ungranted_capabilities(), vec![
+            Capability::ReadSensor(SensorType::Temperature),
+            Capability::SendAlert(AlertLevel::Error),
+        ]);
+        assert!(log.entries().iter().any(|e| matches!(e.activity, Activity::MemoryGrow { from_pages: 0, to_pages: 4 })));
+    }
+}
@@ -1,4 +1,5 @@
-use super::Breakpoint;
+use super::{BreakCondition, Breakpoint, WatchKind, Watchpoint};
+use super::inspector::StateInspector;
 use std::collections::HashMap;
 
 #[derive(Debug)]
@@ -6,6 +7,7 @@ pub struct BreakpointManager {
     breakpoints: HashMap<u32, Breakpoint>,
     next_id: u32,
     address_map: HashMap<u32, u32>, // address -> breakpoint_id
+    watchpoints: HashMap<u32, Watchpoint>,
 }
 
 impl BreakpointManager {
@@ -14,10 +16,20 @@ impl BreakpointManager {
             breakpoints: HashMap::new(),
             next_id: 1,
             address_map: HashMap::new(),
+            watchpoints: HashMap::new(),
         }
     }
 
     pub fn set(&mut self, function_index: u32, instruction_offset: u32) -> u32 {
+        self.insert(function_index, instruction_offset, None)
+    }
+
+    /// Break at the location only while `condition` holds.
+    pub fn set_conditional(&mut self, function_index: u32, instruction_offset: u32, condition: BreakCondition) -> u32 {
+        self.insert(function_index, instruction_offset, Some(condition))
+    }
+
+    fn insert(&mut self, function_index: u32, instruction_offset: u32, condition: Option<BreakCondition>) -> u32 {
         let id = self.next_id;
         self.next_id += 1;
 
@@ -29,6 +41,8 @@ impl BreakpointManager {
             instruction_offset,
             enabled: true,
             hit_count: 0,
+            condition,
+            conditional_hit_count: 0,
         };
 
         self.breakpoints.insert(id, breakpoint);
@@ -69,11 +83,18 @@ impl BreakpointManager {
         self.breakpoints.values().collect()
     }
 
-    pub fn should_break(&mut self, address: u32) -> bool {
+    /// Conditional breakpoints count every hit but only fire when their
+    /// condition holds for `inspector`'s current state.
+    pub fn should_break(&mut self, address: u32, inspector: &StateInspector) -> bool {
         if let Some(&bp_id) = self.address_map.get(&address) {
             if let Some(bp) = self.breakpoints.get_mut(&bp_id) {
                 if bp.enabled {
                     bp.hit_count += 1;
+                    match &bp.condition {
+                        Some(condition) if !condition.evaluate(inspector) => return false,
+                        Some(_) => bp.conditional_hit_count += 1,
+                        None => {}
+                    }
                     return true;
                 }
             }
@@ -85,9 +106,45 @@ impl BreakpointManager {
         self.breakpoints.get(&id).map(|bp| bp.hit_count)
     }
 
+    pub fn get_conditional_hit_count(&self, id: u32) -> Option<u32> {
+        self.breakpoints.get(&id).map(|bp| bp.conditional_hit_count)
+    }
+
+    /// Watchpoint ids share the breakpoint id space.
+    pub fn set_watchpoint(&mut self, address: u32, size: u32, kind: WatchKind) -> u32 {
+        let id = self.next_id;
+        self.next_id += 1;
+        self.watchpoints.insert(id, Watchpoint { id, address, size, kind });
+        id
+    }
+
+    pub fn clear_watchpoint(&mut self, id: u32) -> bool {
+        self.watchpoints.remove(&id).is_some()
+    }
+
+    pub fn list_watchpoints(&self) -> Vec<&Watchpoint> {
+        self.watchpoints.values().collect()
+    }
+
+    /// The lowest-id watchpoint whose range overlaps the access and whose
+    /// kind matches its direction.
+    pub fn check_watchpoints(&self, accessed_addr: u32, accessed_size: u32, is_write: bool) -> Option<u32> {
+        let access_end = accessed_addr as u64 + accessed_size as u64;
+        self.watchpoints.values()
+            .filter(|wp| match wp.kind {
+                WatchKind::Read => !is_write,
+                WatchKind::Write => is_write,
+                WatchKind::ReadWrite => true,
+            })
+            .filter(|wp| (accessed_addr as u64) < wp.address as u64 + wp.size as u64 && (wp.address as u64) < access_end)
+            .map(|wp| wp.id)
+            .min()
+    }
+
     pub fn clear_all(&mut self) {
         self.breakpoints.clear();
         self.address_map.clear();
+        self.watchpoints.clear();
     }
 
     fn calculate_address(&self, function_index: u32, instruction_offset: u32) -> u32 {
@@ -103,32 +160,74 @@ mod tests {
     #[test]
     fn test_breakpoint_management() {
         let mut manager = BreakpointManager::new();
+        let inspector = StateInspector::new();
         
         let id1 = manager.set(0, 10);
         let id2 = manager.set(1, 20);
         
         assert_eq!(manager.list().len(), 2);
         
-        assert!(manager.should_break(10)); // function 0, offset 10
-        assert!(!manager.should_break(15)); // no breakpoint
+        assert!(manager.should_break(10, &inspector)); // function 0, offset 10
+        assert!(!manager.should_break(15, &inspector)); // no breakpoint
         
         assert!(manager.clear(id1));
         assert_eq!(manager.list().len(), 1);
         
-        assert!(!manager.should_break(10)); // breakpoint cleared
+        assert!(!manager.should_break(10, &inspector)); // breakpoint cleared
     }
 
     #[test]
     fn test_breakpoint_enable_disable() {
         let mut manager = BreakpointManager::new();
+        let inspector = StateInspector::new();
         let id = manager.set(0, 10);
         
-        assert!(manager.should_break(10));
+        assert!(manager.should_break(10, &inspector));
         
         manager.disable(id);
-        assert!(!manager.should_break(10));
+        assert!(!manager.should_break(10, &inspector));
         
         manager.enable(id);
-        assert!(manager.should_break(10));
+        assert!(manager.should_break(10, &inspector));
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn test_conditional_breakpoint() {
+        use crate::debugger::{BreakOperand, Comparison};
+
+        let mut manager = BreakpointManager::new();
+        let mut inspector = StateInspector::new();
+        let id = manager.set_conditional(0, 10, BreakCondition::new(BreakOperand::Local(0), Comparison::Eq, 5));
+
+        for value in 0..5 {
+            inspector.update_state(10, 0, vec![value]);
+            assert!(!manager.should_break(10, &inspector));
+        }
+        inspector.update_state(10, 0, vec![5]);
+        assert!(manager.should_break(10, &inspector));
+
+        assert_eq!(manager.get_hit_count(id), Some(6));
+        assert_eq!(manager.get_conditional_hit_count(id), Some(1));
+
+        // a missing local never satisfies the condition
+        inspector.update_state(10, 0, vec![]);
+        assert!(!manager.should_break(10, &inspector));
+    }
+
+    #[test]
+    fn test_write_watchpoint() {
+        let mut manager = BreakpointManager::new();
+        let id = manager.set_watchpoint(8, 4, WatchKind::Write);
+
+        assert_eq!(manager.check_watchpoints(8, 4, true), Some(id));
+        // i32.store at 6 overlaps bytes 8..10
+        assert_eq!(manager.check_watchpoints(6, 4, true), Some(id));
+        assert_eq!(manager.check_watchpoints(64, 4, true), None);
+        assert_eq!(manager.check_watchpoints(12, 4, true), None);
+        // reads don't trigger a write watchpoint
+        assert_eq!(manager.check_watchpoints(8, 4, false), None);
+
+        assert!(manager.clear_watchpoint(id));
+        assert_eq!(manager.check_watchpoints(8, 4, true), None);
+    }
+}
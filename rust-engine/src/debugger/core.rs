@@ -1,6 +1,7 @@
 use super::*;
 use crate::error::{WasmError, Result};
-use crate::parser::WasmModule;
+use crate::decoder;
+use crate::parser::{ImportKind, WasmModule};
 use std::collections::HashMap;
 
 #[derive(Debug)]
@@ -99,16 +100,28 @@ impl DebugContext {
 
     fn parse_debug_info_section(&mut self) {
         // Parse debug_info custom section (DWARF-like)
-        if let Some(debug_data) = self.custom_sections.get("debug_info") {
+        if let Some(debug_data) = self.custom_sections.get("debug_info").cloned() {
             // Parse DWARF debug information
-            self.parse_dwarf_debug_info(debug_data);
+            self.parse_dwarf_debug_info(&debug_data);
         }
     }
 
     fn parse_name_data(&self, data: &[u8]) -> Result<DebugInformation> {
         // Simplified name section parsing
         let mut functions = Vec::new();
-        let mut globals = Vec::new();
+        let globals = self.module.imports.iter()
+            .filter_map(|import| match import.kind {
+                ImportKind::Global { value_type, .. } => Some(value_type),
+                _ => None,
+            })
+            .chain(self.module.globals.iter().map(|global| global.value_type))
+            .enumerate()
+            .map(|(index, value_type)| GlobalDebugInfo {
+                index: index as u32,
+                name: None,
+                type_name: decoder::value_type_name(value_type).to_string(),
+            })
+            .collect();
         let mut locals = Vec::new();
         
         // In real implementation, would parse the actual name section format
@@ -314,6 +327,7 @@ mod tests {
             memory: None,
             exports: vec![],
             code: vec![],
+            ..Default::default()
         };
         
         let mut context = DebugContext::new(module);
@@ -330,6 +344,7 @@ mod tests {
             memory: None,
             exports: vec![],
             code: vec![],
+            ..Default::default()
         };
         
         let context = DebugContext::new(module);
@@ -347,6 +362,7 @@ mod tests {
             memory: None,
             exports: vec![],
             code: vec![],
+            ..Default::default()
         };
         
         let context = DebugContext::new(module);
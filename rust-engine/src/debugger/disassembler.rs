@@ -0,0 +1,140 @@
+use super::tracer::Hotspot;
+use crate::decoder::{self, Instruction};
+use crate::error::Result;
+use std::collections::HashMap;
+
+/// Disassemble a function body into one instruction per line.
+pub fn disassemble(body: &[u8]) -> Result<String> {
+    let instructions = decoder::decode_function_body(body)?;
+    let mut output = String::new();
+    let mut depth = 0;
+
+    for instruction in &instructions {
+        let indent = indent_for(instruction, &mut depth);
+        output.push_str(&format!("{:06x}  {}{}\n", instruction.offset, indent, instruction.to_text()));
+    }
+
+    Ok(output)
+}
+
+/// Disassemble a function body with per-instruction hit counts and average
+/// time taken from the tracer's hotspots. Hotspots should belong to the
+/// function being disassembled; instructions never reached show zero hits.
+pub fn annotate_disassembly(body: &[u8], hotspots: &[Hotspot]) -> String {
+    let by_offset: HashMap<u32, &Hotspot> = hotspots.iter()
+        .map(|h| (h.instruction_offset, h))
+        .collect();
+
+    let mut output = format!("{:<6}  {:>8}  {:>12}  {}\n", "offset", "hits", "avg", "instruction");
+    let mut depth = 0;
+    let mut offset = 0;
+
+    while offset < body.len() {
+        let instruction = match decoder::decode_instruction(body, offset) {
+            Ok(instruction) => instruction,
+            Err(_) => {
+                output.push_str(&format!("{:06x}  <undecodable {} bytes>\n", offset, body.len() - offset));
+                break;
+            }
+        };
+
+        let (hits, avg) = match by_offset.get(&(instruction.offset as u32)) {
+            Some(hotspot) => (hotspot.hit_count, format!("{:?}", hotspot.avg_time)),
+            None => (0, "-".to_string()),
+        };
+        let indent = indent_for(&instruction, &mut depth);
+
+        output.push_str(&format!("{:06x}  {:>8}  {:>12}  {}{}\n",
+            instruction.offset, hits, avg, indent, instruction.to_text()));
+        offset += instruction.len;
+    }
+
+    output
+}
+
+fn indent_for(instruction: &Instruction, depth: &mut usize) -> String {
+    match instruction.opcode {
+        0x0B => {
+            *depth = depth.saturating_sub(1);
+            "  ".repeat(*depth)
+        }
+        0x05 => "  ".repeat(depth.saturating_sub(1)),
+        0x02..=0x04 => {
+            let indent = "  ".repeat(*depth);
+            *depth += 1;
+            indent
+        }
+        _ => "  ".repeat(*depth),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    fn hotspot(instruction_offset: u32, hit_count: u64) -> Hotspot {
+        Hotspot {
+            function_index: 0,
+            instruction_offset,
+            hit_count,
+            total_time: Duration::from_nanos(50 * hit_count),
+            avg_time: Duration::from_nanos(50),
+        }
+    }
+
+    #[test]
+    fn test_annotate_loop_hotspots() {
+        let body = [
+            0x41, 0x03, // i32.const 3
+            0x21, 0x00, // local.set 0
+            0x03, 0x40, // loop
+            0x20, 0x00, // local.get 0
+            0x41, 0x01, // i32.const 1
+            0x6B, // i32.sub
+            0x22, 0x00, // local.tee 0
+            0x0D, 0x00, // br_if 0
+            0x0B, // end
+            0x0F, // return
+            0x41, 0x07, // i32.const 7 (unreachable)
+            0x1A, // drop
+            0x0B, // end
+        ];
+        // Three trips round the loop
+        let hotspots = [
+            hotspot(0, 1), hotspot(2, 1), hotspot(4, 1), hotspot(6, 3), hotspot(8, 3),
+            hotspot(10, 3), hotspot(11, 3), hotspot(13, 3), hotspot(15, 1), hotspot(16, 1),
+        ];
+
+        let annotated = annotate_disassembly(&body, &hotspots);
+        let lines: Vec<&str> = annotated.lines().collect();
+
+        assert_eq!(lines.len(), 14);
+        assert_eq!(lines[0], "offset      hits           avg  instruction");
+        assert_eq!(lines[1], "000000         1          50ns  i32.const 3");
+        assert_eq!(lines[3], "000004         1          50ns  loop");
+        assert_eq!(lines[6], "00000a         3          50ns    i32.sub");
+        assert_eq!(lines[8], "00000d         3          50ns    br_if 0");
+        assert_eq!(lines[9], "00000f         1          50ns  end");
+        assert_eq!(lines[11], "000011         0             -  i32.const 7");
+        assert_eq!(lines[12], "000013         0             -  drop");
+    }
+
+    #[test]
+    fn test_annotate_malformed_align() {
+        // i32.load with align exponent 40
+        let annotated = annotate_disassembly(&[0x41, 0x00, 0x28, 0x28, 0x00, 0x0B], &[]);
+        assert!(annotated.lines().nth(2).unwrap().ends_with("i32.load align=1099511627776"));
+    }
+
+    #[test]
+    fn test_disassemble_nesting() {
+        let body = [0x02, 0x40, 0x01, 0x0B, 0x0B]; // block, nop, end, end
+        let text = disassemble(&body).unwrap();
+        let lines: Vec<&str> = text.lines().collect();
+
+        assert_eq!(lines[0], "000000  block");
+        assert_eq!(lines[1], "000002    nop");
+        assert_eq!(lines[2], "000003  end");
+    }
+}
@@ -1,6 +1,7 @@
 use super::{DebugInfo, CallFrame};
 use crate::error::{WasmError, Result};
-use crate::memory::LinearMemory;
+use crate::memory::{LinearMemory, MemorySnapshot};
+use crate::parser::DataSegment;
 use std::collections::HashMap;
 
 #[derive(Debug)]
@@ -8,9 +9,25 @@ pub struct StateInspector {
     current_ip: u32,
     stack_pointer: u32,
     locals: Vec<i32>,
+    globals: Vec<i32>,
+    /// Operand stack of the current frame, bottom first.
+    operand_stack: Vec<i32>,
     call_stack: Vec<CallFrame>,
     memory: Option<LinearMemory>,
     registers: HashMap<String, i32>,
+    /// `(address, length)` of each active data segment with a constant offset.
+    data_regions: Vec<(u32, u32)>,
+    snapshots: Vec<StateSnapshot>,
+}
+
+/// Memory and execution state saved for rewinding.
+#[derive(Debug, Clone)]
+struct StateSnapshot {
+    memory: Option<MemorySnapshot>,
+    ip: u32,
+    stack_pointer: u32,
+    locals: Vec<i32>,
+    call_stack: Vec<CallFrame>,
 }
 
 impl StateInspector {
@@ -19,9 +36,13 @@ impl StateInspector {
             current_ip: 0,
             stack_pointer: 0,
             locals: Vec::new(),
+            globals: Vec::new(),
+            operand_stack: Vec::new(),
             call_stack: Vec::new(),
             memory: None,
             registers: HashMap::new(),
+            data_regions: Vec::new(),
+            snapshots: Vec::new(),
         }
     }
 
@@ -29,12 +50,67 @@ impl StateInspector {
         self.memory = Some(memory);
     }
 
+    /// Record which memory regions the module's data segments pre-initialize.
+    pub fn set_data_segments(&mut self, segments: &[DataSegment]) {
+        self.data_regions = segments.iter()
+            .filter_map(|segment| Some((segment.static_offset()?, segment.bytes.len() as u32)))
+            .collect();
+    }
+
+    /// Save the current memory and execution state; returns the snapshot id.
+    pub fn take_snapshot(&mut self) -> usize {
+        self.snapshots.push(StateSnapshot {
+            memory: self.memory.as_ref().map(|m| m.snapshot()),
+            ip: self.current_ip,
+            stack_pointer: self.stack_pointer,
+            locals: self.locals.clone(),
+            call_stack: self.call_stack.clone(),
+        });
+        self.snapshots.len() - 1
+    }
+
+    /// Restore the state saved as `id`. Later snapshots are kept so the
+    /// user can step forward and rewind again.
+    pub fn rewind_to_snapshot(&mut self, id: usize) -> Result<()> {
+        let snapshot = self.snapshots.get(id)
+            .ok_or_else(|| WasmError::Runtime(format!("No snapshot {}", id)))?;
+
+        if let (Some(memory), Some(saved)) = (self.memory.as_mut(), &snapshot.memory) {
+            memory.restore(saved)?;
+        }
+        self.current_ip = snapshot.ip;
+        self.stack_pointer = snapshot.stack_pointer;
+        self.locals = snapshot.locals.clone();
+        self.call_stack = snapshot.call_stack.clone();
+        Ok(())
+    }
+
     pub fn update_state(&mut self, ip: u32, sp: u32, locals: Vec<i32>) {
         self.current_ip = ip;
         self.stack_pointer = sp;
         self.locals = locals;
     }
 
+    pub fn update_globals(&mut self, globals: Vec<i32>) {
+        self.globals = globals;
+    }
+
+    pub fn update_operand_stack(&mut self, stack: Vec<i32>) {
+        self.operand_stack = stack;
+    }
+
+    pub fn get_local(&self, index: u32) -> Option<i32> {
+        self.locals.get(index as usize).copied()
+    }
+
+    pub fn get_global(&self, index: u32) -> Option<i32> {
+        self.globals.get(index as usize).copied()
+    }
+
+    pub fn stack_top(&self) -> Option<i32> {
+        self.operand_stack.last().copied()
+    }
+
     pub fn push_call_frame(&mut self, function_index: u32, ip: u32, locals_start: u32) {
         let frame = CallFrame {
             function_index,
@@ -182,6 +258,16 @@ impl StateInspector {
             }
         }
 
+        for (i, &(address, length)) in self.data_regions.iter().enumerate() {
+            if address >= start && address < end {
+                annotations.push(MemoryAnnotation {
+                    address,
+                    label: format!("D{}", i),
+                    description: format!("Data segment {} ({} bytes)", i, length),
+                });
+            }
+        }
+
         annotations
     }
 }
@@ -342,4 +428,41 @@ mod tests {
         assert_eq!(frame.function_index, 1);
         assert_eq!(inspector.get_call_depth(), 1);
     }
+
+    #[test]
+    fn test_rewind_to_snapshot() {
+        let mut inspector = StateInspector::new();
+        let mut memory = LinearMemory::new(1, None).unwrap();
+        memory.write_u32(0, 0xCAFE).unwrap();
+        inspector.set_memory(memory);
+        inspector.update_state(10, 20, vec![1]);
+        let id = inspector.take_snapshot();
+
+        inspector.update_state(30, 40, vec![2]);
+        inspector.push_call_frame(1, 30, 0);
+        inspector.memory.as_mut().unwrap().write_u32(0, 0xBEEF).unwrap();
+
+        inspector.rewind_to_snapshot(id).unwrap();
+        assert_eq!(inspector.read_memory_u32(0).unwrap(), 0xCAFE);
+        let state = inspector.get_current_state();
+        assert_eq!(state.instruction_pointer, 10);
+        assert_eq!(state.locals, vec![1]);
+        assert!(state.call_stack.is_empty());
+        assert!(inspector.rewind_to_snapshot(id + 1).is_err());
+    }
+
+    #[test]
+    fn test_data_segment_annotations() {
+        let mut inspector = StateInspector::new();
+        inspector.set_data_segments(&[
+            DataSegment { memory_index: 0, offset_expr: vec![0x41, 0x10, 0x0B], bytes: vec![1, 2, 3] },
+            DataSegment { memory_index: 0, offset_expr: Vec::new(), bytes: vec![4] },
+        ]);
+
+        let annotations = inspector.get_memory_annotations(0x10, 0x20);
+        let data = annotations.iter().find(|a| a.label == "D0").unwrap();
+        assert_eq!(data.address, 0x10);
+        assert_eq!(data.description, "Data segment 0 (3 bytes)");
+        assert!(!annotations.iter().any(|a| a.label == "D1"));
+    }
 }
\ No newline at end of file
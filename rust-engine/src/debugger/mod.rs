@@ -3,6 +3,7 @@ pub mod breakpoints;
 pub mod tracer;
 pub mod inspector;
 pub mod session;
+pub mod disassembler;
 
 use crate::error::{WasmError, Result};
 use std::collections::HashMap;
@@ -37,6 +38,79 @@ pub struct Breakpoint {
     pub instruction_offset: u32,
     pub enabled: bool,
     pub hit_count: u32,
+    /// Only fire when this holds; `None` breaks unconditionally.
+    pub condition: Option<BreakCondition>,
+    /// Times the condition held and the breakpoint fired.
+    pub conditional_hit_count: u32,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum WatchKind {
+    Read,
+    Write,
+    ReadWrite,
+}
+
+/// Breaks on accesses overlapping `size` bytes at `address`.
+#[derive(Debug, Clone)]
+pub struct Watchpoint {
+    pub id: u32,
+    pub address: u32,
+    pub size: u32,
+    pub kind: WatchKind,
+}
+
+/// Value a breakpoint condition reads from the inspected state.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum BreakOperand {
+    Local(u32),
+    Global(u32),
+    StackTop,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Comparison {
+    Eq,
+    Ne,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+}
+
+/// Compares a local, global, or the top of the operand stack to a constant.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct BreakCondition {
+    pub operand: BreakOperand,
+    pub comparison: Comparison,
+    pub value: i32,
+}
+
+impl BreakCondition {
+    pub fn new(operand: BreakOperand, comparison: Comparison, value: i32) -> Self {
+        Self { operand, comparison, value }
+    }
+
+    /// False if the operand isn't available, e.g. an empty stack.
+    pub fn evaluate(&self, inspector: &inspector::StateInspector) -> bool {
+        let actual = match self.operand {
+            BreakOperand::Local(index) => inspector.get_local(index),
+            BreakOperand::Global(index) => inspector.get_global(index),
+            BreakOperand::StackTop => inspector.stack_top(),
+        };
+        let actual = match actual {
+            Some(actual) => actual,
+            None => return false,
+        };
+        match self.comparison {
+            Comparison::Eq => actual == self.value,
+            Comparison::Ne => actual != self.value,
+            Comparison::Lt => actual < self.value,
+            Comparison::Le => actual <= self.value,
+            Comparison::Gt => actual > self.value,
+            Comparison::Ge => actual >= self.value,
+        }
+    }
 }
 
 #[derive(Debug)]
@@ -64,6 +138,12 @@ impl WasmDebugger {
         self.tracer.start();
     }
 
+    /// Enable against `host` so the trace records its RNG seed.
+    pub fn enable_with_host(&mut self, host: &crate::abi::host_interface::HostInterface) {
+        self.enabled = true;
+        self.tracer.start_with_host(host);
+    }
+
     pub fn disable(&mut self) {
         self.enabled = false;
         self.tracer.stop();
@@ -73,6 +153,10 @@ impl WasmDebugger {
         self.breakpoints.set(function_index, offset)
     }
 
+    pub fn set_conditional_breakpoint(&mut self, function_index: u32, offset: u32, condition: BreakCondition) -> u32 {
+        self.breakpoints.set_conditional(function_index, offset, condition)
+    }
+
     pub fn clear_breakpoint(&mut self, id: u32) -> bool {
         self.breakpoints.clear(id)
     }
@@ -81,6 +165,19 @@ impl WasmDebugger {
         self.breakpoints.list()
     }
 
+    pub fn set_watchpoint(&mut self, address: u32, size: u32, kind: WatchKind) -> u32 {
+        self.breakpoints.set_watchpoint(address, size, kind)
+    }
+
+    pub fn clear_watchpoint(&mut self, id: u32) -> bool {
+        self.breakpoints.clear_watchpoint(id)
+    }
+
+    /// Called around each load and store; returns the triggered watchpoint.
+    pub fn check_watchpoints(&self, address: u32, size: u32, is_write: bool) -> Option<u32> {
+        self.breakpoints.check_watchpoints(address, size, is_write)
+    }
+
     pub fn step(&mut self, mode: StepMode) -> Result<DebugInfo> {
         if !self.enabled {
             return Err(WasmError::Runtime("Debugger not enabled".to_string()));
@@ -102,7 +199,7 @@ impl WasmDebugger {
         loop {
             let info = self.step_into()?;
             
-            if self.breakpoints.should_break(info.instruction_pointer) {
+            if self.breakpoints.should_break(info.instruction_pointer, &self.inspector) {
                 return Ok(info);
             }
         }
@@ -116,6 +213,20 @@ impl WasmDebugger {
         self.inspector.read_memory(address, length)
     }
 
+    pub fn attach_memory(&mut self, memory: crate::memory::LinearMemory) {
+        self.inspector.set_memory(memory);
+    }
+
+    /// Save memory and execution state to rewind to later.
+    pub fn take_snapshot(&mut self) -> usize {
+        self.inspector.take_snapshot()
+    }
+
+    pub fn rewind_to_snapshot(&mut self, id: usize) -> Result<DebugInfo> {
+        self.inspector.rewind_to_snapshot(id)?;
+        Ok(self.inspector.get_current_state())
+    }
+
     pub fn get_call_stack(&self) -> Vec<CallFrame> {
         self.inspector.get_call_stack()
     }
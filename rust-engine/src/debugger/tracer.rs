@@ -1,3 +1,5 @@
+use crate::abi::host_interface::HostInterface;
+use crate::sandbox::syscall_trap::{ArgRedactor, SyscallArg};
 use std::collections::VecDeque;
 use std::time::{Duration, Instant};
 
@@ -15,7 +17,8 @@ pub struct TraceEntry {
 pub struct SyscallTrace {
     pub timestamp: Instant,
     pub name: String,
-    pub args: Vec<u32>,
+    /// Arguments with the sandbox's sensitive ones redacted.
+    pub args: Vec<SyscallArg>,
     pub result: Option<u32>,
     pub duration: Duration,
 }
@@ -35,9 +38,16 @@ pub struct ExecutionTrace {
     pub syscalls: VecDeque<SyscallTrace>,
     pub function_calls: VecDeque<FunctionCall>,
     pub hotspots: Vec<Hotspot>,
+    pub metadata: TraceMetadata,
     max_entries: usize,
 }
 
+/// Run parameters needed to replay a trace.
+#[derive(Debug, Clone, Default)]
+pub struct TraceMetadata {
+    pub rng_seed: Option<u64>,
+}
+
 #[derive(Debug, Clone)]
 pub struct Hotspot {
     pub function_index: u32,
@@ -55,6 +65,7 @@ pub struct ExecutionTracer {
     current_function: Option<u32>,
     call_stack: Vec<FunctionCall>,
     hotspot_map: std::collections::HashMap<u32, Hotspot>,
+    redactor: ArgRedactor,
 }
 
 impl ExecutionTracer {
@@ -65,6 +76,7 @@ impl ExecutionTracer {
                 syscalls: VecDeque::with_capacity(1000),
                 function_calls: VecDeque::with_capacity(1000),
                 hotspots: Vec::new(),
+                metadata: TraceMetadata::default(),
                 max_entries: 10000,
             },
             enabled: false,
@@ -72,6 +84,7 @@ impl ExecutionTracer {
             current_function: None,
             call_stack: Vec::new(),
             hotspot_map: std::collections::HashMap::new(),
+            redactor: ArgRedactor::default(),
         }
     }
 
@@ -80,11 +93,24 @@ impl ExecutionTracer {
         self.start_time = Some(Instant::now());
     }
 
+    /// Start tracing a run against `host`, recording its RNG seed so the
+    /// run can be replayed from the trace.
+    pub fn start_with_host(&mut self, host: &HostInterface) {
+        self.trace.metadata.rng_seed = host.current_seed();
+        self.start();
+    }
+
     pub fn stop(&mut self) {
         self.enabled = false;
         self.update_hotspots();
     }
 
+    /// Redact the given argument indices of `pattern` in traced syscalls,
+    /// on top of the syscall log's defaults.
+    pub fn mark_sensitive(&mut self, pattern: &str, arg_indices: &[usize]) {
+        self.redactor.mark_sensitive(pattern, arg_indices);
+    }
+
     pub fn trace_instruction(&mut self) {
         if !self.enabled {
             return;
@@ -106,6 +132,25 @@ impl ExecutionTracer {
         self.update_hotspot(0, 0, start.elapsed());
     }
 
+    /// Record an instruction executed at `offset` within `function_index`.
+    pub fn trace_instruction_at(&mut self, function_index: u32, offset: u32, opcode: u8, execution_time: Duration) {
+        if !self.enabled {
+            return;
+        }
+
+        let entry = TraceEntry {
+            timestamp: Instant::now(),
+            instruction_pointer: offset,
+            opcode,
+            args: Vec::new(),
+            stack_depth: self.call_stack.len() as u32,
+            execution_time,
+        };
+
+        self.add_instruction_trace(entry);
+        self.update_hotspot(function_index, offset, execution_time);
+    }
+
     pub fn trace_syscall(&mut self, name: &str, args: &[u32]) -> Option<u32> {
         if !self.enabled {
             return None;
@@ -123,7 +168,7 @@ impl ExecutionTracer {
         let syscall = SyscallTrace {
             timestamp: start,
             name: name.to_string(),
-            args: args.to_vec(),
+            args: self.redactor.redact(name, args),
             result,
             duration: start.elapsed(),
         };
@@ -257,10 +302,15 @@ impl ExecutionTracer {
 
     fn export_json(&self) -> String {
         // Simplified JSON export
-        format!("{{\"instructions\":{},\"syscalls\":{},\"functions\":{}}}", 
+        let seed = match self.trace.metadata.rng_seed {
+            Some(seed) => seed.to_string(),
+            None => "null".to_string(),
+        };
+        format!("{{\"instructions\":{},\"syscalls\":{},\"functions\":{},\"rng_seed\":{}}}", 
                 self.trace.instructions.len(),
                 self.trace.syscalls.len(),
-                self.trace.function_calls.len())
+                self.trace.function_calls.len(),
+                seed)
     }
 
     fn export_csv(&self) -> String {
@@ -380,6 +430,20 @@ mod tests {
         assert_eq!(trace.function_calls.len(), 1);
     }
 
+    #[test]
+    fn test_trace_syscall_redacts_sensitive_args() {
+        let mut tracer = ExecutionTracer::new();
+        tracer.mark_sensitive("wasm_http_post", &[2]);
+        tracer.start();
+
+        tracer.trace_syscall("wasm_crypto_sign", &[64, 32, 7]);
+        tracer.trace_syscall("wasm_http_post", &[1, 2, 3]);
+
+        let syscalls = &tracer.get_trace().syscalls;
+        assert_eq!(syscalls[0].args, vec![SyscallArg::Redacted, SyscallArg::Redacted, SyscallArg::Value(7)]);
+        assert_eq!(syscalls[1].args, vec![SyscallArg::Value(1), SyscallArg::Value(2), SyscallArg::Redacted]);
+    }
+
     #[test]
     fn test_call_graph() {
         let mut graph = CallGraph::new();
@@ -0,0 +1,583 @@
+use crate::error::{WasmError, Result};
+use crate::parser::ValueType;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum BlockType {
+    Empty,
+    Value(ValueType),
+    TypeIndex(u32),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Immediate {
+    U32(u32),
+    I32(i32),
+    I64(i64),
+    F32(f32),
+    F64(f64),
+    Block(BlockType),
+    MemArg { align: u32, offset: u32 },
+    BrTable { targets: Vec<u32>, default: u32 },
+    /// Result types of a typed `select`.
+    SelectTypes(Vec<ValueType>),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Instruction {
+    pub offset: usize,
+    pub opcode: u8,
+    pub immediates: Vec<Immediate>,
+    pub len: usize,
+}
+
+impl Instruction {
+    pub fn mnemonic(&self) -> &'static str {
+        if self.opcode == 0xFC {
+            if let Some(Immediate::U32(sub)) = self.immediates.first() {
+                return prefixed_mnemonic(*sub);
+            }
+        }
+        mnemonic(self.opcode)
+    }
+
+    /// Render the instruction as text, e.g. `i32.load offset=4 align=2`.
+    pub fn to_text(&self) -> String {
+        let mut text = self.mnemonic().to_string();
+        let immediates = if self.opcode == 0xFC { &self.immediates[1..] } else { &self.immediates[..] };
+
+        for imm in immediates {
+            let part = match imm {
+                Immediate::U32(v) => v.to_string(),
+                Immediate::I32(v) => v.to_string(),
+                Immediate::I64(v) => v.to_string(),
+                Immediate::F32(v) => v.to_string(),
+                Immediate::F64(v) => v.to_string(),
+                Immediate::Block(BlockType::Empty) => continue,
+                Immediate::Block(BlockType::Value(vt)) => format!("(result {})", value_type_name(*vt)),
+                Immediate::Block(BlockType::TypeIndex(idx)) => format!("(type {})", idx),
+                Immediate::MemArg { align, offset } => {
+                    if *offset == 0 {
+                        format!("align={}", align_text(*align))
+                    } else {
+                        format!("offset={} align={}", offset, align_text(*align))
+                    }
+                }
+                Immediate::BrTable { targets, default } => {
+                    let mut labels: Vec<String> = targets.iter().map(|t| t.to_string()).collect();
+                    labels.push(default.to_string());
+                    labels.join(" ")
+                }
+                Immediate::SelectTypes(types) => {
+                    let names: Vec<&str> = types.iter().map(|vt| value_type_name(*vt)).collect();
+                    format!("(result {})", names.join(" "))
+                }
+            };
+            text.push(' ');
+            text.push_str(&part);
+        }
+
+        text
+    }
+}
+
+/// Decode a whole function body into instructions.
+pub fn decode_function_body(body: &[u8]) -> Result<Vec<Instruction>> {
+    let mut instructions = Vec::new();
+    let mut offset = 0;
+
+    while offset < body.len() {
+        let instruction = decode_instruction(body, offset)?;
+        offset += instruction.len;
+        instructions.push(instruction);
+    }
+
+    Ok(instructions)
+}
+
+/// Decode the single instruction starting at `offset`, including its immediates.
+pub fn decode_instruction(bytes: &[u8], offset: usize) -> Result<Instruction> {
+    let opcode = *bytes.get(offset).ok_or(WasmError::InvalidModule)?;
+    let mut pos = offset + 1;
+    let mut immediates = Vec::new();
+
+    match opcode {
+        // block, loop, if
+        0x02..=0x04 => {
+            let block_type = match *bytes.get(pos).ok_or(WasmError::InvalidModule)? {
+                0x40 => {
+                    pos += 1;
+                    BlockType::Empty
+                }
+                0x7C..=0x7F => {
+                    let vt = value_type_from_byte(bytes[pos]).ok_or(WasmError::InvalidModule)?;
+                    pos += 1;
+                    BlockType::Value(vt)
+                }
+                _ => {
+                    let (idx, len) = read_leb128_i64(bytes, pos)?;
+                    if idx < 0 {
+                        return Err(WasmError::InvalidModule);
+                    }
+                    pos += len;
+                    BlockType::TypeIndex(idx as u32)
+                }
+            };
+            immediates.push(Immediate::Block(block_type));
+        }
+        // br, br_if, call, local.*, global.*, ref.func, table.get/set
+        0x0C | 0x0D | 0x10 | 0x20..=0x24 | 0x25 | 0x26 | 0xD2 => {
+            let (idx, len) = read_leb128_u32(bytes, pos)?;
+            pos += len;
+            immediates.push(Immediate::U32(idx));
+        }
+        // br_table
+        0x0E => {
+            let (count, len) = read_leb128_u32(bytes, pos)?;
+            pos += len;
+            let mut targets = Vec::with_capacity(count.min(1024) as usize);
+            for _ in 0..count {
+                let (target, len) = read_leb128_u32(bytes, pos)?;
+                pos += len;
+                targets.push(target);
+            }
+            let (default, len) = read_leb128_u32(bytes, pos)?;
+            pos += len;
+            immediates.push(Immediate::BrTable { targets, default });
+        }
+        // call_indirect: type index, table index
+        0x11 => {
+            for _ in 0..2 {
+                let (idx, len) = read_leb128_u32(bytes, pos)?;
+                pos += len;
+                immediates.push(Immediate::U32(idx));
+            }
+        }
+        // typed select
+        0x1C => {
+            let (count, len) = read_leb128_u32(bytes, pos)?;
+            pos += len;
+            if bytes.len() < pos + count as usize {
+                return Err(WasmError::InvalidModule);
+            }
+            let types = bytes[pos..pos + count as usize].iter()
+                .map(|&b| value_type_from_byte(b).ok_or(WasmError::InvalidModule))
+                .collect::<Result<Vec<_>>>()?;
+            immediates.push(Immediate::SelectTypes(types));
+            pos += count as usize;
+        }
+        // loads and stores
+        0x28..=0x3E => {
+            let (align, len) = read_leb128_u32(bytes, pos)?;
+            pos += len;
+            let (mem_offset, len) = read_leb128_u32(bytes, pos)?;
+            pos += len;
+            immediates.push(Immediate::MemArg { align, offset: mem_offset });
+        }
+        // memory.size, memory.grow: reserved memory index
+        0x3F | 0x40 => {
+            bytes.get(pos).ok_or(WasmError::InvalidModule)?;
+            pos += 1;
+        }
+        0x41 => {
+            let (value, len) = read_leb128_i64(bytes, pos)?;
+            pos += len;
+            immediates.push(Immediate::I32(value as i32));
+        }
+        0x42 => {
+            let (value, len) = read_leb128_i64(bytes, pos)?;
+            pos += len;
+            immediates.push(Immediate::I64(value));
+        }
+        0x43 => {
+            let raw = bytes.get(pos..pos + 4).ok_or(WasmError::InvalidModule)?;
+            pos += 4;
+            immediates.push(Immediate::F32(f32::from_le_bytes([raw[0], raw[1], raw[2], raw[3]])));
+        }
+        0x44 => {
+            let raw = bytes.get(pos..pos + 8).ok_or(WasmError::InvalidModule)?;
+            pos += 8;
+            let mut buf = [0u8; 8];
+            buf.copy_from_slice(raw);
+            immediates.push(Immediate::F64(f64::from_le_bytes(buf)));
+        }
+        // ref.null heap type
+        0xD0 => {
+            let heap_type = *bytes.get(pos).ok_or(WasmError::InvalidModule)?;
+            pos += 1;
+            immediates.push(Immediate::U32(heap_type as u32));
+        }
+        0xFC => {
+            let (sub, len) = read_leb128_u32(bytes, pos)?;
+            pos += len;
+            immediates.push(Immediate::U32(sub));
+            let operand_count = match sub {
+                0..=7 => 0,     // saturating truncation
+                8 => 2,         // memory.init: data index, memory
+                9 => 1,         // data.drop
+                10 => 2,        // memory.copy: dst, src memory
+                11 => 1,        // memory.fill
+                12 | 14 => 2,   // table.init, table.copy
+                13 | 15..=17 => 1, // elem.drop, table.grow/size/fill
+                _ => return Err(WasmError::InvalidInstruction(opcode)),
+            };
+            for _ in 0..operand_count {
+                let (idx, len) = read_leb128_u32(bytes, pos)?;
+                pos += len;
+                immediates.push(Immediate::U32(idx));
+            }
+        }
+        _ => {
+            if mnemonic(opcode) == "unknown" {
+                return Err(WasmError::InvalidInstruction(opcode));
+            }
+        }
+    }
+
+    Ok(Instruction {
+        offset,
+        opcode,
+        immediates,
+        len: pos - offset,
+    })
+}
+
+/// Read an unsigned LEB128 value at `offset`, returning the value and its encoded length.
+pub fn read_leb128_u32(bytes: &[u8], offset: usize) -> Result<(u32, usize)> {
+    let mut result = 0u32;
+    let mut shift = 0;
+    let mut pos = offset;
+
+    loop {
+        let byte = *bytes.get(pos).ok_or(WasmError::InvalidModule)?;
+        pos += 1;
+        result |= ((byte & 0x7F) as u32) << shift;
+
+        if byte & 0x80 == 0 {
+            break;
+        }
+
+        shift += 7;
+        if shift >= 35 {
+            return Err(WasmError::InvalidModule);
+        }
+    }
+
+    Ok((result, pos - offset))
+}
+
+/// Like `read_leb128_u32`, for byte scanners that skip rather than fail on
+/// truncated or overlong encodings.
+pub fn decode_leb128_u32_at(bytecode: &[u8], offset: usize) -> Option<(u32, usize)> {
+    read_leb128_u32(bytecode, offset).ok()
+}
+
+/// Read a signed LEB128 value (up to 64 bits) at `offset`.
+pub fn read_leb128_i64(bytes: &[u8], offset: usize) -> Result<(i64, usize)> {
+    let mut result = 0i64;
+    let mut shift = 0;
+    let mut pos = offset;
+
+    loop {
+        let byte = *bytes.get(pos).ok_or(WasmError::InvalidModule)?;
+        pos += 1;
+        result |= ((byte & 0x7F) as i64) << shift;
+        shift += 7;
+
+        if byte & 0x80 == 0 {
+            if shift < 64 && byte & 0x40 != 0 {
+                result |= -1i64 << shift;
+            }
+            break;
+        }
+
+        if shift >= 70 {
+            return Err(WasmError::InvalidModule);
+        }
+    }
+
+    Ok((result, pos - offset))
+}
+
+fn value_type_from_byte(byte: u8) -> Option<ValueType> {
+    match byte {
+        0x7F => Some(ValueType::I32),
+        0x7E => Some(ValueType::I64),
+        0x7D => Some(ValueType::F32),
+        0x7C => Some(ValueType::F64),
+        0x70 => Some(ValueType::FuncRef),
+        0x6F => Some(ValueType::ExternRef),
+        _ => None,
+    }
+}
+
+pub(crate) fn value_type_name(vt: ValueType) -> &'static str {
+    match vt {
+        ValueType::I32 => "i32",
+        ValueType::I64 => "i64",
+        ValueType::F32 => "f32",
+        ValueType::F64 => "f64",
+        ValueType::FuncRef => "funcref",
+        ValueType::ExternRef => "externref",
+    }
+}
+
+fn prefixed_mnemonic(sub: u32) -> &'static str {
+    match sub {
+        0 => "i32.trunc_sat_f32_s",
+        1 => "i32.trunc_sat_f32_u",
+        2 => "i32.trunc_sat_f64_s",
+        3 => "i32.trunc_sat_f64_u",
+        4 => "i64.trunc_sat_f32_s",
+        5 => "i64.trunc_sat_f32_u",
+        6 => "i64.trunc_sat_f64_s",
+        7 => "i64.trunc_sat_f64_u",
+        8 => "memory.init",
+        9 => "data.drop",
+        10 => "memory.copy",
+        11 => "memory.fill",
+        12 => "table.init",
+        13 => "elem.drop",
+        14 => "table.copy",
+        15 => "table.grow",
+        16 => "table.size",
+        17 => "table.fill",
+        _ => "unknown",
+    }
+}
+
+pub fn mnemonic(opcode: u8) -> &'static str {
+    match opcode {
+        0x00 => "unreachable",
+        0x01 => "nop",
+        0x02 => "block",
+        0x03 => "loop",
+        0x04 => "if",
+        0x05 => "else",
+        0x0B => "end",
+        0x0C => "br",
+        0x0D => "br_if",
+        0x0E => "br_table",
+        0x0F => "return",
+        0x10 => "call",
+        0x11 => "call_indirect",
+        0x1A => "drop",
+        0x1B => "select",
+        0x1C => "select",
+        0x20 => "local.get",
+        0x21 => "local.set",
+        0x22 => "local.tee",
+        0x23 => "global.get",
+        0x24 => "global.set",
+        0x25 => "table.get",
+        0x26 => "table.set",
+        0x28 => "i32.load",
+        0x29 => "i64.load",
+        0x2A => "f32.load",
+        0x2B => "f64.load",
+        0x2C => "i32.load8_s",
+        0x2D => "i32.load8_u",
+        0x2E => "i32.load16_s",
+        0x2F => "i32.load16_u",
+        0x30 => "i64.load8_s",
+        0x31 => "i64.load8_u",
+        0x32 => "i64.load16_s",
+        0x33 => "i64.load16_u",
+        0x34 => "i64.load32_s",
+        0x35 => "i64.load32_u",
+        0x36 => "i32.store",
+        0x37 => "i64.store",
+        0x38 => "f32.store",
+        0x39 => "f64.store",
+        0x3A => "i32.store8",
+        0x3B => "i32.store16",
+        0x3C => "i64.store8",
+        0x3D => "i64.store16",
+        0x3E => "i64.store32",
+        0x3F => "memory.size",
+        0x40 => "memory.grow",
+        0x41 => "i32.const",
+        0x42 => "i64.const",
+        0x43 => "f32.const",
+        0x44 => "f64.const",
+        0x45 => "i32.eqz",
+        0x46 => "i32.eq",
+        0x47 => "i32.ne",
+        0x48 => "i32.lt_s",
+        0x49 => "i32.lt_u",
+        0x4A => "i32.gt_s",
+        0x4B => "i32.gt_u",
+        0x4C => "i32.le_s",
+        0x4D => "i32.le_u",
+        0x4E => "i32.ge_s",
+        0x4F => "i32.ge_u",
+        0x50 => "i64.eqz",
+        0x51 => "i64.eq",
+        0x52 => "i64.ne",
+        0x53 => "i64.lt_s",
+        0x54 => "i64.lt_u",
+        0x55 => "i64.gt_s",
+        0x56 => "i64.gt_u",
+        0x57 => "i64.le_s",
+        0x58 => "i64.le_u",
+        0x59 => "i64.ge_s",
+        0x5A => "i64.ge_u",
+        0x5B => "f32.eq",
+        0x5C => "f32.ne",
+        0x5D => "f32.lt",
+        0x5E => "f32.gt",
+        0x5F => "f32.le",
+        0x60 => "f32.ge",
+        0x61 => "f64.eq",
+        0x62 => "f64.ne",
+        0x63 => "f64.lt",
+        0x64 => "f64.gt",
+        0x65 => "f64.le",
+        0x66 => "f64.ge",
+        0x67 => "i32.clz",
+        0x68 => "i32.ctz",
+        0x69 => "i32.popcnt",
+        0x6A => "i32.add",
+        0x6B => "i32.sub",
+        0x6C => "i32.mul",
+        0x6D => "i32.div_s",
+        0x6E => "i32.div_u",
+        0x6F => "i32.rem_s",
+        0x70 => "i32.rem_u",
+        0x71 => "i32.and",
+        0x72 => "i32.or",
+        0x73 => "i32.xor",
+        0x74 => "i32.shl",
+        0x75 => "i32.shr_s",
+        0x76 => "i32.shr_u",
+        0x77 => "i32.rotl",
+        0x78 => "i32.rotr",
+        0x79 => "i64.clz",
+        0x7A => "i64.ctz",
+        0x7B => "i64.popcnt",
+        0x7C => "i64.add",
+        0x7D => "i64.sub",
+        0x7E => "i64.mul",
+        0x7F => "i64.div_s",
+        0x80 => "i64.div_u",
+        0x81 => "i64.rem_s",
+        0x82 => "i64.rem_u",
+        0x83 => "i64.and",
+        0x84 => "i64.or",
+        0x85 => "i64.xor",
+        0x86 => "i64.shl",
+        0x87 => "i64.shr_s",
+        0x88 => "i64.shr_u",
+        0x89 => "i64.rotl",
+        0x8A => "i64.rotr",
+        0x8B => "f32.abs",
+        0x8C => "f32.neg",
+        0x8D => "f32.ceil",
+        0x8E => "f32.floor",
+        0x8F => "f32.trunc",
+        0x90 => "f32.nearest",
+        0x91 => "f32.sqrt",
+        0x92 => "f32.add",
+        0x93 => "f32.sub",
+        0x94 => "f32.mul",
+        0x95 => "f32.div",
+        0x96 => "f32.min",
+        0x97 => "f32.max",
+        0x98 => "f32.copysign",
+        0x99 => "f64.abs",
+        0x9A => "f64.neg",
+        0x9B => "f64.ceil",
+        0x9C => "f64.floor",
+        0x9D => "f64.trunc",
+        0x9E => "f64.nearest",
+        0x9F => "f64.sqrt",
+        0xA0 => "f64.add",
+        0xA1 => "f64.sub",
+        0xA2 => "f64.mul",
+        0xA3 => "f64.div",
+        0xA4 => "f64.min",
+        0xA5 => "f64.max",
+        0xA6 => "f64.copysign",
+        0xA7 => "i32.wrap_i64",
+        0xA8 => "i32.trunc_f32_s",
+        0xA9 => "i32.trunc_f32_u",
+        0xAA => "i32.trunc_f64_s",
+        0xAB => "i32.trunc_f64_u",
+        0xAC => "i64.extend_i32_s",
+        0xAD => "i64.extend_i32_u",
+        0xAE => "i64.trunc_f32_s",
+        0xAF => "i64.trunc_f32_u",
+        0xB0 => "i64.trunc_f64_s",
+        0xB1 => "i64.trunc_f64_u",
+        0xB2 => "f32.convert_i32_s",
+        0xB3 => "f32.convert_i32_u",
+        0xB4 => "f32.convert_i64_s",
+        0xB5 => "f32.convert_i64_u",
+        0xB6 => "f32.demote_f64",
+        0xB7 => "f64.convert_i32_s",
+        0xB8 => "f64.convert_i32_u",
+        0xB9 => "f64.convert_i64_s",
+        0xBA => "f64.convert_i64_u",
+        0xBB => "f64.promote_f32",
+        0xBC => "i32.reinterpret_f32",
+        0xBD => "i64.reinterpret_f64",
+        0xBE => "f32.reinterpret_i32",
+        0xBF => "f64.reinterpret_i64",
+        0xC0 => "i32.extend8_s",
+        0xC1 => "i32.extend16_s",
+        0xC2 => "i64.extend8_s",
+        0xC3 => "i64.extend16_s",
+        0xC4 => "i64.extend32_s",
+        0xD0 => "ref.null",
+        0xD1 => "ref.is_null",
+        0xD2 => "ref.func",
+        0xFC => "prefixed",
+        _ => "unknown",
+    }
+}
+
+/// Alignment in bytes, or as a power of two when a malformed exponent is too
+/// large to shift.
+fn align_text(align: u32) -> String {
+    match 1u64.checked_shl(align) {
+        Some(bytes) => bytes.to_string(),
+        None => format!("2^{}", align),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_decode_immediates() {
+        let body = [
+            0x41, 0x80, 0x01, // i32.const 128
+            0x28, 0x02, 0x08, // i32.load offset=8 align=4
+            0x0E, 0x02, 0x00, 0x01, 0x02, // br_table 0 1 2
+            0x0B, // end
+        ];
+
+        let instructions = decode_function_body(&body).unwrap();
+        assert_eq!(instructions.len(), 4);
+        assert_eq!(instructions[0].immediates, vec![Immediate::I32(128)]);
+        assert_eq!(instructions[1].offset, 3);
+        assert_eq!(instructions[1].to_text(), "i32.load offset=8 align=4");
+        assert_eq!(instructions[2].to_text(), "br_table 0 1 2");
+        assert_eq!(instructions[3].offset, 11);
+    }
+
+    #[test]
+    fn test_oversized_align_renders() {
+        // i32.load with align exponent 40, then 64
+        let instructions = decode_function_body(&[0x28, 0x28, 0x00, 0x28, 0xC0, 0x00, 0x04]).unwrap();
+        assert_eq!(instructions[0].to_text(), "i32.load align=1099511627776");
+        assert_eq!(instructions[1].to_text(), "i32.load offset=4 align=2^64");
+    }
+
+    #[test]
+    fn test_signed_leb128() {
+        assert_eq!(read_leb128_i64(&[0x7F], 0).unwrap(), (-1, 1));
+        assert_eq!(read_leb128_i64(&[0x80, 0x7F], 0).unwrap(), (-128, 2));
+        assert!(decode_instruction(&[0x41], 0).is_err());
+    }
+}
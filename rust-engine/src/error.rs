@@ -37,6 +37,21 @@ pub enum WasmError {
     #[error("Runtime error: {0}")]
     Runtime(String),
     
+    #[error("Integer divide by zero")]
+    DivideByZero,
+    
+    #[error("Integer overflow")]
+    IntegerOverflow,
+    
+    #[error("Unreachable executed")]
+    Unreachable,
+    
+    #[error("Instruction budget of {0} exhausted")]
+    BudgetExhausted(u64),
+    
+    #[error("Limit {limit} exceeded: {actual} > {max}")]
+    LimitExceeded { limit: &'static str, actual: u64, max: u64 },
+    
     #[error("IO error: {0}")]
     Io(#[from] std::io::Error),
 }
\ No newline at end of file
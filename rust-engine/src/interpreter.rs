@@ -0,0 +1,1670 @@
+use crate::debugger::inspector::StateInspector;
+use crate::decoder::{self, BlockType, Immediate, Instruction};
+use crate::error::{WasmError, Result};
+use crate::memory::LinearMemory;
+use crate::parser::{ImportKind, RefType, ValueType, WasmModule};
+use crate::sandbox::faults::{Fault, FaultInjector};
+use crate::static_analysis::{RiskLevel, SuspiciousPattern};
+use std::collections::HashMap;
+use std::fmt;
+
+const PAGE_SIZE: u32 = 65536;
+
+/// Post-MVP features (as named in `target_features`) the interpreter can run.
+pub const SUPPORTED_FEATURES: &[&str] = &["mutable-globals", "multivalue"];
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Value {
+    I32(i32),
+    I64(i64),
+    F32(f32),
+    F64(f64),
+    /// A function index, or null.
+    FuncRef(Option<u32>),
+    /// An opaque host reference, or null.
+    ExternRef(Option<u32>),
+}
+
+impl Value {
+    pub fn default_for(value_type: ValueType) -> Self {
+        match value_type {
+            ValueType::I32 => Value::I32(0),
+            ValueType::I64 => Value::I64(0),
+            ValueType::F32 => Value::F32(0.0),
+            ValueType::F64 => Value::F64(0.0),
+            ValueType::FuncRef => Value::FuncRef(None),
+            ValueType::ExternRef => Value::ExternRef(None),
+        }
+    }
+
+    pub fn null_ref(ref_type: RefType) -> Self {
+        match ref_type {
+            RefType::FuncRef => Value::FuncRef(None),
+            RefType::ExternRef => Value::ExternRef(None),
+        }
+    }
+
+    pub fn as_i32(&self) -> Result<i32> {
+        match self {
+            Value::I32(v) => Ok(*v),
+            _ => Err(WasmError::TypeMismatch),
+        }
+    }
+
+    pub fn as_i64(&self) -> Result<i64> {
+        match self {
+            Value::I64(v) => Ok(*v),
+            _ => Err(WasmError::TypeMismatch),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct InterpreterConfig {
+    /// Maximum number of nested call frames.
+    pub max_stack_depth: usize,
+    /// Maximum number of values on the operand stack.
+    pub max_stack_size: usize,
+    /// Record a finding whenever a local or grown memory byte is read before
+    /// it was written. Off by default since it slows every access.
+    pub track_initialization: bool,
+    /// Maximum number of initial entries a single table may declare; tables
+    /// are allocated up front, so this bounds what a module can make us allocate.
+    pub max_table_size: u32,
+    /// How `step` picks the handler for each instruction.
+    pub dispatch: &'static dyn Dispatch,
+}
+
+impl Default for InterpreterConfig {
+    fn default() -> Self {
+        Self {
+            max_stack_depth: 1024,
+            max_stack_size: 65536,
+            track_initialization: false,
+            max_table_size: 10_000,
+            dispatch: &MatchDispatch,
+        }
+    }
+}
+
+/// Invoke `$m` with every implemented opcode, grouped by the handler that
+/// executes it. Handlers are generic over the opcode, so each entry is its
+/// own monomorphized function with the per-opcode `match` folded away.
+macro_rules! for_each_opcode {
+    ($m:ident) => {
+        $m! {
+            exec_control: 0x00 0x01 0x02 0x03 0x04 0x05 0x0B 0x0C 0x0D 0x0F 0x10;
+            exec_parametric: 0x1A 0x1B 0x1C;
+            exec_variable: 0x20 0x21 0x22 0x23 0x24;
+            exec_table: 0x25 0x26;
+            exec_load: 0x28 0x29 0x2C 0x2D 0x2E 0x2F 0x30 0x31 0x32 0x33 0x34 0x35;
+            exec_store: 0x36 0x37 0x3A 0x3B 0x3C 0x3D 0x3E;
+            exec_memory: 0x3F 0x40;
+            exec_const: 0x41 0x42 0x43 0x44;
+            exec_i32_compare: 0x45 0x46 0x47 0x48 0x49 0x4A 0x4B 0x4C 0x4D 0x4E 0x4F;
+            exec_i32_arith: 0x6A 0x6B 0x6C 0x6D 0x6E 0x6F 0x70 0x71 0x72 0x73 0x74 0x75 0x76 0x77 0x78;
+            exec_i64_arith: 0x7C 0x7D 0x7E 0x83 0x84 0x85 0x86 0x87 0x88 0x89 0x8A;
+            exec_reference: 0xD0 0xD1 0xD2;
+        }
+    };
+}
+
+/// An instruction handler, instantiated once per opcode and shared by all
+/// dispatch strategies so they execute identically.
+pub type Handler = fn(&mut Interpreter, &Instruction, usize, Option<&mut LinearMemory>) -> Result<StepResult>;
+
+/// Strategy for routing a decoded instruction to its handler.
+pub trait Dispatch: fmt::Debug + Sync {
+    fn dispatch(&self, interpreter: &mut Interpreter, instruction: &Instruction, pc: usize,
+                memory: Option<&mut LinearMemory>) -> Result<StepResult>;
+}
+
+/// The default: a `match` over opcodes.
+#[derive(Debug)]
+pub struct MatchDispatch;
+
+impl Dispatch for MatchDispatch {
+    fn dispatch(&self, interpreter: &mut Interpreter, instruction: &Instruction, pc: usize,
+                memory: Option<&mut LinearMemory>) -> Result<StepResult> {
+        interpreter.execute(instruction, pc, memory)
+    }
+}
+
+/// Indexes a 256-entry table of per-opcode handlers, skipping the `match`.
+#[derive(Debug)]
+pub struct JumpTableDispatch;
+
+static JUMP_TABLE: [Handler; 256] = Interpreter::jump_table();
+
+impl Dispatch for JumpTableDispatch {
+    fn dispatch(&self, interpreter: &mut Interpreter, instruction: &Instruction, pc: usize,
+                memory: Option<&mut LinearMemory>) -> Result<StepResult> {
+        JUMP_TABLE[instruction.opcode as usize](interpreter, instruction, pc, memory)
+    }
+}
+
+/// Why guest execution trapped.
+#[derive(Debug, Clone, PartialEq)]
+pub enum TrapReason {
+    OutOfBounds { address: u32, size: u32 },
+    DivideByZero,
+    IntegerOverflow,
+    Unreachable,
+}
+
+impl TrapReason {
+    /// The trap behind `error`, if it is one a host may recover from.
+    pub fn from_error(error: &WasmError) -> Option<Self> {
+        match error {
+            WasmError::MemoryOutOfBounds { address, size } => Some(TrapReason::OutOfBounds { address: *address, size: *size }),
+            WasmError::DivideByZero => Some(TrapReason::DivideByZero),
+            WasmError::IntegerOverflow => Some(TrapReason::IntegerOverflow),
+            WasmError::Unreachable => Some(TrapReason::Unreachable),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for TrapReason {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            TrapReason::OutOfBounds { address, size } => write!(f, "out of bounds access at {} ({} bytes)", address, size),
+            TrapReason::DivideByZero => write!(f, "integer divide by zero"),
+            TrapReason::IntegerOverflow => write!(f, "integer overflow"),
+            TrapReason::Unreachable => write!(f, "unreachable executed"),
+        }
+    }
+}
+
+impl From<TrapReason> for WasmError {
+    fn from(reason: TrapReason) -> Self {
+        match reason {
+            TrapReason::OutOfBounds { address, size } => WasmError::MemoryOutOfBounds { address, size },
+            TrapReason::DivideByZero => WasmError::DivideByZero,
+            TrapReason::IntegerOverflow => WasmError::IntegerOverflow,
+            TrapReason::Unreachable => WasmError::Unreachable,
+        }
+    }
+}
+
+/// Consulted before every guest load and store, e.g. to back MMIO-style
+/// registers with host logic.
+pub trait MemoryMediator {
+    /// Return the bytes for a read to handle it, or `None` to use linear memory.
+    fn on_read(&mut self, address: u32, size: u32) -> Option<Vec<u8>>;
+    /// Return `true` if the write was handled and must not reach linear memory.
+    fn on_write(&mut self, address: u32, bytes: &[u8]) -> bool;
+}
+
+/// Host hook deciding whether a trap is survivable: `Some(value)` pushes the
+/// value in place of the trapping instruction's result, `None` re-raises.
+/// Only consulted for instructions with exactly one result, and a value of
+/// the wrong type re-raises too.
+pub type TrapRecoverFn = Box<dyn Fn(&TrapReason) -> Option<Value>>;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum StepResult {
+    Continue,
+    Finished(Vec<Value>),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum LabelKind {
+    Block,
+    Loop,
+}
+
+#[derive(Debug, Clone)]
+struct Label {
+    kind: LabelKind,
+    /// Number of values a branch to this label carries.
+    arity: usize,
+    /// Instruction index to continue at when branching to this label.
+    target: usize,
+    height: usize,
+    /// Values the block must leave on the stack when it reaches its `end`.
+    results: Vec<ValueType>,
+}
+
+#[derive(Debug)]
+struct Frame {
+    function_index: u32,
+    pc: usize,
+    locals: Vec<Value>,
+    local_init: Vec<bool>,
+    labels: Vec<Label>,
+    height: usize,
+    arity: usize,
+}
+
+#[derive(Debug)]
+struct DecodedFunction {
+    type_idx: u32,
+    instructions: Vec<Instruction>,
+    local_types: Vec<ValueType>,
+    /// Index of the matching `end` for each `block`/`loop`/`if` instruction index.
+    block_ends: HashMap<usize, usize>,
+    /// Index of the `else` for each `if` that has one.
+    else_positions: HashMap<usize, usize>,
+}
+
+/// Tracks which bytes of memory pages added by `memory.grow` have been written.
+#[derive(Debug, Default)]
+struct MemoryInitTracker {
+    /// Byte address of the first grown page; 64-bit since a full 4 GiB memory
+    /// ends at 2^32.
+    grown_start: u64,
+    written: Vec<bool>,
+}
+
+impl MemoryInitTracker {
+    fn grow(&mut self, old_pages: u32, new_pages: u32) {
+        if self.written.is_empty() {
+            self.grown_start = old_pages as u64 * PAGE_SIZE as u64;
+        }
+        let len = (new_pages as u64 * PAGE_SIZE as u64 - self.grown_start) as usize;
+        self.written.resize(len, false);
+    }
+
+    fn mark_written(&mut self, address: u32, size: u32) {
+        let start = address as u64;
+        for addr in start..start + size as u64 {
+            if let Some(slot) = self.slot(addr) {
+                self.written[slot] = true;
+            }
+        }
+    }
+
+    fn is_initialized(&self, address: u32, size: u32) -> bool {
+        let start = address as u64;
+        (start..start + size as u64).all(|addr| self.slot(addr).map(|s| self.written[s]).unwrap_or(true))
+    }
+
+    fn slot(&self, address: u64) -> Option<usize> {
+        if address < self.grown_start {
+            return None;
+        }
+        let slot = (address - self.grown_start) as usize;
+        if slot < self.written.len() { Some(slot) } else { None }
+    }
+}
+
+pub struct Interpreter {
+    module: WasmModule,
+    functions: Vec<DecodedFunction>,
+    imported_function_count: u32,
+    globals: Vec<Value>,
+    tables: Vec<Vec<Value>>,
+    stack: Vec<Value>,
+    frames: Vec<Frame>,
+    config: InterpreterConfig,
+    memory_init: MemoryInitTracker,
+    findings: Vec<SuspiciousPattern>,
+    instruction_count: u64,
+    inspector: StateInspector,
+    on_trap_recover: Option<TrapRecoverFn>,
+    mediator: Option<Box<dyn MemoryMediator>>,
+    faults: Option<FaultInjector>,
+}
+
+impl fmt::Debug for Interpreter {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Interpreter")
+            .field("functions", &self.functions.len())
+            .field("stack", &self.stack)
+            .field("frames", &self.frames)
+            .field("config", &self.config)
+            .field("instruction_count", &self.instruction_count)
+            .field("on_trap_recover", &self.on_trap_recover.is_some())
+            .field("mediator", &self.mediator.is_some())
+            .field("faults", &self.faults)
+            .finish()
+    }
+}
+
+impl Interpreter {
+    pub fn new(module: WasmModule) -> Result<Self> {
+        Self::with_config(module, InterpreterConfig::default())
+    }
+
+    pub fn with_config(module: WasmModule, config: InterpreterConfig) -> Result<Self> {
+        let imported_function_count = module.imports.iter()
+            .filter(|i| matches!(i.kind, ImportKind::Function(_)))
+            .count() as u32;
+
+        let mut functions = Vec::with_capacity(module.code.len());
+        for (i, code) in module.code.iter().enumerate() {
+            let type_idx = module.functions.get(i)
+                .map(|f| f.type_idx)
+                .ok_or(WasmError::InvalidModule)?;
+            let instructions = decoder::decode_function_body(&code.body)?;
+            let (block_ends, else_positions) = Self::match_blocks(&instructions)?;
+            let local_types = code.locals.iter()
+                .flat_map(|entry| std::iter::repeat_n(entry.value_type, entry.count as usize))
+                .collect();
+
+            functions.push(DecodedFunction { type_idx, instructions, local_types, block_ends, else_positions });
+        }
+
+        let mut globals: Vec<Value> = module.imports.iter()
+            .filter_map(|i| match i.kind {
+                ImportKind::Global { value_type, .. } => Some(Value::default_for(value_type)),
+                _ => None,
+            })
+            .collect();
+        for global in &module.globals {
+            let value = Self::eval_const_expr(&global.init_expr, &globals)?
+                .unwrap_or(Value::default_for(global.value_type));
+            globals.push(value);
+        }
+
+        let tables = module.imports.iter()
+            .filter_map(|i| match &i.kind {
+                ImportKind::Table(table) => Some(table),
+                _ => None,
+            })
+            .chain(module.table.iter())
+            .map(|table| {
+                if table.min > config.max_table_size {
+                    return Err(WasmError::Runtime(format!(
+                        "Table has {} initial entries, limit is {}", table.min, config.max_table_size)));
+                }
+                Ok(vec![Value::null_ref(table.element_type); table.min as usize])
+            })
+            .collect::<Result<Vec<_>>>()?;
+
+        Ok(Self {
+            module,
+            functions,
+            imported_function_count,
+            globals,
+            tables,
+            stack: Vec::new(),
+            frames: Vec::new(),
+            config,
+            memory_init: MemoryInitTracker::default(),
+            findings: Vec::new(),
+            instruction_count: 0,
+            inspector: StateInspector::new(),
+            on_trap_recover: None,
+            mediator: None,
+            faults: None,
+        })
+    }
+
+    pub fn with_trap_recovery(mut self, hook: impl Fn(&TrapReason) -> Option<Value> + 'static) -> Self {
+        self.on_trap_recover = Some(Box::new(hook));
+        self
+    }
+
+    pub fn with_memory_mediator(mut self, mediator: impl MemoryMediator + 'static) -> Self {
+        self.mediator = Some(Box::new(mediator));
+        self
+    }
+
+    /// Inject scripted faults into `memory.grow`.
+    pub fn with_fault_injector(mut self, injector: FaultInjector) -> Self {
+        self.faults = Some(injector);
+        self
+    }
+
+    /// Run `function_index` to completion and return its results.
+    pub fn invoke(&mut self, function_index: u32, args: &[Value], mut memory: Option<&mut LinearMemory>) -> Result<Vec<Value>> {
+        self.start(function_index, args)?;
+        loop {
+            if let StepResult::Finished(results) = self.step(memory.as_deref_mut())? {
+                return Ok(results);
+            }
+        }
+    }
+
+    /// Set up a call to `function_index` without executing it; drive it with `step`.
+    pub fn start(&mut self, function_index: u32, args: &[Value]) -> Result<()> {
+        self.stack.clear();
+        while self.frames.pop().is_some() {
+            self.inspector.pop_call_frame();
+        }
+        self.stack.extend_from_slice(args);
+        self.push_frame(function_index, 0)
+    }
+
+    /// Execute a single instruction of the current call.
+    pub fn step(&mut self, memory: Option<&mut LinearMemory>) -> Result<StepResult> {
+        let (func_slot, pc) = match self.frames.last() {
+            Some(frame) => ((frame.function_index - self.imported_function_count) as usize, frame.pc),
+            None => return Err(WasmError::Runtime("No active call".to_string())),
+        };
+
+        let instruction = match self.functions[func_slot].instructions.get(pc) {
+            Some(instruction) => instruction.clone(),
+            None => return self.return_from_frame(),
+        };
+        self.current_frame().pc += 1;
+        self.instruction_count += 1;
+
+        match self.config.dispatch.dispatch(self, &instruction, pc, memory) {
+            Err(error) => self.recover_trap(&instruction, error),
+            result => result,
+        }
+    }
+
+    fn recover_trap(&mut self, instruction: &Instruction, error: WasmError) -> Result<StepResult> {
+        let (hook, reason) = match (&self.on_trap_recover, TrapReason::from_error(&error)) {
+            (Some(hook), Some(reason)) => (hook, reason),
+            _ => return Err(error),
+        };
+        // Anything else would leave the stack unbalanced
+        let result_type = match Self::single_result_type(instruction) {
+            Some(result_type) => result_type,
+            None => return Err(error),
+        };
+
+        match hook(&reason) {
+            Some(value) if std::mem::discriminant(&value) == std::mem::discriminant(&Value::default_for(result_type)) => {
+                log::warn!("Recovered from trap ({}), continuing with {:?}", reason, value);
+                self.push(value)?;
+                Ok(StepResult::Continue)
+            }
+            Some(value) => {
+                log::warn!("Trap recovery value {:?} does not match {} result {:?}", value, instruction.mnemonic(), result_type);
+                Err(error)
+            }
+            None => Err(error),
+        }
+    }
+
+    /// The result type of trapping instructions that produce exactly one value.
+    fn single_result_type(instruction: &Instruction) -> Option<ValueType> {
+        match instruction.opcode {
+            0x28 | 0x2C..=0x2F | 0x6D..=0x70 => Some(ValueType::I32),
+            0x29 | 0x30..=0x35 => Some(ValueType::I64),
+            _ => None,
+        }
+    }
+
+    pub fn module(&self) -> &WasmModule {
+        &self.module
+    }
+
+    /// Table contents, imported tables first.
+    pub fn tables(&self) -> &[Vec<Value>] {
+        &self.tables
+    }
+
+    pub fn globals(&self) -> &[Value] {
+        &self.globals
+    }
+
+    pub fn stack(&self) -> &[Value] {
+        &self.stack
+    }
+
+    pub fn instruction_count(&self) -> u64 {
+        self.instruction_count
+    }
+
+    /// Call frames of the running invocation, for the debugger.
+    pub fn inspector(&self) -> &StateInspector {
+        &self.inspector
+    }
+
+    /// Runtime findings such as use-before-init reads.
+    pub fn findings(&self) -> &[SuspiciousPattern] {
+        &self.findings
+    }
+
+    const fn jump_table() -> [Handler; 256] {
+        let mut table: [Handler; 256] = [Self::exec_invalid; 256];
+        macro_rules! fill {
+            ($($handler:ident: $($opcode:literal)*;)*) => {
+                $($(table[$opcode] = Self::$handler::<$opcode>;)*)*
+            };
+        }
+        for_each_opcode!(fill);
+        table
+    }
+
+    fn exec_invalid(&mut self, instruction: &Instruction, _pc: usize, _memory: Option<&mut LinearMemory>) -> Result<StepResult> {
+        Err(WasmError::InvalidInstruction(instruction.opcode))
+    }
+
+    fn execute(&mut self, instruction: &Instruction, pc: usize, memory: Option<&mut LinearMemory>) -> Result<StepResult> {
+        macro_rules! dispatch {
+            ($($handler:ident: $($opcode:literal)*;)*) => {
+                match instruction.opcode {
+                    $($($opcode => self.$handler::<$opcode>(instruction, pc, memory),)*)*
+                    _ => self.exec_invalid(instruction, pc, memory),
+                }
+            };
+        }
+        for_each_opcode!(dispatch)
+    }
+
+    fn exec_control<const OP: u8>(&mut self, instruction: &Instruction, pc: usize, _memory: Option<&mut LinearMemory>) -> Result<StepResult> {
+        match OP {
+            0x00 => return Err(TrapReason::Unreachable.into()),
+            0x01 => {}
+            // block, loop
+            0x02 | 0x03 => {
+                let (params, results) = self.block_signature(instruction)?;
+                let end = self.block_end(pc)?;
+                let height = self.stack.len().checked_sub(params.len()).ok_or(WasmError::StackUnderflow)?;
+                self.check_operand_types(height, &params)?;
+                let label = if OP == 0x03 {
+                    Label { kind: LabelKind::Loop, arity: params.len(), target: pc + 1, height, results }
+                } else {
+                    Label { kind: LabelKind::Block, arity: results.len(), target: end + 1, height, results }
+                };
+                self.current_frame().labels.push(label);
+            }
+            0x04 => {
+                let condition = self.pop_i32()?;
+                let (params, results) = self.block_signature(instruction)?;
+                let end = self.block_end(pc)?;
+                let else_position = self.else_position(pc);
+                let height = self.stack.len().checked_sub(params.len()).ok_or(WasmError::StackUnderflow)?;
+                self.check_operand_types(height, &params)?;
+
+                let frame = self.current_frame();
+                frame.labels.push(Label { kind: LabelKind::Block, arity: results.len(), target: end + 1, height, results });
+                if condition == 0 {
+                    // Without an else arm, land on the `end` so it pops the label
+                    frame.pc = else_position.map(|e| e + 1).unwrap_or(end);
+                }
+            }
+            // Reaching else means the then-arm finished; leave the if like a branch
+            0x05 => self.branch(0)?,
+            0x0B => {
+                match self.current_frame().labels.pop() {
+                    Some(label) => {
+                        if self.stack.len() != label.height + label.results.len() {
+                            return Err(WasmError::TypeMismatch);
+                        }
+                        self.check_operand_types(label.height, &label.results)?;
+                    }
+                    None => return self.return_from_frame(),
+                }
+            }
+            0x0C => self.branch(Self::u32_imm(instruction)?)?,
+            0x0D => {
+                if self.pop_i32()? != 0 {
+                    self.branch(Self::u32_imm(instruction)?)?;
+                }
+            }
+            0x0F => return self.return_from_frame(),
+            0x10 => self.push_frame(Self::u32_imm(instruction)?, instruction.offset as u32)?,
+            opcode => return Err(WasmError::InvalidInstruction(opcode)),
+        }
+
+        Ok(StepResult::Continue)
+    }
+
+    fn exec_parametric<const OP: u8>(&mut self, instruction: &Instruction, _pc: usize, _memory: Option<&mut LinearMemory>) -> Result<StepResult> {
+        match OP {
+            0x1A => {
+                self.pop()?;
+            }
+            0x1B => {
+                let condition = self.pop_i32()?;
+                let second = self.pop()?;
+                let first = self.pop()?;
+                if std::mem::discriminant(&first) != std::mem::discriminant(&second) {
+                    return Err(WasmError::TypeMismatch);
+                }
+                self.push(if condition != 0 { first } else { second })?;
+            }
+            // select with an explicit result type
+            0x1C => {
+                let result_type = match instruction.immediates.first() {
+                    Some(Immediate::SelectTypes(types)) if types.len() == 1 => types[0],
+                    _ => return Err(WasmError::InvalidModule),
+                };
+                let expected = std::mem::discriminant(&Value::default_for(result_type));
+
+                let condition = self.pop_i32()?;
+                let second = self.pop()?;
+                let first = self.pop()?;
+                if std::mem::discriminant(&first) != expected || std::mem::discriminant(&second) != expected {
+                    return Err(WasmError::TypeMismatch);
+                }
+                self.push(if condition != 0 { first } else { second })?;
+            }
+            opcode => return Err(WasmError::InvalidInstruction(opcode)),
+        }
+
+        Ok(StepResult::Continue)
+    }
+
+    fn exec_variable<const OP: u8>(&mut self, instruction: &Instruction, pc: usize, _memory: Option<&mut LinearMemory>) -> Result<StepResult> {
+        match OP {
+            0x20 => {
+                let index = Self::u32_imm(instruction)? as usize;
+                let value = *self.current_frame().locals.get(index).ok_or(WasmError::InvalidModule)?;
+                if self.config.track_initialization && self.current_frame().local_init.get(index) == Some(&false) {
+                    self.record_use_before_init(pc, format!("local {} read before it was written", index));
+                }
+                self.push(value)?;
+            }
+            0x21 | 0x22 => {
+                let index = Self::u32_imm(instruction)? as usize;
+                let value = self.pop()?;
+                let frame = self.current_frame();
+                *frame.locals.get_mut(index).ok_or(WasmError::InvalidModule)? = value;
+                if let Some(init) = frame.local_init.get_mut(index) {
+                    *init = true;
+                }
+                if OP == 0x22 {
+                    self.push(value)?;
+                }
+            }
+            0x23 => {
+                let index = Self::u32_imm(instruction)? as usize;
+                let value = *self.globals.get(index).ok_or(WasmError::InvalidModule)?;
+                self.push(value)?;
+            }
+            0x24 => {
+                let index = Self::u32_imm(instruction)? as usize;
+                let value = self.pop()?;
+                *self.globals.get_mut(index).ok_or(WasmError::InvalidModule)? = value;
+            }
+            opcode => return Err(WasmError::InvalidInstruction(opcode)),
+        }
+
+        Ok(StepResult::Continue)
+    }
+
+    fn exec_table<const OP: u8>(&mut self, instruction: &Instruction, _pc: usize, _memory: Option<&mut LinearMemory>) -> Result<StepResult> {
+        match OP {
+            // table.get
+            0x25 => {
+                let table = Self::u32_imm(instruction)?;
+                let index = self.pop_i32()?;
+                let value = *self.table_slot(table, index)?;
+                self.push(value)?;
+            }
+            // table.set
+            0x26 => {
+                let table = Self::u32_imm(instruction)?;
+                let value = self.pop()?;
+                let index = self.pop_i32()?;
+                let slot = self.table_slot(table, index)?;
+                if std::mem::discriminant(slot) != std::mem::discriminant(&value) {
+                    return Err(WasmError::TypeMismatch);
+                }
+                *slot = value;
+            }
+            opcode => return Err(WasmError::InvalidInstruction(opcode)),
+        }
+
+        Ok(StepResult::Continue)
+    }
+
+    fn exec_load<const OP: u8>(&mut self, instruction: &Instruction, pc: usize, memory: Option<&mut LinearMemory>) -> Result<StepResult> {
+        match OP {
+            // i32/i64 loads, including the narrow sign- and zero-extending forms
+            0x28 | 0x29 | 0x2C..=0x35 => {
+                let (size, signed) = match OP {
+                    0x28 => (4, false),
+                    0x29 => (8, false),
+                    0x2C | 0x30 => (1, true),
+                    0x2D | 0x31 => (1, false),
+                    0x2E | 0x32 => (2, true),
+                    0x2F | 0x33 => (2, false),
+                    0x34 => (4, true),
+                    _ => (4, false),
+                };
+                let address = self.effective_address(instruction, size)?;
+                let bytes = self.read_memory(pc, address, size, memory)?;
+
+                let mut raw = [0u8; 8];
+                raw[..bytes.len()].copy_from_slice(&bytes);
+                let bits = size * 8;
+                let mut value = u64::from_le_bytes(raw);
+                if signed && bits < 64 {
+                    let shift = 64 - bits;
+                    value = (((value << shift) as i64) >> shift) as u64;
+                }
+                let value = match OP {
+                    0x28 | 0x2C..=0x2F => Value::I32(value as i32),
+                    _ => Value::I64(value as i64),
+                };
+                self.push(value)?;
+            }
+            opcode => return Err(WasmError::InvalidInstruction(opcode)),
+        }
+
+        Ok(StepResult::Continue)
+    }
+
+    fn exec_store<const OP: u8>(&mut self, instruction: &Instruction, _pc: usize, memory: Option<&mut LinearMemory>) -> Result<StepResult> {
+        match OP {
+            // i32/i64 stores, including the truncating narrow forms
+            0x36 | 0x37 | 0x3A..=0x3E => {
+                let value = self.pop()?;
+                let size = match OP {
+                    0x36 | 0x3E => 4,
+                    0x37 => 8,
+                    0x3A | 0x3C => 1,
+                    _ => 2,
+                };
+                let address = self.effective_address(instruction, size)?;
+                let bytes = match (OP, value) {
+                    (0x36 | 0x3A | 0x3B, Value::I32(v)) => v.to_le_bytes()[..size as usize].to_vec(),
+                    (0x37 | 0x3C..=0x3E, Value::I64(v)) => v.to_le_bytes()[..size as usize].to_vec(),
+                    _ => return Err(WasmError::TypeMismatch),
+                };
+                self.write_memory(address, &bytes, memory)?;
+            }
+            opcode => return Err(WasmError::InvalidInstruction(opcode)),
+        }
+
+        Ok(StepResult::Continue)
+    }
+
+    fn exec_memory<const OP: u8>(&mut self, _instruction: &Instruction, _pc: usize, memory: Option<&mut LinearMemory>) -> Result<StepResult> {
+        match OP {
+            0x3F => {
+                let memory = memory.ok_or_else(|| WasmError::Runtime("No memory attached".to_string()))?;
+                self.push(Value::I32(memory.size() as i32))?;
+            }
+            0x40 => {
+                let delta = self.pop_i32()? as u32;
+                match self.faults.as_ref().and_then(|f| f.on_call("memory.grow")) {
+                    Some(Fault::Return(value)) => {
+                        self.push(Value::I32(value as i32))?;
+                        return Ok(StepResult::Continue);
+                    }
+                    Some(Fault::Fail(message)) => return Err(WasmError::Runtime(message)),
+                    Some(Fault::ClockJump(_)) | None => {}
+                }
+                let memory = memory.ok_or_else(|| WasmError::Runtime("No memory attached".to_string()))?;
+                let result = match memory.grow(delta) {
+                    Ok(old_pages) => {
+                        if self.config.track_initialization {
+                            self.memory_init.grow(old_pages, old_pages + delta);
+                        }
+                        old_pages as i32
+                    }
+                    Err(_) => -1,
+                };
+                self.push(Value::I32(result))?;
+            }
+            opcode => return Err(WasmError::InvalidInstruction(opcode)),
+        }
+
+        Ok(StepResult::Continue)
+    }
+
+    fn exec_const<const OP: u8>(&mut self, instruction: &Instruction, _pc: usize, _memory: Option<&mut LinearMemory>) -> Result<StepResult> {
+        match OP {
+            0x41..=0x44 => {
+                let value = match instruction.immediates.first() {
+                    Some(Immediate::I32(v)) => Value::I32(*v),
+                    Some(Immediate::I64(v)) => Value::I64(*v),
+                    Some(Immediate::F32(v)) => Value::F32(*v),
+                    Some(Immediate::F64(v)) => Value::F64(*v),
+                    _ => return Err(WasmError::InvalidInstruction(OP)),
+                };
+                self.push(value)?;
+            }
+            opcode => return Err(WasmError::InvalidInstruction(opcode)),
+        }
+
+        Ok(StepResult::Continue)
+    }
+
+    fn exec_i32_compare<const OP: u8>(&mut self, _instruction: &Instruction, _pc: usize, _memory: Option<&mut LinearMemory>) -> Result<StepResult> {
+        match OP {
+            0x45 => {
+                let v = self.pop_i32()?;
+                self.push(Value::I32((v == 0) as i32))?;
+            }
+            0x46..=0x4F => {
+                let b = self.pop_i32()?;
+                let a = self.pop_i32()?;
+                let result = match OP {
+                    0x46 => a == b,
+                    0x47 => a != b,
+                    0x48 => a < b,
+                    0x49 => (a as u32) < (b as u32),
+                    0x4A => a > b,
+                    0x4B => (a as u32) > (b as u32),
+                    0x4C => a <= b,
+                    0x4D => (a as u32) <= (b as u32),
+                    0x4E => a >= b,
+                    _ => (a as u32) >= (b as u32),
+                };
+                self.push(Value::I32(result as i32))?;
+            }
+            opcode => return Err(WasmError::InvalidInstruction(opcode)),
+        }
+
+        Ok(StepResult::Continue)
+    }
+
+    fn exec_i32_arith<const OP: u8>(&mut self, _instruction: &Instruction, _pc: usize, _memory: Option<&mut LinearMemory>) -> Result<StepResult> {
+        match OP {
+            0x6A..=0x70 => {
+                let b = self.pop_i32()?;
+                let a = self.pop_i32()?;
+                let result = match OP {
+                    0x6A => a.wrapping_add(b),
+                    0x6B => a.wrapping_sub(b),
+                    0x6C => a.wrapping_mul(b),
+                    0x6D => {
+                        if b == 0 {
+                            return Err(TrapReason::DivideByZero.into());
+                        }
+                        a.checked_div(b).ok_or(TrapReason::IntegerOverflow)?
+                    }
+                    0x6E => {
+                        if b == 0 {
+                            return Err(TrapReason::DivideByZero.into());
+                        }
+                        ((a as u32) / (b as u32)) as i32
+                    }
+                    0x6F => {
+                        if b == 0 {
+                            return Err(TrapReason::DivideByZero.into());
+                        }
+                        a.wrapping_rem(b)
+                    }
+                    _ => {
+                        if b == 0 {
+                            return Err(TrapReason::DivideByZero.into());
+                        }
+                        ((a as u32) % (b as u32)) as i32
+                    }
+                };
+                self.push(Value::I32(result))?;
+            }
+            // Shift amounts are taken modulo the bit width, which the wrapping ops do
+            0x71..=0x78 => {
+                let b = self.pop_i32()?;
+                let a = self.pop_i32()?;
+                let result = match OP {
+                    0x71 => a & b,
+                    0x72 => a | b,
+                    0x73 => a ^ b,
+                    0x74 => a.wrapping_shl(b as u32),
+                    0x75 => a.wrapping_shr(b as u32),
+                    0x76 => (a as u32).wrapping_shr(b as u32) as i32,
+                    0x77 => a.rotate_left(b as u32 % 32),
+                    _ => a.rotate_right(b as u32 % 32),
+                };
+                self.push(Value::I32(result))?;
+            }
+            opcode => return Err(WasmError::InvalidInstruction(opcode)),
+        }
+
+        Ok(StepResult::Continue)
+    }
+
+    fn exec_i64_arith<const OP: u8>(&mut self, _instruction: &Instruction, _pc: usize, _memory: Option<&mut LinearMemory>) -> Result<StepResult> {
+        match OP {
+            0x7C..=0x7E => {
+                let b = self.pop_i64()?;
+                let a = self.pop_i64()?;
+                let result = match OP {
+                    0x7C => a.wrapping_add(b),
+                    0x7D => a.wrapping_sub(b),
+                    _ => a.wrapping_mul(b),
+                };
+                self.push(Value::I64(result))?;
+            }
+            0x83..=0x8A => {
+                let b = self.pop_i64()?;
+                let a = self.pop_i64()?;
+                let result = match OP {
+                    0x83 => a & b,
+                    0x84 => a | b,
+                    0x85 => a ^ b,
+                    0x86 => a.wrapping_shl(b as u32),
+                    0x87 => a.wrapping_shr(b as u32),
+                    0x88 => (a as u64).wrapping_shr(b as u32) as i64,
+                    0x89 => a.rotate_left((b as u64 % 64) as u32),
+                    _ => a.rotate_right((b as u64 % 64) as u32),
+                };
+                self.push(Value::I64(result))?;
+            }
+            opcode => return Err(WasmError::InvalidInstruction(opcode)),
+        }
+
+        Ok(StepResult::Continue)
+    }
+
+    fn exec_reference<const OP: u8>(&mut self, instruction: &Instruction, _pc: usize, _memory: Option<&mut LinearMemory>) -> Result<StepResult> {
+        match OP {
+            // ref.null
+            0xD0 => {
+                let heap_type = Self::u32_imm(instruction)?;
+                self.push(Self::null_for_heap_type(heap_type)?)?;
+            }
+            // ref.is_null
+            0xD1 => {
+                let is_null = match self.pop()? {
+                    Value::FuncRef(r) | Value::ExternRef(r) => r.is_none(),
+                    _ => return Err(WasmError::TypeMismatch),
+                };
+                self.push(Value::I32(is_null as i32))?;
+            }
+            // ref.func
+            0xD2 => {
+                let index = Self::u32_imm(instruction)?;
+                if index >= self.imported_function_count + self.functions.len() as u32 {
+                    return Err(WasmError::FunctionNotFound(index));
+                }
+                self.push(Value::FuncRef(Some(index)))?;
+            }
+            opcode => return Err(WasmError::InvalidInstruction(opcode)),
+        }
+
+        Ok(StepResult::Continue)
+    }
+
+    /// Move the callee's parameters off the operand stack into a new frame.
+    fn push_frame(&mut self, function_index: u32, caller_ip: u32) -> Result<()> {
+        if function_index < self.imported_function_count {
+            return Err(WasmError::Runtime(format!("Cannot invoke imported function {}", function_index)));
+        }
+        if self.frames.len() >= self.config.max_stack_depth {
+            return Err(WasmError::StackOverflow);
+        }
+
+        let function = self.functions.get((function_index - self.imported_function_count) as usize)
+            .ok_or(WasmError::FunctionNotFound(function_index))?;
+        let func_type = self.module.types.get(function.type_idx as usize)
+            .ok_or(WasmError::InvalidModule)?;
+
+        let param_count = func_type.params.len();
+        if self.stack.len() < param_count {
+            return Err(WasmError::StackUnderflow);
+        }
+        let mut locals = self.stack.split_off(self.stack.len() - param_count);
+        for (value, expected) in locals.iter().zip(&func_type.params) {
+            if std::mem::discriminant(value) != std::mem::discriminant(&Value::default_for(*expected)) {
+                return Err(WasmError::TypeMismatch);
+            }
+        }
+        locals.extend(function.local_types.iter().map(|t| Value::default_for(*t)));
+
+        let local_init = if self.config.track_initialization {
+            let mut init = vec![true; param_count];
+            init.resize(locals.len(), false);
+            init
+        } else {
+            Vec::new()
+        };
+
+        let arity = func_type.results.len();
+        self.inspector.push_call_frame(function_index, caller_ip, self.stack.len() as u32);
+        self.frames.push(Frame {
+            function_index,
+            pc: 0,
+            locals,
+            local_init,
+            labels: Vec::new(),
+            height: self.stack.len(),
+            arity,
+        });
+        Ok(())
+    }
+
+    fn return_from_frame(&mut self) -> Result<StepResult> {
+        let frame = self.frames.pop().ok_or(WasmError::StackUnderflow)?;
+        self.inspector.pop_call_frame();
+        if self.stack.len() < frame.height + frame.arity {
+            return Err(WasmError::StackUnderflow);
+        }
+
+        let results = self.stack.split_off(self.stack.len() - frame.arity);
+        self.stack.truncate(frame.height);
+
+        if self.frames.is_empty() {
+            return Ok(StepResult::Finished(results));
+        }
+        self.stack.extend(results);
+        Ok(StepResult::Continue)
+    }
+
+    fn branch(&mut self, depth: u32) -> Result<()> {
+        let frame = self.frames.last_mut().ok_or(WasmError::StackUnderflow)?;
+        let index = frame.labels.len().checked_sub(depth as usize + 1)
+            .ok_or(WasmError::InvalidModule)?;
+
+        let label = frame.labels[index].clone();
+        if self.stack.len() < label.height + label.arity {
+            return Err(WasmError::StackUnderflow);
+        }
+        let carried = self.stack.split_off(self.stack.len() - label.arity);
+        self.stack.truncate(label.height);
+        self.stack.extend(carried);
+
+        match label.kind {
+            LabelKind::Loop => frame.labels.truncate(index + 1),
+            LabelKind::Block => frame.labels.truncate(index),
+        }
+        frame.pc = label.target;
+        Ok(())
+    }
+
+    /// Read `size` bytes, letting the mediator claim the access first.
+    fn read_memory(&mut self, pc: usize, address: u32, size: u32, memory: Option<&mut LinearMemory>) -> Result<Vec<u8>> {
+        if let Some(mediator) = self.mediator.as_mut() {
+            if let Some(bytes) = mediator.on_read(address, size) {
+                if bytes.len() != size as usize {
+                    return Err(WasmError::Runtime(format!(
+                        "Memory mediator returned {} bytes for a {} byte read", bytes.len(), size)));
+                }
+                return Ok(bytes);
+            }
+        }
+
+        let memory = memory.ok_or_else(|| WasmError::Runtime("No memory attached".to_string()))?;
+        if self.config.track_initialization && !self.memory_init.is_initialized(address, size) {
+            self.record_use_before_init(pc, format!("memory at 0x{:x} read before it was written", address));
+        }
+        Ok(memory.read_bytes(address, size)?.to_vec())
+    }
+
+    fn write_memory(&mut self, address: u32, bytes: &[u8], memory: Option<&mut LinearMemory>) -> Result<()> {
+        if let Some(mediator) = self.mediator.as_mut() {
+            if mediator.on_write(address, bytes) {
+                return Ok(());
+            }
+        }
+
+        let memory = memory.ok_or_else(|| WasmError::Runtime("No memory attached".to_string()))?;
+        memory.write_bytes(address, bytes)?;
+        if self.config.track_initialization {
+            self.memory_init.mark_written(address, bytes.len() as u32);
+        }
+        Ok(())
+    }
+
+    fn effective_address(&mut self, instruction: &Instruction, size: u32) -> Result<u32> {
+        let base = self.pop_i32()? as u32;
+        let offset = match instruction.immediates.first() {
+            Some(Immediate::MemArg { offset, .. }) => *offset,
+            _ => return Err(WasmError::InvalidInstruction(instruction.opcode)),
+        };
+        let address = base as u64 + offset as u64;
+        if address + size as u64 > u32::MAX as u64 {
+            return Err(WasmError::MemoryOutOfBounds { address: base, size });
+        }
+        Ok(address as u32)
+    }
+
+    fn record_use_before_init(&mut self, pc: usize, description: String) {
+        let frame = self.frames.last().expect("active frame");
+        let slot = (frame.function_index - self.imported_function_count) as usize;
+        let offset = self.functions[slot].instructions[pc].offset as u32;
+
+        log::debug!("Use before init in function {} at offset {}: {}", frame.function_index, offset, description);
+        self.findings.push(SuspiciousPattern {
+            pattern_name: "UseBeforeInit".to_string(),
+            function_index: frame.function_index,
+            instruction_offset: offset,
+            description,
+            risk_level: RiskLevel::Warning,
+            occurrences: 1,
+            offsets: Vec::new(),
+        });
+    }
+
+    fn block_end(&self, pc: usize) -> Result<usize> {
+        let frame = self.frames.last().ok_or(WasmError::StackUnderflow)?;
+        let slot = (frame.function_index - self.imported_function_count) as usize;
+        self.functions[slot].block_ends.get(&pc).copied().ok_or(WasmError::InvalidModule)
+    }
+
+    fn else_position(&self, pc: usize) -> Option<usize> {
+        let frame = self.frames.last()?;
+        let slot = (frame.function_index - self.imported_function_count) as usize;
+        self.functions[slot].else_positions.get(&pc).copied()
+    }
+
+    /// Param and result types of a `block`/`loop`/`if`, resolving type indices
+    /// into `module.types`.
+    fn block_signature(&self, instruction: &Instruction) -> Result<(Vec<ValueType>, Vec<ValueType>)> {
+        match instruction.immediates.first() {
+            Some(Immediate::Block(BlockType::Empty)) => Ok((Vec::new(), Vec::new())),
+            Some(Immediate::Block(BlockType::Value(value_type))) => Ok((Vec::new(), vec![*value_type])),
+            Some(Immediate::Block(BlockType::TypeIndex(idx))) => {
+                let func_type = self.module.types.get(*idx as usize).ok_or(WasmError::InvalidModule)?;
+                Ok((func_type.params.clone(), func_type.results.clone()))
+            }
+            _ => Err(WasmError::InvalidInstruction(instruction.opcode)),
+        }
+    }
+
+    /// Check the values from stack position `start` upward against `types`.
+    fn check_operand_types(&self, start: usize, types: &[ValueType]) -> Result<()> {
+        let values = self.stack.get(start..start + types.len()).ok_or(WasmError::StackUnderflow)?;
+        for (value, expected) in values.iter().zip(types) {
+            if std::mem::discriminant(value) != std::mem::discriminant(&Value::default_for(*expected)) {
+                return Err(WasmError::TypeMismatch);
+            }
+        }
+        Ok(())
+    }
+
+    fn match_blocks(instructions: &[Instruction]) -> Result<(HashMap<usize, usize>, HashMap<usize, usize>)> {
+        let mut ends = HashMap::new();
+        let mut elses = HashMap::new();
+        let mut open = Vec::new();
+
+        for (i, instruction) in instructions.iter().enumerate() {
+            match instruction.opcode {
+                0x02..=0x04 => open.push(i),
+                0x05 => {
+                    match open.last() {
+                        Some(&start) if instructions[start].opcode == 0x04 => {
+                            elses.insert(start, i);
+                        }
+                        _ => return Err(WasmError::InvalidModule),
+                    }
+                }
+                0x0B => {
+                    if let Some(start) = open.pop() {
+                        ends.insert(start, i);
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        if !open.is_empty() {
+            return Err(WasmError::InvalidModule);
+        }
+        Ok((ends, elses))
+    }
+
+    fn eval_const_expr(expr: &[u8], globals: &[Value]) -> Result<Option<Value>> {
+        let instruction = decoder::decode_instruction(expr, 0)?;
+        let value = match (instruction.opcode, instruction.immediates.first()) {
+            (0x41, Some(Immediate::I32(v))) => Some(Value::I32(*v)),
+            (0x42, Some(Immediate::I64(v))) => Some(Value::I64(*v)),
+            (0x43, Some(Immediate::F32(v))) => Some(Value::F32(*v)),
+            (0x44, Some(Immediate::F64(v))) => Some(Value::F64(*v)),
+            (0x23, Some(Immediate::U32(idx))) => globals.get(*idx as usize).copied(),
+            (0xD0, Some(Immediate::U32(heap_type))) => Some(Self::null_for_heap_type(*heap_type)?),
+            (0xD2, Some(Immediate::U32(idx))) => Some(Value::FuncRef(Some(*idx))),
+            _ => None,
+        };
+        Ok(value)
+    }
+
+    fn null_for_heap_type(heap_type: u32) -> Result<Value> {
+        match heap_type {
+            0x70 => Ok(Value::FuncRef(None)),
+            0x6F => Ok(Value::ExternRef(None)),
+            _ => Err(WasmError::InvalidModule),
+        }
+    }
+
+    fn table_slot(&mut self, table_idx: u32, index: i32) -> Result<&mut Value> {
+        let table = self.tables.get_mut(table_idx as usize).ok_or(WasmError::InvalidModule)?;
+        let len = table.len();
+        table.get_mut(index as u32 as usize)
+            .ok_or_else(|| WasmError::Runtime(format!("Table index {} out of bounds ({} entries)", index as u32, len)))
+    }
+
+    fn u32_imm(instruction: &Instruction) -> Result<u32> {
+        match instruction.immediates.first() {
+            Some(Immediate::U32(v)) => Ok(*v),
+            _ => Err(WasmError::InvalidInstruction(instruction.opcode)),
+        }
+    }
+
+    fn current_frame(&mut self) -> &mut Frame {
+        self.frames.last_mut().expect("active frame")
+    }
+
+    fn push(&mut self, value: Value) -> Result<()> {
+        if self.stack.len() >= self.config.max_stack_size {
+            return Err(WasmError::StackOverflow);
+        }
+        self.stack.push(value);
+        Ok(())
+    }
+
+    fn pop(&mut self) -> Result<Value> {
+        let floor = self.frames.last().map(|f| f.height).unwrap_or(0);
+        if self.stack.len() <= floor {
+            return Err(WasmError::StackUnderflow);
+        }
+        self.stack.pop().ok_or(WasmError::StackUnderflow)
+    }
+
+    fn pop_i32(&mut self) -> Result<i32> {
+        self.pop()?.as_i32()
+    }
+
+    fn pop_i64(&mut self) -> Result<i64> {
+        self.pop()?.as_i64()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::{CodeSection, Function, FunctionType, LocalEntry, TableType};
+
+    fn single_function_module(params: Vec<ValueType>, results: Vec<ValueType>,
+                              locals: Vec<LocalEntry>, body: Vec<u8>) -> WasmModule {
+        WasmModule {
+            types: vec![FunctionType { params, results }],
+            functions: vec![Function { type_idx: 0 }],
+            code: vec![CodeSection { locals, body }],
+            ..Default::default()
+        }
+    }
+
+    fn countdown_module() -> WasmModule {
+        // Sum 1..=n with a loop
+        let body = vec![
+            0x03, 0x40, // loop
+            0x20, 0x01, 0x20, 0x00, 0x6A, 0x21, 0x01, // acc += n
+            0x20, 0x00, 0x41, 0x01, 0x6B, 0x22, 0x00, // n -= 1
+            0x0D, 0x00, // br_if 0
+            0x0B, // end
+            0x20, 0x01, // local.get acc
+            0x0B, // end
+        ];
+        single_function_module(vec![ValueType::I32], vec![ValueType::I32],
+            vec![LocalEntry { count: 1, value_type: ValueType::I32 }], body)
+    }
+
+    #[test]
+    fn test_multi_value_block_type() {
+        let module_with_body = |body: Vec<u8>| {
+            let mut module = single_function_module(vec![], vec![ValueType::I32, ValueType::I32], vec![], body);
+            // type 1: block (param i32) (result i32 i32)
+            module.types.push(FunctionType { params: vec![ValueType::I32], results: vec![ValueType::I32, ValueType::I32] });
+            module
+        };
+
+        let module = module_with_body(vec![
+            0x41, 0x05, // i32.const 5
+            0x02, 0x01, // block (type 1)
+            0x41, 0x02, 0x6C, // i32.const 2, i32.mul
+            0x41, 0x07, // i32.const 7
+            0x0B, // end
+            0x0B,
+        ]);
+        let mut interpreter = Interpreter::new(module).unwrap();
+        assert_eq!(interpreter.invoke(0, &[], None).unwrap(), vec![Value::I32(10), Value::I32(7)]);
+
+        // The block leaves one value where its type declares two
+        let mut interpreter = Interpreter::new(module_with_body(vec![0x41, 0x05, 0x02, 0x01, 0x0B, 0x41, 0x00, 0x0B])).unwrap();
+        assert!(matches!(interpreter.invoke(0, &[], None), Err(WasmError::TypeMismatch)));
+
+        // An i64 operand cannot satisfy the i32 param
+        let mut interpreter = Interpreter::new(module_with_body(vec![0x42, 0x05, 0x02, 0x01, 0x0B, 0x0B])).unwrap();
+        assert!(matches!(interpreter.invoke(0, &[], None), Err(WasmError::TypeMismatch)));
+    }
+
+    #[test]
+    fn test_loop_countdown() {
+        let mut interpreter = Interpreter::new(countdown_module()).unwrap();
+        let results = interpreter.invoke(0, &[Value::I32(4)], None).unwrap();
+        assert_eq!(results, vec![Value::I32(10)]);
+        assert!(interpreter.findings().is_empty());
+    }
+
+    fn if_else_module() -> WasmModule {
+        // (if (result i32) (local.get 0) (then i32.const 10) (else i32.const 20)) + 1
+        let body = vec![
+            0x20, 0x00, // local.get 0
+            0x04, 0x7F, // if (result i32)
+            0x41, 0x0A, // i32.const 10
+            0x05, // else
+            0x41, 0x14, // i32.const 20
+            0x0B, // end
+            0x41, 0x01, 0x6A, // i32.const 1, i32.add
+            0x0B,
+        ];
+        single_function_module(vec![ValueType::I32], vec![ValueType::I32], vec![], body)
+    }
+
+    #[test]
+    fn test_dispatch_strategies_agree() {
+        let div = single_function_module(vec![ValueType::I32, ValueType::I32], vec![ValueType::I32], vec![],
+            vec![0x20, 0x00, 0x20, 0x01, 0x6D, 0x0B]);
+        // i64.div_s decodes but has no handler
+        let unsupported = single_function_module(vec![], vec![], vec![], vec![0x42, 0x01, 0x42, 0x01, 0x7F, 0x1A, 0x0B]);
+        let cases = [
+            (countdown_module(), vec![Value::I32(100)]),
+            (if_else_module(), vec![Value::I32(0)]),
+            (if_else_module(), vec![Value::I32(1)]),
+            (div.clone(), vec![Value::I32(-9), Value::I32(2)]),
+            (div, vec![Value::I32(1), Value::I32(0)]),
+            (unsupported, vec![]),
+        ];
+
+        for (module, args) in cases {
+            let run = |dispatch: &'static dyn Dispatch| {
+                let config = InterpreterConfig { dispatch, ..Default::default() };
+                let mut interpreter = Interpreter::with_config(module.clone(), config).unwrap();
+                let result = interpreter.invoke(0, &args, None);
+                (format!("{:?}", result), interpreter.instruction_count())
+            };
+            assert_eq!(run(&MatchDispatch), run(&JumpTableDispatch));
+        }
+    }
+
+    #[test]
+    fn test_if_else_arms() {
+        let mut interpreter = Interpreter::new(if_else_module()).unwrap();
+        assert_eq!(interpreter.invoke(0, &[Value::I32(5)], None).unwrap(), vec![Value::I32(11)]);
+        assert_eq!(interpreter.invoke(0, &[Value::I32(0)], None).unwrap(), vec![Value::I32(21)]);
+    }
+
+    #[test]
+    fn test_if_type_index_block_and_missing_else() {
+        // Type 1 is [] -> [i32]; the if without an else is skipped when false
+        let body = vec![
+            0x20, 0x00, 0x04, 0x01, 0x41, 0x03, 0x0B, // if (type 1) i32.const 3 end
+            0x20, 0x00, 0x45, 0x04, 0x40, 0x00, 0x0B, // if (not param) unreachable end
+            0x0B,
+        ];
+        let mut module = single_function_module(vec![ValueType::I32], vec![ValueType::I32], vec![], body);
+        module.types.push(FunctionType { params: vec![], results: vec![ValueType::I32] });
+
+        let mut interpreter = Interpreter::new(module).unwrap();
+        assert_eq!(interpreter.invoke(0, &[Value::I32(1)], None).unwrap(), vec![Value::I32(3)]);
+    }
+
+    #[test]
+    fn test_call_marshals_args_and_results() {
+        let add = FunctionType { params: vec![ValueType::I32, ValueType::I32], results: vec![ValueType::I32] };
+        let module = WasmModule {
+            types: vec![FunctionType { params: vec![], results: vec![ValueType::I32] }, add],
+            functions: vec![Function { type_idx: 0 }, Function { type_idx: 1 }],
+            code: vec![
+                CodeSection { locals: vec![], body: vec![0x41, 0x02, 0x41, 0x03, 0x10, 0x01, 0x0B] },
+                CodeSection { locals: vec![], body: vec![0x20, 0x00, 0x20, 0x01, 0x6A, 0x0B] },
+            ],
+            ..Default::default()
+        };
+        let mut interpreter = Interpreter::new(module).unwrap();
+
+        // Step through: two consts, the call, then the callee body
+        interpreter.start(0, &[]).unwrap();
+        for _ in 0..3 {
+            interpreter.step(None).unwrap();
+        }
+        assert_eq!(interpreter.inspector().get_call_depth(), 2);
+        assert_eq!(interpreter.inspector().get_call_stack()[1].instruction_pointer, 4);
+        for _ in 0..4 {
+            interpreter.step(None).unwrap();
+        }
+        assert_eq!(interpreter.inspector().get_call_depth(), 1);
+        assert_eq!(interpreter.stack(), &[Value::I32(5)]);
+
+        assert_eq!(interpreter.invoke(0, &[], None).unwrap(), vec![Value::I32(5)]);
+    }
+
+    #[test]
+    fn test_call_depth_limit() {
+        // Function 0 calls itself forever
+        let module = single_function_module(vec![], vec![], vec![], vec![0x10, 0x00, 0x0B]);
+        let config = InterpreterConfig { max_stack_depth: 16, ..Default::default() };
+        let mut interpreter = Interpreter::with_config(module, config).unwrap();
+
+        assert!(matches!(interpreter.invoke(0, &[], None), Err(WasmError::StackOverflow)));
+    }
+
+    #[test]
+    fn test_trap_recovery() {
+        // i32.load from far past the end of a one-page memory, plus 1
+        let body = vec![0x41, 0x80, 0x80, 0x80, 0x01, 0x28, 0x02, 0x00, 0x41, 0x01, 0x6A, 0x0B];
+        let module = single_function_module(vec![], vec![ValueType::I32], vec![], body);
+        let mut memory = LinearMemory::new(1, None).unwrap();
+
+        let mut default = Interpreter::new(module.clone()).unwrap();
+        let result = default.invoke(0, &[], Some(&mut memory));
+        assert!(matches!(result, Err(WasmError::MemoryOutOfBounds { .. })));
+
+        let mut recovering = Interpreter::new(module).unwrap().with_trap_recovery(|reason| match reason {
+            TrapReason::OutOfBounds { .. } => Some(Value::I32(0)),
+            _ => None,
+        });
+        assert_eq!(recovering.invoke(0, &[], Some(&mut memory)).unwrap(), vec![Value::I32(1)]);
+    }
+
+    #[test]
+    fn test_trap_recovery_respects_result_arity_and_type() {
+        let recover_all = |module| Interpreter::new(module).unwrap().with_trap_recovery(|_| Some(Value::I32(0)));
+        let mut memory = LinearMemory::new(1, None).unwrap();
+
+        // i32.store past the end produces nothing, so there is nothing to replace
+        let body = vec![0x41, 0x80, 0x80, 0x80, 0x01, 0x41, 0x07, 0x36, 0x02, 0x00, 0x0B];
+        let mut store = recover_all(single_function_module(vec![], vec![], vec![], body));
+        assert!(matches!(store.invoke(0, &[], Some(&mut memory)), Err(WasmError::MemoryOutOfBounds { .. })));
+
+        let mut unreachable = recover_all(single_function_module(vec![], vec![], vec![], vec![0x00, 0x0B]));
+        assert!(matches!(unreachable.invoke(0, &[], None), Err(WasmError::Unreachable)));
+
+        // i64.load can't be replaced by an i32
+        let body = vec![0x41, 0x80, 0x80, 0x80, 0x01, 0x29, 0x03, 0x00, 0x0B];
+        let mut load = recover_all(single_function_module(vec![], vec![ValueType::I64], vec![], body));
+        assert!(matches!(load.invoke(0, &[], Some(&mut memory)), Err(WasmError::MemoryOutOfBounds { .. })));
+
+        // i32.div_s by zero recovers with the i32
+        let body = vec![0x41, 0x01, 0x41, 0x00, 0x6D, 0x0B];
+        let mut div = recover_all(single_function_module(vec![], vec![ValueType::I32], vec![], body));
+        assert_eq!(div.invoke(0, &[], None).unwrap(), vec![Value::I32(0)]);
+    }
+
+    /// Maps a single 4-byte register at 0x1000 to host state.
+    struct StatusRegister {
+        writes: std::rc::Rc<std::cell::RefCell<Vec<u32>>>,
+    }
+
+    impl MemoryMediator for StatusRegister {
+        fn on_read(&mut self, address: u32, _size: u32) -> Option<Vec<u8>> {
+            (address == 0x1000).then(|| 0xABCDu32.to_le_bytes().to_vec())
+        }
+
+        fn on_write(&mut self, address: u32, bytes: &[u8]) -> bool {
+            if address != 0x1000 {
+                return false;
+            }
+            self.writes.borrow_mut().push(u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]));
+            true
+        }
+    }
+
+    #[test]
+    fn test_memory_mediator_register() {
+        let body = vec![
+            0x41, 0x80, 0x20, 0x41, 0x07, 0x36, 0x02, 0x00, // store 7 to 0x1000 (register)
+            0x41, 0x10, 0x41, 0x05, 0x36, 0x02, 0x00, // store 5 to 0x10 (memory)
+            0x41, 0x80, 0x20, 0x28, 0x02, 0x00, // load 0x1000
+            0x41, 0x10, 0x28, 0x02, 0x00, 0x6A, // load 0x10, add
+            0x0B,
+        ];
+        let module = single_function_module(vec![], vec![ValueType::I32], vec![], body);
+        let mut memory = LinearMemory::new(1, None).unwrap();
+        let writes = std::rc::Rc::new(std::cell::RefCell::new(Vec::new()));
+
+        let mut interpreter = Interpreter::new(module).unwrap()
+            .with_memory_mediator(StatusRegister { writes: writes.clone() });
+        let results = interpreter.invoke(0, &[], Some(&mut memory)).unwrap();
+
+        assert_eq!(results, vec![Value::I32(0xABCD + 5)]);
+        assert_eq!(*writes.borrow(), vec![7]);
+        assert_eq!(memory.read_u32(0x1000).unwrap(), 0);
+    }
+
+    fn eval_i32_binop(a: i32, b: i32, opcode: u8) -> Value {
+        let body = vec![0x20, 0x00, 0x20, 0x01, opcode, 0x0B];
+        let module = single_function_module(vec![ValueType::I32, ValueType::I32], vec![ValueType::I32], vec![], body);
+        let mut interpreter = Interpreter::new(module).unwrap();
+        interpreter.invoke(0, &[Value::I32(a), Value::I32(b)], None).unwrap()[0]
+    }
+
+    #[test]
+    fn test_bitwise_and_shifts() {
+        assert_eq!(eval_i32_binop(1, 33, 0x74), Value::I32(2)); // shl
+        assert_eq!(eval_i32_binop(-1, 28, 0x76), Value::I32(15)); // shr_u
+        assert_eq!(eval_i32_binop(-16, 2, 0x75), Value::I32(-4)); // shr_s
+        assert_eq!(eval_i32_binop(0x80000000u32 as i32, 1, 0x77), Value::I32(1)); // rotl
+        assert_eq!(eval_i32_binop(1, 1, 0x78), Value::I32(0x80000000u32 as i32)); // rotr
+        assert_eq!(eval_i32_binop(0b1100, 0b1010, 0x73), Value::I32(0b0110)); // xor
+
+        // i64.shl by 65 shifts by 1
+        let body = vec![0x20, 0x00, 0x42, 0xC1, 0x00, 0x86, 0x0B];
+        let module = single_function_module(vec![ValueType::I64], vec![ValueType::I64], vec![], body);
+        let mut interpreter = Interpreter::new(module).unwrap();
+        assert_eq!(interpreter.invoke(0, &[Value::I64(3)], None).unwrap(), vec![Value::I64(6)]);
+    }
+
+    #[test]
+    fn test_reference_values() {
+        // ref.is_null(ref.null func), ref.is_null(ref.func 0)
+        let body = vec![0xD0, 0x70, 0xD1, 0x0B];
+        let module = single_function_module(vec![], vec![ValueType::I32], vec![], body);
+        let mut interpreter = Interpreter::new(module).unwrap();
+        assert_eq!(interpreter.invoke(0, &[], None).unwrap(), vec![Value::I32(1)]);
+
+        let body = vec![0xD2, 0x00, 0xD1, 0x0B];
+        let module = single_function_module(vec![], vec![ValueType::I32], vec![], body);
+        let mut interpreter = Interpreter::new(module).unwrap();
+        assert_eq!(interpreter.invoke(0, &[], None).unwrap(), vec![Value::I32(0)]);
+
+        // table.set 0 (i32.const 1) (ref.func 0), then table.get 0 (i32.const 1)
+        let body = vec![0x41, 0x01, 0xD2, 0x00, 0x26, 0x00, 0x41, 0x01, 0x25, 0x00, 0x0B];
+        let mut module = single_function_module(vec![], vec![ValueType::FuncRef], vec![], body);
+        module.table = Some(TableType { element_type: RefType::FuncRef, min: 2, max: None });
+        let mut interpreter = Interpreter::new(module).unwrap();
+        assert_eq!(interpreter.invoke(0, &[], None).unwrap(), vec![Value::FuncRef(Some(0))]);
+        assert_eq!(interpreter.tables()[0], vec![Value::FuncRef(None), Value::FuncRef(Some(0))]);
+    }
+
+    #[test]
+    fn test_oversized_table_rejected() {
+        let mut module = single_function_module(vec![], vec![], vec![], vec![0x0B]);
+        module.table = Some(TableType { element_type: RefType::FuncRef, min: u32::MAX, max: None });
+        match Interpreter::new(module) {
+            Err(WasmError::Runtime(msg)) => assert!(msg.contains("limit is 10000")),
+            other => panic!("expected table limit error, got {:?}", other.map(|_| ())),
+        }
+    }
+
+    #[test]
+    fn test_load_store_offsets() {
+        let mut memory = LinearMemory::new(1, None).unwrap();
+        memory.write_bytes(4, &0x1234_5678i32.to_le_bytes()).unwrap();
+
+        // i32.load offset=4 from base 0
+        let body = vec![0x41, 0x00, 0x28, 0x02, 0x04, 0x0B];
+        let module = single_function_module(vec![], vec![ValueType::I32], vec![], body);
+        let mut interpreter = Interpreter::new(module).unwrap();
+        assert_eq!(interpreter.invoke(0, &[], Some(&mut memory)).unwrap(), vec![Value::I32(0x1234_5678)]);
+
+        // i32.store8 offset=16 of -1, then i32.load8_s and i64.load8_u read it back
+        let body = vec![
+            0x41, 0x00, 0x41, 0x7F, 0x3A, 0x00, 0x10, // i32.store8 offset=16
+            0x41, 0x10, 0x2C, 0x00, 0x00, // i32.load8_s
+            0x41, 0x00, 0x31, 0x00, 0x10, // i64.load8_u offset=16
+            0x0B,
+        ];
+        let module = single_function_module(vec![], vec![ValueType::I32, ValueType::I64], vec![], body);
+        let mut interpreter = Interpreter::new(module).unwrap();
+        assert_eq!(interpreter.invoke(0, &[], Some(&mut memory)).unwrap(), vec![Value::I32(-1), Value::I64(0xFF)]);
+
+        // base 0xFFFFFFF0 + offset 0x20 overflows the 32-bit address space
+        let body = vec![0x41, 0x70, 0x28, 0x02, 0x20, 0x0B];
+        let module = single_function_module(vec![], vec![ValueType::I32], vec![], body);
+        let mut interpreter = Interpreter::new(module).unwrap();
+        assert!(matches!(interpreter.invoke(0, &[], Some(&mut memory)),
+            Err(WasmError::MemoryOutOfBounds { .. })));
+    }
+
+    #[test]
+    fn test_typed_select() {
+        // select (result i32) between 10 and 20, then add 1
+        let body = vec![0x41, 0x0A, 0x41, 0x14, 0x20, 0x00, 0x1C, 0x01, 0x7F, 0x41, 0x01, 0x6A, 0x0B];
+        let instructions = decoder::decode_function_body(&body).unwrap();
+        assert_eq!(instructions[3].to_text(), "select (result i32)");
+        assert_eq!(instructions[4].to_text(), "i32.const 1");
+
+        let module = single_function_module(vec![ValueType::I32], vec![ValueType::I32], vec![], body);
+        let mut interpreter = Interpreter::new(module).unwrap();
+        assert_eq!(interpreter.invoke(0, &[Value::I32(1)], None).unwrap(), vec![Value::I32(11)]);
+        assert_eq!(interpreter.invoke(0, &[Value::I32(0)], None).unwrap(), vec![Value::I32(21)]);
+
+        // Operands must match the declared type
+        let body = vec![0x41, 0x0A, 0x41, 0x14, 0x20, 0x00, 0x1C, 0x01, 0x7E, 0x1A, 0x0B];
+        let module = single_function_module(vec![ValueType::I32], vec![], vec![], body);
+        let mut interpreter = Interpreter::new(module).unwrap();
+        assert!(matches!(interpreter.invoke(0, &[Value::I32(1)], None), Err(WasmError::TypeMismatch)));
+    }
+
+    #[test]
+    fn test_use_before_init_local() {
+        let body = vec![
+            0x20, 0x01, // local.get 1 (never set)
+            0x20, 0x00, // local.get 0 (param)
+            0x6A, // i32.add
+            0x0B, // end
+        ];
+        let module = single_function_module(vec![ValueType::I32], vec![ValueType::I32],
+            vec![LocalEntry { count: 1, value_type: ValueType::I32 }], body);
+
+        let config = InterpreterConfig { track_initialization: true, ..Default::default() };
+        let mut interpreter = Interpreter::with_config(module.clone(), config).unwrap();
+        interpreter.invoke(0, &[Value::I32(1)], None).unwrap();
+
+        let findings = interpreter.findings();
+        assert_eq!(findings.len(), 1);
+        assert_eq!(findings[0].pattern_name, "UseBeforeInit");
+        assert_eq!(findings[0].instruction_offset, 0);
+        assert!(findings[0].description.contains("local 1"));
+
+        // An out-of-range index is an invalid module, not a panic
+        let body = vec![0x20, 0x05, 0x0B];
+        let bad = single_function_module(vec![], vec![ValueType::I32], vec![], body);
+        let config = InterpreterConfig { track_initialization: true, ..Default::default() };
+        let mut interpreter = Interpreter::with_config(bad, config).unwrap();
+        assert!(matches!(interpreter.invoke(0, &[], None), Err(WasmError::InvalidModule)));
+
+        // Tracking is off by default
+        let mut untracked = Interpreter::new(module).unwrap();
+        untracked.invoke(0, &[Value::I32(1)], None).unwrap();
+        assert!(untracked.findings().is_empty());
+    }
+
+    #[test]
+    fn test_use_before_init_grown_memory() {
+        let body = vec![
+            0x41, 0x01, 0x40, 0x00, 0x1A, // memory.grow 1, drop
+            0x41, 0x80, 0x80, 0x04, 0x41, 0x07, 0x36, 0x02, 0x00, // store 7 at 0x10000
+            0x41, 0x80, 0x80, 0x04, 0x28, 0x02, 0x00, 0x1A, // load 0x10000 (written)
+            0x41, 0x84, 0x80, 0x04, 0x28, 0x02, 0x00, // load 0x10004 (never written)
+            0x0B,
+        ];
+        let module = single_function_module(vec![], vec![ValueType::I32], vec![], body);
+        let mut memory = LinearMemory::new(1, Some(4)).unwrap();
+
+        let config = InterpreterConfig { track_initialization: true, ..Default::default() };
+        let mut interpreter = Interpreter::with_config(module, config).unwrap();
+        let results = interpreter.invoke(0, &[], Some(&mut memory)).unwrap();
+
+        assert_eq!(results, vec![Value::I32(0)]);
+        assert_eq!(interpreter.findings().len(), 1);
+        assert!(interpreter.findings()[0].description.contains("0x10004"));
+    }
+
+    #[test]
+    fn test_init_tracker_full_address_space() {
+        let mut tracker = MemoryInitTracker::default();
+        tracker.grow(65535, 65536);
+        assert_eq!(tracker.grown_start, 65535 * PAGE_SIZE as u64);
+        assert_eq!(tracker.written.len(), PAGE_SIZE as usize);
+        assert!(!tracker.is_initialized(u32::MAX, 1));
+    }
+}
@@ -2,9 +2,15 @@ pub mod parser;
 pub mod memory;
 pub mod interpreter;
 pub mod vm;
+pub mod verifier;
+pub mod registry;
 pub mod error;
 pub mod sandbox;
 pub mod abi;
+pub mod decoder;
+pub mod rng;
+pub mod debugger;
+pub mod static_analysis;
 
 pub use error::{WasmError, Result};
 pub use vm::{WasmModule, WasmInstance, WasmEngine};
@@ -1,15 +1,45 @@
 use crate::error::{WasmError, Result};
 use std::collections::HashMap;
+use std::fmt;
 
 const PAGE_SIZE: u32 = 65536; // 64KB
 const MAX_PAGES: u32 = 65536; // 4GB max
 
-#[derive(Debug)]
+/// Called with the requested page count before `grow` resizes; an error
+/// vetoes the growth.
+pub type GrowCallback = Box<dyn FnMut(u32) -> Result<()>>;
+
 pub struct LinearMemory {
     data: Vec<u8>,
     min_pages: u32,
     max_pages: Option<u32>,
     current_pages: u32,
+    grow_callback: Option<GrowCallback>,
+}
+
+/// A copy of a memory's pages, taken by `LinearMemory::snapshot`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct MemorySnapshot {
+    current_pages: u32,
+    data: Vec<u8>,
+}
+
+impl MemorySnapshot {
+    pub fn pages(&self) -> u32 {
+        self.current_pages
+    }
+}
+
+impl fmt::Debug for LinearMemory {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("LinearMemory")
+            .field("data", &self.data)
+            .field("min_pages", &self.min_pages)
+            .field("max_pages", &self.max_pages)
+            .field("current_pages", &self.current_pages)
+            .field("grow_callback", &self.grow_callback.is_some())
+            .finish()
+    }
 }
 
 impl LinearMemory {
@@ -29,9 +59,14 @@ impl LinearMemory {
             min_pages,
             max_pages,
             current_pages: min_pages,
+            grow_callback: None,
         })
     }
     
+    pub fn set_grow_callback(&mut self, callback: impl FnMut(u32) -> Result<()> + 'static) {
+        self.grow_callback = Some(Box::new(callback));
+    }
+    
     pub fn size(&self) -> u32 {
         self.current_pages
     }
@@ -50,6 +85,10 @@ impl LinearMemory {
             return Err(WasmError::Runtime("Memory grow failed".to_string()));
         }
         
+        if let Some(callback) = self.grow_callback.as_mut() {
+            callback(new_size)?;
+        }
+        
         let new_byte_size = (new_size * PAGE_SIZE) as usize;
         self.data.resize(new_byte_size, 0);
         self.current_pages = new_size;
@@ -131,6 +170,13 @@ impl LinearMemory {
         Ok(&self.data[address as usize..(address + len) as usize])
     }
     
+    /// Mutable view of `len` bytes at `address` for filling in place. The
+    /// borrow ends before any `grow`, which may reallocate the backing buffer.
+    pub fn read_bytes_mut(&mut self, address: u32, len: u32) -> Result<&mut [u8]> {
+        self.check_bounds(address, len)?;
+        Ok(&mut self.data[address as usize..(address + len) as usize])
+    }
+    
     pub fn write_bytes(&mut self, address: u32, data: &[u8]) -> Result<()> {
         self.check_bounds(address, data.len() as u32)?;
         let start = address as usize;
@@ -139,6 +185,44 @@ impl LinearMemory {
         Ok(())
     }
     
+    pub fn snapshot(&self) -> MemorySnapshot {
+        MemorySnapshot {
+            current_pages: self.current_pages,
+            data: self.data.clone(),
+        }
+    }
+    
+    /// Roll memory back to `snapshot`, shrinking or growing it to the
+    /// snapshot's size. Rejects snapshots larger than `max_pages`.
+    pub fn restore(&mut self, snapshot: &MemorySnapshot) -> Result<()> {
+        if let Some(max) = self.max_pages {
+            if snapshot.current_pages > max {
+                return Err(WasmError::Runtime(format!(
+                    "Snapshot has {} pages, memory allows at most {}", snapshot.current_pages, max
+                )));
+            }
+        }
+        
+        self.data.clone_from(&snapshot.data);
+        self.current_pages = snapshot.current_pages;
+        Ok(())
+    }
+    
+    /// `memory.copy`: copy `len` bytes from `src` to `dest`; the ranges may overlap.
+    pub fn copy_within(&mut self, dest: u32, src: u32, len: u32) -> Result<()> {
+        self.check_bounds(src, len)?;
+        self.check_bounds(dest, len)?;
+        self.data.copy_within(src as usize..(src + len) as usize, dest as usize);
+        Ok(())
+    }
+    
+    /// `memory.fill`: set `len` bytes starting at `dest` to `value`.
+    pub fn fill(&mut self, dest: u32, value: u8, len: u32) -> Result<()> {
+        self.check_bounds(dest, len)?;
+        self.data[dest as usize..(dest + len) as usize].fill(value);
+        Ok(())
+    }
+    
     fn check_bounds(&self, address: u32, size: u32) -> Result<()> {
         let end_address = address.checked_add(size)
             .ok_or(WasmError::MemoryOutOfBounds { address, size })?;
@@ -198,6 +282,78 @@ mod tests {
         assert_eq!(memory.size(), 1);
     }
 
+    #[test]
+    fn test_copy_within_overlapping() {
+        let mut memory = LinearMemory::new(1, None).unwrap();
+        memory.write_bytes(0, &[1, 2, 3, 4, 5]).unwrap();
+        
+        memory.copy_within(2, 0, 3).unwrap();
+        assert_eq!(memory.read_bytes(0, 5).unwrap(), &[1, 2, 1, 2, 3]);
+        
+        memory.copy_within(0, 1, 4).unwrap();
+        assert_eq!(memory.read_bytes(0, 5).unwrap(), &[2, 1, 2, 3, 3]);
+        
+        assert!(memory.copy_within(PAGE_SIZE - 2, 0, 3).is_err());
+    }
+
+    #[test]
+    fn test_snapshot_restore() {
+        let mut memory = LinearMemory::new(1, Some(4)).unwrap();
+        memory.write_bytes(0, b"before").unwrap();
+        let snapshot = memory.snapshot();
+        
+        memory.write_bytes(0, b"after!").unwrap();
+        memory.grow(1).unwrap();
+        memory.restore(&snapshot).unwrap();
+        assert_eq!(memory.read_bytes(0, 6).unwrap(), b"before");
+        assert_eq!(memory.size(), 1);
+        
+        let mut large = LinearMemory::new(3, None).unwrap();
+        let mut small = LinearMemory::new(1, Some(2)).unwrap();
+        large.write_u8(0, 1).unwrap();
+        assert!(small.restore(&large.snapshot()).is_err());
+        assert_eq!(small.read_u8(0).unwrap(), 0);
+    }
+
+    #[test]
+    fn test_read_bytes_mut() {
+        let mut memory = LinearMemory::new(1, None).unwrap();
+        let buffer = memory.read_bytes_mut(8, 8).unwrap();
+        buffer[..4].copy_from_slice(&7u32.to_le_bytes());
+        buffer[4..].copy_from_slice(&9u32.to_le_bytes());
+        
+        assert_eq!(memory.read_u32(8).unwrap(), 7);
+        assert_eq!(memory.read_u32(12).unwrap(), 9);
+        assert!(memory.read_bytes_mut(PAGE_SIZE - 4, 8).is_err());
+    }
+
+    #[test]
+    fn test_fill_bounds() {
+        let mut memory = LinearMemory::new(1, None).unwrap();
+        memory.fill(10, 0xAB, 4).unwrap();
+        assert_eq!(memory.read_bytes(9, 6).unwrap(), &[0, 0xAB, 0xAB, 0xAB, 0xAB, 0]);
+        
+        let result = memory.fill(PAGE_SIZE - 1, 0xFF, 2);
+        assert!(matches!(result, Err(WasmError::MemoryOutOfBounds { .. })));
+        assert_eq!(memory.read_u8(PAGE_SIZE - 1).unwrap(), 0);
+    }
+
+    #[test]
+    fn test_grow_callback_veto() {
+        let mut memory = LinearMemory::new(1, None).unwrap();
+        memory.set_grow_callback(|pages| {
+            if pages > 5 {
+                return Err(WasmError::Runtime(format!("Memory quota exceeded: {} pages", pages)));
+            }
+            Ok(())
+        });
+        
+        assert_eq!(memory.grow(4).unwrap(), 1);
+        assert!(memory.grow(1).is_err());
+        assert_eq!(memory.size(), 5);
+        assert_eq!(memory.data.len(), (5 * PAGE_SIZE) as usize);
+    }
+
     #[test]
     fn test_memory_read_write() {
         let mut memory = LinearMemory::new(1, None).unwrap();
@@ -1,17 +1,134 @@
 use crate::error::{WasmError, Result};
 use byteorder::{LittleEndian, ReadBytesExt};
+use std::collections::hash_map::DefaultHasher;
+use std::collections::HashSet;
+use std::hash::{Hash, Hasher};
 use std::io::{Cursor, Read};
 
 const WASM_MAGIC: u32 = 0x6d736100; // "\0asm"
 const WASM_VERSION: u32 = 0x01;
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Default)]
 pub struct WasmModule {
     pub types: Vec<FunctionType>,
+    pub imports: Vec<Import>,
     pub functions: Vec<Function>,
+    pub table: Option<TableType>,
     pub memory: Option<MemoryType>,
+    pub globals: Vec<Global>,
     pub exports: Vec<Export>,
     pub code: Vec<CodeSection>,
+    pub data_segments: Vec<DataSegment>,
+    /// Function run at instantiation, from the start section.
+    pub start: Option<u32>,
+    pub custom_sections: Vec<CustomSection>,
+    /// Length of the binary this module was parsed from; 0 for modules built by hand.
+    pub total_size: usize,
+    /// Hash of the bytes this module was parsed from.
+    #[cfg(feature = "hashing")]
+    pub(crate) content_hash: Option<String>,
+}
+
+/// Work done up front by instantiating a module, before any export runs.
+#[derive(Debug, Clone, PartialEq)]
+pub struct InstantiationCost {
+    pub initial_memory_bytes: u64,
+    /// Bytes of active data segments copied into memory.
+    pub data_copy_bytes: u64,
+    pub global_init_count: usize,
+    pub has_start: bool,
+}
+
+impl WasmModule {
+    /// Content hash recorded while parsing; `None` for modules built by hand,
+    /// which can be hashed with `registry::content_hash` instead.
+    #[cfg(feature = "hashing")]
+    pub fn content_hash(&self) -> Option<&str> {
+        self.content_hash.as_deref()
+    }
+    
+    pub fn instantiation_cost(&self) -> InstantiationCost {
+        InstantiationCost {
+            initial_memory_bytes: self.memory.as_ref().map_or(0, |m| m.min as u64 * 65536),
+            data_copy_bytes: self.data_segments.iter()
+                .filter(|segment| !segment.is_passive())
+                .map(|segment| segment.bytes.len() as u64)
+                .sum(),
+            global_init_count: self.globals.len(),
+            has_start: self.start.is_some(),
+        }
+    }
+    
+    /// Imported plus defined tables.
+    pub fn table_count(&self) -> u32 {
+        let imported = self.imports.iter().filter(|i| matches!(i.kind, ImportKind::Table(_))).count();
+        (imported + self.table.is_some() as usize) as u32
+    }
+    
+    /// Features the `target_features` custom section marks as used (`+`)
+    /// or required (`=`). Empty when the section is absent or malformed.
+    pub fn required_features(&self) -> Vec<String> {
+        let section = match self.custom_sections.iter().find(|s| s.name == "target_features") {
+            Some(section) => section,
+            None => return Vec::new(),
+        };
+        
+        let mut cursor = Cursor::new(section.data.as_slice());
+        let mut features = Vec::new();
+        let count = match WasmParser::read_leb128_u32(&mut cursor) {
+            Ok(count) => count,
+            Err(_) => return Vec::new(),
+        };
+        for _ in 0..count {
+            let prefix = match cursor.read_u8() {
+                Ok(prefix) => prefix,
+                Err(_) => break,
+            };
+            let name = match WasmParser::read_name(&mut cursor) {
+                Ok(name) => name,
+                Err(_) => break,
+            };
+            if prefix == b'+' || prefix == b'=' {
+                features.push(name);
+            }
+        }
+        features
+    }
+    
+    /// One hash per function over its signature, locals and body, so modules
+    /// that differ only in names, exports or custom sections fingerprint alike.
+    pub fn function_fingerprints(&self) -> Vec<u64> {
+        self.code.iter().enumerate().map(|(idx, code)| {
+            let mut hasher = DefaultHasher::new();
+            if let Some(ty) = self.functions.get(idx).and_then(|f| self.types.get(f.type_idx as usize)) {
+                ty.params.hash(&mut hasher);
+                ty.results.hash(&mut hasher);
+            }
+            for local in &code.locals {
+                local.count.hash(&mut hasher);
+                local.value_type.hash(&mut hasher);
+            }
+            code.body.hash(&mut hasher);
+            hasher.finish()
+        }).collect()
+    }
+}
+
+/// Jaccard overlap of the two modules' function fingerprints, from 0.0 to 1.0.
+pub fn similarity(a: &WasmModule, b: &WasmModule) -> f32 {
+    let a: HashSet<u64> = a.function_fingerprints().into_iter().collect();
+    let b: HashSet<u64> = b.function_fingerprints().into_iter().collect();
+    let union = a.union(&b).count();
+    if union == 0 {
+        return 0.0;
+    }
+    a.intersection(&b).count() as f32 / union as f32
+}
+
+#[derive(Debug, Clone)]
+pub struct CustomSection {
+    pub name: String,
+    pub data: Vec<u8>,
 }
 
 #[derive(Debug, Clone)]
@@ -20,17 +137,53 @@ pub struct FunctionType {
     pub results: Vec<ValueType>,
 }
 
+#[derive(Debug, Clone)]
+pub struct Import {
+    pub module: String,
+    pub name: String,
+    pub kind: ImportKind,
+}
+
+#[derive(Debug, Clone)]
+pub enum ImportKind {
+    Function(u32),
+    Table(TableType),
+    Memory(MemoryType),
+    Global { value_type: ValueType, mutable: bool },
+}
+
 #[derive(Debug, Clone)]
 pub struct Function {
     pub type_idx: u32,
 }
 
+#[derive(Debug, Clone)]
+pub struct TableType {
+    pub element_type: RefType,
+    pub min: u32,
+    pub max: Option<u32>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum RefType {
+    FuncRef,
+    ExternRef,
+}
+
 #[derive(Debug, Clone)]
 pub struct MemoryType {
     pub min: u32,
     pub max: Option<u32>,
 }
 
+#[derive(Debug, Clone)]
+pub struct Global {
+    pub value_type: ValueType,
+    pub mutable: bool,
+    /// Raw constant initializer expression, including the trailing `end`.
+    pub init_expr: Vec<u8>,
+}
+
 #[derive(Debug, Clone)]
 pub struct Export {
     pub name: String,
@@ -52,24 +205,80 @@ pub struct CodeSection {
     pub body: Vec<u8>,
 }
 
+#[derive(Debug, Clone)]
+pub struct DataSegment {
+    pub memory_index: u32,
+    /// Raw constant offset expression, including the trailing `end`; empty
+    /// for passive segments.
+    pub offset_expr: Vec<u8>,
+    pub bytes: Vec<u8>,
+}
+
+impl DataSegment {
+    pub fn is_passive(&self) -> bool {
+        self.offset_expr.is_empty()
+    }
+    
+    /// Load address when the offset is an `i32.const`; `None` for passive
+    /// segments and `global.get` offsets.
+    pub fn static_offset(&self) -> Option<u32> {
+        match crate::decoder::decode_instruction(&self.offset_expr, 0).ok()? {
+            instr if instr.opcode == 0x41 => match instr.immediates.first() {
+                Some(crate::decoder::Immediate::I32(offset)) => Some(*offset as u32),
+                _ => None,
+            },
+            _ => None,
+        }
+    }
+}
+
 #[derive(Debug, Clone)]
 pub struct LocalEntry {
     pub count: u32,
     pub value_type: ValueType,
 }
 
-#[derive(Debug, Clone, Copy, PartialEq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub enum ValueType {
     I32,
     I64,
     F32,
     F64,
+    FuncRef,
+    ExternRef,
+}
+
+/// Limits applied to a binary before any section is parsed.
+#[derive(Debug, Clone)]
+pub struct ParseLimits {
+    pub max_module_bytes: usize,
+}
+
+impl Default for ParseLimits {
+    fn default() -> Self {
+        Self {
+            max_module_bytes: 64 * 1024 * 1024, // 64MB
+        }
+    }
 }
 
 pub struct WasmParser;
 
 impl WasmParser {
     pub fn parse(bytes: &[u8]) -> Result<WasmModule> {
+        Self::parse_with_limits(bytes, &ParseLimits::default())
+    }
+    
+    pub fn parse_with_limits(bytes: &[u8], limits: &ParseLimits) -> Result<WasmModule> {
+        if bytes.len() > limits.max_module_bytes {
+            log::warn!("Rejected module of {} bytes, limit is {} bytes", bytes.len(), limits.max_module_bytes);
+            return Err(WasmError::LimitExceeded {
+                limit: "max_module_bytes",
+                actual: bytes.len() as u64,
+                max: limits.max_module_bytes as u64,
+            });
+        }
+        
         let mut cursor = Cursor::new(bytes);
         
         // Validate magic number and version
@@ -84,12 +293,13 @@ impl WasmParser {
         }
         
         let mut module = WasmModule {
-            types: Vec::new(),
-            functions: Vec::new(),
-            memory: None,
-            exports: Vec::new(),
-            code: Vec::new(),
+            total_size: bytes.len(),
+            ..Default::default()
         };
+        #[cfg(feature = "hashing")]
+        let mut hasher = crate::registry::ContentHasher::new();
+        #[cfg(feature = "hashing")]
+        let mut hashed = 0usize;
         
         // Parse sections
         while cursor.position() < bytes.len() as u64 {
@@ -98,15 +308,47 @@ impl WasmParser {
             
             match section_id {
                 1 => module.types = Self::parse_type_section(&mut cursor, section_size)?,
+                2 => module.imports = Self::parse_import_section(&mut cursor, section_size)?,
                 3 => module.functions = Self::parse_function_section(&mut cursor, section_size)?,
+                4 => module.table = Self::parse_table_section(&mut cursor, section_size)?,
                 5 => module.memory = Self::parse_memory_section(&mut cursor, section_size)?,
+                6 => module.globals = Self::parse_global_section(&mut cursor, section_size)?,
                 7 => module.exports = Self::parse_export_section(&mut cursor, section_size)?,
+                8 => module.start = Some(Self::read_leb128_u32(&mut cursor)?),
+                0 => module.custom_sections.push(Self::parse_custom_section(&mut cursor, section_size)?),
                 10 => module.code = Self::parse_code_section(&mut cursor, section_size)?,
+                11 => module.data_segments = Self::parse_data_section(&mut cursor, section_size)?,
                 _ => {
                     // Skip unknown sections
                     cursor.set_position(cursor.position() + section_size as u64);
                 }
             }
+            
+            // Hash each section as it is consumed rather than rereading the module
+            #[cfg(feature = "hashing")]
+            {
+                let consumed = (cursor.position() as usize).min(bytes.len());
+                hasher.update(&bytes[hashed..consumed]);
+                hashed = consumed;
+            }
+        }
+        
+        #[cfg(feature = "hashing")]
+        {
+            hasher.update(&bytes[hashed..]);
+            module.content_hash = Some(hasher.finish());
+        }
+        
+        if let Some(export) = module.exports.iter()
+            .find(|e| matches!(e.kind, ExportKind::Table) && e.index >= module.table_count()) {
+            log::warn!("Export '{}' refers to missing table {}", export.name, export.index);
+            return Err(WasmError::InvalidModule);
+        }
+        
+        if module.functions.len() != module.code.len() {
+            log::warn!("Module declares {} functions but has {} code bodies",
+                module.functions.len(), module.code.len());
+            return Err(WasmError::InvalidModule);
         }
         
         Ok(module)
@@ -114,7 +356,7 @@ impl WasmParser {
     
     fn parse_type_section(cursor: &mut Cursor<&[u8]>, _size: u32) -> Result<Vec<FunctionType>> {
         let count = Self::read_leb128_u32(cursor)?;
-        let mut types = Vec::with_capacity(count as usize);
+        let mut types = Vec::with_capacity(Self::capacity_for(cursor, count));
         
         for _ in 0..count {
             let form = cursor.read_u8()?;
@@ -123,13 +365,13 @@ impl WasmParser {
             }
             
             let param_count = Self::read_leb128_u32(cursor)?;
-            let mut params = Vec::with_capacity(param_count as usize);
+            let mut params = Vec::with_capacity(Self::capacity_for(cursor, param_count));
             for _ in 0..param_count {
                 params.push(Self::read_value_type(cursor)?);
             }
             
             let result_count = Self::read_leb128_u32(cursor)?;
-            let mut results = Vec::with_capacity(result_count as usize);
+            let mut results = Vec::with_capacity(Self::capacity_for(cursor, result_count));
             for _ in 0..result_count {
                 results.push(Self::read_value_type(cursor)?);
             }
@@ -140,9 +382,34 @@ impl WasmParser {
         Ok(types)
     }
     
+    fn parse_import_section(cursor: &mut Cursor<&[u8]>, _size: u32) -> Result<Vec<Import>> {
+        let count = Self::read_leb128_u32(cursor)?;
+        let mut imports = Vec::with_capacity(Self::capacity_for(cursor, count));
+        
+        for _ in 0..count {
+            let module = Self::read_name(cursor)?;
+            let name = Self::read_name(cursor)?;
+            
+            let kind = match cursor.read_u8()? {
+                0 => ImportKind::Function(Self::read_leb128_u32(cursor)?),
+                1 => ImportKind::Table(Self::read_table_type(cursor)?),
+                2 => ImportKind::Memory(Self::read_memory_type(cursor)?),
+                3 => {
+                    let (value_type, mutable) = Self::read_global_type(cursor)?;
+                    ImportKind::Global { value_type, mutable }
+                }
+                _ => return Err(WasmError::InvalidModule),
+            };
+            
+            imports.push(Import { module, name, kind });
+        }
+        
+        Ok(imports)
+    }
+    
     fn parse_function_section(cursor: &mut Cursor<&[u8]>, _size: u32) -> Result<Vec<Function>> {
         let count = Self::read_leb128_u32(cursor)?;
-        let mut functions = Vec::with_capacity(count as usize);
+        let mut functions = Vec::with_capacity(Self::capacity_for(cursor, count));
         
         for _ in 0..count {
             let type_idx = Self::read_leb128_u32(cursor)?;
@@ -152,32 +419,47 @@ impl WasmParser {
         Ok(functions)
     }
     
+    fn parse_table_section(cursor: &mut Cursor<&[u8]>, _size: u32) -> Result<Option<TableType>> {
+        let count = Self::read_leb128_u32(cursor)?;
+        if count == 0 {
+            return Ok(None);
+        }
+        // The engine models a single `module.table`, so only one table is accepted
+        if count > 1 {
+            return Err(WasmError::InvalidModule);
+        }
+        
+        Ok(Some(Self::read_table_type(cursor)?))
+    }
+    
     fn parse_memory_section(cursor: &mut Cursor<&[u8]>, _size: u32) -> Result<Option<MemoryType>> {
         let count = Self::read_leb128_u32(cursor)?;
         if count == 0 {
             return Ok(None);
         }
         
-        let flags = cursor.read_u8()?;
-        let min = Self::read_leb128_u32(cursor)?;
-        let max = if flags & 0x01 != 0 {
-            Some(Self::read_leb128_u32(cursor)?)
-        } else {
-            None
-        };
+        Ok(Some(Self::read_memory_type(cursor)?))
+    }
+    
+    fn parse_global_section(cursor: &mut Cursor<&[u8]>, _size: u32) -> Result<Vec<Global>> {
+        let count = Self::read_leb128_u32(cursor)?;
+        let mut globals = Vec::with_capacity(Self::capacity_for(cursor, count));
+        
+        for _ in 0..count {
+            let (value_type, mutable) = Self::read_global_type(cursor)?;
+            let init_expr = Self::read_const_expr(cursor)?;
+            globals.push(Global { value_type, mutable, init_expr });
+        }
         
-        Ok(Some(MemoryType { min, max }))
+        Ok(globals)
     }
     
     fn parse_export_section(cursor: &mut Cursor<&[u8]>, _size: u32) -> Result<Vec<Export>> {
         let count = Self::read_leb128_u32(cursor)?;
-        let mut exports = Vec::with_capacity(count as usize);
+        let mut exports = Vec::with_capacity(Self::capacity_for(cursor, count));
         
         for _ in 0..count {
-            let name_len = Self::read_leb128_u32(cursor)?;
-            let mut name_bytes = vec![0u8; name_len as usize];
-            cursor.read_exact(&mut name_bytes)?;
-            let name = String::from_utf8_lossy(&name_bytes).to_string();
+            let name = Self::read_name(cursor)?;
             
             let kind = match cursor.read_u8()? {
                 0 => ExportKind::Function,
@@ -196,22 +478,23 @@ impl WasmParser {
     
     fn parse_code_section(cursor: &mut Cursor<&[u8]>, _size: u32) -> Result<Vec<CodeSection>> {
         let count = Self::read_leb128_u32(cursor)?;
-        let mut code_sections = Vec::with_capacity(count as usize);
+        let mut code_sections = Vec::with_capacity(Self::capacity_for(cursor, count));
         
         for _ in 0..count {
             let body_size = Self::read_leb128_u32(cursor)?;
+            let body_start = cursor.position();
             let local_count = Self::read_leb128_u32(cursor)?;
             
-            let mut locals = Vec::with_capacity(local_count as usize);
+            let mut locals = Vec::with_capacity(Self::capacity_for(cursor, local_count));
             for _ in 0..local_count {
                 let count = Self::read_leb128_u32(cursor)?;
                 let value_type = Self::read_value_type(cursor)?;
                 locals.push(LocalEntry { count, value_type });
             }
             
-            let body_len = body_size - (cursor.position() as u32 - (body_size - body_size));
-            let mut body = vec![0u8; body_len as usize];
-            cursor.read_exact(&mut body)?;
+            let locals_len = (cursor.position() - body_start) as u32;
+            let body_len = body_size.checked_sub(locals_len).ok_or(WasmError::InvalidModule)?;
+            let body = Self::read_bytes(cursor, body_len)?;
             
             code_sections.push(CodeSection { locals, body });
         }
@@ -219,16 +502,143 @@ impl WasmParser {
         Ok(code_sections)
     }
     
+    fn parse_data_section(cursor: &mut Cursor<&[u8]>, _size: u32) -> Result<Vec<DataSegment>> {
+        let count = Self::read_leb128_u32(cursor)?;
+        let mut segments = Vec::with_capacity(Self::capacity_for(cursor, count));
+        
+        for _ in 0..count {
+            let (memory_index, offset_expr) = match Self::read_leb128_u32(cursor)? {
+                0 => (0, Self::read_const_expr(cursor)?),
+                1 => (0, Vec::new()),
+                2 => {
+                    let memory_index = Self::read_leb128_u32(cursor)?;
+                    (memory_index, Self::read_const_expr(cursor)?)
+                }
+                _ => return Err(WasmError::InvalidModule),
+            };
+            
+            let len = Self::read_leb128_u32(cursor)?;
+            let bytes = Self::read_bytes(cursor, len)?;
+            
+            segments.push(DataSegment { memory_index, offset_expr, bytes });
+        }
+        
+        Ok(segments)
+    }
+    
+    fn parse_custom_section(cursor: &mut Cursor<&[u8]>, size: u32) -> Result<CustomSection> {
+        let start = cursor.position();
+        let name = Self::read_name(cursor)?;
+        let consumed = (cursor.position() - start) as u32;
+        
+        let data_len = size.checked_sub(consumed).ok_or(WasmError::InvalidModule)?;
+        let data = Self::read_bytes(cursor, data_len)?;
+        
+        Ok(CustomSection { name, data })
+    }
+    
+    fn read_name(cursor: &mut Cursor<&[u8]>) -> Result<String> {
+        let name_len = Self::read_leb128_u32(cursor)?;
+        let name_bytes = Self::read_bytes(cursor, name_len)?;
+        Ok(String::from_utf8_lossy(&name_bytes).to_string())
+    }
+    
+    /// Reads `len` bytes, rejecting lengths that run past the end of the input
+    /// before anything is allocated for them.
+    fn read_bytes(cursor: &mut Cursor<&[u8]>, len: u32) -> Result<Vec<u8>> {
+        let remaining = (cursor.get_ref().len() as u64).saturating_sub(cursor.position());
+        if len as u64 > remaining {
+            return Err(WasmError::InvalidModule);
+        }
+        
+        let mut bytes = vec![0u8; len as usize];
+        cursor.read_exact(&mut bytes)?;
+        Ok(bytes)
+    }
+    
+    /// Caps a declared entry count by the bytes left, since every entry takes
+    /// at least one byte, so a bogus count cannot force a huge reservation.
+    fn capacity_for(cursor: &Cursor<&[u8]>, count: u32) -> usize {
+        let remaining = (cursor.get_ref().len() as u64).saturating_sub(cursor.position());
+        (count as u64).min(remaining) as usize
+    }
+    
+    fn read_limits(cursor: &mut Cursor<&[u8]>) -> Result<(u32, Option<u32>)> {
+        let flags = cursor.read_u8()?;
+        let min = Self::read_leb128_u32(cursor)?;
+        let max = if flags & 0x01 != 0 {
+            Some(Self::read_leb128_u32(cursor)?)
+        } else {
+            None
+        };
+        
+        Ok((min, max))
+    }
+    
+    fn read_memory_type(cursor: &mut Cursor<&[u8]>) -> Result<MemoryType> {
+        let (min, max) = Self::read_limits(cursor)?;
+        Ok(MemoryType { min, max })
+    }
+    
+    fn read_table_type(cursor: &mut Cursor<&[u8]>) -> Result<TableType> {
+        let element_type = match cursor.read_u8()? {
+            0x70 => RefType::FuncRef,
+            0x6F => RefType::ExternRef,
+            _ => return Err(WasmError::InvalidModule),
+        };
+        let (min, max) = Self::read_limits(cursor)?;
+        Ok(TableType { element_type, min, max })
+    }
+    
+    fn read_global_type(cursor: &mut Cursor<&[u8]>) -> Result<(ValueType, bool)> {
+        let value_type = Self::read_value_type(cursor)?;
+        let mutable = match cursor.read_u8()? {
+            0x00 => false,
+            0x01 => true,
+            _ => return Err(WasmError::InvalidModule),
+        };
+        Ok((value_type, mutable))
+    }
+    
     fn read_value_type(cursor: &mut Cursor<&[u8]>) -> Result<ValueType> {
         match cursor.read_u8()? {
             0x7F => Ok(ValueType::I32),
             0x7E => Ok(ValueType::I64),
             0x7D => Ok(ValueType::F32),
             0x7C => Ok(ValueType::F64),
+            0x70 => Ok(ValueType::FuncRef),
+            0x6F => Ok(ValueType::ExternRef),
             _ => Err(WasmError::InvalidModule),
         }
     }
     
+    fn read_const_expr(cursor: &mut Cursor<&[u8]>) -> Result<Vec<u8>> {
+        let start = cursor.position() as usize;
+        
+        loop {
+            match cursor.read_u8()? {
+                0x0B => break,
+                0x41 | 0x42 => Self::skip_leb128(cursor)?, // i32.const / i64.const
+                0x43 => cursor.set_position(cursor.position() + 4), // f32.const
+                0x44 => cursor.set_position(cursor.position() + 8), // f64.const
+                0x23 | 0xD2 => Self::skip_leb128(cursor)?, // global.get / ref.func
+                0xD0 => { // ref.null
+                    cursor.read_u8()?;
+                }
+                _ => return Err(WasmError::InvalidModule),
+            }
+        }
+        
+        let end = cursor.position() as usize;
+        let bytes = *cursor.get_ref();
+        Ok(bytes[start..end].to_vec())
+    }
+    
+    fn skip_leb128(cursor: &mut Cursor<&[u8]>) -> Result<()> {
+        while cursor.read_u8()? & 0x80 != 0 {}
+        Ok(())
+    }
+    
     fn read_leb128_u32(cursor: &mut Cursor<&[u8]>) -> Result<u32> {
         let mut result = 0u32;
         let mut shift = 0;
@@ -272,4 +682,261 @@ mod tests {
         let result = WasmParser::parse(&bytes);
         assert!(matches!(result, Err(WasmError::InvalidMagic)));
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn test_parse_globals_and_table() {
+        let bytes = [
+            0x00, 0x61, 0x73, 0x6d, // magic
+            0x01, 0x00, 0x00, 0x00, // version
+            0x04, 0x04, 0x01, 0x70, 0x00, 0x02, // table: funcref, min 2
+            0x06, 0x0B, 0x02, // global section, 2 globals
+            0x7F, 0x00, 0x41, 0x0B, 0x0B, // i32 const = 11
+            0x7E, 0x01, 0x42, 0x00, 0x0B, // mut i64 = 0
+        ];
+        
+        let module = WasmParser::parse(&bytes).unwrap();
+        let table = module.table.unwrap();
+        assert_eq!(table.element_type, RefType::FuncRef);
+        assert_eq!(table.min, 2);
+        assert_eq!(table.max, None);
+        
+        assert_eq!(module.globals.len(), 2);
+        assert!(!module.globals[0].mutable);
+        assert_eq!(module.globals[0].init_expr, vec![0x41, 0x0B, 0x0B]);
+        assert_eq!(module.globals[1].value_type, ValueType::I64);
+        assert!(module.globals[1].mutable);
+    }
+
+    #[test]
+    fn test_multiple_tables_rejected() {
+        let bytes = [
+            0x00, 0x61, 0x73, 0x6d, // magic
+            0x01, 0x00, 0x00, 0x00, // version
+            0x04, 0x07, 0x02, 0x70, 0x00, 0x01, 0x70, 0x00, 0x10, // tables: funcref min 1, funcref min 16
+        ];
+        assert!(matches!(WasmParser::parse(&bytes), Err(WasmError::InvalidModule)));
+    }
+
+    #[test]
+    fn test_table_export_validated() {
+        let mut bytes = vec![
+            0x00, 0x61, 0x73, 0x6d, // magic
+            0x01, 0x00, 0x00, 0x00, // version
+            0x04, 0x05, 0x01, 0x70, 0x01, 0x01, 0x08, // table: funcref, min 1, max 8
+            0x07, 0x05, 0x01, 0x01, b't', 0x01, 0x00, // export "t" table 0
+        ];
+        let module = WasmParser::parse(&bytes).unwrap();
+        assert_eq!(module.table_count(), 1);
+        assert_eq!(module.table.as_ref().unwrap().max, Some(8));
+        
+        // Point the export at table 1, which doesn't exist
+        let last = bytes.len() - 1;
+        bytes[last] = 0x01;
+        assert!(matches!(WasmParser::parse(&bytes), Err(WasmError::InvalidModule)));
+        
+        let info = crate::static_analysis::StaticAnalyzer::new().analyze(&module).unwrap().module_info;
+        assert_eq!(info.table_size, Some(1));
+    }
+
+    #[test]
+    fn test_parse_data_segments() {
+        let bytes = [
+            0x00, 0x61, 0x73, 0x6d, // magic
+            0x01, 0x00, 0x00, 0x00, // version
+            0x05, 0x03, 0x01, 0x00, 0x01, // memory: min 1
+            0x0B, 0x0F, 0x02, // data section, 2 segments
+            0x00, 0x41, 0x80, 0x01, 0x0B, 0x05, b'h', b'e', b'l', b'l', b'o', // active at 128
+            0x01, 0x02, 0xDE, 0xAD, // passive
+        ];
+        
+        let module = WasmParser::parse(&bytes).unwrap();
+        assert_eq!(module.data_segments.len(), 2);
+        
+        let active = &module.data_segments[0];
+        assert_eq!(active.memory_index, 0);
+        assert_eq!(active.offset_expr, vec![0x41, 0x80, 0x01, 0x0B]);
+        assert_eq!(active.static_offset(), Some(128));
+        assert_eq!(active.bytes, b"hello");
+        
+        assert!(module.data_segments[1].is_passive());
+        assert_eq!(module.data_segments[1].bytes, vec![0xDE, 0xAD]);
+    }
+
+    #[test]
+    fn test_instantiation_cost() {
+        let bytes = [
+            0x00, 0x61, 0x73, 0x6d, // magic
+            0x01, 0x00, 0x00, 0x00, // version
+            0x05, 0x03, 0x01, 0x00, 0x64, // memory: min 100
+            0x06, 0x06, 0x01, 0x7F, 0x00, 0x41, 0x00, 0x0B, // global: i32 = 0
+            0x08, 0x01, 0x00, // start: function 0
+            0x0B, 0x0C, 0x02, // data section, 2 segments
+            0x00, 0x41, 0x00, 0x0B, 0x04, 1, 2, 3, 4, // active at 0
+            0x01, 0x02, 5, 6, // passive, copied only by memory.init
+        ];
+        
+        let cost = WasmParser::parse(&bytes).unwrap().instantiation_cost();
+        assert_eq!(cost, InstantiationCost {
+            initial_memory_bytes: 100 * 65536,
+            data_copy_bytes: 4,
+            global_init_count: 1,
+            has_start: true,
+        });
+    }
+
+    #[test]
+    fn test_parse_code_body_after_locals() {
+        let bytes = [
+            0x00, 0x61, 0x73, 0x6d, // magic
+            0x01, 0x00, 0x00, 0x00, // version
+            0x03, 0x03, 0x02, 0x00, 0x00, // functions 0 and 1 have type 0
+            0x0A, 0x10, 0x02, // code section, 2 bodies
+            0x0B, 0x02, 0x02, 0x7F, 0x01, 0x7E, // 2 x i32, 1 x i64
+            0x20, 0x00, 0x20, 0x01, 0x6A, 0x0B, // local.get 0, local.get 1, i32.add, end
+            0x02, 0x00, 0x0B, // no locals, end
+        ];
+
+        let module = WasmParser::parse(&bytes).unwrap();
+        assert_eq!(module.code.len(), 2);
+        assert_eq!(module.code[0].locals.len(), 2);
+        assert_eq!(module.code[0].locals[0].count, 2);
+        assert_eq!(module.code[0].locals[1].value_type, ValueType::I64);
+        assert_eq!(module.code[0].body, vec![0x20, 0x00, 0x20, 0x01, 0x6A, 0x0B]);
+        assert_eq!(module.code[1].body, vec![0x0B]);
+    }
+
+    #[test]
+    fn test_function_code_count_mismatch() {
+        let bytes = [
+            0x00, 0x61, 0x73, 0x6d, // magic
+            0x01, 0x00, 0x00, 0x00, // version
+            0x01, 0x04, 0x01, 0x60, 0x00, 0x00, // type: () -> ()
+            0x03, 0x03, 0x02, 0x00, 0x00, // two functions
+            0x0A, 0x04, 0x01, 0x02, 0x00, 0x0B, // one code body
+        ];
+        assert!(matches!(WasmParser::parse(&bytes), Err(WasmError::InvalidModule)));
+        
+        let mut fixed = bytes[..bytes.len() - 11].to_vec();
+        fixed.extend_from_slice(&[0x03, 0x02, 0x01, 0x00, 0x0A, 0x04, 0x01, 0x02, 0x00, 0x0B]); // one function
+        assert_eq!(WasmParser::parse(&fixed).unwrap().code.len(), 1);
+    }
+
+    #[cfg(feature = "hashing")]
+    #[test]
+    fn test_streaming_content_hash() {
+        let bytes = [
+            0x00, 0x61, 0x73, 0x6d, // magic
+            0x01, 0x00, 0x00, 0x00, // version
+            0x01, 0x04, 0x01, 0x60, 0x00, 0x00, // type: () -> ()
+            0x03, 0x02, 0x01, 0x00, // one function
+            0x00, 0x04, 0x03, b'a', b'b', b'c', // custom section "abc"
+            0x0A, 0x04, 0x01, 0x02, 0x00, 0x0B, // one code body
+        ];
+        let module = WasmParser::parse(&bytes).unwrap();
+        assert_eq!(module.content_hash(), Some(crate::registry::content_hash(&bytes).as_str()));
+        assert_eq!(WasmModule::default().content_hash(), None);
+    }
+
+    #[test]
+    fn test_parse_imports() {
+        let mut bytes = vec![
+            0x00, 0x61, 0x73, 0x6d, // magic
+            0x01, 0x00, 0x00, 0x00, // version
+        ];
+        let mut section = vec![0x02]; // 2 imports
+        for (module, name) in [("env", "log"), ("env", "memory")] {
+            section.push(module.len() as u8);
+            section.extend_from_slice(module.as_bytes());
+            section.push(name.len() as u8);
+            section.extend_from_slice(name.as_bytes());
+            if name == "log" {
+                section.extend_from_slice(&[0x00, 0x03]); // function, type 3
+            } else {
+                section.extend_from_slice(&[0x02, 0x00, 0x01]); // memory, min 1, no max
+            }
+        }
+        bytes.push(0x02);
+        bytes.push(section.len() as u8);
+        bytes.extend(section);
+        
+        let module = WasmParser::parse(&bytes).unwrap();
+        assert_eq!(module.imports.len(), 2);
+        assert_eq!(module.imports[0].module, "env");
+        assert_eq!(module.imports[0].name, "log");
+        assert!(matches!(module.imports[0].kind, ImportKind::Function(3)));
+        match &module.imports[1].kind {
+            ImportKind::Memory(mem) => {
+                assert_eq!(mem.min, 1);
+                assert_eq!(mem.max, None);
+            }
+            other => panic!("expected memory import, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_module_size_limit() {
+        // Garbage past the magic would fail with InvalidMagic if parsing started
+        let bytes = vec![0xFF; 1024];
+        let limits = ParseLimits { max_module_bytes: 512 };
+        
+        let err = WasmParser::parse_with_limits(&bytes, &limits).unwrap_err();
+        assert!(matches!(err, WasmError::LimitExceeded { limit: "max_module_bytes", actual: 1024, max: 512 }));
+        assert!(err.to_string().contains("max_module_bytes"));
+        assert!(matches!(WasmParser::parse(&bytes), Err(WasmError::InvalidMagic)));
+    }
+    
+    #[test]
+    fn test_declared_length_past_end_rejected() {
+        // Custom section whose name claims ~4GB while only a few bytes follow
+        let bytes = vec![
+            0x00, 0x61, 0x73, 0x6d, 0x01, 0x00, 0x00, 0x00,
+            0x00, 0x07, 0xFF, 0xFF, 0xFF, 0xFF, 0x0F, b'a', b'b',
+        ];
+        assert!(matches!(WasmParser::parse(&bytes), Err(WasmError::InvalidModule)));
+        
+        // Type section declaring ~4 billion entries
+        let bytes = vec![
+            0x00, 0x61, 0x73, 0x6d, 0x01, 0x00, 0x00, 0x00,
+            0x01, 0x05, 0xFF, 0xFF, 0xFF, 0xFF, 0x0F,
+        ];
+        assert!(WasmParser::parse(&bytes).is_err());
+    }
+
+    #[test]
+    fn test_parse_target_features() {
+        let mut section = vec![0x0F];
+        section.extend_from_slice(b"target_features");
+        section.extend_from_slice(&[0x02, b'+', 0x07]);
+        section.extend_from_slice(b"simd128");
+        section.extend_from_slice(&[b'-', 0x07]);
+        section.extend_from_slice(b"atomics");
+        
+        let mut bytes = vec![0x00, 0x61, 0x73, 0x6d, 0x01, 0x00, 0x00, 0x00, 0x00, section.len() as u8];
+        bytes.extend(section);
+        
+        let module = WasmParser::parse(&bytes).unwrap();
+        assert_eq!(module.custom_sections[0].name, "target_features");
+        assert_eq!(module.required_features(), vec!["simd128".to_string()]);
+    }
+
+    #[test]
+    fn test_function_similarity() {
+        fn module(bodies: &[&[u8]], export: &str) -> WasmModule {
+            WasmModule {
+                types: vec![FunctionType { params: vec![], results: vec![ValueType::I32] }],
+                functions: bodies.iter().map(|_| Function { type_idx: 0 }).collect(),
+                code: bodies.iter().map(|body| CodeSection { locals: vec![], body: body.to_vec() }).collect(),
+                exports: vec![Export { name: export.to_string(), kind: ExportKind::Function, index: 0 }],
+                ..Default::default()
+            }
+        }
+
+        let bodies: &[&[u8]] = &[&[0x41, 0x01, 0x0B], &[0x41, 0x02, 0x0B], &[0x41, 0x03, 0x0B]];
+        let original = module(bodies, "run");
+        let repackaged = module(bodies, "totally_different_name");
+        assert!(similarity(&original, &repackaged) > 0.99);
+
+        let unrelated = module(&[&[0x41, 0x07, 0x41, 0x01, 0x6A, 0x0B], &[0x41, 0x00, 0x0B]], "run");
+        assert!(similarity(&original, &unrelated) < 0.2);
+    }
+}
@@ -0,0 +1,110 @@
+use crate::error::{WasmError, Result};
+use std::collections::HashSet;
+
+#[cfg(feature = "hashing")]
+pub use self::hashing::{content_hash, ContentHasher};
+
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum RegistryMode {
+    /// Anything not denied is accepted.
+    #[default]
+    Denylist,
+    /// Only explicitly allowed hashes are accepted.
+    Allowlist,
+}
+
+/// Approved and banned modules by content hash.
+#[derive(Debug, Clone, Default)]
+pub struct ModuleRegistry {
+    mode: RegistryMode,
+    allowed: HashSet<String>,
+    denied: HashSet<String>,
+}
+
+impl ModuleRegistry {
+    pub fn new(mode: RegistryMode) -> Self {
+        Self { mode, ..Default::default() }
+    }
+
+    pub fn allow(&mut self, hash: &str) {
+        self.allowed.insert(hash.to_ascii_lowercase());
+    }
+
+    pub fn deny(&mut self, hash: &str) {
+        self.denied.insert(hash.to_ascii_lowercase());
+    }
+
+    /// Accept or reject a module by hash. The denylist wins over the allowlist.
+    pub fn check(&self, hash: &str) -> Result<()> {
+        let hash = hash.to_ascii_lowercase();
+        if self.denied.contains(&hash) {
+            return Err(WasmError::Runtime(format!("Module {} is denylisted", hash)));
+        }
+        if self.mode == RegistryMode::Allowlist && !self.allowed.contains(&hash) {
+            return Err(WasmError::Runtime(format!("Module {} is not allowlisted", hash)));
+        }
+        Ok(())
+    }
+}
+
+#[cfg(feature = "hashing")]
+mod hashing {
+    use sha2::{Digest, Sha256};
+    use std::fmt::Write;
+
+    /// Lowercase hex SHA-256 of the raw module bytes.
+    pub fn content_hash(bytes: &[u8]) -> String {
+        let mut hasher = ContentHasher::new();
+        hasher.update(bytes);
+        hasher.finish()
+    }
+
+    /// Incremental form of `content_hash`, fed chunk by chunk.
+    #[derive(Debug, Clone, Default)]
+    pub struct ContentHasher(Sha256);
+
+    impl ContentHasher {
+        pub fn new() -> Self {
+            Self::default()
+        }
+
+        pub fn update(&mut self, bytes: &[u8]) {
+            self.0.update(bytes);
+        }
+
+        pub fn finish(self) -> String {
+            self.0.finalize().iter().fold(String::with_capacity(64), |mut hex, byte| {
+                write!(hex, "{:02x}", byte).unwrap();
+                hex
+            })
+        }
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+
+        #[test]
+        fn test_content_hash() {
+            assert_eq!(content_hash(b"abc"), "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad");
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_registry_modes() {
+        let mut registry = ModuleRegistry::new(RegistryMode::Denylist);
+        registry.deny("ABCD");
+        assert!(registry.check("abcd").is_err());
+        assert!(registry.check("1234").is_ok());
+
+        let mut registry = ModuleRegistry::new(RegistryMode::Allowlist);
+        registry.allow("1234");
+        assert!(registry.check("1234").is_ok());
+        assert!(registry.check("5678").is_err());
+    }
+}
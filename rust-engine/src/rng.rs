@@ -0,0 +1,28 @@
+use std::cell::Cell;
+
+/// Deterministic generator (splitmix64) so a run can be replayed from its seed.
+#[derive(Debug, Clone)]
+pub struct SeededRng {
+    seed: u64,
+    state: Cell<u64>,
+}
+
+impl SeededRng {
+    pub fn new(seed: u64) -> Self {
+        Self { seed, state: Cell::new(seed) }
+    }
+    
+    pub fn seed(&self) -> u64 {
+        self.seed
+    }
+    
+    pub fn next_u32(&self) -> u32 {
+        let state = self.state.get().wrapping_add(0x9E37_79B9_7F4A_7C15);
+        self.state.set(state);
+        
+        let mut z = state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        ((z ^ (z >> 31)) >> 32) as u32
+    }
+}
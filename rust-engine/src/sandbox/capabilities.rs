@@ -1,4 +1,5 @@
-use std::collections::HashSet;
+use std::collections::{HashMap, HashSet};
+use std::time::Instant;
 
 #[derive(Debug, Clone, Hash, PartialEq, Eq)]
 pub enum Capability {
@@ -11,8 +12,10 @@ pub enum Capability {
     // Alert system
     SendAlert(AlertLevel),
     
-    // Time access
-    GetTime,
+    // Time access: wall-clock time can fingerprint the host, monotonic
+    // time only measures elapsed intervals
+    WallClock,
+    MonotonicTime,
     
     // Random number generation
     Random,
@@ -20,6 +23,9 @@ pub enum Capability {
     // Memory operations
     MemoryGrow,
     
+    // Persistent key-value storage
+    Storage,
+    
     // Network (if ever needed)
     NetworkAccess,
     
@@ -28,6 +34,28 @@ pub enum Capability {
     FileSystemWrite,
 }
 
+impl Capability {
+    /// The capability behind a name from static analysis `CapabilityRequirements`.
+    /// Names that describe resource needs rather than grants, such as
+    /// "HighComplexity", have no capability.
+    pub fn from_analysis_name(name: &str) -> Option<Self> {
+        match name {
+            "Log" => Some(Capability::Log),
+            "ReadSensor" => Some(Capability::ReadSensor(SensorType::Any)),
+            "SendAlert" => Some(Capability::SendAlert(AlertLevel::Any)),
+            "GetTime" | "WallClock" => Some(Capability::WallClock),
+            "MonotonicTime" => Some(Capability::MonotonicTime),
+            "Random" => Some(Capability::Random),
+            "MemoryGrow" => Some(Capability::MemoryGrow),
+            "Storage" => Some(Capability::Storage),
+            "NetworkAccess" => Some(Capability::NetworkAccess),
+            "FileSystemAccess" | "FileSystemRead" => Some(Capability::FileSystemRead),
+            "FileSystemWrite" => Some(Capability::FileSystemWrite),
+            _ => None,
+        }
+    }
+}
+
 #[derive(Debug, Clone, Hash, PartialEq, Eq)]
 pub enum SensorType {
     Temperature,
@@ -39,6 +67,21 @@ pub enum SensorType {
     Any,
 }
 
+impl SensorType {
+    /// The sensor behind an ABI sensor id.
+    pub fn from_id(sensor_id: u32) -> Option<Self> {
+        match sensor_id {
+            0 => Some(SensorType::Temperature),
+            1 => Some(SensorType::Humidity),
+            2 => Some(SensorType::Pressure),
+            3 => Some(SensorType::Motion),
+            4 => Some(SensorType::Light),
+            5 => Some(SensorType::Sound),
+            _ => None,
+        }
+    }
+}
+
 #[derive(Debug, Clone, Hash, PartialEq, Eq)]
 pub enum AlertLevel {
     Info,
@@ -48,22 +91,39 @@ pub enum AlertLevel {
     Any,
 }
 
-#[derive(Debug)]
+impl AlertLevel {
+    /// The level behind an ABI alert level argument.
+    pub fn from_level(level: u32) -> Option<Self> {
+        match level {
+            0 => Some(AlertLevel::Info),
+            1 => Some(AlertLevel::Warning),
+            2 => Some(AlertLevel::Error),
+            3 => Some(AlertLevel::Critical),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
 pub struct CapabilitySet {
     capabilities: HashSet<Capability>,
+    /// Grants that lapse at their deadline; expiry is checked on lookup.
+    timed_grants: HashMap<Capability, Instant>,
 }
 
 impl CapabilitySet {
     pub fn new() -> Self {
         Self {
             capabilities: HashSet::new(),
+            timed_grants: HashMap::new(),
         }
     }
     
     pub fn with_basic() -> Self {
         let mut set = Self::new();
         set.grant(Capability::Log);
-        set.grant(Capability::GetTime);
+        set.grant(Capability::WallClock);
+        set.grant(Capability::MonotonicTime);
         set.grant(Capability::Random);
         set
     }
@@ -84,28 +144,51 @@ impl CapabilitySet {
         self.capabilities.insert(capability);
     }
     
+    /// Grants `capability` until `deadline`, after which `has` treats it as absent.
+    pub fn grant_until(&mut self, capability: Capability, deadline: Instant) {
+        self.timed_grants.insert(capability, deadline);
+    }
+    
     pub fn revoke(&mut self, capability: &Capability) {
         self.capabilities.remove(capability);
+        self.timed_grants.remove(capability);
+    }
+    
+    fn is_granted(&self, capability: &Capability) -> bool {
+        self.capabilities.contains(capability)
+            || self.timed_grants.get(capability).is_some_and(|deadline| Instant::now() < *deadline)
     }
     
     pub fn has(&self, capability: &Capability) -> bool {
         // Check exact match first
-        if self.capabilities.contains(capability) {
+        if self.is_granted(capability) {
             return true;
         }
         
         // Check for wildcard permissions
         match capability {
             Capability::ReadSensor(_) => {
-                self.capabilities.contains(&Capability::ReadSensor(SensorType::Any))
+                self.is_granted(&Capability::ReadSensor(SensorType::Any))
             }
             Capability::SendAlert(_) => {
-                self.capabilities.contains(&Capability::SendAlert(AlertLevel::Any))
+                self.is_granted(&Capability::SendAlert(AlertLevel::Any))
             }
             _ => false,
         }
     }
     
+    /// Returns the analysis capability names that are not granted, for a
+    /// pre-flight check of a module's required capabilities.
+    pub fn has_all(&self, names: &[String]) -> Vec<String> {
+        names.iter()
+            .filter(|name| match Capability::from_analysis_name(name) {
+                Some(capability) => !self.has(&capability),
+                None => false,
+            })
+            .cloned()
+            .collect()
+    }
+    
     pub fn can_read_sensor(&self, sensor_type: &SensorType) -> bool {
         self.has(&Capability::ReadSensor(sensor_type.clone())) ||
         self.has(&Capability::ReadSensor(SensorType::Any))
@@ -117,11 +200,16 @@ impl CapabilitySet {
     }
     
     pub fn list_capabilities(&self) -> Vec<&Capability> {
-        self.capabilities.iter().collect()
+        let now = Instant::now();
+        let timed = self.timed_grants.iter()
+            .filter(|(capability, deadline)| now < **deadline && !self.capabilities.contains(*capability))
+            .map(|(capability, _)| capability);
+        self.capabilities.iter().chain(timed).collect()
     }
     
     pub fn clear(&mut self) {
         self.capabilities.clear();
+        self.timed_grants.clear();
     }
 }
 
@@ -180,6 +268,7 @@ impl CapabilityPolicy {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use std::time::Duration;
 
     #[test]
     fn test_capability_set_basic() {
@@ -187,7 +276,7 @@ mod tests {
         caps.grant(Capability::Log);
         
         assert!(caps.has(&Capability::Log));
-        assert!(!caps.has(&Capability::GetTime));
+        assert!(!caps.has(&Capability::WallClock));
     }
 
     #[test]
@@ -208,6 +297,32 @@ mod tests {
         assert!(caps.can_send_alert(&AlertLevel::Critical));
     }
 
+    #[test]
+    fn test_has_all_reports_missing() {
+        let caps = CapabilitySet::with_basic();
+        let required: Vec<String> = ["Log", "GetTime", "ReadSensor", "HighComplexity"]
+            .iter()
+            .map(|name| name.to_string())
+            .collect();
+        
+        assert_eq!(caps.has_all(&required), vec!["ReadSensor".to_string()]);
+        assert!(CapabilitySet::with_sensor_access().has_all(&required).is_empty());
+    }
+
+    #[test]
+    fn test_timed_grant_expires() {
+        let mut caps = CapabilitySet::new();
+        caps.grant_until(Capability::ReadSensor(SensorType::Any), Instant::now() + Duration::from_millis(10));
+        
+        assert!(caps.has(&Capability::ReadSensor(SensorType::Any)));
+        assert!(caps.can_read_sensor(&SensorType::Light));
+        
+        std::thread::sleep(Duration::from_millis(20));
+        assert!(!caps.has(&Capability::ReadSensor(SensorType::Any)));
+        assert!(!caps.can_read_sensor(&SensorType::Light));
+        assert!(caps.list_capabilities().is_empty());
+    }
+
     #[test]
     fn test_capability_policy() {
         let mut policy = CapabilityPolicy::strict();
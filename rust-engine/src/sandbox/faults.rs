@@ -0,0 +1,72 @@
+use std::cell::RefCell;
+use std::collections::HashMap;
+
+/// A failure to inject in place of a call's normal behaviour.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Fault {
+    /// Fail the call with `WasmError::Runtime`.
+    Fail(String),
+    /// Skip the call and return this value instead (e.g. `u32::MAX` for a -1
+    /// from `memory.grow`).
+    Return(u32),
+    /// Move the host clock by this many seconds from now on.
+    ClockJump(i64),
+}
+
+/// Scripted faults keyed by function name and call index, for deterministic
+/// resilience tests. Keys are ABI names (`wasm_read_sensor`, `wasm_get_time`),
+/// syscall names, or `memory.grow`.
+#[derive(Debug, Default)]
+pub struct FaultInjector {
+    script: HashMap<String, Vec<(u32, Fault)>>,
+    calls: RefCell<HashMap<String, u32>>,
+}
+
+impl FaultInjector {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Inject `fault` on the `call_index`-th (0-based) call to `name`.
+    pub fn inject(mut self, name: &str, call_index: u32, fault: Fault) -> Self {
+        self.script.entry(name.to_string()).or_default().push((call_index, fault));
+        self
+    }
+
+    /// Record a call to `name` and return the fault scripted for it, if any.
+    pub fn on_call(&self, name: &str) -> Option<Fault> {
+        let scripted = self.script.get(name)?;
+        let mut calls = self.calls.borrow_mut();
+        let count = calls.entry(name.to_string()).or_insert(0);
+        let index = *count;
+        *count += 1;
+
+        let fault = scripted.iter().find(|(at, _)| *at == index).map(|(_, fault)| fault.clone());
+        if let Some(fault) = &fault {
+            log::info!("Injecting fault into {} call {}: {:?}", name, index, fault);
+        }
+        fault
+    }
+
+    /// Calls seen so far to `name`; only scripted names are counted.
+    pub fn call_count(&self, name: &str) -> u32 {
+        self.calls.borrow().get(name).copied().unwrap_or(0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_fault_script() {
+        let injector = FaultInjector::new()
+            .inject("memory.grow", 1, Fault::Return(u32::MAX));
+
+        assert_eq!(injector.on_call("memory.grow"), None);
+        assert_eq!(injector.on_call("memory.grow"), Some(Fault::Return(u32::MAX)));
+        assert_eq!(injector.on_call("memory.grow"), None);
+        assert_eq!(injector.on_call("wasm_random"), None);
+        assert_eq!(injector.call_count("memory.grow"), 3);
+    }
+}
@@ -1,3 +1,5 @@
+use crate::rng::SeededRng;
+use crate::static_analysis::capabilities::SandboxConstraints;
 use std::time::{Duration, Instant};
 
 #[derive(Debug, Clone)]
@@ -71,6 +73,20 @@ impl ResourceLimits {
     }
 }
 
+/// Apply constraints recommended by static analysis; limits they don't
+/// cover keep their defaults.
+impl From<&SandboxConstraints> for ResourceLimits {
+    fn from(constraints: &SandboxConstraints) -> Self {
+        Self {
+            max_memory_pages: constraints.max_memory_pages,
+            max_cpu_time: Duration::from_millis(constraints.max_cpu_time_ms),
+            max_syscalls: constraints.max_syscalls,
+            max_instructions: constraints.max_instructions,
+            ..Self::default()
+        }
+    }
+}
+
 #[derive(Debug)]
 pub struct ResourceMonitor {
     limits: ResourceLimits,
@@ -123,6 +139,60 @@ impl ResourceMonitor {
     }
 }
 
+/// Decides at which instruction counts a soft limit check runs. With jitter,
+/// each interval varies so a module cannot learn the exact preemption points.
+#[derive(Debug)]
+pub struct CheckScheduler {
+    interval: u64,
+    jitter: u64,
+    rng: Option<SeededRng>,
+    next_check: u64,
+}
+
+impl CheckScheduler {
+    pub fn new(interval: u64) -> Self {
+        Self {
+            interval,
+            jitter: 0,
+            rng: None,
+            next_check: interval,
+        }
+    }
+    
+    /// Vary each interval by up to `jitter` instructions either way, drawn from `seed`.
+    pub fn with_jitter(mut self, jitter: u64, seed: u64) -> Self {
+        self.jitter = jitter;
+        self.rng = Some(SeededRng::new(seed));
+        self.next_check = self.schedule_after(0);
+        self
+    }
+    
+    pub fn next_check(&self) -> u64 {
+        self.next_check
+    }
+    
+    /// Whether a check is due at `count`. Always due once `count` exceeds
+    /// `hard_limit`, so jitter never delays enforcement past the limit.
+    pub fn is_due(&mut self, count: u64, hard_limit: u64) -> bool {
+        if count > hard_limit {
+            return true;
+        }
+        if count < self.next_check {
+            return false;
+        }
+        self.next_check = self.schedule_after(count);
+        true
+    }
+    
+    fn schedule_after(&self, count: u64) -> u64 {
+        let offset = match &self.rng {
+            Some(rng) if self.jitter > 0 => rng.next_u32() as u64 % (2 * self.jitter + 1),
+            _ => self.jitter,
+        };
+        count + (self.interval + offset).saturating_sub(self.jitter).max(1)
+    }
+}
+
 #[derive(Debug)]
 pub struct MemoryLimiter {
     max_pages: u32,
@@ -245,6 +315,22 @@ impl InstructionCounter {
 mod tests {
     use super::*;
 
+    #[test]
+    fn test_limits_from_sandbox_constraints() {
+        let constraints = SandboxConstraints {
+            max_cpu_time_ms: 10000,
+            max_memory_pages: 32,
+            ..SandboxConstraints::default()
+        };
+        
+        let limits = ResourceLimits::from(&constraints);
+        assert_eq!(limits.max_cpu_time, Duration::from_secs(10));
+        assert_eq!(limits.max_memory_pages, 32);
+        assert_eq!(limits.max_instructions, constraints.max_instructions);
+        assert_eq!(limits.max_syscalls, constraints.max_syscalls);
+        assert_eq!(limits.max_stack_depth, ResourceLimits::default().max_stack_depth);
+    }
+
     #[test]
     fn test_resource_limits_creation() {
         let limits = ResourceLimits::default();
@@ -279,6 +365,20 @@ mod tests {
         assert!(counter.increment(50).is_ok()); // Exactly at limit
     }
 
+    #[test]
+    fn test_check_scheduler_jitter() {
+        let mut plain = CheckScheduler::new(100);
+        assert!(!plain.is_due(99, 1000));
+        assert!(plain.is_due(100, 1000));
+        assert_eq!(plain.next_check(), 200);
+        
+        let first_checks: Vec<u64> = (0..8)
+            .map(|seed| CheckScheduler::new(100).with_jitter(40, seed).next_check())
+            .collect();
+        assert!(first_checks.iter().all(|&c| (60..=140).contains(&c)));
+        assert!(first_checks.iter().any(|&c| c != first_checks[0]));
+    }
+
     #[test]
     fn test_resource_monitor() {
         let limits = ResourceLimits::default();
@@ -2,31 +2,23 @@ pub mod limits;
 pub mod capabilities;
 pub mod syscall_trap;
 pub mod policy;
+pub mod faults;
 
 use crate::error::{WasmError, Result};
+use crate::parser::{ImportKind, WasmModule};
+use std::cell::{Ref, RefCell};
+use std::fmt;
 use std::time::{Duration, Instant};
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::collections::hash_map::RandomState;
+use std::hash::{BuildHasher, Hasher};
 
-#[derive(Debug, Clone)]
-pub struct ResourceLimits {
-    pub max_memory_pages: u32,
-    pub max_cpu_time: Duration,
-    pub max_syscalls: u32,
-    pub max_instructions: u64,
-}
+pub use limits::ResourceLimits;
 
-impl Default for ResourceLimits {
-    fn default() -> Self {
-        Self {
-            max_memory_pages: 256, // 16MB
-            max_cpu_time: Duration::from_secs(30),
-            max_syscalls: 1000,
-            max_instructions: 1_000_000,
-        }
-    }
-}
+/// Instructions between soft limit checks in `Sandbox::consume_instructions`.
+pub const DEFAULT_CHECK_INTERVAL: u64 = 10_000;
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct ResourceUsage {
     pub memory_pages: u32,
     pub cpu_time: Duration,
@@ -66,6 +58,164 @@ pub struct Sandbox {
     capabilities: capabilities::CapabilitySet,
     syscall_trap: syscall_trap::SyscallTrap,
     violations: Vec<SecurityViolation>,
+    /// Present in observe-only mode, where nothing is enforced.
+    activity_log: Option<RefCell<ActivityLog>>,
+    watchdog: Option<LimitWatchdog>,
+    check_scheduler: limits::CheckScheduler,
+    faults: Option<faults::FaultInjector>,
+    preemption_interval: Option<Duration>,
+    preemption_deadline: Option<Instant>,
+    syscall_rate_limit: Option<u32>,
+    /// Times of syscalls admitted in the last second, oldest first.
+    recent_syscalls: VecDeque<Instant>,
+    /// Capability each syscall requires; sensor and alert entries are narrowed
+    /// by the call's first argument.
+    syscall_capability_map: HashMap<String, capabilities::Capability>,
+}
+
+fn default_syscall_capabilities() -> HashMap<String, capabilities::Capability> {
+    use capabilities::{AlertLevel, Capability, SensorType};
+    [
+        ("wasm_log", Capability::Log),
+        ("wasm_read_sensor", Capability::ReadSensor(SensorType::Any)),
+        ("wasm_read_sensor_ex", Capability::ReadSensor(SensorType::Any)),
+        ("wasm_sensor_subscribe", Capability::ReadSensor(SensorType::Any)),
+        ("wasm_poll_events", Capability::ReadSensor(SensorType::Any)),
+        ("wasm_send_alert", Capability::SendAlert(AlertLevel::Any)),
+        ("wasm_get_time", Capability::WallClock),
+        ("wasm_monotonic_ms", Capability::MonotonicTime),
+        ("wasm_random", Capability::Random),
+        ("wasm_kv_get", Capability::Storage),
+        ("wasm_kv_set", Capability::Storage),
+        ("wasm_memory_grow", Capability::MemoryGrow),
+        ("wasm_http_post", Capability::NetworkAccess),
+    ]
+    .into_iter()
+    .map(|(name, capability)| (name.to_string(), capability))
+    .collect()
+}
+
+/// Raised once per resource when usage first crosses the watchdog threshold.
+#[derive(Debug, Clone)]
+pub struct LimitWarning {
+    pub violation_type: ViolationType,
+    pub resource: &'static str,
+    pub used: f64,
+    pub limit: f64,
+    /// Usage at the moment the threshold was crossed.
+    pub usage: ResourceUsage,
+}
+
+/// Current and maximum value of one limited resource.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ResourceGauge {
+    pub current: f64,
+    pub max: f64,
+    /// `current / max`, clamped to `0.0..=1.0`.
+    pub utilization: f64,
+}
+
+impl ResourceGauge {
+    fn new(current: f64, max: f64) -> Self {
+        let utilization = if max == 0.0 { 0.0 } else { (current / max).min(1.0) };
+        Self { current, max, utilization }
+    }
+}
+
+/// Point-in-time view of how close each limit is to being reached. CPU time
+/// is in seconds.
+#[derive(Debug, Clone, PartialEq)]
+pub struct UsageReport {
+    pub memory_pages: ResourceGauge,
+    pub cpu_time: ResourceGauge,
+    pub syscalls: ResourceGauge,
+    pub instructions: ResourceGauge,
+}
+
+/// Called with each `LimitWarning`; hosts capture memory or stack snapshots here,
+/// while the module is still running.
+pub type LimitWarningFn = Box<dyn FnMut(&LimitWarning)>;
+
+struct LimitWatchdog {
+    threshold: f64,
+    callback: LimitWarningFn,
+    fired: HashSet<&'static str>,
+}
+
+impl fmt::Debug for LimitWatchdog {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("LimitWatchdog")
+            .field("threshold", &self.threshold)
+            .field("fired", &self.fired)
+            .finish()
+    }
+}
+
+/// Fraction of a limit at which observe-only mode records a `LimitApproach`.
+const LIMIT_APPROACH_RATIO: f64 = 0.8;
+
+/// Entries kept in an `ActivityLog`; older ones are dropped first.
+pub const MAX_ACTIVITY_ENTRIES: usize = 10_000;
+
+#[derive(Debug, Clone)]
+pub enum Activity {
+    /// `granted` is what enforcement would have decided.
+    CapabilityCheck { capability: capabilities::Capability, granted: bool },
+    /// Arguments marked sensitive in the `SyscallTrap` are redacted.
+    Syscall { name: String, args: Vec<syscall_trap::SyscallArg>, allowed: bool },
+    MemoryGrow { from_pages: u32, to_pages: u32 },
+    LimitApproach { resource: &'static str, used: f64, limit: f64 },
+    LimitExceeded { violation_type: ViolationType, message: String },
+}
+
+#[derive(Debug, Clone)]
+pub struct ActivityEntry {
+    pub activity: Activity,
+    pub timestamp: Instant,
+}
+
+/// What a module did while running in an observe-only sandbox: the most
+/// recent `MAX_ACTIVITY_ENTRIES` entries, and every ungranted capability.
+#[derive(Debug, Default)]
+pub struct ActivityLog {
+    entries: VecDeque<ActivityEntry>,
+    dropped: u64,
+    ungranted: Vec<capabilities::Capability>,
+    approached: HashSet<&'static str>,
+}
+
+impl ActivityLog {
+    fn record(&mut self, activity: Activity) {
+        log::debug!("Observed: {:?}", activity);
+        if let Activity::CapabilityCheck { capability, granted: false } = &activity {
+            if !self.ungranted.contains(capability) {
+                self.ungranted.push(capability.clone());
+            }
+        }
+        if self.entries.len() >= MAX_ACTIVITY_ENTRIES {
+            self.entries.pop_front();
+            self.dropped += 1;
+        }
+        self.entries.push_back(ActivityEntry {
+            activity,
+            timestamp: Instant::now(),
+        });
+    }
+    
+    pub fn entries(&self) -> &VecDeque<ActivityEntry> {
+        &self.entries
+    }
+    
+    /// Entries evicted to stay within `MAX_ACTIVITY_ENTRIES`.
+    pub fn dropped_entries(&self) -> u64 {
+        self.dropped
+    }
+    
+    /// Capabilities the module used that were not granted, including ones
+    /// whose entries have since been dropped.
+    pub fn ungranted_capabilities(&self) -> Vec<capabilities::Capability> {
+        self.ungranted.clone()
+    }
 }
 
 #[derive(Debug, Clone)]
@@ -73,6 +223,9 @@ pub struct SecurityViolation {
     pub violation_type: ViolationType,
     pub message: String,
     pub timestamp: Instant,
+    /// Position in the sandbox's violation log, starting at 0; unlike
+    /// `timestamp`, never shared by two violations.
+    pub sequence: u64,
 }
 
 #[derive(Debug, Clone)]
@@ -83,6 +236,9 @@ pub enum ViolationType {
     InstructionLimit,
     UnauthorizedSyscall,
     CapabilityViolation,
+    GlobalLimit,
+    TableLimit,
+    SyscallTimeout,
 }
 
 impl Sandbox {
@@ -93,11 +249,120 @@ impl Sandbox {
             capabilities: capabilities::CapabilitySet::new(),
             syscall_trap: syscall_trap::SyscallTrap::new(),
             violations: Vec::new(),
+            activity_log: None,
+            watchdog: None,
+            check_scheduler: limits::CheckScheduler::new(DEFAULT_CHECK_INTERVAL),
+            faults: None,
+            preemption_interval: None,
+            preemption_deadline: None,
+            syscall_rate_limit: None,
+            recent_syscalls: VecDeque::new(),
+            syscall_capability_map: default_syscall_capabilities(),
+        }
+    }
+    
+    /// Inject scripted faults into intercepted syscalls, keyed by syscall name.
+    pub fn with_fault_injector(mut self, injector: faults::FaultInjector) -> Self {
+        self.faults = Some(injector);
+        self
+    }
+    
+    /// Randomize the soft check points in `consume_instructions` by up to
+    /// `jitter` instructions, seeded per sandbox.
+    pub fn with_check_jitter(mut self, jitter: u64) -> Self {
+        let seed = RandomState::new().build_hasher().finish();
+        self.check_scheduler = limits::CheckScheduler::new(DEFAULT_CHECK_INTERVAL).with_jitter(jitter, seed);
+        self
+    }
+    
+    /// Reject syscalls beyond `per_second` within any one-second window.
+    pub fn with_syscall_rate_limit(mut self, per_second: u32) -> Self {
+        self.syscall_rate_limit = Some(per_second);
+        self
+    }
+    
+    pub fn with_check_scheduler(mut self, scheduler: limits::CheckScheduler) -> Self {
+        self.check_scheduler = scheduler;
+        self
+    }
+    
+    /// Call `callback` when usage of any limit first reaches `threshold` (a
+    /// fraction, e.g. 0.9), before the limit itself is enforced.
+    pub fn with_limit_warning(mut self, threshold: f64, callback: impl FnMut(&LimitWarning) + 'static) -> Self {
+        self.watchdog = Some(LimitWatchdog {
+            threshold,
+            callback: Box::new(callback),
+            fired: HashSet::new(),
+        });
+        self
+    }
+    
+    /// A sandbox that allows everything but records every capability check,
+    /// syscall, memory grow, and limit approach in an `ActivityLog`.
+    pub fn observe_only(limits: ResourceLimits) -> Self {
+        let mut sandbox = Self::new(limits);
+        sandbox.activity_log = Some(RefCell::new(ActivityLog::default()));
+        sandbox
+    }
+    
+    /// Arm a deadline `interval` from now for `should_preempt`.
+    pub fn set_preemption_interval(&mut self, interval: Duration) {
+        self.preemption_interval = Some(interval);
+        self.preemption_deadline = Some(Instant::now() + interval);
+    }
+    
+    /// Arm the next deadline after the interpreter has yielded.
+    pub fn rearm_preemption(&mut self) {
+        if let Some(interval) = self.preemption_interval {
+            self.preemption_deadline = Some(Instant::now() + interval);
+        }
+    }
+    
+    /// Whether the running module has used up its time slice and should yield.
+    pub fn should_preempt(&self) -> bool {
+        self.preemption_deadline.is_some_and(|deadline| Instant::now() >= deadline)
+    }
+    
+    pub fn is_observe_only(&self) -> bool {
+        self.activity_log.is_some()
+    }
+    
+    pub fn activity_log(&self) -> Option<Ref<'_, ActivityLog>> {
+        self.activity_log.as_ref().map(|log| log.borrow())
+    }
+    
+    pub fn limits(&self) -> &ResourceLimits {
+        &self.limits
+    }
+    
+    pub fn get_usage(&self) -> &ResourceUsage {
+        &self.usage
+    }
+    
+    pub fn usage_report(&self) -> UsageReport {
+        let [memory_pages, cpu_time, syscalls, instructions] =
+            self.limit_readings().map(|(_, used, limit, _)| ResourceGauge::new(used, limit));
+        UsageReport { memory_pages, cpu_time, syscalls, instructions }
+    }
+    
+    pub fn get_syscall_log(&self) -> &[syscall_trap::SyscallEntry] {
+        self.syscall_trap.get_syscall_log()
+    }
+    
+    fn observe(&self, activity: Activity) {
+        if let Some(log) = &self.activity_log {
+            log.borrow_mut().record(activity);
         }
     }
     
     pub fn check_limits(&mut self) -> Result<()> {
         self.usage.update_cpu_time();
+        self.check_warning_thresholds();
+        
+        if self.is_observe_only() {
+            self.observe_limits();
+            return Ok(());
+        }
         
         if self.usage.memory_pages > self.limits.max_memory_pages {
             self.log_violation(ViolationType::MemoryLimit, 
@@ -130,31 +395,240 @@ impl Sandbox {
         Ok(())
     }
     
+    /// Current usage of each enforced limit as (resource, used, limit, violation).
+    fn limit_readings(&self) -> [(&'static str, f64, f64, ViolationType); 4] {
+        [
+            ("memory_pages", self.usage.memory_pages as f64, self.limits.max_memory_pages as f64, ViolationType::MemoryLimit),
+            ("cpu_time", self.usage.cpu_time.as_secs_f64(), self.limits.max_cpu_time.as_secs_f64(), ViolationType::CpuTimeLimit),
+            ("syscalls", self.usage.syscall_count as f64, self.limits.max_syscalls as f64, ViolationType::SyscallQuota),
+            ("instructions", self.usage.instruction_count as f64, self.limits.max_instructions as f64, ViolationType::InstructionLimit),
+        ]
+    }
+    
+    fn check_warning_thresholds(&mut self) {
+        let readings = self.limit_readings();
+        let watchdog = match &mut self.watchdog {
+            Some(watchdog) => watchdog,
+            None => return,
+        };
+        for (resource, used, limit, violation_type) in readings {
+            if used >= limit * watchdog.threshold && watchdog.fired.insert(resource) {
+                let warning = LimitWarning {
+                    violation_type,
+                    resource,
+                    used,
+                    limit,
+                    usage: self.usage.clone(),
+                };
+                log::warn!("Approaching {} limit: {} of {}", resource, used, limit);
+                (watchdog.callback)(&warning);
+            }
+        }
+    }
+    
+    fn observe_limits(&mut self) {
+        let readings = self.limit_readings();
+        
+        let log = match &self.activity_log {
+            Some(log) => log,
+            None => return,
+        };
+        let mut log = log.borrow_mut();
+        for (resource, used, limit, violation_type) in readings {
+            if used > limit {
+                log.record(Activity::LimitExceeded {
+                    violation_type,
+                    message: format!("{} limit exceeded: {} > {}", resource, used, limit),
+                });
+            } else if used >= limit * LIMIT_APPROACH_RATIO && log.approached.insert(resource) {
+                log.record(Activity::LimitApproach { resource, used, limit });
+            }
+        }
+    }
+    
+    /// Check the static shape of a module against the configured limits
+    /// before it is instantiated. Imported globals and tables count too.
+    pub fn check_module_limits(&mut self, module: &WasmModule) -> Result<()> {
+        let imported_globals = module.imports.iter()
+            .filter(|i| matches!(i.kind, ImportKind::Global { .. }))
+            .count();
+        let global_count = (imported_globals + module.globals.len()) as u32;
+        if global_count > self.limits.max_globals {
+            self.log_violation(ViolationType::GlobalLimit,
+                format!("Global limit exceeded: {} > {}",
+                    global_count, self.limits.max_globals));
+            return Err(WasmError::Runtime(format!(
+                "Module declares {} globals, limit is {}", global_count, self.limits.max_globals)));
+        }
+        
+        let tables = module.imports.iter()
+            .filter_map(|i| match &i.kind {
+                ImportKind::Table(table) => Some(table),
+                _ => None,
+            })
+            .chain(module.table.iter());
+        for table in tables {
+            // A declared maximum is how far the table may grow; without one, check the initial size
+            let size = table.max.unwrap_or(table.min).max(table.min);
+            if size > self.limits.max_table_size {
+                self.log_violation(ViolationType::TableLimit,
+                    format!("Table size limit exceeded: {} > {}",
+                        size, self.limits.max_table_size));
+                return Err(WasmError::Runtime(format!(
+                    "Module table has up to {} entries, limit is {}", size, self.limits.max_table_size)));
+            }
+        }
+        
+        Ok(())
+    }
+    
     pub fn grant_capability(&mut self, capability: capabilities::Capability) {
         self.capabilities.grant(capability);
     }
     
+    /// Whether `capability` is granted, without recording a check.
+    pub fn has_capability(&self, capability: &capabilities::Capability) -> bool {
+        self.capabilities.has(capability)
+    }
+    
     pub fn check_capability(&self, capability: &capabilities::Capability) -> Result<()> {
+        if self.is_observe_only() {
+            self.observe(Activity::CapabilityCheck {
+                capability: capability.clone(),
+                granted: self.capabilities.has(capability),
+            });
+            return Ok(());
+        }
+        
         if !self.capabilities.has(capability) {
             return Err(WasmError::Runtime("Capability violation".to_string()));
         }
         Ok(())
     }
     
+    /// Require `capability` for `syscall` in `intercept_syscall`.
+    pub fn map_syscall_capability(&mut self, syscall: &str, capability: capabilities::Capability) {
+        self.syscall_capability_map.insert(syscall.to_string(), capability);
+    }
+    
+    fn required_capability(&self, syscall: &str, args: &[u32]) -> Option<capabilities::Capability> {
+        use capabilities::{AlertLevel, Capability, SensorType};
+        // wasm_poll_events takes a buffer pointer first, not a sensor id
+        let first_arg = match syscall {
+            "wasm_poll_events" => None,
+            _ => args.first().copied(),
+        };
+        match self.syscall_capability_map.get(syscall)? {
+            Capability::ReadSensor(SensorType::Any) => Some(Capability::ReadSensor(
+                first_arg.and_then(SensorType::from_id).unwrap_or(SensorType::Any))),
+            Capability::SendAlert(AlertLevel::Any) => Some(Capability::SendAlert(
+                first_arg.and_then(AlertLevel::from_level).unwrap_or(AlertLevel::Any))),
+            capability => Some(capability.clone()),
+        }
+    }
+    
     pub fn intercept_syscall(&mut self, syscall: &str, args: &[u32]) -> Result<u32> {
         self.usage.increment_syscall();
         self.check_limits()?;
         
+        if !self.is_observe_only() {
+            self.check_syscall_rate(syscall)?;
+        }
+        
+        if self.is_observe_only() {
+            if let Some(capability) = self.required_capability(syscall, args) {
+                self.check_capability(&capability)?;
+            }
+            self.observe(Activity::Syscall {
+                name: syscall.to_string(),
+                args: self.syscall_trap.redact_args(syscall, args),
+                allowed: self.syscall_trap.is_allowed(syscall),
+            });
+            if let Some(value) = self.injected_fault(syscall)? {
+                return Ok(value);
+            }
+            return self.syscall_trap.handle(syscall, args);
+        }
+        
         if !self.syscall_trap.is_allowed(syscall) {
             self.log_violation(ViolationType::UnauthorizedSyscall,
                 format!("Unauthorized syscall: {}", syscall));
             return Err(WasmError::Runtime("Unauthorized syscall".to_string()));
         }
         
-        self.syscall_trap.handle(syscall, args)
+        if let Some(capability) = self.required_capability(syscall, args) {
+            if let Err(e) = self.check_capability(&capability) {
+                self.log_violation(ViolationType::CapabilityViolation,
+                    format!("{} requires {:?}", syscall, capability));
+                return Err(e);
+            }
+        }
+        
+        if let Some(value) = self.injected_fault(syscall)? {
+            return Ok(value);
+        }
+        
+        let result = self.syscall_trap.handle(syscall, args);
+        if matches!(&result, Err(WasmError::Runtime(msg)) if msg == syscall_trap::HANDLER_TIMEOUT_ERROR) {
+            self.log_violation(ViolationType::SyscallTimeout,
+                format!("Syscall handler timed out: {}", syscall));
+        }
+        result
+    }
+    
+    /// The value an injected fault returns in place of an authorized
+    /// syscall. Syscalls have no clock to move, so `ClockJump` is rejected.
+    fn injected_fault(&self, syscall: &str) -> Result<Option<u32>> {
+        match self.faults.as_ref().and_then(|f| f.on_call(syscall)) {
+            Some(faults::Fault::Fail(message)) => Err(WasmError::Runtime(message)),
+            Some(faults::Fault::Return(value)) => Ok(Some(value)),
+            Some(faults::Fault::ClockJump(_)) => Err(WasmError::Runtime(format!(
+                "Clock jump faults cannot be injected into syscall {}", syscall))),
+            None => Ok(None),
+        }
+    }
+    
+    fn check_syscall_rate(&mut self, syscall: &str) -> Result<()> {
+        let limit = match self.syscall_rate_limit {
+            Some(limit) => limit,
+            None => return Ok(()),
+        };
+        
+        let now = Instant::now();
+        while self.recent_syscalls.front().is_some_and(|&t| now.duration_since(t) >= Duration::from_secs(1)) {
+            self.recent_syscalls.pop_front();
+        }
+        
+        if self.recent_syscalls.len() >= limit as usize {
+            self.log_violation(ViolationType::SyscallQuota,
+                format!("Syscall rate limit exceeded: {} > {}/s ({})", self.recent_syscalls.len() + 1, limit, syscall));
+            return Err(WasmError::Runtime("Syscall rate limit exceeded".to_string()));
+        }
+        self.recent_syscalls.push_back(now);
+        Ok(())
+    }
+    
+    pub fn syscall_trap_mut(&mut self) -> &mut syscall_trap::SyscallTrap {
+        &mut self.syscall_trap
+    }
+    
+    /// A `LinearMemory` grow callback that rejects growth past
+    /// `max_memory_pages` before the memory is resized.
+    pub fn memory_grow_guard(&self) -> crate::memory::GrowCallback {
+        let max_pages = self.limits.max_memory_pages;
+        let enforce = !self.is_observe_only();
+        Box::new(move |pages| {
+            if enforce && pages > max_pages {
+                return Err(WasmError::Runtime(format!("Memory limit exceeded: {} > {}", pages, max_pages)));
+            }
+            Ok(())
+        })
     }
     
     pub fn update_memory_usage(&mut self, pages: u32) {
+        if pages > self.usage.memory_pages {
+            self.observe(Activity::MemoryGrow { from_pages: self.usage.memory_pages, to_pages: pages });
+        }
         self.usage.memory_pages = pages;
     }
     
@@ -162,16 +636,289 @@ impl Sandbox {
         self.usage.increment_instructions(count);
     }
     
+    /// Count executed instructions and run `check_limits` whenever the
+    /// scheduler says a check is due.
+    pub fn consume_instructions(&mut self, count: u64) -> Result<()> {
+        self.usage.increment_instructions(count);
+        if self.check_scheduler.is_due(self.usage.instruction_count, self.limits.max_instructions) {
+            self.check_limits()?;
+        }
+        Ok(())
+    }
+    
+    /// Violations in `sequence` order.
     pub fn get_violations(&self) -> &[SecurityViolation] {
         &self.violations
     }
     
     fn log_violation(&mut self, violation_type: ViolationType, message: String) {
         log::warn!("Security violation: {:?} - {}", violation_type, message);
+        let sequence = self.violations.len() as u64;
         self.violations.push(SecurityViolation {
             violation_type,
             message,
             timestamp: Instant::now(),
+            sequence,
         });
     }
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::rc::Rc;
+
+    #[test]
+    fn test_limit_warning_before_violation() {
+        let warnings = Rc::new(RefCell::new(Vec::new()));
+        let recorded = Rc::clone(&warnings);
+        let limits = ResourceLimits::default().with_instruction_limit(1000);
+        let mut sandbox = Sandbox::new(limits)
+            .with_limit_warning(0.9, move |warning| recorded.borrow_mut().push(warning.clone()));
+
+        sandbox.increment_instructions(950);
+        assert!(sandbox.check_limits().is_ok());
+        {
+            let warnings = warnings.borrow();
+            assert_eq!(warnings.len(), 1);
+            assert!(matches!(warnings[0].violation_type, ViolationType::InstructionLimit));
+            assert_eq!(warnings[0].usage.instruction_count, 950);
+        }
+        assert!(sandbox.get_violations().is_empty());
+
+        sandbox.increment_instructions(100);
+        assert!(sandbox.check_limits().is_err());
+        assert_eq!(warnings.borrow().len(), 1);
+        assert_eq!(sandbox.get_violations().len(), 1);
+    }
+
+    #[test]
+    fn test_usage_report_utilization() {
+        let limits = ResourceLimits::default()
+            .with_memory_limit(64)
+            .with_cpu_time_limit(Duration::from_secs(10))
+            .with_syscall_limit(100)
+            .with_instruction_limit(1000);
+        let mut sandbox = Sandbox::new(limits);
+        sandbox.update_memory_usage(32);
+        sandbox.usage.cpu_time = Duration::from_secs(5);
+        sandbox.usage.syscall_count = 50;
+        sandbox.increment_instructions(500);
+
+        let report = sandbox.usage_report();
+        for gauge in [&report.memory_pages, &report.cpu_time, &report.syscalls, &report.instructions] {
+            assert!((gauge.utilization - 0.5).abs() < 1e-9);
+        }
+        assert_eq!(report.memory_pages.current, 32.0);
+        assert_eq!(report.instructions.max, 1000.0);
+    }
+
+    #[test]
+    fn test_violation_sequence_order() {
+        let mut sandbox = Sandbox::new(ResourceLimits::default());
+        for message in ["first", "second", "third"] {
+            sandbox.log_violation(ViolationType::UnauthorizedSyscall, message.to_string());
+        }
+
+        let violations = sandbox.get_violations();
+        let sequences: Vec<u64> = violations.iter().map(|v| v.sequence).collect();
+        assert_eq!(sequences, vec![0, 1, 2]);
+        let messages: Vec<&str> = violations.iter().map(|v| v.message.as_str()).collect();
+        assert_eq!(messages, vec!["first", "second", "third"]);
+    }
+
+    #[test]
+    fn test_jittered_checks_enforce_hard_limit() {
+        for seed in 0..4 {
+            let scheduler = limits::CheckScheduler::new(300).with_jitter(250, seed);
+            let limits = ResourceLimits::default().with_instruction_limit(1000);
+            let mut sandbox = Sandbox::new(limits).with_check_scheduler(scheduler);
+
+            let mut executed = 0u64;
+            while sandbox.consume_instructions(1).is_ok() {
+                executed += 1;
+            }
+            assert_eq!(executed, 1000);
+            assert_eq!(sandbox.get_usage().instruction_count, 1001);
+        }
+
+        let sandbox = Sandbox::new(ResourceLimits::default()).with_check_jitter(100);
+        assert!(sandbox.check_scheduler.next_check() >= DEFAULT_CHECK_INTERVAL - 100);
+    }
+
+    #[test]
+    fn test_module_limits_cover_imports_and_table_max() {
+        use crate::parser::{Import, RefType, TableType, ValueType};
+        
+        let import = |name: &str, kind: ImportKind| Import { module: "env".to_string(), name: name.to_string(), kind };
+        let table = |min: u32, max: Option<u32>| TableType { element_type: RefType::FuncRef, min, max };
+        let limits = ResourceLimits::strict();
+        
+        let globals = (0..=limits.max_globals)
+            .map(|i| import(&format!("g{}", i), ImportKind::Global { value_type: ValueType::I32, mutable: false }))
+            .collect();
+        let module = WasmModule { imports: globals, ..Default::default() };
+        assert!(Sandbox::new(limits.clone()).check_module_limits(&module).is_err());
+        
+        let module = WasmModule { table: Some(table(1, Some(limits.max_table_size + 1))), ..Default::default() };
+        assert!(Sandbox::new(limits.clone()).check_module_limits(&module).is_err());
+        
+        let module = WasmModule {
+            imports: vec![import("t", ImportKind::Table(table(limits.max_table_size + 1, None)))],
+            table: Some(table(1, Some(limits.max_table_size))),
+            ..Default::default()
+        };
+        let mut sandbox = Sandbox::new(limits.clone());
+        assert!(sandbox.check_module_limits(&module).is_err());
+        assert!(matches!(sandbox.get_violations()[0].violation_type, ViolationType::TableLimit));
+        
+        let module = WasmModule { table: Some(table(1, Some(limits.max_table_size))), ..Default::default() };
+        assert!(Sandbox::new(limits).check_module_limits(&module).is_ok());
+    }
+
+    #[test]
+    fn test_preemption_deadline() {
+        let mut sandbox = Sandbox::new(ResourceLimits::default());
+        assert!(!sandbox.should_preempt());
+
+        sandbox.set_preemption_interval(Duration::from_millis(10));
+        assert!(!sandbox.should_preempt());
+        std::thread::sleep(Duration::from_millis(15));
+        assert!(sandbox.should_preempt());
+
+        sandbox.rearm_preemption();
+        assert!(!sandbox.should_preempt());
+    }
+
+    #[test]
+    fn test_syscall_rate_limit() {
+        let mut sandbox = Sandbox::new(ResourceLimits::default()).with_syscall_rate_limit(100);
+        sandbox.grant_capability(capabilities::Capability::WallClock);
+
+        let results: Vec<_> = (0..200).map(|_| sandbox.intercept_syscall("wasm_get_time", &[])).collect();
+        assert!(results[..100].iter().all(|r| r.is_ok()));
+        assert!(results[100].is_err());
+        assert_eq!(sandbox.get_usage().syscall_count, 200);
+        assert!(matches!(sandbox.get_violations()[0].violation_type, ViolationType::SyscallQuota));
+    }
+
+    #[test]
+    fn test_syscall_requires_mapped_capability() {
+        use capabilities::{Capability, SensorType};
+
+        let mut sandbox = Sandbox::new(ResourceLimits::default());
+        sandbox.grant_capability(Capability::Log);
+        assert!(sandbox.intercept_syscall("wasm_read_sensor", &[0]).is_err());
+        assert!(matches!(sandbox.get_violations()[0].violation_type, ViolationType::CapabilityViolation));
+
+        // A grant for one sensor type covers only that sensor
+        sandbox.grant_capability(Capability::ReadSensor(SensorType::Temperature));
+        assert_eq!(sandbox.intercept_syscall("wasm_read_sensor", &[0]).unwrap(), 25);
+        assert!(sandbox.intercept_syscall("wasm_read_sensor", &[1]).is_err());
+
+        sandbox.grant_capability(Capability::ReadSensor(SensorType::Any));
+        assert!(sandbox.intercept_syscall("wasm_read_sensor", &[1]).is_ok());
+    }
+
+    #[test]
+    fn test_poll_events_requires_sensor_capability() {
+        use capabilities::{Capability, SensorType};
+
+        let mut sandbox = Sandbox::new(ResourceLimits::default());
+        sandbox.grant_capability(Capability::ReadSensor(SensorType::Temperature));
+
+        // A buffer at address 0 must not be read as the temperature sensor id
+        let required = sandbox.required_capability("wasm_poll_events", &[0, 4]).unwrap();
+        assert!(matches!(required, Capability::ReadSensor(SensorType::Any)));
+        assert!(sandbox.check_capability(&required).is_err());
+
+        sandbox.grant_capability(Capability::ReadSensor(SensorType::Any));
+        assert!(sandbox.check_capability(&required).is_ok());
+    }
+
+    #[derive(Debug)]
+    struct SleepyHandler;
+
+    impl syscall_trap::SyscallHandler for SleepyHandler {
+        fn handle(&self, _args: &[u32]) -> Result<u32> {
+            std::thread::sleep(Duration::from_millis(500));
+            Ok(0)
+        }
+    }
+
+    #[test]
+    fn test_syscall_handler_timeout() {
+        let mut sandbox = Sandbox::new(ResourceLimits::default());
+        sandbox.syscall_trap_mut().register_syscall("wasm_slow_alert", Box::new(SleepyHandler));
+        sandbox.syscall_trap_mut().set_handler_timeout("wasm_slow_alert", Duration::from_millis(20));
+
+        let started = Instant::now();
+        match sandbox.intercept_syscall("wasm_slow_alert", &[]) {
+            Err(WasmError::Runtime(msg)) => assert_eq!(msg, syscall_trap::HANDLER_TIMEOUT_ERROR),
+            other => panic!("expected timeout, got {:?}", other),
+        }
+        assert!(started.elapsed() < Duration::from_millis(400));
+        assert!(matches!(sandbox.get_violations()[0].violation_type, ViolationType::SyscallTimeout));
+        assert!(sandbox.get_syscall_log()[0].result.is_err());
+        
+        // Later calls fail fast instead of spawning another thread
+        let started = Instant::now();
+        match sandbox.intercept_syscall("wasm_slow_alert", &[]) {
+            Err(WasmError::Runtime(msg)) => assert_eq!(msg, syscall_trap::HANDLER_DISABLED_ERROR),
+            other => panic!("expected disabled handler, got {:?}", other),
+        }
+        assert!(started.elapsed() < Duration::from_millis(20));
+    }
+
+    #[test]
+    fn test_observe_only_redacts_and_records_capabilities() {
+        use capabilities::Capability;
+        
+        let mut sandbox = Sandbox::observe_only(ResourceLimits::default());
+        sandbox.syscall_trap_mut().mark_sensitive("wasm_kv_set", &[0]);
+        let _ = sandbox.intercept_syscall("wasm_kv_set", &[1234, 8]);
+        
+        let log = sandbox.activity_log().unwrap();
+        let args = log.entries().iter().find_map(|e| match &e.activity {
+            Activity::Syscall { name, args, .. } if name == "wasm_kv_set" => Some(args.clone()),
+            _ => None,
+        }).unwrap();
+        assert_eq!(args, vec![syscall_trap::SyscallArg::Redacted, syscall_trap::SyscallArg::Value(8)]);
+        assert_eq!(log.ungranted_capabilities(), vec![Capability::Storage]);
+    }
+    
+    #[test]
+    fn test_activity_log_is_bounded() {
+        let mut sandbox = Sandbox::observe_only(ResourceLimits::default());
+        sandbox.grant_capability(capabilities::Capability::Log);
+        for _ in 0..MAX_ACTIVITY_ENTRIES {
+            let _ = sandbox.intercept_syscall("wasm_log", &[0, 0]);
+        }
+        
+        let log = sandbox.activity_log().unwrap();
+        assert_eq!(log.entries().len(), MAX_ACTIVITY_ENTRIES);
+        // At least a capability check and a syscall per call
+        assert!(log.dropped_entries() >= MAX_ACTIVITY_ENTRIES as u64);
+    }
+
+    #[test]
+    fn test_faults_apply_after_authorization() {
+        use faults::{Fault, FaultInjector};
+        
+        let injector = FaultInjector::new()
+            .inject("mmap", 0, Fault::Return(3))
+            .inject("wasm_random", 0, Fault::Return(4))
+            .inject("wasm_log", 0, Fault::ClockJump(60));
+        let mut sandbox = Sandbox::new(ResourceLimits::default()).with_fault_injector(injector);
+        
+        // Denied syscalls and missing capabilities still fail
+        assert!(sandbox.intercept_syscall("mmap", &[]).is_err());
+        assert!(sandbox.intercept_syscall("wasm_random", &[]).is_err());
+        
+        sandbox.grant_capability(capabilities::Capability::Random);
+        sandbox.grant_capability(capabilities::Capability::Log);
+        // Rejected calls never reached the injector
+        assert_eq!(sandbox.intercept_syscall("wasm_random", &[]).unwrap(), 4);
+        assert!(sandbox.intercept_syscall("wasm_log", &[0, 0]).is_err());
+    }
+}
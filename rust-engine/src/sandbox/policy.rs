@@ -1,5 +1,6 @@
 use super::capabilities::{Capability, CapabilitySet, SensorType, AlertLevel};
 use super::limits::ResourceLimits;
+use crate::static_analysis::CapabilityRequirements;
 use std::collections::HashMap;
 use std::time::Duration;
 
@@ -42,6 +43,36 @@ impl Default for NetworkPolicy {
     }
 }
 
+impl NetworkPolicy {
+    /// Whether an outbound request to `url` is permitted: the scheme must be
+    /// http or https, the host listed in `allowed_hosts` and, when
+    /// `allowed_ports` is non-empty, the port listed there.
+    pub fn allows_url(&self, url: &str) -> bool {
+        if !self.allow_outbound {
+            return false;
+        }
+        
+        let (rest, default_port) = if let Some(rest) = url.strip_prefix("https://") {
+            (rest, 443)
+        } else if let Some(rest) = url.strip_prefix("http://") {
+            (rest, 80)
+        } else {
+            return false;
+        };
+        let authority = rest.split(['/', '?', '#']).next().unwrap_or("");
+        let (host, port) = match authority.rsplit_once(':') {
+            Some((host, port)) => match port.parse::<u16>() {
+                Ok(port) => (host, port),
+                Err(_) => return false,
+            },
+            None => (authority, default_port),
+        };
+        
+        self.allowed_hosts.iter().any(|allowed| allowed.eq_ignore_ascii_case(host))
+            && (self.allowed_ports.is_empty() || self.allowed_ports.contains(&port))
+    }
+}
+
 impl Default for FilePolicy {
     fn default() -> Self {
         Self {
@@ -57,13 +88,14 @@ impl SecurityPolicy {
     pub fn strict() -> Self {
         let mut capabilities = CapabilitySet::new();
         capabilities.grant(Capability::Log);
+        capabilities.grant(Capability::MonotonicTime);
         
         Self {
             name: "Strict".to_string(),
             description: "Minimal permissions for untrusted code".to_string(),
             resource_limits: ResourceLimits::strict(),
             allowed_capabilities: capabilities,
-            syscall_whitelist: vec!["wasm_log".to_string()],
+            syscall_whitelist: vec!["wasm_log".to_string(), "wasm_monotonic_ms".to_string()],
             syscall_blacklist: vec![
                 "open".to_string(),
                 "read".to_string(),
@@ -90,6 +122,7 @@ impl SecurityPolicy {
             syscall_whitelist: vec![
                 "wasm_log".to_string(),
                 "wasm_get_time".to_string(),
+                "wasm_monotonic_ms".to_string(),
                 "wasm_random".to_string(),
                 "wasm_read_sensor".to_string(),
             ],
@@ -117,6 +150,7 @@ impl SecurityPolicy {
             syscall_whitelist: vec![
                 "wasm_log".to_string(),
                 "wasm_get_time".to_string(),
+                "wasm_monotonic_ms".to_string(),
                 "wasm_random".to_string(),
                 "wasm_read_sensor".to_string(),
                 "wasm_send_alert".to_string(),
@@ -146,6 +180,7 @@ impl SecurityPolicy {
             syscall_whitelist: vec![
                 "wasm_log".to_string(),
                 "wasm_get_time".to_string(),
+                "wasm_monotonic_ms".to_string(),
                 "wasm_random".to_string(),
                 "wasm_read_sensor".to_string(),
                 "wasm_send_alert".to_string(),
@@ -194,6 +229,158 @@ impl SecurityPolicy {
         
         Ok(())
     }
+
+    /// Compare this policy against `other`, describing what moving from
+    /// `self` to `other` would newly allow or deny.
+    pub fn diff(&self, other: &SecurityPolicy) -> PolicyDiff {
+        let mut added_capabilities: Vec<Capability> = other.allowed_capabilities.list_capabilities()
+            .into_iter()
+            .filter(|c| !self.allowed_capabilities.list_capabilities().contains(c))
+            .cloned()
+            .collect();
+        let mut removed_capabilities: Vec<Capability> = self.allowed_capabilities.list_capabilities()
+            .into_iter()
+            .filter(|c| !other.allowed_capabilities.list_capabilities().contains(c))
+            .cloned()
+            .collect();
+        added_capabilities.sort_by_key(|c| format!("{:?}", c));
+        removed_capabilities.sort_by_key(|c| format!("{:?}", c));
+
+        let old = &self.resource_limits;
+        let new = &other.resource_limits;
+        let mut limit_changes = Vec::new();
+        LimitChange::compare(&mut limit_changes, "max_memory_pages", old.max_memory_pages, new.max_memory_pages);
+        LimitChange::compare(&mut limit_changes, "max_cpu_time", old.max_cpu_time, new.max_cpu_time);
+        LimitChange::compare(&mut limit_changes, "max_syscalls", old.max_syscalls, new.max_syscalls);
+        LimitChange::compare(&mut limit_changes, "max_instructions", old.max_instructions, new.max_instructions);
+        LimitChange::compare(&mut limit_changes, "max_stack_depth", old.max_stack_depth, new.max_stack_depth);
+        LimitChange::compare(&mut limit_changes, "max_globals", old.max_globals, new.max_globals);
+        LimitChange::compare(&mut limit_changes, "max_table_size", old.max_table_size, new.max_table_size);
+
+        PolicyDiff {
+            from: self.name.clone(),
+            to: other.name.clone(),
+            added_capabilities,
+            removed_capabilities,
+            whitelist_added: list_additions(&self.syscall_whitelist, &other.syscall_whitelist),
+            whitelist_removed: list_additions(&other.syscall_whitelist, &self.syscall_whitelist),
+            blacklist_added: list_additions(&self.syscall_blacklist, &other.syscall_blacklist),
+            blacklist_removed: list_additions(&other.syscall_blacklist, &self.syscall_blacklist),
+            limit_changes,
+        }
+    }
+}
+
+fn list_additions(old: &[String], new: &[String]) -> Vec<String> {
+    new.iter().filter(|s| !old.contains(s)).cloned().collect()
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum LimitDirection {
+    Tighter,
+    Looser,
+}
+
+#[derive(Debug, Clone)]
+pub struct LimitChange {
+    pub field: String,
+    pub old_value: String,
+    pub new_value: String,
+    pub direction: LimitDirection,
+}
+
+impl LimitChange {
+    fn compare<T: PartialOrd + std::fmt::Debug>(changes: &mut Vec<LimitChange>, field: &str, old: T, new: T) {
+        if old == new {
+            return;
+        }
+
+        changes.push(LimitChange {
+            field: field.to_string(),
+            old_value: format!("{:?}", old),
+            new_value: format!("{:?}", new),
+            direction: if new > old { LimitDirection::Looser } else { LimitDirection::Tighter },
+        });
+    }
+}
+
+/// Structured difference between two policies, for audit review.
+#[derive(Debug, Clone)]
+pub struct PolicyDiff {
+    pub from: String,
+    pub to: String,
+    pub added_capabilities: Vec<Capability>,
+    pub removed_capabilities: Vec<Capability>,
+    pub whitelist_added: Vec<String>,
+    pub whitelist_removed: Vec<String>,
+    pub blacklist_added: Vec<String>,
+    pub blacklist_removed: Vec<String>,
+    pub limit_changes: Vec<LimitChange>,
+}
+
+impl PolicyDiff {
+    pub fn is_empty(&self) -> bool {
+        self.added_capabilities.is_empty()
+            && self.removed_capabilities.is_empty()
+            && self.whitelist_added.is_empty()
+            && self.whitelist_removed.is_empty()
+            && self.blacklist_added.is_empty()
+            && self.blacklist_removed.is_empty()
+            && self.limit_changes.is_empty()
+    }
+}
+
+impl std::fmt::Display for PolicyDiff {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        writeln!(f, "Policy diff: {} -> {}", self.from, self.to)?;
+
+        if self.is_empty() {
+            return writeln!(f, "No differences");
+        }
+
+        if !self.added_capabilities.is_empty() || !self.removed_capabilities.is_empty() {
+            writeln!(f, "Capabilities:")?;
+            for cap in &self.added_capabilities {
+                writeln!(f, "  + {:?}", cap)?;
+            }
+            for cap in &self.removed_capabilities {
+                writeln!(f, "  - {:?}", cap)?;
+            }
+        }
+
+        if !self.whitelist_added.is_empty() || !self.whitelist_removed.is_empty() {
+            writeln!(f, "Syscall whitelist:")?;
+            for syscall in &self.whitelist_added {
+                writeln!(f, "  + {}", syscall)?;
+            }
+            for syscall in &self.whitelist_removed {
+                writeln!(f, "  - {}", syscall)?;
+            }
+        }
+
+        if !self.blacklist_added.is_empty() || !self.blacklist_removed.is_empty() {
+            writeln!(f, "Syscall blacklist:")?;
+            for syscall in &self.blacklist_added {
+                writeln!(f, "  + {}", syscall)?;
+            }
+            for syscall in &self.blacklist_removed {
+                writeln!(f, "  - {}", syscall)?;
+            }
+        }
+
+        if !self.limit_changes.is_empty() {
+            writeln!(f, "Resource limits:")?;
+            for change in &self.limit_changes {
+                let direction = match change.direction {
+                    LimitDirection::Tighter => "tighter",
+                    LimitDirection::Looser => "looser",
+                };
+                writeln!(f, "  {}: {} -> {} ({})", change.field, change.old_value, change.new_value, direction)?;
+            }
+        }
+
+        Ok(())
+    }
 }
 
 #[derive(Debug)]
@@ -314,6 +501,21 @@ impl PolicyManager {
     pub fn remove_policy(&mut self, name: &str) -> bool {
         self.policies.remove(&name.to_lowercase()).is_some()
     }
+    
+    /// Dry run of `candidate` against analyzed modules: each module that would
+    /// lose a required capability, with the capabilities it would lose.
+    pub fn what_if(
+        &self,
+        results: &[(String, CapabilityRequirements)],
+        candidate: &SecurityPolicy,
+    ) -> Vec<(String, Vec<String>)> {
+        results.iter()
+            .map(|(module, requirements)| {
+                (module.clone(), candidate.allowed_capabilities.has_all(&requirements.required_capabilities))
+            })
+            .filter(|(_, unmet)| !unmet.is_empty())
+            .collect()
+    }
 }
 
 #[cfg(test)]
@@ -343,6 +545,23 @@ mod tests {
         assert!(!policy.is_syscall_allowed("open"));
     }
 
+    #[test]
+    fn test_network_policy_allows_url() {
+        let policy = NetworkPolicy {
+            allow_outbound: true,
+            allowed_hosts: vec!["telemetry.example.com".to_string()],
+            allowed_ports: vec![443],
+            ..NetworkPolicy::default()
+        };
+        
+        assert!(policy.allows_url("https://telemetry.example.com/v1/ingest"));
+        assert!(!policy.allows_url("http://telemetry.example.com/v1/ingest")); // port 80
+        assert!(!policy.allows_url("https://telemetry.example.com:8443/"));
+        assert!(!policy.allows_url("https://evil.example.com/"));
+        assert!(!policy.allows_url("ftp://telemetry.example.com/"));
+        assert!(!NetworkPolicy::default().allows_url("https://telemetry.example.com/"));
+    }
+
     #[test]
     fn test_policy_manager() {
         let mut manager = PolicyManager::new();
@@ -364,4 +583,43 @@ mod tests {
         
         assert!(policy.is_err());
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn test_policy_diff() {
+        let diff = SecurityPolicy::strict().diff(&SecurityPolicy::sensor_access());
+
+        assert!(diff.added_capabilities.contains(&Capability::ReadSensor(SensorType::Any)));
+        assert!(diff.removed_capabilities.is_empty());
+        assert!(diff.whitelist_added.contains(&"wasm_read_sensor".to_string()));
+        assert!(diff.blacklist_removed.contains(&"connect".to_string()));
+
+        let memory = diff.limit_changes.iter().find(|c| c.field == "max_memory_pages").unwrap();
+        assert_eq!(memory.direction, LimitDirection::Looser);
+
+        let text = diff.to_string();
+        assert!(text.contains("+ ReadSensor(Any)"));
+        assert!(text.contains("+ wasm_read_sensor"));
+
+        assert!(SecurityPolicy::strict().diff(&SecurityPolicy::strict()).is_empty());
+    }
+
+    #[test]
+    fn test_what_if_strict_candidate() {
+        let requirements = |names: &[&str]| CapabilityRequirements {
+            required_capabilities: names.iter().map(|name| name.to_string()).collect(),
+            optional_capabilities: Vec::new(),
+            inferred_permissions: Vec::new(),
+        };
+        let results = vec![
+            ("thermostat".to_string(), requirements(&["Log", "ReadSensor"])),
+            ("uploader".to_string(), requirements(&["Log", "NetworkAccess"])),
+            ("logger".to_string(), requirements(&["Log"])),
+        ];
+        
+        let broken = PolicyManager::new().what_if(&results, &SecurityPolicy::strict());
+        assert_eq!(broken, vec![
+            ("thermostat".to_string(), vec!["ReadSensor".to_string()]),
+            ("uploader".to_string(), vec!["NetworkAccess".to_string()]),
+        ]);
+    }
+}
@@ -1,22 +1,123 @@
 use crate::error::{WasmError, Result};
 use std::collections::{HashMap, HashSet};
+use std::fmt;
+use std::sync::{mpsc, Arc};
+use std::time::Duration;
+
+/// Error message returned when a handler outlives its timeout.
+pub const HANDLER_TIMEOUT_ERROR: &str = "syscall handler timeout";
+
+/// Error message for calls to a handler disabled by an earlier timeout.
+pub const HANDLER_DISABLED_ERROR: &str = "syscall handler disabled after timeout";
+
+/// Syscalls whose arguments are redacted by default, with the argument
+/// indices to hide. A trailing `*` matches any syscall with that prefix.
+const DEFAULT_SENSITIVE_ARGS: &[(&str, &[usize])] = &[
+    ("wasm_crypto_*", &[0, 1]), // key_ptr, key_len
+];
+
+/// Which syscall arguments are sensitive, shared by the syscall log and the
+/// debugger's tracer. Patterns ending in `*` match by prefix.
+#[derive(Debug, Clone)]
+pub struct ArgRedactor {
+    sensitive_args: HashMap<String, Vec<usize>>,
+}
+
+impl ArgRedactor {
+    /// A redactor with no sensitive arguments.
+    pub fn empty() -> Self {
+        Self { sensitive_args: HashMap::new() }
+    }
+    
+    pub fn mark_sensitive(&mut self, pattern: &str, arg_indices: &[usize]) {
+        self.sensitive_args.entry(pattern.to_string())
+            .or_default()
+            .extend_from_slice(arg_indices);
+    }
+    
+    fn is_sensitive_arg(&self, syscall: &str, index: usize) -> bool {
+        self.sensitive_args.iter().any(|(pattern, indices)| {
+            let matches = match pattern.strip_suffix('*') {
+                Some(prefix) => syscall.starts_with(prefix),
+                None => syscall == pattern,
+            };
+            matches && indices.contains(&index)
+        })
+    }
+    
+    /// `args` as they may be recorded, with sensitive ones hidden.
+    pub fn redact(&self, syscall: &str, args: &[u32]) -> Vec<SyscallArg> {
+        args.iter().enumerate()
+            .map(|(i, &arg)| {
+                if self.is_sensitive_arg(syscall, i) {
+                    SyscallArg::Redacted
+                } else {
+                    SyscallArg::Value(arg)
+                }
+            })
+            .collect()
+    }
+}
+
+/// Redacts `DEFAULT_SENSITIVE_ARGS`.
+impl Default for ArgRedactor {
+    fn default() -> Self {
+        let mut redactor = Self::empty();
+        for (pattern, indices) in DEFAULT_SENSITIVE_ARGS {
+            redactor.mark_sensitive(pattern, indices);
+        }
+        redactor
+    }
+}
 
 #[derive(Debug)]
 pub struct SyscallTrap {
     allowed_syscalls: HashSet<String>,
-    syscall_handlers: HashMap<String, Box<dyn SyscallHandler>>,
+    syscall_handlers: HashMap<String, Arc<dyn SyscallHandler>>,
     syscall_log: Vec<SyscallEntry>,
+    redactor: ArgRedactor,
+    handler_timeouts: HashMap<String, Duration>,
+    /// Handlers that timed out; each still holds its abandoned thread.
+    disabled_handlers: HashSet<String>,
 }
 
 #[derive(Debug, Clone)]
 pub struct SyscallEntry {
     pub name: String,
-    pub args: Vec<u32>,
+    pub args: Vec<SyscallArg>,
     pub result: Result<u32>,
     pub timestamp: std::time::Instant,
 }
 
-pub trait SyscallHandler: std::fmt::Debug {
+/// A logged syscall argument; sensitive values are never stored.
+#[derive(Debug, Clone, PartialEq)]
+pub enum SyscallArg {
+    Value(u32),
+    Redacted,
+}
+
+impl fmt::Display for SyscallArg {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            SyscallArg::Value(v) => write!(f, "{}", v),
+            SyscallArg::Redacted => write!(f, "[redacted]"),
+        }
+    }
+}
+
+impl fmt::Display for SyscallEntry {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let args: Vec<String> = self.args.iter().map(|a| a.to_string()).collect();
+        match &self.result {
+            Ok(value) => write!(f, "{}({}) -> {}", self.name, args.join(", "), value),
+            Err(e) => write!(f, "{}({}) -> error: {}", self.name, args.join(", "), e),
+        }
+    }
+}
+
+/// Handlers must be `Send + Sync` so a timed call can run on its own thread;
+/// handlers holding `Rc` or `RefCell` state need `Arc` and `Mutex` instead.
+pub trait SyscallHandler: std::fmt::Debug + Send + Sync {
     fn handle(&self, args: &[u32]) -> Result<u32>;
 }
 
@@ -138,6 +239,9 @@ impl SyscallTrap {
             allowed_syscalls: HashSet::new(),
             syscall_handlers: HashMap::new(),
             syscall_log: Vec::new(),
+            redactor: ArgRedactor::default(),
+            handler_timeouts: HashMap::new(),
+            disabled_handlers: HashSet::new(),
         };
         
         trap.setup_default_handlers();
@@ -164,30 +268,77 @@ impl SyscallTrap {
     
     pub fn register_syscall(&mut self, name: &str, handler: Box<dyn SyscallHandler>) {
         self.allowed_syscalls.insert(name.to_string());
-        self.syscall_handlers.insert(name.to_string(), handler);
+        self.syscall_handlers.insert(name.to_string(), Arc::from(handler));
+        self.disabled_handlers.remove(name);
+    }
+    
+    /// Run `syscall`'s handler on its own thread and give up after `timeout`.
+    /// A handler that times out keeps its thread and is disabled until it is
+    /// registered again, so a hanging handler leaks at most one thread.
+    pub fn set_handler_timeout(&mut self, syscall: &str, timeout: Duration) {
+        self.handler_timeouts.insert(syscall.to_string(), timeout);
+    }
+    
+    fn run_handler(handler: &Arc<dyn SyscallHandler>, args: &[u32], timeout: Option<Duration>) -> Result<u32> {
+        let timeout = match timeout {
+            Some(timeout) => timeout,
+            None => return handler.handle(args),
+        };
+        
+        let (sender, receiver) = mpsc::channel();
+        let handler = Arc::clone(handler);
+        let args = args.to_vec();
+        std::thread::spawn(move || {
+            let _ = sender.send(handler.handle(&args));
+        });
+        
+        match receiver.recv_timeout(timeout) {
+            Ok(result) => result,
+            Err(mpsc::RecvTimeoutError::Timeout) => Err(WasmError::Runtime(HANDLER_TIMEOUT_ERROR.to_string())),
+            Err(mpsc::RecvTimeoutError::Disconnected) => Err(WasmError::Runtime("syscall handler panicked".to_string())),
+        }
     }
     
     pub fn is_allowed(&self, syscall: &str) -> bool {
         self.allowed_syscalls.contains(syscall)
     }
     
+    /// Redact the given argument indices of `pattern` in the syscall log.
+    pub fn mark_sensitive(&mut self, pattern: &str, arg_indices: &[usize]) {
+        self.redactor.mark_sensitive(pattern, arg_indices);
+    }
+    
+    /// `args` as they may be logged, with sensitive ones hidden.
+    pub fn redact_args(&self, syscall: &str, args: &[u32]) -> Vec<SyscallArg> {
+        self.redactor.redact(syscall, args)
+    }
+    
     pub fn handle(&mut self, syscall: &str, args: &[u32]) -> Result<u32> {
         let timestamp = std::time::Instant::now();
         
-        let result = if let Some(handler) = self.syscall_handlers.get(syscall) {
-            handler.handle(args)
+        let result = if self.disabled_handlers.contains(syscall) {
+            Err(WasmError::Runtime(HANDLER_DISABLED_ERROR.to_string()))
+        } else if let Some(handler) = self.syscall_handlers.get(syscall) {
+            let result = Self::run_handler(handler, args, self.handler_timeouts.get(syscall).copied());
+            if matches!(&result, Err(WasmError::Runtime(msg)) if msg == HANDLER_TIMEOUT_ERROR) {
+                log::warn!("Disabling syscall handler {} after timeout", syscall);
+                self.disabled_handlers.insert(syscall.to_string());
+            }
+            result
         } else {
             log::warn!("Unknown syscall intercepted: {}", syscall);
             Err(WasmError::Runtime(format!("Unknown syscall: {}", syscall)))
         };
         
         // Log the syscall attempt
-        self.syscall_log.push(SyscallEntry {
+        let entry = SyscallEntry {
             name: syscall.to_string(),
-            args: args.to_vec(),
+            args: self.redact_args(syscall, args),
             result: result.clone(),
             timestamp,
-        });
+        };
+        log::debug!("Syscall: {}", entry);
+        self.syscall_log.push(entry);
         
         // Limit log size to prevent memory exhaustion
         if self.syscall_log.len() > 1000 {
@@ -219,7 +370,7 @@ impl SyscallTrap {
         self.allowed_syscalls.remove(syscall);
         self.syscall_handlers.insert(
             syscall.to_string(), 
-            Box::new(DeniedSyscallHandler)
+            Arc::new(DeniedSyscallHandler)
         );
     }
     
@@ -271,4 +422,31 @@ mod tests {
         assert_eq!(log.len(), 1);
         assert_eq!(log[0].name, "wasm_get_time");
     }
+
+    #[derive(Debug)]
+    struct EchoHandler;
+
+    impl SyscallHandler for EchoHandler {
+        fn handle(&self, args: &[u32]) -> Result<u32> {
+            Ok(args.len() as u32)
+        }
+    }
+
+    #[test]
+    fn test_sensitive_args_redacted() {
+        let mut trap = SyscallTrap::new();
+        trap.register_syscall("wasm_crypto_sign", Box::new(EchoHandler));
+        trap.register_syscall("wasm_store_secret", Box::new(EchoHandler));
+        trap.mark_sensitive("wasm_store_secret", &[1]);
+        
+        trap.handle("wasm_crypto_sign", &[1024, 32, 2048]).unwrap();
+        trap.handle("wasm_store_secret", &[7, 99]).unwrap();
+        trap.handle("wasm_read_sensor", &[0]).unwrap();
+        
+        let log = trap.get_syscall_log();
+        assert_eq!(log[0].args, vec![SyscallArg::Redacted, SyscallArg::Redacted, SyscallArg::Value(2048)]);
+        assert_eq!(log[0].to_string(), "wasm_crypto_sign([redacted], [redacted], 2048) -> 3");
+        assert_eq!(log[1].args, vec![SyscallArg::Value(7), SyscallArg::Redacted]);
+        assert_eq!(log[2].to_string(), "wasm_read_sensor(0) -> 25");
+    }
 }
\ No newline at end of file
@@ -1,5 +1,5 @@
 use super::*;
-use crate::parser::WasmModule;
+use crate::parser::{CodeSection, Export, Import, ImportKind, MemoryType, WasmModule};
 use crate::error::{WasmError, Result};
 use std::time::Instant;
 
@@ -10,7 +10,12 @@ pub struct FastAnalyzer {
 
 struct AnalysisCache {
     results: std::collections::HashMap<String, AnalysisResult>,
+    /// Cached hashes from least to most recently used.
+    recency: std::collections::VecDeque<String>,
     max_entries: usize,
+    hits: u64,
+    misses: u64,
+    evictions: u64,
 }
 
 impl FastAnalyzer {
@@ -19,16 +24,31 @@ impl FastAnalyzer {
             static_analyzer: StaticAnalyzer::new(),
             cache: AnalysisCache {
                 results: std::collections::HashMap::new(),
+                recency: std::collections::VecDeque::new(),
                 max_entries: 100,
+                hits: 0,
+                misses: 0,
+                evictions: 0,
             },
         }
     }
 
+    pub fn with_cache_capacity(mut self, max_entries: usize) -> Self {
+        self.cache.max_entries = max_entries;
+        self
+    }
+
     pub fn analyze_fast(&mut self, module: &WasmModule, module_hash: &str) -> Result<AnalysisResult> {
         // Check cache first
         if let Some(cached) = self.cache.results.get(module_hash) {
+            self.cache.hits += 1;
+            if let Some(pos) = self.cache.recency.iter().position(|hash| hash == module_hash) {
+                let hash = self.cache.recency.remove(pos).unwrap();
+                self.cache.recency.push_back(hash);
+            }
             return Ok(cached.clone());
         }
+        self.cache.misses += 1;
 
         let start_time = Instant::now();
         
@@ -73,13 +93,14 @@ impl FastAnalyzer {
 
     fn extract_module_info_fast(&self, module: &WasmModule) -> ModuleInfo {
         ModuleInfo {
-            size: 0, // Would calculate from binary size
+            size: module.total_size,
             function_count: module.functions.len(),
-            import_count: 0, // Quick count from imports section
+            import_count: module.imports.len(),
             export_count: module.exports.len(),
             memory_pages: module.memory.as_ref().map(|m| m.min),
-            table_size: None, // Quick extraction
-            global_count: 0, // Quick count from globals section
+            table_size: module.table.as_ref().map(|t| t.min),
+            global_count: module.globals.len(),
+            demangled_names: Vec::new(),
         }
     }
 
@@ -104,6 +125,8 @@ impl FastAnalyzer {
                     instruction_offset: 0,
                     description: "Critical security pattern detected".to_string(),
                     risk_level: RiskLevel::Severe,
+                    occurrences: 1,
+                    offsets: Vec::new(),
                 });
             }
 
@@ -114,6 +137,7 @@ impl FastAnalyzer {
                     locations: vec![func_idx as u32],
                     risk_level: RiskLevel::Warning,
                     description: "Memory growth detected".to_string(),
+                    code_locations: Vec::new(),
                 });
             }
         }
@@ -203,6 +227,8 @@ impl FastAnalyzer {
                         name: "Log".to_string(),
                         required: true,
                         reason: "Logging function exported".to_string(),
+                        risk_level: RiskLevel::OK,
+                        locations: Vec::new(),
                     });
                 }
                 name if name.contains("time") => {
@@ -331,44 +357,237 @@ impl FastAnalyzer {
 
     fn cache_result(&mut self, hash: String, result: AnalysisResult) {
         if self.cache.results.len() >= self.cache.max_entries {
-            // Simple LRU eviction - remove first entry
-            if let Some(first_key) = self.cache.results.keys().next().cloned() {
-                self.cache.results.remove(&first_key);
+            if let Some(oldest) = self.cache.recency.pop_front() {
+                self.cache.results.remove(&oldest);
+                self.cache.evictions += 1;
             }
         }
+        self.cache.recency.push_back(hash.clone());
         self.cache.results.insert(hash, result);
     }
 
     pub fn clear_cache(&mut self) {
         self.cache.results.clear();
+        self.cache.recency.clear();
     }
 
     pub fn get_cache_stats(&self) -> CacheStats {
+        let lookups = self.cache.hits + self.cache.misses;
         CacheStats {
             entries: self.cache.results.len(),
             max_entries: self.cache.max_entries,
-            hit_rate: 0.0, // Would track in real implementation
+            hits: self.cache.hits,
+            misses: self.cache.misses,
+            evictions: self.cache.evictions,
+            hit_rate: if lookups == 0 { 0.0 } else { self.cache.hits as f64 / lookups as f64 },
         }
     }
+
+    /// Zero the hit/miss/eviction counters without touching cached results.
+    pub fn reset_stats(&mut self) {
+        self.cache.hits = 0;
+        self.cache.misses = 0;
+        self.cache.evictions = 0;
+    }
 }
 
 #[derive(Debug)]
 pub struct CacheStats {
     pub entries: usize,
     pub max_entries: usize,
+    pub hits: u64,
+    pub misses: u64,
+    pub evictions: u64,
+    /// Fraction of lookups served from the cache.
     pub hit_rate: f64,
 }
 
+/// Scores a module section by section as it is parsed, so a host can abort on
+/// a Severe finding before the rest arrives. Checks that span sections, such
+/// as recursion and capability inference, run in `finalize`.
+pub struct IncrementalAnalyzer {
+    analyzer: StaticAnalyzer,
+    module: WasmModule,
+    running: SecurityAssessment,
+    demangled_names: Vec<demangle::DemangledName>,
+    started: Instant,
+}
+
+impl IncrementalAnalyzer {
+    pub fn new() -> Self {
+        Self::with_analyzer(StaticAnalyzer::new())
+    }
+
+    /// Use `analyzer`'s patterns and weights for both the running assessment
+    /// and the final result.
+    pub fn with_analyzer(analyzer: StaticAnalyzer) -> Self {
+        Self {
+            analyzer,
+            module: WasmModule::default(),
+            running: SecurityAssessment {
+                memory_patterns: Vec::new(),
+                control_flow_complexity: 0,
+                suspicious_patterns: Vec::new(),
+                syscall_functions: Vec::new(),
+                resource_requirements: ResourceRequirements {
+                    estimated_memory: 0,
+                    estimated_cpu_cycles: 0,
+                    max_stack_depth: 0,
+                    max_call_depth: 0,
+                },
+            },
+            demangled_names: Vec::new(),
+            started: Instant::now(),
+        }
+    }
+
+    /// Everything other than imports, exports, memory and code, such as the
+    /// type and function sections, which do not affect the running assessment.
+    pub fn module_mut(&mut self) -> &mut WasmModule {
+        &mut self.module
+    }
+
+    /// Demangle names in `chunk` if the analyzer is configured to.
+    fn normalize(&mut self, chunk: &mut WasmModule) {
+        if self.analyzer.demangle_names {
+            self.demangled_names.extend(demangle::normalize_module(chunk));
+        }
+    }
+
+    pub fn feed_imports(&mut self, imports: Vec<Import>) {
+        let mut chunk = WasmModule { imports, ..Default::default() };
+        self.normalize(&mut chunk);
+
+        self.running.suspicious_patterns.extend(self.analyzer.security.detect_unlimited_imported_memory(&chunk));
+        self.module.imports.extend(chunk.imports);
+    }
+
+    pub fn feed_exports(&mut self, exports: Vec<Export>) {
+        let mut chunk = WasmModule { exports, ..Default::default() };
+        self.normalize(&mut chunk);
+
+        let first_index = self.running.syscall_functions.len() as u32;
+        self.running.syscall_functions.extend(self.analyzer.security.syscall_exports(&chunk, first_index));
+        self.module.exports.extend(chunk.exports);
+    }
+
+    pub fn feed_memory(&mut self, memory: MemoryType) {
+        self.running.resource_requirements.estimated_memory = security::SecurityAnalyzer::estimate_memory(Some(&memory));
+        self.module.memory = Some(memory);
+    }
+
+    /// Scan `bodies` as the next functions of the code section.
+    pub fn feed_code(&mut self, bodies: Vec<CodeSection>) {
+        let first_index = self.module.code.len() as u32;
+        let chunk = WasmModule { code: bodies, ..Default::default() };
+        let assessment = self.analyzer.security.analyze_code_chunk(&chunk, first_index);
+
+        let running = &mut self.running;
+        running.memory_patterns.extend(assessment.memory_patterns);
+        running.control_flow_complexity += assessment.control_flow_complexity;
+        running.suspicious_patterns.extend(assessment.suspicious_patterns);
+        let requirements = &mut running.resource_requirements;
+        requirements.estimated_cpu_cycles = requirements.estimated_cpu_cycles
+            .saturating_add(assessment.resource_requirements.estimated_cpu_cycles);
+        requirements.max_stack_depth = requirements.max_stack_depth.max(assessment.resource_requirements.max_stack_depth);
+        requirements.max_call_depth = requirements.max_call_depth.max(assessment.resource_requirements.max_call_depth);
+        self.module.code.extend(chunk.code);
+    }
+
+    /// Findings from the sections fed so far; syscall usage counts are not
+    /// known until `finalize`.
+    pub fn assessment(&self) -> &SecurityAssessment {
+        &self.running
+    }
+
+    pub fn has_severe_finding(&self) -> bool {
+        self.running.suspicious_patterns.iter().any(|p| p.risk_level == RiskLevel::Severe)
+            || self.running.memory_patterns.iter().any(|p| p.risk_level == RiskLevel::Severe)
+            || self.running.syscall_functions.iter().any(|s| s.risk_level == RiskLevel::Severe)
+    }
+
+    /// Complete the running assessment with the cross-section checks, then
+    /// infer capabilities and score as `StaticAnalyzer::analyze` would.
+    pub fn finalize(self) -> Result<AnalysisResult> {
+        let security_assessment = self.analyzer.security.finish_incremental(&self.module, self.running);
+        self.analyzer.complete_analysis(&self.module, security_assessment, self.demangled_names, self.started)
+    }
+}
+
+impl Default for IncrementalAnalyzer {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Cache key covering every section that affects analysis. With the
+/// `hashing` feature, parsed modules use the SHA-256 of their bytes instead.
 pub fn calculate_module_hash(module: &WasmModule) -> String {
-    // Simple hash calculation - in production would use proper hashing
     use std::collections::hash_map::DefaultHasher;
     use std::hash::{Hash, Hasher};
+    use std::mem::discriminant;
+    
+    #[cfg(feature = "hashing")]
+    if let Some(hash) = module.content_hash() {
+        return hash.to_string();
+    }
     
     let mut hasher = DefaultHasher::new();
-    module.functions.len().hash(&mut hasher);
-    module.exports.len().hash(&mut hasher);
+    for ty in &module.types {
+        ty.params.hash(&mut hasher);
+        ty.results.hash(&mut hasher);
+    }
+    for import in &module.imports {
+        import.module.hash(&mut hasher);
+        import.name.hash(&mut hasher);
+        discriminant(&import.kind).hash(&mut hasher);
+        // Payloads change findings, e.g. an imported memory without a max
+        match &import.kind {
+            ImportKind::Function(type_idx) => type_idx.hash(&mut hasher),
+            ImportKind::Table(table) => {
+                discriminant(&table.element_type).hash(&mut hasher);
+                (table.min, table.max).hash(&mut hasher);
+            }
+            ImportKind::Memory(memory) => (memory.min, memory.max).hash(&mut hasher),
+            ImportKind::Global { value_type, mutable } => (value_type, mutable).hash(&mut hasher),
+        }
+    }
+    for function in &module.functions {
+        function.type_idx.hash(&mut hasher);
+    }
+    if let Some(table) = &module.table {
+        discriminant(&table.element_type).hash(&mut hasher);
+        (table.min, table.max).hash(&mut hasher);
+    }
     if let Some(memory) = &module.memory {
-        memory.min.hash(&mut hasher);
+        (memory.min, memory.max).hash(&mut hasher);
+    }
+    for global in &module.globals {
+        global.value_type.hash(&mut hasher);
+        global.mutable.hash(&mut hasher);
+        global.init_expr.hash(&mut hasher);
+    }
+    for export in &module.exports {
+        export.name.hash(&mut hasher);
+        discriminant(&export.kind).hash(&mut hasher);
+        export.index.hash(&mut hasher);
+    }
+    module.start.hash(&mut hasher);
+    for code in &module.code {
+        for local in &code.locals {
+            local.count.hash(&mut hasher);
+            local.value_type.hash(&mut hasher);
+        }
+        code.body.hash(&mut hasher);
+    }
+    for segment in &module.data_segments {
+        segment.memory_index.hash(&mut hasher);
+        segment.offset_expr.hash(&mut hasher);
+        segment.bytes.hash(&mut hasher);
+    }
+    for section in &module.custom_sections {
+        section.name.hash(&mut hasher);
+        section.data.hash(&mut hasher);
     }
     
     format!("{:x}", hasher.finish())
@@ -410,6 +629,108 @@ mod tests {
         assert_eq!(stats.entries, 1);
     }
 
+    #[test]
+    fn test_cache_stats_counters() {
+        let mut analyzer = FastAnalyzer::new().with_cache_capacity(1);
+        let module = create_test_module();
+        
+        analyzer.analyze_fast(&module, "a").unwrap(); // miss
+        analyzer.analyze_fast(&module, "a").unwrap(); // hit
+        analyzer.analyze_fast(&module, "b").unwrap(); // miss, evicts "a"
+        analyzer.analyze_fast(&module, "a").unwrap(); // miss, evicts "b"
+        
+        let stats = analyzer.get_cache_stats();
+        assert_eq!(stats.hits, 1);
+        assert_eq!(stats.misses, 3);
+        assert_eq!(stats.evictions, 2);
+        assert_eq!(stats.entries, 1);
+        assert!((stats.hit_rate - 0.25).abs() < f64::EPSILON);
+        
+        analyzer.reset_stats();
+        let stats = analyzer.get_cache_stats();
+        assert_eq!((stats.hits, stats.misses, stats.evictions), (0, 0, 0));
+        assert_eq!(stats.hit_rate, 0.0);
+        assert_eq!(stats.entries, 1);
+    }
+
+    #[test]
+    fn test_module_hash_covers_code() {
+        let module = create_test_module();
+        let mut variant = create_test_module();
+        variant.code[0].body = vec![0x41, 0x02]; // i32.const 2
+        
+        assert_ne!(calculate_module_hash(&module), calculate_module_hash(&variant));
+        assert_eq!(calculate_module_hash(&module), calculate_module_hash(&create_test_module()));
+        
+        variant = create_test_module();
+        variant.exports[0].name = "main".to_string();
+        assert_ne!(calculate_module_hash(&module), calculate_module_hash(&variant));
+    }
+
+    #[test]
+    fn test_module_hash_covers_import_limits() {
+        let with_memory = |max| {
+            let mut module = create_test_module();
+            module.imports.push(Import {
+                module: "env".to_string(),
+                name: "memory".to_string(),
+                kind: ImportKind::Memory(MemoryType { min: 1, max }),
+            });
+            module
+        };
+        let limited = with_memory(Some(2));
+        let unlimited = with_memory(None);
+        assert_ne!(calculate_module_hash(&limited), calculate_module_hash(&unlimited));
+
+        // The riskier variant must not be served the cached analysis
+        let mut analyzer = FastAnalyzer::new();
+        analyzer.analyze_fast(&limited, &calculate_module_hash(&limited)).unwrap();
+        analyzer.analyze_fast(&unlimited, &calculate_module_hash(&unlimited)).unwrap();
+        let stats = analyzer.get_cache_stats();
+        assert_eq!((stats.hits, stats.misses), (0, 2));
+    }
+
+    #[test]
+    fn test_cache_evicts_least_recently_used() {
+        let mut analyzer = FastAnalyzer::new().with_cache_capacity(3);
+        let module = create_test_module();
+        
+        for hash in ["a", "b", "c"] {
+            analyzer.analyze_fast(&module, hash).unwrap();
+        }
+        analyzer.analyze_fast(&module, "a").unwrap(); // hit, "b" is now the oldest
+        analyzer.analyze_fast(&module, "d").unwrap(); // evicts "b"
+        
+        assert_eq!(analyzer.get_cache_stats().evictions, 1);
+        for hash in ["a", "c", "d"] {
+            analyzer.analyze_fast(&module, hash).unwrap();
+        }
+        assert_eq!(analyzer.get_cache_stats().hits, 4);
+        analyzer.analyze_fast(&module, "b").unwrap();
+        assert_eq!(analyzer.get_cache_stats().misses, 5);
+    }
+
+    #[test]
+    fn test_unsupported_target_feature_recommendation() {
+        use crate::parser::CustomSection;
+        
+        let mut data = vec![0x01, b'+', 0x07];
+        data.extend_from_slice(b"simd128");
+        let mut module = create_test_module();
+        module.custom_sections.push(CustomSection { name: "target_features".to_string(), data });
+        
+        assert_eq!(module.required_features(), vec!["simd128".to_string()]);
+        let result = StaticAnalyzer::new().analyze(&module).unwrap();
+        let compat = result.recommendations.iter()
+            .find(|r| r.category == "Compatibility")
+            .unwrap();
+        assert!(compat.message.contains("simd128"));
+        assert_eq!(compat.severity, RiskLevel::Severe);
+        
+        let plain = StaticAnalyzer::new().analyze(&create_test_module()).unwrap();
+        assert!(plain.recommendations.iter().all(|r| r.category != "Compatibility"));
+    }
+
     #[test]
     fn test_critical_pattern_detection() {
         let analyzer = FastAnalyzer::new();
@@ -427,6 +748,83 @@ mod tests {
         assert_eq!(analyzer.quick_syscall_risk("wasm_log"), RiskLevel::OK);
     }
 
+    #[test]
+    fn test_incremental_matches_one_shot() {
+        let mut module = create_test_module();
+        module.code.push(CodeSection { locals: vec![], body: vec![0x03, 0x40, 0x0C, 0x00, 0x0B, 0x0B] }); // infinite loop
+        module.functions.push(Function { type_idx: 0 });
+        module.memory = Some(crate::parser::MemoryType { min: 2, max: None });
+
+        let mut incremental = IncrementalAnalyzer::new();
+        incremental.module_mut().types = module.types.clone();
+        incremental.module_mut().functions = module.functions.clone();
+        incremental.feed_memory(module.memory.clone().unwrap());
+        incremental.feed_exports(module.exports.clone());
+        incremental.feed_code(vec![module.code[0].clone()]);
+        assert!(!incremental.has_severe_finding());
+        incremental.feed_code(vec![module.code[1].clone()]);
+        assert!(incremental.has_severe_finding());
+        let running = incremental.assessment().suspicious_patterns.iter()
+            .find(|p| p.pattern_name == "InfiniteLoop")
+            .unwrap();
+        assert_eq!(running.function_index, 1);
+
+        let one_shot = StaticAnalyzer::new().analyze(&module).unwrap();
+        assert_eq!(incremental.assessment().control_flow_complexity, one_shot.security_assessment.control_flow_complexity);
+
+        let result = incremental.finalize().unwrap();
+        assert_eq!(result.risk_score.score, one_shot.risk_score.score);
+        assert_eq!(result.risk_score.overall, one_shot.risk_score.overall);
+        assert_eq!(result.security_assessment.suspicious_patterns.len(), one_shot.security_assessment.suspicious_patterns.len());
+        let sorted = |result: &AnalysisResult| {
+            let mut names = result.capability_requirements.required_capabilities.clone();
+            names.sort();
+            names
+        };
+        assert_eq!(sorted(&result), sorted(&one_shot));
+    }
+
+    #[test]
+    fn test_incremental_assesses_imports_and_exports() {
+        use crate::parser::{Import, ImportKind, MemoryType};
+
+        let mut module = create_test_module();
+        module.imports.push(Import {
+            module: "env".to_string(),
+            name: "memory".to_string(),
+            kind: ImportKind::Memory(MemoryType { min: 1, max: None }),
+        });
+        module.exports.push(Export { name: "wasm_exec".to_string(), kind: ExportKind::Function, index: 0 });
+        module.code[0].body = vec![0x10, 0x01, 0x10, 0x01, 0x0B]; // call wasm_exec twice
+
+        let mut incremental = IncrementalAnalyzer::new();
+        incremental.module_mut().types = module.types.clone();
+        incremental.module_mut().functions = module.functions.clone();
+        incremental.feed_imports(module.imports.clone());
+        assert!(incremental.assessment().suspicious_patterns.iter().any(|p| p.pattern_name == "UnlimitedImportedMemory"));
+        assert!(!incremental.has_severe_finding());
+
+        // A severe syscall export is enough to abort before any code arrives
+        incremental.feed_exports(module.exports.clone());
+        assert!(incremental.has_severe_finding());
+        incremental.feed_code(module.code.clone());
+
+        let result = incremental.finalize().unwrap();
+        let one_shot = StaticAnalyzer::new().analyze(&module).unwrap();
+        let syscalls = |result: &AnalysisResult| -> Vec<(String, u32, u32)> {
+            result.security_assessment.syscall_functions.iter()
+                .map(|s| (s.name.clone(), s.import_index, s.usage_count))
+                .collect()
+        };
+        assert_eq!(syscalls(&result), syscalls(&one_shot));
+        assert_eq!(syscalls(&result)[1], ("wasm_exec".to_string(), 1, 2));
+        assert_eq!(result.security_assessment.memory_patterns.len(), one_shot.security_assessment.memory_patterns.len());
+        assert_eq!(result.security_assessment.suspicious_patterns.len(), one_shot.security_assessment.suspicious_patterns.len());
+        assert_eq!(result.security_assessment.resource_requirements.estimated_cpu_cycles,
+                   one_shot.security_assessment.resource_requirements.estimated_cpu_cycles);
+        assert_eq!(result.risk_score.score, one_shot.risk_score.score);
+    }
+
     fn create_test_module() -> WasmModule {
         WasmModule {
             types: vec![FunctionType { params: vec![], results: vec![] }],
@@ -441,6 +839,7 @@ mod tests {
                 locals: vec![],
                 body: vec![0x41, 0x01], // i32.const 1
             }],
+            ..Default::default()
         }
     }
 }
\ No newline at end of file
@@ -1,8 +1,13 @@
-use super::{CapabilityRequirements, Permission, SecurityAssessment, RiskLevel};
-use crate::parser::WasmModule;
+use super::{CapabilityRequirements, CodeLocation, Permission, SecurityAssessment, SuspiciousPattern, RiskLevel};
+use crate::parser::{Import, WasmModule};
 use crate::error::{WasmError, Result};
 use std::collections::HashSet;
 
+const WASI_MODULES: &[&str] = &["wasi_snapshot_preview1", "wasi_unstable"];
+
+/// WASI imports that can hand the guest a descriptor beyond stdin/stdout/stderr.
+const WASI_DESCRIPTOR_SOURCES: &[&str] = &["path_open", "sock_accept", "sock_open"];
+
 pub struct CapabilityInferrer {
     capability_rules: Vec<CapabilityRule>,
 }
@@ -20,6 +25,8 @@ struct CapabilityRule {
 enum Trigger {
     ExportName(String),
     ImportName(String),
+    /// WASI import by field name; a trailing `*` matches a prefix (e.g. `sock_*`).
+    WasiImport(String),
     MemoryUsage(u32),
     SyscallPattern(String),
     InstructionPattern(Vec<u8>),
@@ -44,13 +51,24 @@ impl CapabilityInferrer {
             self.analyze_export(&export.name, &mut required_capabilities, &mut optional_capabilities, &mut inferred_permissions);
         }
 
+        // Analyze imports; WASI ones have their own rules
+        let opens_descriptors = module.imports.iter()
+            .any(|i| is_wasi_module(&i.module) && WASI_DESCRIPTOR_SOURCES.contains(&i.name.as_str()));
+        for import in &module.imports {
+            if is_wasi_module(&import.module) {
+                self.analyze_wasi_import(import, opens_descriptors, &mut required_capabilities, &mut optional_capabilities, &mut inferred_permissions);
+            } else {
+                self.analyze_import(import, &mut required_capabilities, &mut optional_capabilities, &mut inferred_permissions);
+            }
+        }
+
         // Analyze based on memory usage
         if let Some(memory) = &module.memory {
             self.analyze_memory_requirements(memory, &mut required_capabilities, &mut inferred_permissions);
         }
 
         // Analyze based on security assessment
-        self.analyze_security_patterns(security, &mut required_capabilities, &mut inferred_permissions);
+        self.analyze_security_patterns(security, module.table_count() > 0, &mut required_capabilities, &mut inferred_permissions);
 
         // Analyze syscall functions
         for syscall in &security.syscall_functions {
@@ -164,6 +182,65 @@ impl CapabilityInferrer {
             risk_level: RiskLevel::Severe,
             description: "Module requires file system access - HIGH RISK".to_string(),
         });
+
+        // WASI clocks
+        self.capability_rules.push(CapabilityRule {
+            name: "GetTime".to_string(),
+            triggers: vec![
+                Trigger::WasiImport("clock_time_get".to_string()),
+                Trigger::WasiImport("clock_res_get".to_string()),
+            ],
+            required: true,
+            risk_level: RiskLevel::OK,
+            description: "WASI clock import requires time access".to_string(),
+        });
+
+        // WASI randomness
+        self.capability_rules.push(CapabilityRule {
+            name: "Random".to_string(),
+            triggers: vec![Trigger::WasiImport("random_get".to_string())],
+            required: true,
+            risk_level: RiskLevel::OK,
+            description: "WASI random_get requires random number generation".to_string(),
+        });
+
+        // WASI sockets (high risk)
+        self.capability_rules.push(CapabilityRule {
+            name: "NetworkAccess".to_string(),
+            triggers: vec![Trigger::WasiImport("sock_*".to_string())],
+            required: true,
+            risk_level: RiskLevel::Severe,
+            description: "WASI socket import requires network access - HIGH RISK".to_string(),
+        });
+
+        // WASI file writes (high risk); plain fd_write to stdio is handled separately
+        self.capability_rules.push(CapabilityRule {
+            name: "FileSystemWrite".to_string(),
+            triggers: vec![
+                Trigger::WasiImport("fd_write".to_string()),
+                Trigger::WasiImport("fd_pwrite".to_string()),
+                Trigger::WasiImport("path_create_directory".to_string()),
+                Trigger::WasiImport("path_remove_directory".to_string()),
+                Trigger::WasiImport("path_rename".to_string()),
+                Trigger::WasiImport("path_unlink_file".to_string()),
+            ],
+            required: true,
+            risk_level: RiskLevel::Severe,
+            description: "WASI import requires file system write access - HIGH RISK".to_string(),
+        });
+
+        // WASI file access (high risk)
+        self.capability_rules.push(CapabilityRule {
+            name: "FileSystemAccess".to_string(),
+            triggers: vec![
+                Trigger::WasiImport("path_open".to_string()),
+                Trigger::WasiImport("fd_readdir".to_string()),
+                Trigger::WasiImport("path_filestat_get".to_string()),
+            ],
+            required: true,
+            risk_level: RiskLevel::Severe,
+            description: "WASI import requires file system access - HIGH RISK".to_string(),
+        });
     }
 
     fn analyze_export(&self, export_name: &str, required: &mut HashSet<String>, 
@@ -182,6 +259,75 @@ impl CapabilityInferrer {
                             name: rule.name.clone(),
                             required: rule.required,
                             reason: format!("Export '{}' detected", export_name),
+                            risk_level: rule.risk_level.clone(),
+                            locations: Vec::new(),
+                        });
+                    }
+                }
+            }
+        }
+    }
+
+    fn analyze_import(&self, import: &Import, required: &mut HashSet<String>,
+                     optional: &mut HashSet<String>, permissions: &mut Vec<Permission>) {
+        for rule in &self.capability_rules {
+            for trigger in &rule.triggers {
+                if let Trigger::ImportName(name) = trigger {
+                    if import.name.contains(name.as_str()) || import.module.contains(name.as_str()) {
+                        if rule.required {
+                            required.insert(rule.name.clone());
+                        } else {
+                            optional.insert(rule.name.clone());
+                        }
+
+                        permissions.push(Permission {
+                            name: rule.name.clone(),
+                            required: rule.required,
+                            reason: format!("Import '{}.{}' detected", import.module, import.name),
+                            risk_level: rule.risk_level.clone(),
+                            locations: Vec::new(),
+                        });
+                    }
+                }
+            }
+        }
+    }
+
+    fn analyze_wasi_import(&self, import: &Import, opens_descriptors: bool, required: &mut HashSet<String>,
+                          optional: &mut HashSet<String>, permissions: &mut Vec<Permission>) {
+        if !is_wasi_module(&import.module) {
+            return;
+        }
+
+        // Without a way to open new descriptors, writes can only reach stdio
+        if !opens_descriptors && matches!(import.name.as_str(), "fd_write" | "fd_pwrite") {
+            required.insert("Log".to_string());
+            permissions.push(Permission {
+                name: "Log".to_string(),
+                required: true,
+                reason: format!("WASI import '{}' limited to stdio", import.name),
+                risk_level: RiskLevel::OK,
+                locations: Vec::new(),
+            });
+            return;
+        }
+
+        for rule in &self.capability_rules {
+            for trigger in &rule.triggers {
+                if let Trigger::WasiImport(pattern) = trigger {
+                    if wasi_import_matches(pattern, &import.name) {
+                        if rule.required {
+                            required.insert(rule.name.clone());
+                        } else {
+                            optional.insert(rule.name.clone());
+                        }
+
+                        permissions.push(Permission {
+                            name: rule.name.clone(),
+                            required: rule.required,
+                            reason: format!("WASI import '{}.{}' detected", import.module, import.name),
+                            risk_level: rule.risk_level.clone(),
+                            locations: Vec::new(),
                         });
                     }
                 }
@@ -197,6 +343,8 @@ impl CapabilityInferrer {
                 name: "MemoryGrow".to_string(),
                 required: true,
                 reason: format!("Large memory requirement: {} pages", memory.min),
+                risk_level: RiskLevel::Warning,
+                locations: Vec::new(),
             });
         }
 
@@ -206,11 +354,13 @@ impl CapabilityInferrer {
                 name: "UnlimitedMemory".to_string(),
                 required: true,
                 reason: "Module requests unlimited memory growth".to_string(),
+                risk_level: RiskLevel::Warning,
+                locations: Vec::new(),
             });
         }
     }
 
-    fn analyze_security_patterns(&self, security: &SecurityAssessment, 
+    fn analyze_security_patterns(&self, security: &SecurityAssessment, has_table: bool,
                                required: &mut HashSet<String>, permissions: &mut Vec<Permission>) {
         // Check for memory growth patterns
         for pattern in &security.memory_patterns {
@@ -220,6 +370,8 @@ impl CapabilityInferrer {
                     name: "MemoryGrow".to_string(),
                     required: true,
                     reason: "Memory growth operations detected".to_string(),
+                    risk_level: pattern.risk_level.clone(),
+                    locations: pattern.code_locations.clone(),
                 });
             }
         }
@@ -231,6 +383,8 @@ impl CapabilityInferrer {
                 name: "HighComplexity".to_string(),
                 required: true,
                 reason: format!("High control flow complexity: {}", security.control_flow_complexity),
+                risk_level: RiskLevel::Warning,
+                locations: Vec::new(),
             });
         }
 
@@ -243,14 +397,24 @@ impl CapabilityInferrer {
                         name: "LongRunning".to_string(),
                         required: true,
                         reason: "Potential infinite loop detected".to_string(),
+                        risk_level: pattern.risk_level.clone(),
+                        locations: pattern_locations(pattern),
                     });
                 }
                 "IndirectCall" => {
+                    // Without a table every call_indirect traps or the module is malformed
+                    let (reason, risk_level) = if has_table {
+                        ("Indirect function calls detected", pattern.risk_level.clone())
+                    } else {
+                        ("Indirect function calls without a table", RiskLevel::Severe)
+                    };
                     required.insert("DynamicExecution".to_string());
                     permissions.push(Permission {
                         name: "DynamicExecution".to_string(),
                         required: true,
-                        reason: "Indirect function calls detected".to_string(),
+                        reason: reason.to_string(),
+                        risk_level,
+                        locations: pattern_locations(pattern),
                     });
                 }
                 _ => {}
@@ -269,6 +433,8 @@ impl CapabilityInferrer {
                             name: rule.name.clone(),
                             required: true,
                             reason: format!("Syscall '{}' requires capability", syscall_name),
+                            risk_level: rule.risk_level.clone(),
+                            locations: Vec::new(),
                         });
                     }
                 }
@@ -282,6 +448,8 @@ impl CapabilityInferrer {
             name: capability_name,
             required: true,
             reason: format!("Direct syscall access: {}", syscall_name),
+            risk_level,
+            locations: Vec::new(),
         });
     }
 
@@ -336,6 +504,28 @@ impl CapabilityInferrer {
     }
 }
 
+fn is_wasi_module(module: &str) -> bool {
+    WASI_MODULES.contains(&module)
+}
+
+fn wasi_import_matches(pattern: &str, name: &str) -> bool {
+    match pattern.strip_suffix('*') {
+        Some(prefix) => name.starts_with(prefix),
+        None => name == pattern,
+    }
+}
+
+fn pattern_locations(pattern: &SuspiciousPattern) -> Vec<CodeLocation> {
+    // Grouped findings carry every offset; single ones only `instruction_offset`
+    let offsets = match pattern.offsets.is_empty() {
+        true => std::slice::from_ref(&pattern.instruction_offset),
+        false => &pattern.offsets[..],
+    };
+    offsets.iter()
+        .map(|&offset| CodeLocation { function_index: pattern.function_index, offset })
+        .collect()
+}
+
 #[derive(Debug, Clone)]
 pub struct SandboxConstraints {
     pub max_memory_pages: u32,
@@ -372,7 +562,7 @@ impl Default for SandboxConstraints {
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::parser::{WasmModule, Export, ExportKind, MemoryType};
+    use crate::parser::{WasmModule, Export, ExportKind, MemoryType, Import, ImportKind};
 
     #[test]
     fn test_capability_inference() {
@@ -389,6 +579,7 @@ mod tests {
                 }
             ],
             code: vec![],
+            ..Default::default()
         };
 
         let security = SecurityAssessment {
@@ -453,6 +644,7 @@ mod tests {
                 }
             ],
             code: vec![],
+            ..Default::default()
         };
 
         let security = SecurityAssessment {
@@ -484,4 +676,126 @@ mod tests {
         let constraints = inferrer.recommend_sandbox_constraints(&capabilities, &security);
         assert!(!constraints.network_allowed);
     }
-}
\ No newline at end of file
+
+    fn wasi_module(names: &[&str]) -> WasmModule {
+        WasmModule {
+            imports: names.iter().map(|name| Import {
+                module: "wasi_snapshot_preview1".to_string(),
+                name: name.to_string(),
+                kind: ImportKind::Function(0),
+            }).collect(),
+            ..Default::default()
+        }
+    }
+
+    fn empty_assessment() -> SecurityAssessment {
+        SecurityAssessment {
+            memory_patterns: vec![],
+            control_flow_complexity: 1,
+            suspicious_patterns: vec![],
+            syscall_functions: vec![],
+            resource_requirements: crate::static_analysis::ResourceRequirements {
+                estimated_memory: 0,
+                estimated_cpu_cycles: 0,
+                max_stack_depth: 1,
+                max_call_depth: 1,
+            },
+        }
+    }
+
+    #[test]
+    fn test_wasi_socket_import() {
+        let inferrer = CapabilityInferrer::new();
+        let module = wasi_module(&["sock_connect"]);
+
+        let capabilities = inferrer.infer(&module, &empty_assessment()).unwrap();
+        assert!(capabilities.required_capabilities.contains(&"NetworkAccess".to_string()));
+
+        let permission = capabilities.inferred_permissions.iter()
+            .find(|p| p.name == "NetworkAccess")
+            .unwrap();
+        assert_eq!(permission.risk_level, RiskLevel::Severe);
+        assert!(permission.reason.contains("sock_connect"));
+    }
+
+    #[test]
+    fn test_wasi_fd_write_stdio_only() {
+        let inferrer = CapabilityInferrer::new();
+
+        let stdio = inferrer.infer(&wasi_module(&["fd_write", "clock_time_get"]), &empty_assessment()).unwrap();
+        assert!(stdio.required_capabilities.contains(&"Log".to_string()));
+        assert!(stdio.required_capabilities.contains(&"GetTime".to_string()));
+        assert!(!stdio.required_capabilities.contains(&"FileSystemWrite".to_string()));
+
+        let files = inferrer.infer(&wasi_module(&["path_open", "fd_write"]), &empty_assessment()).unwrap();
+        assert!(files.required_capabilities.contains(&"FileSystemWrite".to_string()));
+        assert!(files.required_capabilities.contains(&"FileSystemAccess".to_string()));
+    }
+
+    #[test]
+    fn test_import_name_triggers() {
+        let inferrer = CapabilityInferrer::new();
+        let module = WasmModule {
+            imports: vec![
+                Import { module: "env".to_string(), name: "host_log".to_string(), kind: ImportKind::Function(0) },
+                Import { module: "network".to_string(), name: "send".to_string(), kind: ImportKind::Function(0) },
+            ],
+            ..Default::default()
+        };
+
+        let capabilities = inferrer.infer(&module, &empty_assessment()).unwrap();
+        assert!(capabilities.required_capabilities.contains(&"Log".to_string()));
+        assert!(capabilities.required_capabilities.contains(&"NetworkAccess".to_string()));
+        assert!(capabilities.inferred_permissions.iter().any(|p| p.reason == "Import 'env.host_log' detected"));
+
+        let info = crate::static_analysis::StaticAnalyzer::new().analyze(&module).unwrap().module_info;
+        assert_eq!(info.import_count, 2);
+    }
+
+    #[test]
+    fn test_memory_grow_permission_location() {
+        use crate::parser::CodeSection;
+        use crate::static_analysis::report::ReportGenerator;
+
+        let module = WasmModule {
+            memory: Some(MemoryType { min: 1, max: Some(4) }),
+            code: vec![
+                CodeSection { locals: vec![], body: vec![0x0B] },
+                // i32.const 1; memory.grow 0; drop; end
+                CodeSection { locals: vec![], body: vec![0x41, 0x01, 0x40, 0x00, 0x1A, 0x0B] },
+            ],
+            ..Default::default()
+        };
+
+        let analysis = crate::static_analysis::StaticAnalyzer::new().analyze(&module).unwrap();
+        let permission = analysis.capability_requirements.inferred_permissions.iter()
+            .find(|p| p.name == "MemoryGrow")
+            .expect("MemoryGrow inferred");
+        assert_eq!(permission.locations, vec![CodeLocation { function_index: 1, offset: 2 }]);
+
+        let html = ReportGenerator::generate_html_report(&analysis);
+        assert!(html.contains(r##"href="#func-1-2">func 1 +0x2</a>"##));
+    }
+
+    #[test]
+    fn test_indirect_call_without_table() {
+        use crate::parser::{CodeSection, RefType, TableType};
+
+        let mut module = WasmModule {
+            // i32.const 0; call_indirect type 0 table 0; end
+            code: vec![CodeSection { locals: vec![], body: vec![0x41, 0x00, 0x11, 0x00, 0x00, 0x0B] }],
+            ..Default::default()
+        };
+        let dynamic = |module: &WasmModule| {
+            crate::static_analysis::StaticAnalyzer::new().analyze(module).unwrap()
+                .capability_requirements.inferred_permissions.into_iter()
+                .find(|p| p.name == "DynamicExecution")
+                .expect("DynamicExecution inferred")
+        };
+
+        assert_eq!(dynamic(&module).risk_level, RiskLevel::Severe);
+
+        module.table = Some(TableType { element_type: RefType::FuncRef, min: 1, max: None });
+        assert_eq!(dynamic(&module).risk_level, RiskLevel::Warning);
+    }
+}
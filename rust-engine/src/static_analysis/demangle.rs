@@ -0,0 +1,78 @@
+use crate::parser::WasmModule;
+use serde::{Deserialize, Serialize};
+
+/// An export or import name that was normalized before analysis.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct DemangledName {
+    pub raw: String,
+    pub demangled: String,
+}
+
+/// Demangle a Rust or C++ symbol, returning `None` if `name` is not mangled.
+#[cfg(feature = "demangle")]
+pub fn demangle(name: &str) -> Option<String> {
+    if let Ok(symbol) = rustc_demangle::try_demangle(name) {
+        // `{:#}` omits the trailing hash
+        return Some(format!("{:#}", symbol));
+    }
+    cpp_demangle::Symbol::new(name).ok()
+        .and_then(|symbol| symbol.demangle(&cpp_demangle::DemangleOptions::default()).ok())
+}
+
+/// Without the `demangle` feature no name is treated as mangled.
+#[cfg(not(feature = "demangle"))]
+pub fn demangle(_name: &str) -> Option<String> {
+    None
+}
+
+/// Replace mangled export and import names in `module` with their demangled
+/// form, returning the pairs that changed.
+pub fn normalize_module(module: &mut WasmModule) -> Vec<DemangledName> {
+    let names = module.exports.iter_mut().map(|e| &mut e.name)
+        .chain(module.imports.iter_mut().map(|i| &mut i.name));
+
+    let mut normalized = Vec::new();
+    for name in names {
+        if let Some(demangled) = demangle(name) {
+            let raw = std::mem::replace(name, demangled.clone());
+            normalized.push(DemangledName { raw, demangled });
+        }
+    }
+    normalized
+}
+
+#[cfg(all(test, feature = "demangle"))]
+mod tests {
+    use super::*;
+    use crate::parser::{Export, ExportKind};
+    use crate::static_analysis::StaticAnalyzer;
+    use crate::static_analysis::report::ReportGenerator;
+
+    #[test]
+    fn test_demangle_rust_legacy() {
+        assert_eq!(demangle("_ZN3hal16wasm_read_sensor17h0123456789abcdefE").as_deref(),
+                   Some("hal::wasm_read_sensor"));
+        assert_eq!(demangle("wasm_read_sensor"), None);
+    }
+
+    #[test]
+    fn test_analysis_uses_demangled_names() {
+        let raw = "_ZN3hal16wasm_read_sensor17h0123456789abcdefE";
+        let module = WasmModule {
+            exports: vec![Export { name: raw.to_string(), kind: ExportKind::Function, index: 0 }],
+            ..Default::default()
+        };
+
+        let analysis = StaticAnalyzer::new().with_name_demangling(true).analyze(&module).unwrap();
+        assert_eq!(analysis.module_info.demangled_names,
+                   vec![DemangledName { raw: raw.to_string(), demangled: "hal::wasm_read_sensor".to_string() }]);
+
+        let permission = analysis.capability_requirements.inferred_permissions.iter()
+            .find(|p| p.name == "ReadSensor")
+            .expect("ReadSensor inferred");
+        assert_eq!(permission.reason, "Export 'hal::wasm_read_sensor' detected");
+
+        let report = ReportGenerator::generate_text_report(&analysis);
+        assert!(report.contains("hal::wasm_read_sensor"));
+    }
+}
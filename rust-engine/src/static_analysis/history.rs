@@ -0,0 +1,162 @@
+use super::{AnalysisResult, RiskLevel};
+use crate::error::{WasmError, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ScoreSample {
+    pub timestamp: u64, // seconds since the Unix epoch
+    pub score: u32,
+    pub overall: RiskLevel,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum TrendDirection {
+    Improving,
+    Stable,
+    Worsening,
+}
+
+#[derive(Debug, Clone)]
+pub struct ScoreTrend {
+    pub direction: TrendDirection,
+    /// Latest score minus the first recorded score.
+    pub change: i64,
+    pub latest_score: u32,
+    pub samples: usize,
+}
+
+/// Risk score history per module, for tracking a module across versions.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct AnalysisHistory {
+    entries: HashMap<String, Vec<ScoreSample>>,
+}
+
+impl AnalysisHistory {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn record(&mut self, module_id: &str, result: &AnalysisResult) {
+        let timestamp = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_secs())
+            .unwrap_or(0);
+
+        self.entries.entry(module_id.to_string()).or_default().push(ScoreSample {
+            timestamp,
+            score: result.risk_score.score,
+            overall: result.risk_score.overall.clone(),
+        });
+    }
+
+    pub fn samples(&self, module_id: &str) -> &[ScoreSample] {
+        self.entries.get(module_id).map(|s| s.as_slice()).unwrap_or(&[])
+    }
+
+    pub fn trend(&self, module_id: &str) -> Option<ScoreTrend> {
+        let samples = self.entries.get(module_id)?;
+        let first = samples.first()?;
+        let latest = samples.last()?;
+
+        let change = latest.score as i64 - first.score as i64;
+        let direction = match change {
+            c if c > 0 => TrendDirection::Worsening,
+            c if c < 0 => TrendDirection::Improving,
+            _ => TrendDirection::Stable,
+        };
+
+        Some(ScoreTrend {
+            direction,
+            change,
+            latest_score: latest.score,
+            samples: samples.len(),
+        })
+    }
+
+    pub fn to_json(&self) -> Result<String> {
+        serde_json::to_string(self)
+            .map_err(|e| WasmError::Runtime(format!("Failed to serialize analysis history: {}", e)))
+    }
+
+    pub fn from_json(json: &str) -> Result<Self> {
+        serde_json::from_str(json)
+            .map_err(|e| WasmError::Runtime(format!("Failed to parse analysis history: {}", e)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::static_analysis::*;
+    use std::time::Duration;
+
+    fn result_with_score(score: u32, overall: RiskLevel) -> AnalysisResult {
+        AnalysisResult {
+            module_info: ModuleInfo {
+                size: 0,
+                function_count: 1,
+                import_count: 0,
+                export_count: 1,
+                memory_pages: None,
+                table_size: None,
+                global_count: 0,
+                demangled_names: vec![],
+            },
+            security_assessment: SecurityAssessment {
+                memory_patterns: vec![],
+                control_flow_complexity: 1,
+                suspicious_patterns: vec![],
+                syscall_functions: vec![],
+                resource_requirements: ResourceRequirements {
+                    estimated_memory: 0,
+                    estimated_cpu_cycles: 0,
+                    max_stack_depth: 1,
+                    max_call_depth: 1,
+                },
+            },
+            capability_requirements: CapabilityRequirements {
+                required_capabilities: vec![],
+                optional_capabilities: vec![],
+                inferred_permissions: vec![],
+            },
+            risk_score: RiskScore {
+                overall: overall.clone(),
+                memory_risk: RiskLevel::OK,
+                execution_risk: RiskLevel::OK,
+                syscall_risk: RiskLevel::OK,
+                complexity_risk: RiskLevel::OK,
+                score,
+            },
+            recommendations: vec![],
+            analysis_time: Duration::ZERO,
+        }
+    }
+
+    #[test]
+    fn test_worsening_trend() {
+        let mut history = AnalysisHistory::new();
+        history.record("sensor-app", &result_with_score(10, RiskLevel::OK));
+        history.record("sensor-app", &result_with_score(35, RiskLevel::Warning));
+        history.record("sensor-app", &result_with_score(72, RiskLevel::Severe));
+
+        let trend = history.trend("sensor-app").unwrap();
+        assert_eq!(trend.direction, TrendDirection::Worsening);
+        assert_eq!(trend.change, 62);
+        assert_eq!(trend.latest_score, 72);
+        assert_eq!(trend.samples, 3);
+        assert!(history.trend("unknown").is_none());
+    }
+
+    #[test]
+    fn test_history_round_trip() {
+        let mut history = AnalysisHistory::new();
+        history.record("a", &result_with_score(40, RiskLevel::Warning));
+        history.record("a", &result_with_score(20, RiskLevel::OK));
+
+        let restored = AnalysisHistory::from_json(&history.to_json().unwrap()).unwrap();
+        assert_eq!(restored.samples("a"), history.samples("a"));
+        assert_eq!(restored.trend("a").unwrap().direction, TrendDirection::Improving);
+    }
+}
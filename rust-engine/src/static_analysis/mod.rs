@@ -2,23 +2,41 @@ pub mod analyzer;
 pub mod patterns;
 pub mod security;
 pub mod capabilities;
+pub mod demangle;
 pub mod report;
+pub mod history;
 
 use crate::error::{WasmError, Result};
 use crate::parser::WasmModule;
+use serde::{Deserialize, Serialize};
 use std::time::{Duration, Instant};
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct AnalysisResult {
     pub module_info: ModuleInfo,
     pub security_assessment: SecurityAssessment,
     pub capability_requirements: CapabilityRequirements,
     pub risk_score: RiskScore,
     pub recommendations: Vec<Recommendation>,
+    #[serde(rename = "analysis_time_ms", with = "duration_ms")]
     pub analysis_time: Duration,
 }
 
-#[derive(Debug, Clone)]
+/// Serializes a `Duration` as whole milliseconds.
+mod duration_ms {
+    use serde::{Deserialize, Deserializer, Serializer};
+    use std::time::Duration;
+
+    pub fn serialize<S: Serializer>(duration: &Duration, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_u64(duration.as_millis() as u64)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Duration, D::Error> {
+        u64::deserialize(deserializer).map(Duration::from_millis)
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ModuleInfo {
     pub size: usize,
     pub function_count: usize,
@@ -27,9 +45,11 @@ pub struct ModuleInfo {
     pub memory_pages: Option<u32>,
     pub table_size: Option<u32>,
     pub global_count: usize,
+    /// Export and import names that were demangled before analysis.
+    pub demangled_names: Vec<demangle::DemangledName>,
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct SecurityAssessment {
     pub memory_patterns: Vec<MemoryPattern>,
     pub control_flow_complexity: u32,
@@ -38,14 +58,14 @@ pub struct SecurityAssessment {
     pub resource_requirements: ResourceRequirements,
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct CapabilityRequirements {
     pub required_capabilities: Vec<String>,
     pub optional_capabilities: Vec<String>,
     pub inferred_permissions: Vec<Permission>,
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct RiskScore {
     pub overall: RiskLevel,
     pub memory_risk: RiskLevel,
@@ -55,14 +75,18 @@ pub struct RiskScore {
     pub score: u32, // 0-100
 }
 
-#[derive(Debug, Clone, PartialEq)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "UPPERCASE")]
 pub enum RiskLevel {
     OK,
+    // Aliases read history saved before levels were uppercased
+    #[serde(alias = "Warning")]
     Warning,
+    #[serde(alias = "Severe")]
     Severe,
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Recommendation {
     pub category: String,
     pub message: String,
@@ -70,24 +94,44 @@ pub struct Recommendation {
     pub action: String,
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct MemoryPattern {
     pub pattern_type: String,
     pub locations: Vec<u32>,
     pub risk_level: RiskLevel,
     pub description: String,
+    /// Instructions that produced the finding, where known.
+    pub code_locations: Vec<CodeLocation>,
 }
 
-#[derive(Debug, Clone)]
+/// An instruction within a function body.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct CodeLocation {
+    pub function_index: u32,
+    /// Byte offset of the instruction within the function body.
+    pub offset: u32,
+}
+
+impl std::fmt::Display for CodeLocation {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "func {} +0x{:x}", self.function_index, self.offset)
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct SuspiciousPattern {
     pub pattern_name: String,
     pub function_index: u32,
     pub instruction_offset: u32,
     pub description: String,
     pub risk_level: RiskLevel,
+    /// How many identical findings this entry stands for.
+    pub occurrences: u32,
+    /// Offsets of every grouped occurrence, capped; empty for a single finding.
+    pub offsets: Vec<u32>,
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct SyscallFunction {
     pub name: String,
     pub import_index: u32,
@@ -95,7 +139,7 @@ pub struct SyscallFunction {
     pub risk_level: RiskLevel,
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ResourceRequirements {
     pub estimated_memory: u64,
     pub estimated_cpu_cycles: u64,
@@ -103,36 +147,137 @@ pub struct ResourceRequirements {
     pub max_call_depth: u32,
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Permission {
     pub name: String,
     pub required: bool,
     pub reason: String,
+    pub risk_level: RiskLevel,
+    /// Instructions that triggered the inference; empty for module-level reasons.
+    pub locations: Vec<CodeLocation>,
+}
+
+/// Points each risk category adds to the 0-100 score.
+#[derive(Debug, Clone)]
+pub struct RiskWeights {
+    pub memory_severe: u32,
+    pub memory_warning: u32,
+    pub execution_severe: u32,
+    pub execution_warning: u32,
+    pub syscall_severe: u32,
+    pub syscall_warning: u32,
+    pub complexity_severe: u32,
+    pub complexity_warning: u32,
+    /// Control flow complexity above which execution risk is Severe.
+    pub execution_severe_complexity: u32,
+    pub execution_warning_complexity: u32,
+    /// Suspicious finding count, summing the occurrences of grouped
+    /// findings, above which complexity risk is Severe.
+    pub complexity_severe_findings: usize,
+    pub complexity_warning_findings: usize,
+    /// Scores at or above which the overall level is Severe or Warning.
+    pub overall_severe_score: u32,
+    pub overall_warning_score: u32,
+    /// Lowest score for a module that requires a Severe capability, such as
+    /// network or file system access.
+    pub severe_capability_floor: u32,
+    /// Findings, counting every grouped occurrence, above which the module is
+    /// recommended for rejection whatever its score.
+    pub auto_reject_threshold: u32,
+    /// Binary size in bytes above which a module is flagged as bloated.
+    pub large_module_bytes: usize,
+}
+
+impl Default for RiskWeights {
+    fn default() -> Self {
+        Self {
+            memory_severe: 30,
+            memory_warning: 15,
+            execution_severe: 25,
+            execution_warning: 10,
+            syscall_severe: 25,
+            syscall_warning: 10,
+            complexity_severe: 20,
+            complexity_warning: 8,
+            execution_severe_complexity: 100,
+            execution_warning_complexity: 50,
+            complexity_severe_findings: 5,
+            complexity_warning_findings: 2,
+            overall_severe_score: 70,
+            overall_warning_score: 30,
+            severe_capability_floor: 50,
+            auto_reject_threshold: 200,
+            large_module_bytes: 4 * 1024 * 1024,
+        }
+    }
 }
 
 pub struct StaticAnalyzer {
-    patterns: patterns::PatternMatcher,
     security: security::SecurityAnalyzer,
     capabilities: capabilities::CapabilityInferrer,
+    demangle_names: bool,
+    risk_weights: RiskWeights,
 }
 
 impl StaticAnalyzer {
     pub fn new() -> Self {
         Self {
-            patterns: patterns::PatternMatcher::new(),
             security: security::SecurityAnalyzer::new(),
             capabilities: capabilities::CapabilityInferrer::new(),
+            demangle_names: false,
+            risk_weights: RiskWeights::default(),
         }
     }
 
+    /// Match `matcher`'s byte patterns instead of the built-in set.
+    pub fn with_pattern_matcher(mut self, matcher: patterns::PatternMatcher) -> Self {
+        self.security = self.security.with_pattern_matcher(matcher);
+        self
+    }
+
+    pub fn with_risk_weights(mut self, weights: RiskWeights) -> Self {
+        self.risk_weights = weights;
+        self
+    }
+
+    /// Demangle Rust/C++ export and import names before inference and reporting.
+    /// Names are left as-is without the `demangle` feature.
+    pub fn with_name_demangling(mut self, enabled: bool) -> Self {
+        self.demangle_names = enabled;
+        self
+    }
+
     pub fn analyze(&self, module: &WasmModule) -> Result<AnalysisResult> {
         let start_time = Instant::now();
 
-        let module_info = self.analyze_module_info(module);
+        let mut normalized;
+        let mut demangled_names = Vec::new();
+        let module = if self.demangle_names {
+            normalized = module.clone();
+            demangled_names = demangle::normalize_module(&mut normalized);
+            &normalized
+        } else {
+            module
+        };
+
         let security_assessment = self.security.analyze(module)?;
+        self.complete_analysis(module, security_assessment, demangled_names, start_time)
+    }
+
+    /// Infer capabilities, score and recommend for a module whose security
+    /// assessment is already done.
+    fn complete_analysis(
+        &self,
+        module: &WasmModule,
+        security_assessment: SecurityAssessment,
+        demangled_names: Vec<demangle::DemangledName>,
+        start_time: Instant,
+    ) -> Result<AnalysisResult> {
+        let mut module_info = self.analyze_module_info(module);
+        module_info.demangled_names = demangled_names;
         let capability_requirements = self.capabilities.infer(module, &security_assessment)?;
         let risk_score = self.calculate_risk_score(&security_assessment, &capability_requirements);
-        let recommendations = self.generate_recommendations(&security_assessment, &risk_score);
+        let recommendations = self.generate_recommendations(module, &security_assessment, &risk_score);
 
         Ok(AnalysisResult {
             module_info,
@@ -146,62 +291,75 @@ impl StaticAnalyzer {
 
     fn analyze_module_info(&self, module: &WasmModule) -> ModuleInfo {
         ModuleInfo {
-            size: 0, // Would calculate from binary
+            size: module.total_size,
             function_count: module.functions.len(),
-            import_count: 0, // Would count imports
+            import_count: module.imports.len(),
             export_count: module.exports.len(),
             memory_pages: module.memory.as_ref().map(|m| m.min),
-            table_size: None,
-            global_count: 0,
+            table_size: module.table.as_ref().map(|t| t.min),
+            global_count: module.globals.len(),
+            demangled_names: Vec::new(),
         }
     }
 
-    fn calculate_risk_score(&self, security: &SecurityAssessment, _capabilities: &CapabilityRequirements) -> RiskScore {
+    fn calculate_risk_score(&self, security: &SecurityAssessment, capabilities: &CapabilityRequirements) -> RiskScore {
+        let weights = &self.risk_weights;
         let mut score = 0u32;
 
-        let memory_risk = if security.memory_patterns.iter().any(|p| p.risk_level == RiskLevel::Severe) {
-            score += 30;
+        let memory_levels: Vec<&RiskLevel> = security.memory_patterns.iter().map(|p| &p.risk_level)
+            .chain(security.suspicious_patterns.iter()
+                .filter(|p| p.pattern_name == "UnlimitedImportedMemory")
+                .map(|p| &p.risk_level))
+            .collect();
+        let memory_risk = if memory_levels.contains(&&RiskLevel::Severe) {
+            score += weights.memory_severe;
             RiskLevel::Severe
-        } else if security.memory_patterns.iter().any(|p| p.risk_level == RiskLevel::Warning) {
-            score += 15;
+        } else if memory_levels.contains(&&RiskLevel::Warning) {
+            score += weights.memory_warning;
             RiskLevel::Warning
         } else {
             RiskLevel::OK
         };
 
-        let execution_risk = if security.control_flow_complexity > 100 {
-            score += 25;
+        let execution_risk = if security.control_flow_complexity > weights.execution_severe_complexity {
+            score += weights.execution_severe;
             RiskLevel::Severe
-        } else if security.control_flow_complexity > 50 {
-            score += 10;
+        } else if security.control_flow_complexity > weights.execution_warning_complexity {
+            score += weights.execution_warning;
             RiskLevel::Warning
         } else {
             RiskLevel::OK
         };
 
         let syscall_risk = if security.syscall_functions.iter().any(|s| s.risk_level == RiskLevel::Severe) {
-            score += 25;
+            score += weights.syscall_severe;
             RiskLevel::Severe
         } else if security.syscall_functions.iter().any(|s| s.risk_level == RiskLevel::Warning) {
-            score += 10;
+            score += weights.syscall_warning;
             RiskLevel::Warning
         } else {
             RiskLevel::OK
         };
 
-        let complexity_risk = if security.suspicious_patterns.len() > 5 {
-            score += 20;
+        // Grouped findings count once per occurrence, so grouping doesn't lower the score
+        let findings: usize = security.suspicious_patterns.iter().map(|p| p.occurrences as usize).sum();
+        let complexity_risk = if findings > weights.complexity_severe_findings {
+            score += weights.complexity_severe;
             RiskLevel::Severe
-        } else if security.suspicious_patterns.len() > 2 {
-            score += 8;
+        } else if findings > weights.complexity_warning_findings {
+            score += weights.complexity_warning;
             RiskLevel::Warning
         } else {
             RiskLevel::OK
         };
 
-        let overall = if score >= 70 {
+        if capabilities.inferred_permissions.iter().any(|p| p.required && p.risk_level == RiskLevel::Severe) {
+            score = score.max(weights.severe_capability_floor);
+        }
+
+        let overall = if score >= weights.overall_severe_score {
             RiskLevel::Severe
-        } else if score >= 30 {
+        } else if score >= weights.overall_warning_score {
             RiskLevel::Warning
         } else {
             RiskLevel::OK
@@ -217,9 +375,31 @@ impl StaticAnalyzer {
         }
     }
 
-    fn generate_recommendations(&self, _security: &SecurityAssessment, risk: &RiskScore) -> Vec<Recommendation> {
+    fn generate_recommendations(&self, module: &WasmModule, security: &SecurityAssessment, risk: &RiskScore) -> Vec<Recommendation> {
         let mut recommendations = Vec::new();
 
+        let unsupported: Vec<String> = module.required_features().into_iter()
+            .filter(|f| !crate::interpreter::SUPPORTED_FEATURES.contains(&f.as_str()))
+            .collect();
+        if !unsupported.is_empty() {
+            recommendations.push(Recommendation {
+                category: "Compatibility".to_string(),
+                message: format!("Module requires unsupported features: {}", unsupported.join(", ")),
+                severity: RiskLevel::Severe,
+                action: "Rebuild the module without these target features".to_string(),
+            });
+        }
+
+        if module.total_size > self.risk_weights.large_module_bytes {
+            recommendations.push(Recommendation {
+                category: "Size".to_string(),
+                message: format!("Module binary is {} bytes, over the {} byte limit",
+                    module.total_size, self.risk_weights.large_module_bytes),
+                severity: RiskLevel::Warning,
+                action: "Strip debug sections and unused code from the build".to_string(),
+            });
+        }
+
         if risk.memory_risk != RiskLevel::OK {
             recommendations.push(Recommendation {
                 category: "Memory".to_string(),
@@ -238,7 +418,17 @@ impl StaticAnalyzer {
             });
         }
 
-        if risk.overall == RiskLevel::Severe {
+        let findings = security.memory_patterns.len() as u32
+            + security.suspicious_patterns.iter().map(|p| p.occurrences).sum::<u32>();
+        if findings > self.risk_weights.auto_reject_threshold {
+            recommendations.push(Recommendation {
+                category: "General".to_string(),
+                message: format!("Module produced {} findings, over the limit of {}",
+                    findings, self.risk_weights.auto_reject_threshold),
+                severity: RiskLevel::Severe,
+                action: "reject".to_string(),
+            });
+        } else if risk.overall == RiskLevel::Severe {
             recommendations.push(Recommendation {
                 category: "General".to_string(),
                 message: "Module poses significant security risks".to_string(),
@@ -249,4 +439,110 @@ impl StaticAnalyzer {
 
         recommendations
     }
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::{CodeSection, Import, ImportKind};
+
+    #[test]
+    fn test_severe_capability_score_floor() {
+        let module = WasmModule {
+            imports: vec![Import {
+                module: "wasi_snapshot_preview1".to_string(),
+                name: "sock_connect".to_string(),
+                kind: ImportKind::Function(0),
+            }],
+            ..Default::default()
+        };
+
+        let result = StaticAnalyzer::new().analyze(&module).unwrap();
+        assert!(result.risk_score.score >= RiskWeights::default().severe_capability_floor);
+        assert_ne!(result.risk_score.overall, RiskLevel::OK);
+
+        let unfloored = StaticAnalyzer::new()
+            .with_risk_weights(RiskWeights { severe_capability_floor: 0, ..RiskWeights::default() })
+            .analyze(&module)
+            .unwrap();
+        assert!(unfloored.risk_score.score < result.risk_score.score);
+    }
+
+    #[test]
+    fn test_lowered_complexity_threshold() {
+        // 59 empty blocks: complexity 60
+        let body: Vec<u8> = [0x02, 0x40, 0x0B].repeat(59);
+        let module = WasmModule {
+            code: vec![CodeSection { locals: vec![], body }],
+            ..Default::default()
+        };
+
+        let default = StaticAnalyzer::new().analyze(&module).unwrap();
+        assert_eq!(default.security_assessment.control_flow_complexity, 60);
+        assert_eq!(default.risk_score.execution_risk, RiskLevel::Warning);
+
+        let strict = StaticAnalyzer::new()
+            .with_risk_weights(RiskWeights { execution_severe_complexity: 50, ..RiskWeights::default() })
+            .analyze(&module)
+            .unwrap();
+        assert_eq!(strict.risk_score.execution_risk, RiskLevel::Severe);
+        assert!(strict.risk_score.score > default.risk_score.score);
+    }
+
+    #[test]
+    fn test_grouping_keeps_complexity_risk() {
+        // 40 unaligned loads, grouped into one finding by default
+        let body: Vec<u8> = [0x41, 0x00, 0x28, 0x00, 0x00, 0x1A].repeat(40);
+        let module = WasmModule {
+            code: vec![CodeSection { locals: vec![], body }],
+            ..Default::default()
+        };
+
+        let analyzer = StaticAnalyzer::new();
+        let result = analyzer.analyze(&module).unwrap();
+        let ungrouped = security::SecurityAnalyzer::new().with_finding_grouping(false).analyze(&module).unwrap();
+        let ungrouped_score = analyzer.calculate_risk_score(&ungrouped, &result.capability_requirements);
+
+        assert_eq!(result.risk_score.complexity_risk, RiskLevel::Severe);
+        assert_eq!(result.risk_score.complexity_risk, ungrouped_score.complexity_risk);
+        assert_eq!(result.risk_score.score, ungrouped_score.score);
+    }
+
+    #[test]
+    fn test_large_module_recommendation() {
+        let module = WasmModule { total_size: 2048, ..Default::default() };
+        let large = |result: &AnalysisResult| result.recommendations.iter().any(|r| r.category == "Size");
+
+        let result = StaticAnalyzer::new().analyze(&module).unwrap();
+        assert_eq!(result.module_info.size, 2048);
+        assert!(!large(&result));
+
+        let strict = StaticAnalyzer::new()
+            .with_risk_weights(RiskWeights { large_module_bytes: 1024, ..RiskWeights::default() })
+            .analyze(&module)
+            .unwrap();
+        assert!(large(&strict));
+    }
+
+    #[test]
+    fn test_auto_reject_on_finding_count() {
+        // 300 x (i32.const 0, i32.load align=0 offset=0, drop), each an UnalignedAccess finding
+        let body: Vec<u8> = [0x41, 0x00, 0x28, 0x00, 0x00, 0x1A].repeat(300);
+        let module = WasmModule {
+            code: vec![CodeSection { locals: vec![], body }],
+            ..Default::default()
+        };
+        let rejects = |result: &AnalysisResult| result.recommendations.iter()
+            .any(|r| r.category == "General" && r.severity == RiskLevel::Severe && r.action == "reject");
+
+        let result = StaticAnalyzer::new().analyze(&module).unwrap();
+        assert!(rejects(&result));
+        assert!(result.risk_score.score < 70);
+
+        let lenient = StaticAnalyzer::new()
+            .with_risk_weights(RiskWeights { auto_reject_threshold: 1000, ..RiskWeights::default() })
+            .analyze(&module)
+            .unwrap();
+        assert!(!rejects(&lenient));
+    }
+}
@@ -1,17 +1,24 @@
 use super::{SuspiciousPattern, RiskLevel};
-use crate::parser::WasmModule;
+use crate::decoder::{self, Immediate, Instruction};
+use crate::parser::{ImportKind, WasmModule};
 use std::collections::HashMap;
 
+/// Consecutive push-only instructions before a `call_indirect` that count as
+/// spraying the operand stack.
+const STACK_SPRAY_THRESHOLD: usize = 32;
+
 pub struct PatternMatcher {
-    patterns: Vec<Pattern>,
+    patterns: Vec<PatternDef>,
 }
 
+/// A byte sequence reported as a suspicious pattern wherever it occurs in a
+/// function body.
 #[derive(Debug, Clone)]
-struct Pattern {
-    name: String,
-    opcodes: Vec<u8>,
-    risk_level: RiskLevel,
-    description: String,
+pub struct PatternDef {
+    pub name: String,
+    pub opcodes: Vec<u8>,
+    pub risk_level: RiskLevel,
+    pub description: String,
 }
 
 impl PatternMatcher {
@@ -23,12 +30,55 @@ impl PatternMatcher {
         matcher
     }
 
+    /// A matcher with only `patterns`, none of the built-in ones.
+    pub fn from_patterns(patterns: Vec<PatternDef>) -> Self {
+        Self { patterns }
+    }
+
+    pub fn add_pattern(&mut self, name: &str, opcodes: Vec<u8>, risk_level: RiskLevel, description: &str) {
+        self.patterns.push(PatternDef {
+            name: name.to_string(),
+            opcodes,
+            risk_level,
+            description: description.to_string(),
+        });
+    }
+
+    pub fn list_patterns(&self) -> &[PatternDef] {
+        &self.patterns
+    }
+
     pub fn find_patterns(&self, module: &WasmModule) -> Vec<SuspiciousPattern> {
         let mut findings = Vec::new();
 
         for (func_idx, code_section) in module.code.iter().enumerate() {
             let bytecode = &code_section.body;
             
+            if let Ok(instructions) = decoder::decode_function_body(bytecode) {
+                for offset in Self::find_infinite_loops(&instructions) {
+                    findings.push(SuspiciousPattern {
+                        pattern_name: "InfiniteLoop".to_string(),
+                        function_index: func_idx as u32,
+                        instruction_offset: offset,
+                        description: "Loop always branches back to itself with no exit".to_string(),
+                        risk_level: RiskLevel::Severe,
+                        occurrences: 1,
+                        offsets: Vec::new(),
+                    });
+                }
+                for (offset, run) in Self::find_stack_sprays(&instructions) {
+                    findings.push(SuspiciousPattern {
+                        pattern_name: "StackSpray".to_string(),
+                        function_index: func_idx as u32,
+                        instruction_offset: offset,
+                        description: format!("{} consecutive stack pushes before an indirect call", run),
+                        risk_level: RiskLevel::Warning,
+                        occurrences: 1,
+                        offsets: Vec::new(),
+                    });
+                }
+            }
+            
             for pattern in &self.patterns {
                 let matches = self.find_pattern_matches(bytecode, &pattern.opcodes);
                 
@@ -39,6 +89,8 @@ impl PatternMatcher {
                         instruction_offset: offset,
                         description: pattern.description.clone(),
                         risk_level: pattern.risk_level.clone(),
+                        occurrences: 1,
+                        offsets: Vec::new(),
                     });
                 }
             }
@@ -47,33 +99,85 @@ impl PatternMatcher {
         findings
     }
 
-    fn load_patterns(&mut self) {
-        // Infinite loop pattern
-        self.patterns.push(Pattern {
-            name: "InfiniteLoop".to_string(),
-            opcodes: vec![0x03, 0x40, 0x0C, 0x00, 0x0B], // loop, br 0, end
-            risk_level: RiskLevel::Severe,
-            description: "Potential infinite loop detected".to_string(),
-        });
+    /// Offsets of back-edges that every path through a loop reaches, with no
+    /// reachable branch, `return` or trap leaving the loop first.
+    fn find_infinite_loops(instructions: &[Instruction]) -> Vec<u32> {
+        let mut back_edges = Vec::new();
 
+        for (start, instruction) in instructions.iter().enumerate() {
+            if instruction.opcode != 0x03 {
+                continue;
+            }
+
+            // Opcodes of the blocks opened inside the loop body; label `frames.len()` is the loop itself
+            let mut frames: Vec<u8> = Vec::new();
+            // Frames whose end some path may jump to, skipping what comes before it
+            let mut skipped: Vec<usize> = Vec::new();
+            for inner in &instructions[start + 1..] {
+                let label = match inner.immediates.first() {
+                    Some(Immediate::U32(label)) => *label as usize,
+                    _ => 0,
+                };
+                let unconditional = skipped.is_empty() && !frames.contains(&0x04);
+                match inner.opcode {
+                    0x02..=0x04 => frames.push(inner.opcode),
+                    0x0B => match frames.pop() {
+                        Some(_) => skipped.retain(|&frame| frame < frames.len()),
+                        None => break, // fell out of the loop
+                    },
+                    0x00 | 0x0E | 0x0F => break, // unreachable, br_table, return
+                    0x0C | 0x0D if label > frames.len() => break, // branch out of the loop
+                    0x0C if label == frames.len() && unconditional => {
+                        back_edges.push(inner.offset as u32);
+                        break;
+                    }
+                    0x0C | 0x0D if label < frames.len() => {
+                        let target = frames.len() - 1 - label;
+                        if frames[target] != 0x03 {
+                            skipped.push(target);
+                        } else if inner.opcode == 0x0C && unconditional {
+                            break; // caught by an inner loop, which is checked on its own
+                        }
+                    }
+                    _ => {}
+                }
+            }
+        }
+
+        back_edges
+    }
+
+    /// `(offset, length)` of runs of at least `STACK_SPRAY_THRESHOLD` const,
+    /// `local.get` or `global.get` instructions ending at a `call_indirect`.
+    fn find_stack_sprays(instructions: &[Instruction]) -> Vec<(u32, usize)> {
+        let mut sprays = Vec::new();
+        let mut run_start = 0;
+
+        for (i, instruction) in instructions.iter().enumerate() {
+            match instruction.opcode {
+                0x20 | 0x23 | 0x41..=0x44 => continue,
+                0x11 if i - run_start >= STACK_SPRAY_THRESHOLD => {
+                    sprays.push((instructions[run_start].offset as u32, i - run_start));
+                }
+                _ => {}
+            }
+            run_start = i + 1;
+        }
+
+        sprays
+    }
+
+    fn load_patterns(&mut self) {
         // Memory bomb pattern
-        self.patterns.push(Pattern {
+        self.patterns.push(PatternDef {
             name: "MemoryBomb".to_string(),
             opcodes: vec![0x40, 0x00], // memory.grow
             risk_level: RiskLevel::Warning,
             description: "Memory growth operation detected".to_string(),
         });
 
-        // Excessive recursion
-        self.patterns.push(Pattern {
-            name: "DeepRecursion".to_string(),
-            opcodes: vec![0x10], // call
-            risk_level: RiskLevel::Warning,
-            description: "Function call detected - check for recursion".to_string(),
-        });
-
         // Indirect calls (potential ROP)
-        self.patterns.push(Pattern {
+        self.patterns.push(PatternDef {
             name: "IndirectCall".to_string(),
             opcodes: vec![0x11], // call_indirect
             risk_level: RiskLevel::Warning,
@@ -81,7 +185,7 @@ impl PatternMatcher {
         });
 
         // Memory access patterns
-        self.patterns.push(Pattern {
+        self.patterns.push(PatternDef {
             name: "UnalignedAccess".to_string(),
             opcodes: vec![0x28, 0x00], // i32.load with alignment 0
             risk_level: RiskLevel::Warning,
@@ -89,7 +193,7 @@ impl PatternMatcher {
         });
 
         // Stack manipulation
-        self.patterns.push(Pattern {
+        self.patterns.push(PatternDef {
             name: "StackManipulation".to_string(),
             opcodes: vec![0x1A, 0x1B], // drop, select
             risk_level: RiskLevel::OK,
@@ -97,7 +201,7 @@ impl PatternMatcher {
         });
 
         // Crypto-like operations
-        self.patterns.push(Pattern {
+        self.patterns.push(PatternDef {
             name: "CryptoOperations".to_string(),
             opcodes: vec![0x73, 0x74, 0x75], // i32.xor, i32.shl, i32.shr_s
             risk_level: RiskLevel::OK,
@@ -131,30 +235,23 @@ impl PatternMatcher {
         complexity
     }
 
+    /// Walks whole instructions, so immediates and memory arguments that happen
+    /// to look like control-flow opcodes are not counted. Stops at the first
+    /// instruction that fails to decode.
     fn calculate_cyclomatic_complexity(&self, bytecode: &[u8]) -> u32 {
         let mut complexity = 1; // Base complexity
-        let mut i = 0;
-
-        while i < bytecode.len() {
-            match bytecode[i] {
-                0x04 => complexity += 1, // if
-                0x05 => complexity += 1, // else
-                0x03 => complexity += 1, // loop
-                0x02 => complexity += 1, // block
-                0x0C => complexity += 1, // br (conditional branch)
-                0x0D => complexity += 1, // br_if
-                0x0E => {                // br_table
-                    complexity += 1;
-                    // Skip br_table operands
-                    i += 1;
-                    if i < bytecode.len() {
-                        let count = bytecode[i] as usize;
-                        i += count + 1; // Skip all targets + default
-                    }
-                }
-                _ => {}
+        let mut offset = 0;
+
+        while offset < bytecode.len() {
+            let instruction = match decoder::decode_instruction(bytecode, offset) {
+                Ok(instruction) => instruction,
+                Err(_) => break,
+            };
+            // block, loop, if, else, br, br_if, br_table
+            if matches!(instruction.opcode, 0x02..=0x05 | 0x0C..=0x0E) {
+                complexity += 1;
             }
-            i += 1;
+            offset += instruction.len;
         }
 
         complexity
@@ -192,8 +289,25 @@ impl PatternMatcher {
             memory_reads: 0,
             memory_writes: 0,
             table_accesses: 0,
+            invalid_global_refs: Vec::new(),
         };
 
+        let global_count = module.imports.iter()
+            .filter(|import| matches!(import.kind, ImportKind::Global { .. }))
+            .count() + module.globals.len();
+
+        for (func_idx, code_section) in module.code.iter().enumerate() {
+            if let Ok(instructions) = decoder::decode_function_body(&code_section.body) {
+                for instr in instructions.iter().filter(|instr| matches!(instr.opcode, 0x23 | 0x24)) {
+                    if let Some(Immediate::U32(index)) = instr.immediates.first() {
+                        if *index as usize >= global_count {
+                            analysis.invalid_global_refs.push((func_idx as u32, *index));
+                        }
+                    }
+                }
+            }
+        }
+
         for code_section in &module.code {
             let bytecode = &code_section.body;
             let mut i = 0;
@@ -207,6 +321,14 @@ impl PatternMatcher {
                     // Memory operations
                     0x28..=0x35 => analysis.memory_reads += 1,  // load operations
                     0x36..=0x3E => analysis.memory_writes += 1, // store operations
+                    0xFC => match bytecode.get(i + 1) {
+                        Some(0x0A) => { // memory.copy
+                            analysis.memory_reads += 1;
+                            analysis.memory_writes += 1;
+                        }
+                        Some(0x0B) => analysis.memory_writes += 1, // memory.fill
+                        _ => {}
+                    },
                     
                     // Table operations
                     0x25 => analysis.table_accesses += 1, // table.get
@@ -229,6 +351,9 @@ pub struct DataFlowAnalysis {
     pub memory_reads: u32,
     pub memory_writes: u32,
     pub table_accesses: u32,
+    /// `(function_index, global_index)` of `global.get`/`global.set` past the
+    /// imported and defined globals.
+    pub invalid_global_refs: Vec<(u32, u32)>,
 }
 
 impl DataFlowAnalysis {
@@ -247,6 +372,10 @@ impl DataFlowAnalysis {
             indicators.push("Frequent table access operations".to_string());
         }
 
+        for (func_idx, global_idx) in &self.invalid_global_refs {
+            indicators.push(format!("Function {} accesses undefined global {}", func_idx, global_idx));
+        }
+
         indicators
     }
 }
@@ -265,6 +394,111 @@ mod tests {
         assert_eq!(matches, vec![0]);
     }
 
+    #[test]
+    fn test_custom_pattern() {
+        let mut matcher = PatternMatcher::from_patterns(Vec::new());
+        matcher.add_pattern("I64Rotate", vec![0x89, 0x8A], RiskLevel::Warning, "i64.rotl followed by i64.rotr");
+        assert_eq!(matcher.list_patterns().len(), 1);
+
+        let module = WasmModule {
+            code: vec![CodeSection { locals: vec![], body: vec![0x42, 0x01, 0x42, 0x02, 0x89, 0x8A, 0x1A] }],
+            ..Default::default()
+        };
+        let findings = matcher.find_patterns(&module);
+        assert_eq!(findings.len(), 1);
+        assert_eq!(findings[0].pattern_name, "I64Rotate");
+        assert_eq!(findings[0].instruction_offset, 4);
+
+        assert!(PatternMatcher::new().list_patterns().iter().any(|p| p.name == "MemoryBomb"));
+    }
+
+    fn loop_findings(body: Vec<u8>) -> Vec<SuspiciousPattern> {
+        let module = WasmModule {
+            code: vec![CodeSection { locals: vec![], body }],
+            ..Default::default()
+        };
+        PatternMatcher::new().find_patterns(&module).into_iter()
+            .filter(|p| p.pattern_name == "InfiniteLoop")
+            .collect()
+    }
+
+    #[test]
+    fn test_infinite_loop_with_body() {
+        let findings = loop_findings(vec![
+            0x01, // nop
+            0x03, 0x40, // loop
+            0x20, 0x00, 0x41, 0x01, 0x6A, 0x21, 0x00, // local0 += 1
+            0x02, 0x40, 0x20, 0x00, 0x0D, 0x00, 0x0B, // block; br_if 0 (stays in loop); end
+            0x0C, 0x00, // br 0
+            0x0B, 0x0B,
+        ]);
+        assert_eq!(findings.len(), 1);
+        assert_eq!(findings[0].instruction_offset, 17);
+
+        assert_eq!(loop_findings(vec![0x03, 0x40, 0x0C, 0x00, 0x0B, 0x0B]).len(), 1);
+
+        // The back-edge need not be `br 0`: `br 1` from a block inside the loop
+        let findings = loop_findings(vec![
+            0x03, 0x40, // loop
+            0x02, 0x40, 0x0C, 0x01, 0x0B, // block; br 1; end
+            0x0B, 0x0B,
+        ]);
+        assert_eq!(findings.len(), 1);
+        assert_eq!(findings[0].instruction_offset, 4);
+    }
+
+    #[test]
+    fn test_guarded_loop_not_flagged() {
+        let findings = loop_findings(vec![
+            0x02, 0x40, // block
+            0x03, 0x40, // loop
+            0x20, 0x00, 0x45, 0x0D, 0x01, // br_if 1 (exit when local0 == 0)
+            0x20, 0x00, 0x41, 0x01, 0x6B, 0x21, 0x00, // local0 -= 1
+            0x0C, 0x00, // br 0
+            0x0B, 0x0B, 0x0B,
+        ]);
+        assert!(findings.is_empty());
+
+        // A back-edge that only happens conditionally is not infinite either
+        assert!(loop_findings(vec![0x03, 0x40, 0x20, 0x00, 0x0D, 0x00, 0x0B, 0x0B]).is_empty());
+
+        // br_if 0 can skip the back-edge to the block end, then fall out of the loop
+        assert!(loop_findings(vec![
+            0x03, 0x40, // loop
+            0x02, 0x40, 0x20, 0x00, 0x0D, 0x00, 0x0C, 0x01, 0x0B, // block; br_if 0; br 1; end
+            0x0B, 0x0B,
+        ]).is_empty());
+
+        // A back-edge inside an `if` runs only when the condition holds
+        assert!(loop_findings(vec![
+            0x03, 0x40, // loop
+            0x20, 0x00, 0x04, 0x40, 0x0C, 0x01, 0x0B, // if; br 1; end
+            0x0B, 0x0B,
+        ]).is_empty());
+    }
+
+    #[test]
+    fn test_stack_spray_before_indirect_call() {
+        let sprays = |body: Vec<u8>| {
+            let module = WasmModule { code: vec![CodeSection { locals: vec![], body }], ..Default::default() };
+            PatternMatcher::new().find_patterns(&module).into_iter()
+                .filter(|p| p.pattern_name == "StackSpray")
+                .collect::<Vec<_>>()
+        };
+
+        let mut body = vec![0x01]; // nop
+        for _ in 0..50 {
+            body.extend_from_slice(&[0x41, 0x00]); // i32.const 0
+        }
+        body.extend_from_slice(&[0x11, 0x00, 0x00, 0x0B]); // call_indirect, end
+        let findings = sprays(body);
+        assert_eq!(findings.len(), 1);
+        assert_eq!(findings[0].instruction_offset, 1);
+        assert_eq!(findings[0].risk_level, RiskLevel::Warning);
+
+        assert!(sprays(vec![0x20, 0x00, 0x41, 0x01, 0x11, 0x00, 0x00, 0x0B]).is_empty());
+    }
+
     #[test]
     fn test_control_flow_complexity() {
         let matcher = PatternMatcher::new();
@@ -277,12 +511,27 @@ mod tests {
                 locals: vec![],
                 body: vec![0x04, 0x05, 0x03, 0x0B], // if, else, loop, end
             }],
+            ..Default::default()
         };
 
         let complexity = matcher.analyze_control_flow(&module);
         assert!(complexity > 1);
     }
 
+    #[test]
+    fn test_complexity_skips_immediates() {
+        let matcher = PatternMatcher::new();
+        let complexity = |body: Vec<u8>| matcher.calculate_cyclomatic_complexity(&body);
+
+        // i32.const 1; if; end
+        let plain = complexity(vec![0x41, 0x01, 0x04, 0x40, 0x0B]);
+        assert_eq!(plain, 2);
+        // i32.const 0x0C; if; end
+        assert_eq!(complexity(vec![0x41, 0x0C, 0x04, 0x40, 0x0B]), plain);
+        // i32.const 0; i32.load align=2 offset=0x0D; drop; i32.const 1; if; end
+        assert_eq!(complexity(vec![0x41, 0x00, 0x28, 0x02, 0x0D, 0x1A, 0x41, 0x01, 0x04, 0x40, 0x0B]), plain);
+    }
+
     #[test]
     fn test_syscall_detection() {
         let matcher = PatternMatcher::new();
@@ -303,6 +552,7 @@ mod tests {
                 }
             ],
             code: vec![],
+            ..Default::default()
         };
 
         let syscalls = matcher.detect_syscall_patterns(&module);
@@ -322,6 +572,7 @@ mod tests {
                 locals: vec![],
                 body: vec![0x23, 0x24, 0x28, 0x36], // global.get, global.set, i32.load, i32.store
             }],
+            ..Default::default()
         };
 
         let analysis = matcher.analyze_data_flow(&module);
@@ -329,5 +580,36 @@ mod tests {
         assert_eq!(analysis.global_writes, 1);
         assert_eq!(analysis.memory_reads, 1);
         assert_eq!(analysis.memory_writes, 1);
+
+        let bulk = WasmModule {
+            code: vec![CodeSection { locals: vec![], body: vec![0xFC, 0x0A, 0x00, 0x00, 0xFC, 0x0B, 0x00] }],
+            ..Default::default()
+        };
+        let analysis = matcher.analyze_data_flow(&bulk);
+        assert_eq!(analysis.memory_reads, 1);
+        assert_eq!(analysis.memory_writes, 2);
+    }
+
+    #[test]
+    fn test_global_index_range() {
+        use crate::parser::{Global, ValueType};
+        use crate::static_analysis::StaticAnalyzer;
+
+        let module = WasmModule {
+            globals: vec![Global { value_type: ValueType::I32, mutable: true, init_expr: vec![0x41, 0x00, 0x0B] }],
+            code: vec![CodeSection {
+                locals: vec![],
+                // global.get 0; global.set 3; end
+                body: vec![0x23, 0x00, 0x24, 0x03, 0x0B],
+            }],
+            ..Default::default()
+        };
+
+        let analysis = PatternMatcher::new().analyze_data_flow(&module);
+        assert_eq!(analysis.invalid_global_refs, vec![(0, 3)]);
+        assert!(analysis.get_risk_indicators().iter().any(|i| i.contains("undefined global 3")));
+
+        let result = StaticAnalyzer::new().analyze(&module).unwrap();
+        assert_eq!(result.module_info.global_count, 1);
     }
 }
\ No newline at end of file
@@ -1,89 +1,152 @@
 use super::{AnalysisResult, RiskLevel, RiskScore};
+use std::collections::HashMap;
 use std::fmt::Write;
+use std::io;
 
 pub struct ReportGenerator;
 
+/// Buffers one report section, then hands it to the underlying writer.
+struct SectionWriter<'a, W: io::Write> {
+    buf: String,
+    writer: &'a mut W,
+}
+
+impl<'a, W: io::Write> SectionWriter<'a, W> {
+    fn new(writer: &'a mut W) -> Self {
+        Self { buf: String::new(), writer }
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.writer.write_all(self.buf.as_bytes())?;
+        self.buf.clear();
+        self.writer.flush()
+    }
+}
+
 impl ReportGenerator {
     pub fn generate_text_report(analysis: &AnalysisResult) -> String {
-        let mut report = String::new();
+        let mut out = Vec::new();
+        Self::render_text_to(analysis, &mut out).expect("writing to a Vec cannot fail");
+        String::from_utf8(out).expect("report is UTF-8")
+    }
+
+    /// Stream the text report section by section, flushing after each.
+    pub fn render_text_to<W: io::Write>(analysis: &AnalysisResult, writer: &mut W) -> io::Result<()> {
+        let mut section = SectionWriter::new(writer);
         
-        writeln!(report, "=== WASM Static Analysis Report ===").unwrap();
-        writeln!(report, "Analysis completed in {:?}", analysis.analysis_time).unwrap();
-        writeln!(report).unwrap();
+        writeln!(section.buf, "=== WASM Static Analysis Report ===").unwrap();
+        writeln!(section.buf, "Analysis completed in {:?}", analysis.analysis_time).unwrap();
+        writeln!(section.buf).unwrap();
+        section.flush()?;
 
         // Module Information
-        Self::write_module_info(&mut report, &analysis.module_info);
+        Self::write_module_info(&mut section.buf, &analysis.module_info);
+        section.flush()?;
         
         // Risk Assessment
-        Self::write_risk_assessment(&mut report, &analysis.risk_score);
+        Self::write_risk_assessment(&mut section.buf, &analysis.risk_score);
+        section.flush()?;
         
         // Security Assessment
-        Self::write_security_assessment(&mut report, &analysis.security_assessment);
+        Self::write_security_assessment(&mut section.buf, &analysis.security_assessment);
+        section.flush()?;
         
         // Capability Requirements
-        Self::write_capability_requirements(&mut report, &analysis.capability_requirements);
+        Self::write_capability_requirements(&mut section.buf, &analysis.capability_requirements);
+        section.flush()?;
         
         // Recommendations
-        Self::write_recommendations(&mut report, &analysis.recommendations);
-
-        report
+        Self::write_recommendations(&mut section.buf, &analysis.recommendations);
+        section.flush()
     }
 
     pub fn generate_json_report(analysis: &AnalysisResult) -> String {
-        // Simplified JSON generation - in production would use serde
-        format!(r#"{{
-  "analysis_time_ms": {},
-  "module_info": {{
-    "size": {},
-    "function_count": {},
-    "export_count": {},
-    "memory_pages": {}
-  }},
-  "risk_score": {{
-    "overall": "{}",
-    "score": {},
-    "memory_risk": "{}",
-    "execution_risk": "{}",
-    "syscall_risk": "{}",
-    "complexity_risk": "{}"
-  }},
-  "security_assessment": {{
-    "control_flow_complexity": {},
-    "memory_patterns_count": {},
-    "suspicious_patterns_count": {},
-    "syscall_functions_count": {}
-  }},
-  "capability_requirements": {{
-    "required_capabilities": {},
-    "optional_capabilities": {},
-    "inferred_permissions": {}
-  }},
-  "recommendations_count": {}
-}}"#,
-            analysis.analysis_time.as_millis(),
-            analysis.module_info.size,
-            analysis.module_info.function_count,
-            analysis.module_info.export_count,
-            analysis.module_info.memory_pages.unwrap_or(0),
-            Self::risk_level_to_string(&analysis.risk_score.overall),
-            analysis.risk_score.score,
-            Self::risk_level_to_string(&analysis.risk_score.memory_risk),
-            Self::risk_level_to_string(&analysis.risk_score.execution_risk),
-            Self::risk_level_to_string(&analysis.risk_score.syscall_risk),
-            Self::risk_level_to_string(&analysis.risk_score.complexity_risk),
-            analysis.security_assessment.control_flow_complexity,
-            analysis.security_assessment.memory_patterns.len(),
-            analysis.security_assessment.suspicious_patterns.len(),
-            analysis.security_assessment.syscall_functions.len(),
-            analysis.capability_requirements.required_capabilities.len(),
-            analysis.capability_requirements.optional_capabilities.len(),
-            analysis.capability_requirements.inferred_permissions.len(),
-            analysis.recommendations.len()
-        )
+        serde_json::to_string_pretty(analysis).expect("analysis results always serialize")
+    }
+
+    /// SARIF 2.1.0 log with one result per suspicious pattern and per Severe
+    /// syscall import, for code-scanning dashboards.
+    pub fn generate_sarif_report(analysis: &AnalysisResult) -> String {
+        let security = &analysis.security_assessment;
+        let mut rules: Vec<&str> = Vec::new();
+        let mut results = Vec::new();
+
+        for pattern in &security.suspicious_patterns {
+            if !rules.contains(&pattern.pattern_name.as_str()) {
+                rules.push(&pattern.pattern_name);
+            }
+            results.push(serde_json::json!({
+                "ruleId": pattern.pattern_name,
+                "level": Self::sarif_level(&pattern.risk_level),
+                "message": { "text": pattern.description },
+                "locations": [{
+                    "logicalLocations": [{
+                        "name": format!("func {} +0x{:x}", pattern.function_index, pattern.instruction_offset),
+                        "kind": "function",
+                    }],
+                    "properties": {
+                        "functionIndex": pattern.function_index,
+                        "instructionOffset": pattern.instruction_offset,
+                    },
+                }],
+                "occurrenceCount": pattern.occurrences,
+            }));
+        }
+
+        for syscall in security.syscall_functions.iter().filter(|s| s.risk_level == RiskLevel::Severe) {
+            if !rules.contains(&"DangerousSyscall") {
+                rules.push("DangerousSyscall");
+            }
+            results.push(serde_json::json!({
+                "ruleId": "DangerousSyscall",
+                "level": Self::sarif_level(&syscall.risk_level),
+                "message": { "text": format!("Imports {} ({} uses)", syscall.name, syscall.usage_count) },
+                "locations": [{
+                    "logicalLocations": [{
+                        "name": syscall.name,
+                        "kind": "function",
+                    }],
+                    "properties": { "importIndex": syscall.import_index },
+                }],
+            }));
+        }
+
+        let sarif = serde_json::json!({
+            "$schema": "https://json.schemastore.org/sarif-2.1.0.json",
+            "version": "2.1.0",
+            "runs": [{
+                "tool": {
+                    "driver": {
+                        "name": "wasm-engine-static-analysis",
+                        "version": env!("CARGO_PKG_VERSION"),
+                        "rules": rules.iter().map(|id| serde_json::json!({ "id": id })).collect::<Vec<_>>(),
+                    },
+                },
+                "results": results,
+            }],
+        });
+        serde_json::to_string_pretty(&sarif).expect("SARIF logs always serialize")
+    }
+
+    fn sarif_level(level: &RiskLevel) -> &'static str {
+        match level {
+            RiskLevel::OK => "note",
+            RiskLevel::Warning => "warning",
+            RiskLevel::Severe => "error",
+        }
     }
 
     pub fn generate_html_report(analysis: &AnalysisResult) -> String {
-        let mut html = String::new();
+        let mut out = Vec::new();
+        Self::render_html_to(analysis, &mut out).expect("writing to a Vec cannot fail");
+        String::from_utf8(out).expect("report is UTF-8")
+    }
+
+    /// Stream the HTML report section by section, flushing after each so a
+    /// slow or disconnected client surfaces as an error early.
+    pub fn render_html_to<W: io::Write>(analysis: &AnalysisResult, writer: &mut W) -> io::Result<()> {
+        let mut section = SectionWriter::new(writer);
+        let html = &mut section.buf;
         
         writeln!(html, r#"<!DOCTYPE html>
 <html>
@@ -111,24 +174,30 @@ impl ReportGenerator {
     <h1>WASM Static Analysis Report</h1>
     <p>Analysis completed in {:?}</p>
 </div>"#, analysis.analysis_time).unwrap();
+        section.flush()?;
 
         // Risk Overview
-        Self::write_html_risk_overview(&mut html, &analysis.risk_score);
+        Self::write_html_risk_overview(&mut section.buf, &analysis.risk_score);
+        section.flush()?;
         
         // Module Information
-        Self::write_html_module_info(&mut html, &analysis.module_info);
+        Self::write_html_module_info(&mut section.buf, &analysis.module_info);
+        section.flush()?;
         
         // Security Assessment
-        Self::write_html_security_assessment(&mut html, &analysis.security_assessment);
+        Self::write_html_security_assessment(&mut section.buf, &analysis.security_assessment);
+        section.flush()?;
         
         // Capabilities
-        Self::write_html_capabilities(&mut html, &analysis.capability_requirements);
+        Self::write_html_capabilities(&mut section.buf, &analysis.capability_requirements);
+        section.flush()?;
         
         // Recommendations
-        Self::write_html_recommendations(&mut html, &analysis.recommendations);
+        Self::write_html_recommendations(&mut section.buf, &analysis.recommendations);
+        section.flush()?;
 
-        writeln!(html, "</body></html>").unwrap();
-        html
+        writeln!(section.buf, "</body></html>").unwrap();
+        section.flush()
     }
 
     fn write_module_info(report: &mut String, info: &super::ModuleInfo) {
@@ -139,6 +208,12 @@ impl ReportGenerator {
         if let Some(pages) = info.memory_pages {
             writeln!(report, "Memory Pages: {} ({} KB)", pages, pages * 64).unwrap();
         }
+        if !info.demangled_names.is_empty() {
+            writeln!(report, "Demangled Symbols:").unwrap();
+            for name in &info.demangled_names {
+                writeln!(report, "  - {} ({})", name.demangled, name.raw).unwrap();
+            }
+        }
         writeln!(report).unwrap();
     }
 
@@ -166,6 +241,9 @@ impl ReportGenerator {
                 writeln!(report, "  - {} (Function {}, Offset {}): {}", 
                         pattern.pattern_name, pattern.function_index, 
                         pattern.instruction_offset, pattern.description).unwrap();
+                if pattern.occurrences > 1 {
+                    writeln!(report, "    {} occurrences", pattern.occurrences).unwrap();
+                }
             }
         }
         
@@ -204,6 +282,9 @@ impl ReportGenerator {
                         perm.name, 
                         if perm.required { "Required" } else { "Optional" },
                         perm.reason).unwrap();
+                for location in &perm.locations {
+                    writeln!(report, "      at {}", location).unwrap();
+                }
             }
         }
         writeln!(report).unwrap();
@@ -303,10 +384,26 @@ impl ReportGenerator {
     <ul>"#).unwrap();
     
     for cap in &capabilities.required_capabilities {
-        writeln!(html, "<li>{}</li>", cap).unwrap();
+        writeln!(html, "<li>{}</li>", html_escape(cap)).unwrap();
     }
     
-    writeln!(html, "</ul></div>").unwrap();
+    writeln!(html, "</ul>").unwrap();
+    
+    if !capabilities.inferred_permissions.is_empty() {
+        writeln!(html, "<h3>Inferred Permissions</h3>\n<ul>").unwrap();
+        for perm in &capabilities.inferred_permissions {
+            write!(html, "<li><strong>{}</strong>: {}",
+                html_escape(&perm.name), html_escape(&perm.reason)).unwrap();
+            for location in &perm.locations {
+                let anchor = format!("func-{}-{:x}", location.function_index, location.offset);
+                write!(html, r##" <a class="code-location" id="{0}" href="#{0}">{1}</a>"##, anchor, location).unwrap();
+            }
+            writeln!(html, "</li>").unwrap();
+        }
+        writeln!(html, "</ul>").unwrap();
+    }
+    
+    writeln!(html, "</div>").unwrap();
     }
 
     fn write_html_recommendations(html: &mut String, recommendations: &[super::Recommendation]) {
@@ -322,7 +419,7 @@ impl ReportGenerator {
             writeln!(html, r#"<div class="{}">
         <strong>{}:</strong> {}<br>
         <em>Action: {}</em>
-    </div>"#, risk_class, rec.category, rec.message, rec.action).unwrap();
+    </div>"#, risk_class, html_escape(&rec.category), html_escape(&rec.message), html_escape(&rec.action)).unwrap();
         }
         
         writeln!(html, "</div>").unwrap();
@@ -361,11 +458,264 @@ impl ReportGenerator {
     }
 }
 
+/// Modules listed under "Riskiest Modules" in fleet reports.
+const FLEET_TOP_MODULES: usize = 5;
+
+/// Roll-up of many analyses shared by the text and HTML fleet reports.
+struct FleetSummary<'a> {
+    ok: usize,
+    warning: usize,
+    severe: usize,
+    severe_findings: u32,
+    /// Capabilities by number of modules requiring them, most common first.
+    capabilities: Vec<(&'a str, usize)>,
+    /// Modules by descending risk score.
+    ranked: Vec<&'a (String, AnalysisResult)>,
+}
+
+impl<'a> FleetSummary<'a> {
+    fn new(results: &'a [(String, AnalysisResult)]) -> Self {
+        let mut summary = Self {
+            ok: 0,
+            warning: 0,
+            severe: 0,
+            severe_findings: 0,
+            capabilities: Vec::new(),
+            ranked: results.iter().collect(),
+        };
+        let mut capability_counts: HashMap<&str, usize> = HashMap::new();
+
+        for (_, analysis) in results {
+            match analysis.risk_score.overall {
+                RiskLevel::OK => summary.ok += 1,
+                RiskLevel::Warning => summary.warning += 1,
+                RiskLevel::Severe => summary.severe += 1,
+            }
+            summary.severe_findings += Self::severe_findings(analysis);
+            for cap in &analysis.capability_requirements.required_capabilities {
+                *capability_counts.entry(cap.as_str()).or_insert(0) += 1;
+            }
+        }
+
+        summary.capabilities = capability_counts.into_iter().collect();
+        summary.capabilities.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(b.0)));
+        summary.ranked.sort_by_key(|entry| std::cmp::Reverse(entry.1.risk_score.score));
+        summary
+    }
+
+    fn severe_findings(analysis: &AnalysisResult) -> u32 {
+        let security = &analysis.security_assessment;
+        let patterns: u32 = security.suspicious_patterns.iter()
+            .filter(|p| p.risk_level == RiskLevel::Severe)
+            .map(|p| p.occurrences)
+            .sum();
+        let memory = security.memory_patterns.iter()
+            .filter(|p| p.risk_level == RiskLevel::Severe)
+            .count() as u32;
+        patterns + memory
+    }
+}
+
+/// Text report summarizing risk levels, common capabilities and the riskiest
+/// modules across a fleet of `(module name, analysis)` pairs.
+pub fn generate_fleet_report(results: &[(String, AnalysisResult)]) -> String {
+    let summary = FleetSummary::new(results);
+    let mut report = String::new();
+
+    writeln!(report, "=== WASM Fleet Analysis Report ===").unwrap();
+    writeln!(report, "Modules: {}", results.len()).unwrap();
+    writeln!(report, "Risk Levels: OK {}, WARNING {}, SEVERE {}", summary.ok, summary.warning, summary.severe).unwrap();
+    writeln!(report, "Severe Findings: {}", summary.severe_findings).unwrap();
+    writeln!(report).unwrap();
+
+    if !summary.capabilities.is_empty() {
+        writeln!(report, "--- Most Common Capabilities ---").unwrap();
+        for (cap, count) in &summary.capabilities {
+            writeln!(report, "  - {}: {} modules", cap, count).unwrap();
+        }
+        writeln!(report).unwrap();
+    }
+
+    writeln!(report, "--- Riskiest Modules ---").unwrap();
+    for (name, analysis) in summary.ranked.iter().take(FLEET_TOP_MODULES).map(|entry| (&entry.0, &entry.1)) {
+        writeln!(report, "  - {} [{}] (Score: {}/100)", name,
+                ReportGenerator::risk_level_to_string(&analysis.risk_score.overall),
+                analysis.risk_score.score).unwrap();
+    }
+
+    report
+}
+
+/// HTML form of `generate_fleet_report`, with every module in a table that
+/// sorts by any column when its header is clicked.
+pub fn generate_fleet_html_report(results: &[(String, AnalysisResult)]) -> String {
+    let summary = FleetSummary::new(results);
+    let mut html = String::new();
+
+    writeln!(html, r#"<!DOCTYPE html>
+<html>
+<head>
+    <title>WASM Fleet Analysis Report</title>
+    <style>
+        body {{ font-family: Arial, sans-serif; margin: 20px; }}
+        .section {{ margin: 20px 0; padding: 15px; border: 1px solid #ddd; border-radius: 5px; }}
+        .risk-ok {{ color: green; }}
+        .risk-warning {{ color: orange; }}
+        .risk-severe {{ color: red; }}
+        table {{ border-collapse: collapse; width: 100%; }}
+        th, td {{ border: 1px solid #ddd; padding: 8px; text-align: left; }}
+        th {{ background-color: #f2f2f2; cursor: pointer; }}
+    </style>
+</head>
+<body>
+<div class="section">
+    <h1>WASM Fleet Analysis Report</h1>
+    <p>Modules: {} &mdash; OK {}, WARNING {}, SEVERE {} &mdash; Severe findings: {}</p>
+</div>"#,
+        results.len(), summary.ok, summary.warning, summary.severe, summary.severe_findings).unwrap();
+
+    writeln!(html, "<div class=\"section\">\n    <h2>Most Common Capabilities</h2>\n    <ul>").unwrap();
+    for (cap, count) in &summary.capabilities {
+        writeln!(html, "<li>{}: {} modules</li>", html_escape(cap), count).unwrap();
+    }
+    writeln!(html, "</ul>\n</div>").unwrap();
+
+    writeln!(html, r#"<div class="section">
+    <h2>Modules</h2>
+    <table id="fleet">
+        <tr><th onclick="sortFleet(0, false)">Module</th><th onclick="sortFleet(1, false)">Risk</th><th onclick="sortFleet(2, true)">Score</th><th onclick="sortFleet(3, true)">Severe Findings</th><th onclick="sortFleet(4, true)">Capabilities</th></tr>"#).unwrap();
+    for (name, analysis) in summary.ranked.iter().map(|entry| (&entry.0, &entry.1)) {
+        writeln!(html, r#"        <tr><td>{}</td><td class="{}">{}</td><td>{}</td><td>{}</td><td>{}</td></tr>"#,
+            html_escape(name),
+            ReportGenerator::get_risk_class(&analysis.risk_score.overall),
+            ReportGenerator::risk_level_to_string(&analysis.risk_score.overall),
+            analysis.risk_score.score,
+            FleetSummary::severe_findings(analysis),
+            analysis.capability_requirements.required_capabilities.len()).unwrap();
+    }
+    writeln!(html, r#"    </table>
+</div>
+<script>
+function sortFleet(column, numeric) {{
+    const table = document.getElementById("fleet");
+    const rows = Array.from(table.rows).slice(1);
+    const key = row => numeric ? Number(row.cells[column].textContent) : row.cells[column].textContent;
+    const descending = table.dataset.sorted === String(column) && table.dataset.order !== "desc";
+    rows.sort((a, b) => (key(a) > key(b) ? 1 : key(a) < key(b) ? -1 : 0) * (descending ? -1 : 1));
+    table.dataset.sorted = column;
+    table.dataset.order = descending ? "desc" : "asc";
+    rows.forEach(row => table.appendChild(row));
+}}
+</script>
+</body></html>"#).unwrap();
+
+    html
+}
+
+/// Escape a string for interpolation into HTML text or attribute values.
+/// Everything derived from the module (names, reasons, messages) must pass
+/// through here, since reports of untrusted modules are viewed in browsers.
+pub fn html_escape(input: &str) -> String {
+    let mut escaped = String::with_capacity(input.len());
+    for c in input.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&#39;"),
+            _ => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+/// Receiver for analysis metrics. Implemented for OpenTelemetry meters
+/// behind the `otel` feature so the core crate stays dependency-light.
+pub trait MetricsSink {
+    fn record_gauge(&self, name: &'static str, value: f64);
+    fn record_histogram(&self, name: &'static str, value: f64);
+    fn add_counter(&self, name: &'static str, value: u64, label: (&'static str, String));
+}
+
+pub const RISK_SCORE_METRIC: &str = "wasm_analysis.risk_score";
+pub const ANALYSIS_DURATION_METRIC: &str = "wasm_analysis.duration_ms";
+pub const CAPABILITY_METRIC: &str = "wasm_analysis.capabilities";
+pub const PATTERN_METRIC: &str = "wasm_analysis.suspicious_patterns";
+
+/// Record the risk score, analysis time, and per-capability and
+/// per-pattern counts of an analysis.
+pub fn emit_metrics(analysis: &AnalysisResult, sink: &dyn MetricsSink) {
+    sink.record_gauge(RISK_SCORE_METRIC, analysis.risk_score.score as f64);
+    sink.record_histogram(ANALYSIS_DURATION_METRIC, analysis.analysis_time.as_secs_f64() * 1000.0);
+    
+    for capability in &analysis.capability_requirements.required_capabilities {
+        sink.add_counter(CAPABILITY_METRIC, 1, ("capability", capability.clone()));
+    }
+    for pattern in &analysis.security_assessment.suspicious_patterns {
+        sink.add_counter(PATTERN_METRIC, pattern.occurrences as u64, ("pattern", pattern.pattern_name.clone()));
+    }
+}
+
+#[cfg(feature = "otel")]
+pub fn emit_otel(analysis: &AnalysisResult, meter: &opentelemetry::metrics::Meter) {
+    emit_metrics(analysis, meter);
+}
+
+#[cfg(feature = "otel")]
+impl MetricsSink for opentelemetry::metrics::Meter {
+    fn record_gauge(&self, name: &'static str, value: f64) {
+        self.f64_gauge(name).init().record(value, &[]);
+    }
+    
+    fn record_histogram(&self, name: &'static str, value: f64) {
+        self.f64_histogram(name).init().record(value, &[]);
+    }
+    
+    fn add_counter(&self, name: &'static str, value: u64, label: (&'static str, String)) {
+        let attributes = [opentelemetry::KeyValue::new(label.0, label.1)];
+        self.u64_counter(name).init().add(value, &attributes);
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use std::cell::RefCell;
     use std::time::Duration;
 
+    #[derive(Default)]
+    struct MockMeter {
+        gauges: RefCell<Vec<(&'static str, f64)>>,
+        histograms: RefCell<Vec<(&'static str, f64)>>,
+        counters: RefCell<Vec<(&'static str, u64, String)>>,
+    }
+
+    impl MetricsSink for MockMeter {
+        fn record_gauge(&self, name: &'static str, value: f64) {
+            self.gauges.borrow_mut().push((name, value));
+        }
+
+        fn record_histogram(&self, name: &'static str, value: f64) {
+            self.histograms.borrow_mut().push((name, value));
+        }
+
+        fn add_counter(&self, name: &'static str, value: u64, label: (&'static str, String)) {
+            self.counters.borrow_mut().push((name, value, label.1));
+        }
+    }
+
+    #[test]
+    fn test_emit_metrics() {
+        let analysis = create_test_analysis();
+        let meter = MockMeter::default();
+        emit_metrics(&analysis, &meter);
+
+        assert_eq!(*meter.gauges.borrow(), vec![(RISK_SCORE_METRIC, 15.0)]);
+        assert_eq!(*meter.histograms.borrow(), vec![(ANALYSIS_DURATION_METRIC, 50.0)]);
+        assert_eq!(*meter.counters.borrow(), vec![(CAPABILITY_METRIC, 1, "Log".to_string())]);
+    }
+
     #[test]
     fn test_text_report_generation() {
         let analysis = create_test_analysis();
@@ -386,6 +736,54 @@ mod tests {
         assert!(report.contains("module_info"));
     }
 
+    #[test]
+    fn test_json_report_round_trip() {
+        let analysis = create_test_analysis();
+        let report = ReportGenerator::generate_json_report(&analysis);
+        assert!(report.contains("\"overall\": \"OK\""));
+
+        let parsed: AnalysisResult = serde_json::from_str(&report).unwrap();
+        assert_eq!(parsed.risk_score.score, analysis.risk_score.score);
+        assert_eq!(parsed.risk_score.overall, analysis.risk_score.overall);
+        assert_eq!(parsed.analysis_time, analysis.analysis_time);
+        assert_eq!(parsed.security_assessment.suspicious_patterns.len(),
+            analysis.security_assessment.suspicious_patterns.len());
+    }
+
+    #[test]
+    fn test_sarif_report() {
+        let mut analysis = create_test_analysis();
+        analysis.security_assessment.suspicious_patterns.push(crate::static_analysis::SuspiciousPattern {
+            pattern_name: "InfiniteLoop".to_string(),
+            function_index: 2,
+            instruction_offset: 17,
+            description: "Loop always branches back to itself with no exit".to_string(),
+            risk_level: RiskLevel::Severe,
+            occurrences: 1,
+            offsets: vec![],
+        });
+        analysis.security_assessment.syscall_functions.push(crate::static_analysis::SyscallFunction {
+            name: "sock_connect".to_string(),
+            import_index: 0,
+            usage_count: 3,
+            risk_level: RiskLevel::Severe,
+        });
+        analysis.security_assessment.syscall_functions.push(crate::static_analysis::SyscallFunction {
+            name: "wasm_log".to_string(),
+            import_index: 1,
+            usage_count: 1,
+            risk_level: RiskLevel::OK,
+        });
+
+        let sarif: serde_json::Value = serde_json::from_str(&ReportGenerator::generate_sarif_report(&analysis)).unwrap();
+        assert_eq!(sarif["version"], "2.1.0");
+        let results = sarif["runs"][0]["results"].as_array().unwrap();
+        let rule_ids: Vec<&str> = results.iter().map(|r| r["ruleId"].as_str().unwrap()).collect();
+        assert_eq!(rule_ids, vec!["InfiniteLoop", "DangerousSyscall"]);
+        assert_eq!(results[0]["level"], "error");
+        assert_eq!(results[0]["locations"][0]["properties"]["instructionOffset"], 17);
+    }
+
     #[test]
     fn test_html_report_generation() {
         let analysis = create_test_analysis();
@@ -396,6 +794,88 @@ mod tests {
         assert!(report.contains("Risk Overview"));
     }
 
+    #[test]
+    fn test_render_to_writer_matches_string_report() {
+        struct Chunked { out: Vec<u8>, flushes: usize }
+        impl io::Write for Chunked {
+            fn write(&mut self, buf: &[u8]) -> io::Result<usize> { self.out.write(buf) }
+            fn flush(&mut self) -> io::Result<()> { self.flushes += 1; Ok(()) }
+        }
+
+        let analysis = create_test_analysis();
+        let mut writer = Chunked { out: Vec::new(), flushes: 0 };
+        ReportGenerator::render_html_to(&analysis, &mut writer).unwrap();
+        assert_eq!(writer.out, ReportGenerator::generate_html_report(&analysis).into_bytes());
+        assert!(writer.flushes > 1);
+
+        let mut out = Vec::new();
+        ReportGenerator::render_text_to(&analysis, &mut out).unwrap();
+        assert_eq!(out, ReportGenerator::generate_text_report(&analysis).into_bytes());
+    }
+
+    #[test]
+    fn test_html_report_escapes_module_strings() {
+        use crate::parser::{Export, ExportKind, WasmModule};
+        use crate::static_analysis::StaticAnalyzer;
+
+        let module = WasmModule {
+            exports: vec![Export {
+                name: "wasm_log<script>alert('x')</script>".to_string(),
+                kind: ExportKind::Function,
+                index: 0,
+            }],
+            ..Default::default()
+        };
+        let analysis = StaticAnalyzer::new().analyze(&module).unwrap();
+        let report = ReportGenerator::generate_html_report(&analysis);
+
+        assert!(!report.contains("<script>"));
+        assert!(report.contains("wasm_log&lt;script&gt;alert(&#39;x&#39;)&lt;/script&gt;"));
+        assert_eq!(html_escape("a & \"b\""), "a &amp; &quot;b&quot;");
+    }
+
+    #[test]
+    fn test_fleet_report() {
+        use crate::static_analysis::SuspiciousPattern;
+
+        let mut risky = create_test_analysis();
+        risky.risk_score.overall = RiskLevel::Severe;
+        risky.risk_score.score = 85;
+        risky.capability_requirements.required_capabilities.push("NetworkAccess".to_string());
+        risky.security_assessment.suspicious_patterns.push(SuspiciousPattern {
+            pattern_name: "InfiniteLoop".to_string(),
+            function_index: 0,
+            instruction_offset: 4,
+            description: "Loop always branches back to itself with no exit".to_string(),
+            risk_level: RiskLevel::Severe,
+            occurrences: 2,
+            offsets: vec![4, 12],
+        });
+        let mut warning = create_test_analysis();
+        warning.risk_score.overall = RiskLevel::Warning;
+        warning.risk_score.score = 40;
+
+        let fleet = vec![
+            ("sensor_a".to_string(), create_test_analysis()),
+            ("uploader".to_string(), risky),
+            ("sensor_b".to_string(), create_test_analysis()),
+            ("logger".to_string(), warning),
+        ];
+
+        let report = generate_fleet_report(&fleet);
+        assert!(report.contains("Modules: 4"));
+        assert!(report.contains("Risk Levels: OK 2, WARNING 1, SEVERE 1"));
+        assert!(report.contains("Severe Findings: 2"));
+        assert!(report.contains("  - Log: 4 modules"));
+        let riskiest = report.split("--- Riskiest Modules ---\n").nth(1).unwrap();
+        assert!(riskiest.starts_with("  - uploader [SEVERE] (Score: 85/100)"));
+
+        let html = generate_fleet_html_report(&fleet);
+        assert!(html.contains("<table id=\"fleet\">"));
+        let first_row = html.find("<td>uploader</td>").unwrap();
+        assert!(first_row < html.find("<td>logger</td>").unwrap());
+    }
+
     fn create_test_analysis() -> AnalysisResult {
         AnalysisResult {
             module_info: super::ModuleInfo {
@@ -406,6 +886,7 @@ mod tests {
                 memory_pages: Some(1),
                 table_size: None,
                 global_count: 0,
+                demangled_names: vec![],
             },
             security_assessment: super::SecurityAssessment {
                 memory_patterns: vec![],
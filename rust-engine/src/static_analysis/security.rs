@@ -1,23 +1,72 @@
-use super::{SecurityAssessment, MemoryPattern, SuspiciousPattern, SyscallFunction, ResourceRequirements, RiskLevel};
+use super::{SecurityAssessment, MemoryPattern, CodeLocation, SuspiciousPattern, SyscallFunction, ResourceRequirements, RiskLevel};
 use super::patterns::{PatternMatcher, DataFlowAnalysis};
-use crate::parser::WasmModule;
+use crate::decoder::{self, decode_leb128_u32_at, Immediate, Instruction};
+use crate::parser::{CodeSection, ExportKind, ImportKind, MemoryType, WasmModule};
 use crate::error::{WasmError, Result};
+use std::collections::HashMap;
+
+/// Byte sequences in data segments that point at embedded payloads.
+const DATA_SIGNATURES: &[(&str, &[u8], RiskLevel)] = &[
+    ("EmbeddedUrl", b"http://", RiskLevel::Warning),
+    ("EmbeddedUrl", b"https://", RiskLevel::Warning),
+    ("ShellcodeSignature", b"/bin/sh", RiskLevel::Severe),
+    // x86 NOP sled
+    ("ShellcodeSignature", &[0x90; 16], RiskLevel::Severe),
+];
+
+/// Shannon entropy, in bits per byte, above which a region looks packed or
+/// encrypted.
+const HIGH_ENTROPY_BITS: f64 = 7.5;
+
+/// Default cap on offsets kept per grouped finding.
+pub const MAX_GROUPED_OFFSETS: usize = 16;
+
+/// Locals holding a known i32 constant, with the block depth they were set at.
+type LocalConstants = HashMap<u32, (i32, usize)>;
 
 pub struct SecurityAnalyzer {
     pattern_matcher: PatternMatcher,
+    group_findings: bool,
+    max_grouped_offsets: usize,
 }
 
 impl SecurityAnalyzer {
     pub fn new() -> Self {
         Self {
             pattern_matcher: PatternMatcher::new(),
+            group_findings: true,
+            max_grouped_offsets: MAX_GROUPED_OFFSETS,
         }
     }
 
+    pub fn with_pattern_matcher(mut self, matcher: PatternMatcher) -> Self {
+        self.pattern_matcher = matcher;
+        self
+    }
+
+    /// Collapse identical findings within a function into one counted entry.
+    pub fn with_finding_grouping(mut self, enabled: bool) -> Self {
+        self.group_findings = enabled;
+        self
+    }
+
+    pub fn with_max_grouped_offsets(mut self, max: usize) -> Self {
+        self.max_grouped_offsets = max;
+        self
+    }
+
     pub fn analyze(&self, module: &WasmModule) -> Result<SecurityAssessment> {
         let memory_patterns = self.analyze_memory_patterns(module);
         let control_flow_complexity = self.pattern_matcher.analyze_control_flow(module);
-        let suspicious_patterns = self.pattern_matcher.find_patterns(module);
+        let mut suspicious_patterns = self.pattern_matcher.find_patterns(module);
+        if self.group_findings {
+            suspicious_patterns = self.group_suspicious_patterns(suspicious_patterns);
+        }
+        suspicious_patterns.extend(self.detect_unlimited_imported_memory(module));
+        suspicious_patterns.extend(self.detect_data_payloads(module));
+        suspicious_patterns.extend(self.detect_exported_mutable_globals(module));
+        suspicious_patterns.extend(self.detect_recursion(module));
+        suspicious_patterns.extend(self.detect_stack_imbalance(module));
         let syscall_functions = self.analyze_syscall_functions(module);
         let resource_requirements = self.estimate_resource_requirements(module);
 
@@ -30,7 +79,86 @@ impl SecurityAnalyzer {
         })
     }
 
+    /// Assess `chunk`'s functions, numbered from `first_index`, as the next part
+    /// of a streamed code section. Grouping is per function, so grouping each
+    /// chunk matches grouping the whole section.
+    pub(super) fn analyze_code_chunk(&self, chunk: &WasmModule, first_index: u32) -> SecurityAssessment {
+        let mut suspicious_patterns = self.pattern_matcher.find_patterns(chunk);
+        if self.group_findings {
+            suspicious_patterns = self.group_suspicious_patterns(suspicious_patterns);
+        }
+        for finding in &mut suspicious_patterns {
+            finding.function_index += first_index;
+        }
+
+        SecurityAssessment {
+            memory_patterns: self.code_memory_patterns(&chunk.code, first_index),
+            control_flow_complexity: self.pattern_matcher.analyze_control_flow(chunk),
+            suspicious_patterns,
+            syscall_functions: Vec::new(),
+            resource_requirements: self.estimate_code_requirements(&chunk.code),
+        }
+    }
+
+    /// Add the checks that need the whole module to an assessment built from
+    /// streamed sections.
+    pub(super) fn finish_incremental(&self, module: &WasmModule, mut running: SecurityAssessment) -> SecurityAssessment {
+        for syscall in &mut running.syscall_functions {
+            syscall.usage_count = self.count_syscall_usage(module, &syscall.name);
+        }
+
+        let mut memory_patterns = self.detect_excessive_writes(module);
+        memory_patterns.append(&mut running.memory_patterns);
+        memory_patterns.extend(self.detect_high_entropy(module));
+        running.memory_patterns = memory_patterns;
+
+        running.suspicious_patterns.extend(self.detect_data_payloads(module));
+        running.suspicious_patterns.extend(self.detect_exported_mutable_globals(module));
+        running.suspicious_patterns.extend(self.detect_recursion(module));
+        running.suspicious_patterns.extend(self.detect_stack_imbalance(module));
+        running
+    }
+
+    /// Merge findings sharing a pattern name and function, keeping the first one's
+    /// offset and description.
+    fn group_suspicious_patterns(&self, findings: Vec<SuspiciousPattern>) -> Vec<SuspiciousPattern> {
+        let mut grouped: Vec<SuspiciousPattern> = Vec::new();
+        let mut index: HashMap<(String, u32), usize> = HashMap::new();
+
+        for finding in findings {
+            let key = (finding.pattern_name.clone(), finding.function_index);
+            match index.get(&key) {
+                Some(&i) => {
+                    let group = &mut grouped[i];
+                    group.occurrences += finding.occurrences;
+                    if group.offsets.is_empty() {
+                        group.offsets.push(group.instruction_offset);
+                    }
+                    if group.offsets.len() < self.max_grouped_offsets {
+                        group.offsets.push(finding.instruction_offset);
+                    }
+                }
+                None => {
+                    index.insert(key, grouped.len());
+                    grouped.push(finding);
+                }
+            }
+        }
+
+        grouped
+    }
+
     fn analyze_memory_patterns(&self, module: &WasmModule) -> Vec<MemoryPattern> {
+        let mut patterns = self.detect_excessive_writes(module);
+        patterns.extend(self.code_memory_patterns(&module.code, 0));
+
+        // Check for packed or encrypted payloads
+        patterns.extend(self.detect_high_entropy(module));
+
+        patterns
+    }
+
+    fn detect_excessive_writes(&self, module: &WasmModule) -> Vec<MemoryPattern> {
         let mut patterns = Vec::new();
         let data_flow = self.pattern_matcher.analyze_data_flow(module);
 
@@ -41,38 +169,487 @@ impl SecurityAnalyzer {
                 locations: vec![0], // Would track actual locations
                 risk_level: RiskLevel::Warning,
                 description: "Module performs excessive memory write operations".to_string(),
+                code_locations: Vec::new(),
             });
         }
 
+        patterns
+    }
+
+    /// Memory patterns local to each function of `code`, numbered from `first_index`.
+    fn code_memory_patterns(&self, code: &[CodeSection], first_index: u32) -> Vec<MemoryPattern> {
+        let mut patterns = Vec::new();
+
         // Check for memory growth patterns
-        for (func_idx, code_section) in module.code.iter().enumerate() {
+        for (func_idx, code_section) in (first_index..).zip(code) {
             if self.contains_memory_grow(&code_section.body) {
                 patterns.push(MemoryPattern {
                     pattern_type: "MemoryGrowth".to_string(),
-                    locations: vec![func_idx as u32],
+                    locations: vec![func_idx],
                     risk_level: RiskLevel::Warning,
                     description: "Function contains memory growth operations".to_string(),
+                    code_locations: Self::memory_grow_sites(func_idx, &code_section.body),
                 });
             }
         }
 
         // Check for unaligned access patterns
-        patterns.extend(self.detect_unaligned_access(module));
+        patterns.extend(self.detect_unaligned_access(code, first_index));
 
         // Check for buffer overflow patterns
-        patterns.extend(self.detect_buffer_overflow_patterns(module));
+        patterns.extend(self.detect_buffer_overflow_patterns(code, first_index));
+
+        patterns
+    }
+
+    /// Imported memories without a maximum can grow as far as the host allows.
+    pub(super) fn detect_unlimited_imported_memory(&self, module: &WasmModule) -> Vec<SuspiciousPattern> {
+        module.imports.iter()
+            .filter(|import| matches!(&import.kind, ImportKind::Memory(memory) if memory.max.is_none()))
+            .map(|import| SuspiciousPattern {
+                pattern_name: "UnlimitedImportedMemory".to_string(),
+                function_index: 0,
+                instruction_offset: 0,
+                description: format!("Imported memory '{}.{}' declares no maximum", import.module, import.name),
+                risk_level: RiskLevel::Warning,
+                occurrences: 1,
+                offsets: Vec::new(),
+            })
+            .collect()
+    }
+
+    /// Exported mutable globals let the host or another module change internal state.
+    fn detect_exported_mutable_globals(&self, module: &WasmModule) -> Vec<SuspiciousPattern> {
+        let global_mutability: Vec<bool> = module.imports.iter()
+            .filter_map(|import| match &import.kind {
+                ImportKind::Global { mutable, .. } => Some(*mutable),
+                _ => None,
+            })
+            .chain(module.globals.iter().map(|global| global.mutable))
+            .collect();
+
+        module.exports.iter()
+            .filter(|export| matches!(export.kind, ExportKind::Global))
+            .filter(|export| global_mutability.get(export.index as usize).copied().unwrap_or(false))
+            .map(|export| SuspiciousPattern {
+                pattern_name: "ExportedMutableGlobal".to_string(),
+                function_index: 0,
+                instruction_offset: 0,
+                description: format!("Mutable global {} is exported as '{}'", export.index, export.name),
+                risk_level: RiskLevel::Warning,
+                occurrences: 1,
+                offsets: Vec::new(),
+            })
+            .collect()
+    }
+
+    /// One finding per cycle in the static call graph, at the first call in the
+    /// cycle's lowest function that stays within the cycle. Function indices
+    /// are code section indices.
+    fn detect_recursion(&self, module: &WasmModule) -> Vec<SuspiciousPattern> {
+        let imported = module.imports.iter()
+            .filter(|import| matches!(import.kind, ImportKind::Function(_)))
+            .count() as u32;
+
+        // (callee, call offset) for every direct call to a defined function
+        let calls: Vec<Vec<(usize, u32)>> = module.code.iter().map(|code| {
+            decoder::decode_function_body(&code.body).unwrap_or_default().iter()
+                .filter(|instr| instr.opcode == 0x10)
+                .filter_map(|instr| match instr.immediates.first() {
+                    Some(Immediate::U32(callee)) if *callee >= imported => {
+                        Some(((callee - imported) as usize, instr.offset as u32))
+                    }
+                    _ => None,
+                })
+                .filter(|(callee, _)| *callee < module.code.len())
+                .collect()
+        }).collect();
+        let graph: Vec<Vec<usize>> = calls.iter()
+            .map(|callees| callees.iter().map(|(callee, _)| *callee).collect())
+            .collect();
+
+        Self::call_cycles(&graph).into_iter().map(|cycle| {
+            let first = cycle[0];
+            let offset = calls[first].iter()
+                .find(|(callee, _)| cycle.contains(callee))
+                .map_or(0, |(_, offset)| *offset);
+            let description = if cycle.len() == 1 {
+                format!("Function {} calls itself", first)
+            } else {
+                let members: Vec<String> = cycle.iter().map(|f| f.to_string()).collect();
+                format!("Recursive call cycle through functions {}", members.join(", "))
+            };
+            SuspiciousPattern {
+                pattern_name: "Recursion".to_string(),
+                function_index: first as u32,
+                instruction_offset: offset,
+                description,
+                risk_level: RiskLevel::Warning,
+                occurrences: 1,
+                offsets: Vec::new(),
+            }
+        }).collect()
+    }
+
+    /// Strongly connected components of `graph` that contain a cycle, each
+    /// sorted, found by an iterative Tarjan DFS so deep call chains can't
+    /// overflow the stack.
+    fn call_cycles(graph: &[Vec<usize>]) -> Vec<Vec<usize>> {
+        let unvisited = usize::MAX;
+        let mut index = vec![unvisited; graph.len()];
+        let mut lowlink = vec![0; graph.len()];
+        let mut on_stack = vec![false; graph.len()];
+        let mut stack = Vec::new();
+        let mut next_index = 0;
+        let mut cycles = Vec::new();
+
+        for root in 0..graph.len() {
+            if index[root] != unvisited {
+                continue;
+            }
+
+            // (function, next edge to follow)
+            let mut work = vec![(root, 0)];
+            index[root] = next_index;
+            lowlink[root] = next_index;
+            next_index += 1;
+            stack.push(root);
+            on_stack[root] = true;
+
+            while let Some((node, edge)) = work.pop() {
+                if let Some(&callee) = graph[node].get(edge) {
+                    work.push((node, edge + 1));
+                    if index[callee] == unvisited {
+                        index[callee] = next_index;
+                        lowlink[callee] = next_index;
+                        next_index += 1;
+                        stack.push(callee);
+                        on_stack[callee] = true;
+                        work.push((callee, 0));
+                    } else if on_stack[callee] {
+                        lowlink[node] = lowlink[node].min(index[callee]);
+                    }
+                    continue;
+                }
+
+                if let Some(&(caller, _)) = work.last() {
+                    lowlink[caller] = lowlink[caller].min(lowlink[node]);
+                }
+                if lowlink[node] == index[node] {
+                    let mut component = Vec::new();
+                    while let Some(member) = stack.pop() {
+                        on_stack[member] = false;
+                        component.push(member);
+                        if member == node {
+                            break;
+                        }
+                    }
+                    if component.len() > 1 || graph[node].contains(&node) {
+                        component.sort_unstable();
+                        cycles.push(component);
+                    }
+                }
+            }
+        }
+
+        cycles.sort();
+        cycles
+    }
+
+    /// One finding per function whose simulated operand stack underflows or
+    /// doesn't end at the declared result count. Function indices are code
+    /// section indices.
+    fn detect_stack_imbalance(&self, module: &WasmModule) -> Vec<SuspiciousPattern> {
+        // type index of every function in the index space, imports first
+        let func_types: Vec<u32> = module.imports.iter()
+            .filter_map(|import| match import.kind {
+                ImportKind::Function(type_idx) => Some(type_idx),
+                _ => None,
+            })
+            .chain(module.functions.iter().map(|func| func.type_idx))
+            .collect();
+        let imported = func_types.len() - module.functions.len();
+
+        module.code.iter().enumerate().filter_map(|(idx, code)| {
+            let results = func_types.get(imported + idx)
+                .and_then(|type_idx| module.types.get(*type_idx as usize))?
+                .results.len();
+            let instructions = decoder::decode_function_body(&code.body).ok()?;
+            let (offset, description) = Self::simulate_stack(module, &func_types, &instructions, results)?;
+            Some(SuspiciousPattern {
+                pattern_name: "StackImbalance".to_string(),
+                function_index: idx as u32,
+                instruction_offset: offset,
+                description: format!("Function {} {}", idx, description),
+                risk_level: RiskLevel::Warning,
+                occurrences: 1,
+                offsets: Vec::new(),
+            })
+        }).collect()
+    }
+
+    /// Track the operand stack height through `instructions`, returning the
+    /// offset and a description of the first inconsistency. Anything whose
+    /// effect can't be determined ends the simulation without a finding.
+    fn simulate_stack(
+        module: &WasmModule,
+        func_types: &[u32],
+        instructions: &[Instruction],
+        results: usize,
+    ) -> Option<(u32, String)> {
+        struct Frame {
+            base: usize,
+            params: usize,
+            results: usize,
+            is_loop: bool,
+            unreachable: bool,
+        }
+
+        let type_arity = |type_idx: u32| {
+            module.types.get(type_idx as usize).map(|ty| (ty.params.len(), ty.results.len()))
+        };
+        let block_arity = |instr: &Instruction| match instr.immediates.first() {
+            Some(Immediate::Block(decoder::BlockType::Empty)) => Some((0, 0)),
+            Some(Immediate::Block(decoder::BlockType::Value(_))) => Some((0, 1)),
+            Some(Immediate::Block(decoder::BlockType::TypeIndex(idx))) => type_arity(*idx),
+            _ => None,
+        };
+        let first_u32 = |instr: &Instruction| match instr.immediates.first() {
+            Some(Immediate::U32(v)) => Some(*v),
+            _ => None,
+        };
+
+        // pop `n` values, failing on an underflow in reachable code
+        fn pop(frames: &[Frame], height: &mut usize, n: usize) -> bool {
+            let frame = match frames.last() {
+                Some(frame) => frame,
+                None => return true,
+            };
+            if *height >= frame.base + n {
+                *height -= n;
+            } else if frame.unreachable {
+                *height = frame.base;
+            } else {
+                return false;
+            }
+            true
+        }
+
+        let mut frames = vec![Frame { base: 0, params: 0, results, is_loop: false, unreachable: false }];
+        let mut height = 0usize;
+
+        for instr in instructions {
+            let offset = instr.offset as u32;
+            let label_arity = |depth: u32| {
+                let frame = frames.len().checked_sub(depth as usize + 1).map(|i| &frames[i])?;
+                Some(if frame.is_loop { frame.params } else { frame.results })
+            };
+
+            // operands consumed and produced, before any change to the frames
+            let (pops, pushes) = match instr.opcode {
+                0x00 | 0x01 | 0x05 | 0x0B => (0, 0),
+                0x02 | 0x03 => (block_arity(instr)?.0, 0),
+                0x04 => (block_arity(instr)?.0 + 1, 0),
+                0x0C => (label_arity(first_u32(instr)?)?, 0),
+                0x0D => {
+                    let arity = label_arity(first_u32(instr)?)?;
+                    (arity + 1, arity)
+                }
+                0x0E => match instr.immediates.first() {
+                    Some(Immediate::BrTable { default, .. }) => (label_arity(*default)? + 1, 0),
+                    _ => return None,
+                },
+                0x0F => (results, 0),
+                0x10 => type_arity(*func_types.get(first_u32(instr)? as usize)?)?,
+                0x11 => {
+                    let (params, call_results) = type_arity(first_u32(instr)?)?;
+                    (params + 1, call_results)
+                }
+                _ => Self::stack_effect(instr)?,
+            };
+            if !pop(&frames, &mut height, pops) {
+                return Some((offset, format!("pops an empty stack at {}", instr.mnemonic())));
+            }
+            height += pushes;
+
+            match instr.opcode {
+                // unreachable, br, br_table, return
+                0x00 | 0x0C | 0x0E | 0x0F => {
+                    let frame = frames.last_mut()?;
+                    height = frame.base;
+                    frame.unreachable = true;
+                }
+                0x02..=0x04 => {
+                    let (params, block_results) = block_arity(instr)?;
+                    frames.push(Frame {
+                        base: height,
+                        params,
+                        results: block_results,
+                        is_loop: instr.opcode == 0x03,
+                        unreachable: false,
+                    });
+                    height += params;
+                }
+                0x05 => {
+                    let frame = frames.last_mut()?;
+                    if !frame.unreachable && height != frame.base + frame.results {
+                        return Some((offset, format!("leaves {} values at else, expected {}", height - frame.base, frame.results)));
+                    }
+                    height = frame.base + frame.params;
+                    frame.unreachable = false;
+                }
+                0x0B => {
+                    let frame = frames.pop()?;
+                    if !frame.unreachable && height != frame.base + frame.results {
+                        return Some((offset, format!("leaves {} values at end, expected {}", height - frame.base, frame.results)));
+                    }
+                    if frames.is_empty() {
+                        return None;
+                    }
+                    height = frame.base + frame.results;
+                }
+                _ => {}
+            }
+        }
+
+        None
+    }
+
+    /// (pops, pushes) for instructions without control flow or type-dependent
+    /// arity, or `None` if unknown.
+    fn stack_effect(instr: &Instruction) -> Option<(usize, usize)> {
+        Some(match instr.opcode {
+            0x1A => (1, 0),                          // drop
+            0x1B | 0x1C => (3, 1),                   // select
+            0x20 | 0x23 => (0, 1),                   // local.get, global.get
+            0x21 | 0x24 => (1, 0),                   // local.set, global.set
+            0x22 | 0x25 => (1, 1),                   // local.tee, table.get
+            0x26 => (2, 0),                          // table.set
+            0x28..=0x35 => (1, 1),                   // loads
+            0x36..=0x3E => (2, 0),                   // stores
+            0x3F => (0, 1),                          // memory.size
+            0x40 => (1, 1),                          // memory.grow
+            0x41..=0x44 => (0, 1),                   // consts
+            0x45 | 0x50 => (1, 1),                   // eqz
+            0x46..=0x4F | 0x51..=0x66 => (2, 1),     // comparisons
+            0x67..=0x69 | 0x79..=0x7B => (1, 1),     // clz, ctz, popcnt
+            0x6A..=0x78 | 0x7C..=0x8A => (2, 1),     // integer binary ops
+            0x8B..=0x91 | 0x99..=0x9F => (1, 1),     // float unary ops
+            0x92..=0x98 | 0xA0..=0xA6 => (2, 1),     // float binary ops
+            0xA7..=0xC4 => (1, 1),                   // conversions, sign extension
+            0xD0 | 0xD2 => (0, 1),                   // ref.null, ref.func
+            0xD1 => (1, 1),                          // ref.is_null
+            0xFC => match instr.immediates.first() {
+                Some(Immediate::U32(0..=7)) => (1, 1),          // saturating truncation
+                Some(Immediate::U32(9 | 13)) => (0, 0),         // data.drop, elem.drop
+                Some(Immediate::U32(15)) => (2, 1),             // table.grow
+                Some(Immediate::U32(16)) => (0, 1),             // table.size
+                Some(Immediate::U32(8 | 10..=12 | 14 | 17)) => (3, 0),
+                _ => return None,
+            },
+            _ => return None,
+        })
+    }
+
+    /// Signature matches in initialized memory; `instruction_offset` is the
+    /// offset within the segment.
+    fn detect_data_payloads(&self, module: &WasmModule) -> Vec<SuspiciousPattern> {
+        let mut findings = Vec::new();
+        for (segment_idx, segment) in module.data_segments.iter().enumerate() {
+            for (name, signature, risk_level) in DATA_SIGNATURES {
+                if let Some(offset) = segment.bytes.windows(signature.len()).position(|w| w == *signature) {
+                    findings.push(SuspiciousPattern {
+                        pattern_name: name.to_string(),
+                        function_index: 0,
+                        instruction_offset: offset as u32,
+                        description: format!("Data segment {} contains {}",
+                            segment_idx, String::from_utf8_lossy(signature).escape_debug()),
+                        risk_level: risk_level.clone(),
+                        occurrences: 1,
+                        offsets: Vec::new(),
+                    });
+                }
+            }
+        }
+        findings
+    }
+
+    /// Data segments, and the code section as a whole, whose byte entropy
+    /// suggests compressed or encrypted content decoded at runtime.
+    fn detect_high_entropy(&self, module: &WasmModule) -> Vec<MemoryPattern> {
+        let mut patterns: Vec<MemoryPattern> = module.data_segments.iter().enumerate()
+            .filter_map(|(segment_idx, segment)| {
+                let entropy = Self::shannon_entropy(&segment.bytes);
+                (entropy > HIGH_ENTROPY_BITS).then(|| MemoryPattern {
+                    pattern_type: "HighEntropy".to_string(),
+                    locations: vec![segment_idx as u32],
+                    risk_level: RiskLevel::Warning,
+                    description: format!("Data segment {} ({}) has entropy {:.2} bits/byte",
+                        segment_idx, Self::approximate_size(segment.bytes.len()), entropy),
+                    code_locations: Vec::new(),
+                })
+            })
+            .collect();
+
+        let code: Vec<u8> = module.code.iter().flat_map(|code| code.body.iter().copied()).collect();
+        let entropy = Self::shannon_entropy(&code);
+        if entropy > HIGH_ENTROPY_BITS {
+            patterns.push(MemoryPattern {
+                pattern_type: "HighEntropy".to_string(),
+                locations: (0..module.code.len() as u32).collect(),
+                risk_level: RiskLevel::Warning,
+                description: format!("Function bodies ({}) have entropy {:.2} bits/byte",
+                    Self::approximate_size(code.len()), entropy),
+                code_locations: Vec::new(),
+            });
+        }
 
         patterns
     }
 
+    fn shannon_entropy(bytes: &[u8]) -> f64 {
+        if bytes.is_empty() {
+            return 0.0;
+        }
+        let mut counts = [0usize; 256];
+        for &byte in bytes {
+            counts[byte as usize] += 1;
+        }
+        let len = bytes.len() as f64;
+        counts.iter()
+            .filter(|&&count| count > 0)
+            .map(|&count| {
+                let p = count as f64 / len;
+                -p * p.log2()
+            })
+            .sum()
+    }
+
+    fn approximate_size(len: usize) -> String {
+        if len >= 1024 * 1024 {
+            format!("~{} MiB", len / (1024 * 1024))
+        } else if len >= 1024 {
+            format!("~{} KiB", len / 1024)
+        } else {
+            format!("{} bytes", len)
+        }
+    }
+
     fn contains_memory_grow(&self, bytecode: &[u8]) -> bool {
         bytecode.contains(&0x40) // memory.grow opcode
     }
 
-    fn detect_unaligned_access(&self, module: &WasmModule) -> Vec<MemoryPattern> {
+    fn memory_grow_sites(function_index: u32, bytecode: &[u8]) -> Vec<CodeLocation> {
+        decoder::decode_function_body(bytecode).unwrap_or_default().iter()
+            .filter(|instr| instr.opcode == 0x40)
+            .map(|instr| CodeLocation { function_index, offset: instr.offset as u32 })
+            .collect()
+    }
+
+    fn detect_unaligned_access(&self, code: &[CodeSection], first_index: u32) -> Vec<MemoryPattern> {
         let mut patterns = Vec::new();
 
-        for (func_idx, code_section) in module.code.iter().enumerate() {
+        for (func_idx, code_section) in (first_index..).zip(code) {
             let bytecode = &code_section.body;
             let mut i = 0;
 
@@ -88,6 +665,7 @@ impl SecurityAnalyzer {
                                     locations: vec![i as u32],
                                     risk_level: RiskLevel::Warning,
                                     description: format!("Potentially unaligned memory access in function {}", func_idx),
+                                    code_locations: vec![CodeLocation { function_index: func_idx, offset: i as u32 }],
                                 });
                             }
                         }
@@ -101,25 +679,27 @@ impl SecurityAnalyzer {
         patterns
     }
 
-    fn detect_buffer_overflow_patterns(&self, module: &WasmModule) -> Vec<MemoryPattern> {
+    fn detect_buffer_overflow_patterns(&self, code: &[CodeSection], first_index: u32) -> Vec<MemoryPattern> {
         let mut patterns = Vec::new();
 
-        for (func_idx, code_section) in module.code.iter().enumerate() {
+        for (func_idx, code_section) in (first_index..).zip(code) {
             let risk_score = self.analyze_bounds_checking(&code_section.body);
             
             if risk_score > 0.7 {
                 patterns.push(MemoryPattern {
                     pattern_type: "BufferOverflow".to_string(),
-                    locations: vec![func_idx as u32],
+                    locations: vec![func_idx],
                     risk_level: RiskLevel::Severe,
                     description: "Function shows patterns consistent with buffer overflow vulnerabilities".to_string(),
+                    code_locations: Vec::new(),
                 });
             } else if risk_score > 0.4 {
                 patterns.push(MemoryPattern {
                     pattern_type: "PotentialBufferOverflow".to_string(),
-                    locations: vec![func_idx as u32],
+                    locations: vec![func_idx],
                     risk_level: RiskLevel::Warning,
                     description: "Function may have insufficient bounds checking".to_string(),
+                    code_locations: Vec::new(),
                 });
             }
         }
@@ -164,24 +744,27 @@ impl SecurityAnalyzer {
     }
 
     fn analyze_syscall_functions(&self, module: &WasmModule) -> Vec<SyscallFunction> {
-        let mut syscalls = Vec::new();
-        let syscall_names = self.pattern_matcher.detect_syscall_patterns(module);
-
-        for (idx, name) in syscall_names.iter().enumerate() {
-            let risk_level = self.assess_syscall_risk(name);
-            let usage_count = self.count_syscall_usage(module, name);
-
-            syscalls.push(SyscallFunction {
-                name: name.clone(),
-                import_index: idx as u32,
-                usage_count,
-                risk_level,
-            });
+        let mut syscalls = self.syscall_exports(module, 0);
+        for syscall in &mut syscalls {
+            syscall.usage_count = self.count_syscall_usage(module, &syscall.name);
         }
-
         syscalls
     }
 
+    /// Syscall-like exports of `chunk`, numbered from `first_index`. Usage
+    /// counts need the code section and are left at 0.
+    pub(super) fn syscall_exports(&self, chunk: &WasmModule, first_index: u32) -> Vec<SyscallFunction> {
+        self.pattern_matcher.detect_syscall_patterns(chunk).into_iter()
+            .zip(first_index..)
+            .map(|(name, import_index)| SyscallFunction {
+                risk_level: self.assess_syscall_risk(&name),
+                name,
+                import_index,
+                usage_count: 0,
+            })
+            .collect()
+    }
+
     fn assess_syscall_risk(&self, name: &str) -> RiskLevel {
         let high_risk = ["exec", "fork", "system", "open", "write", "socket", "connect"];
         let medium_risk = ["read", "malloc", "free", "mmap", "signal"];
@@ -213,54 +796,185 @@ impl SecurityAnalyzer {
     }
 
     fn count_function_calls(&self, bytecode: &[u8], target_function: u32) -> u32 {
-        let mut count = 0;
-        let mut i = 0;
+        // Decode so immediates (call_indirect's type index, constants) are
+        // never mistaken for call opcodes
+        decoder::decode_function_body(bytecode).unwrap_or_default().iter()
+            .filter(|instr| instr.opcode == 0x10)
+            .filter(|instr| matches!(instr.immediates.first(), Some(Immediate::U32(callee)) if *callee == target_function))
+            .count() as u32
+    }
 
-        while i < bytecode.len() {
-            if bytecode[i] == 0x10 { // call instruction
-                if i + 1 < bytecode.len() {
-                    // In real implementation, would decode LEB128 function index
-                    if bytecode[i + 1] == target_function as u8 {
-                        count += 1;
-                    }
-                }
-            }
-            i += 1;
-        }
+    fn estimate_resource_requirements(&self, module: &WasmModule) -> ResourceRequirements {
+        let mut requirements = self.estimate_code_requirements(&module.code);
+        requirements.estimated_memory = Self::estimate_memory(module.memory.as_ref());
+        requirements
+    }
 
-        count
+    /// Bytes of linear memory a module declaring `memory` may use.
+    pub(super) fn estimate_memory(memory: Option<&MemoryType>) -> u64 {
+        match memory {
+            Some(memory) => (memory.min as u64).max(memory.max.unwrap_or(0) as u64) * 65536, // Pages to bytes
+            None => 0,
+        }
     }
 
-    fn estimate_resource_requirements(&self, module: &WasmModule) -> ResourceRequirements {
-        let mut estimated_memory = 0u64;
+    /// CPU and stack estimates for `code`; `estimated_memory` is left at 0.
+    fn estimate_code_requirements(&self, code: &[CodeSection]) -> ResourceRequirements {
         let mut estimated_cpu_cycles = 0u64;
         let mut max_stack_depth = 0u32;
         let mut max_call_depth = 0u32;
 
-        // Estimate memory requirements
-        if let Some(memory) = &module.memory {
-            estimated_memory = (memory.min as u64) * 65536; // Pages to bytes
-            if let Some(max) = memory.max {
-                estimated_memory = estimated_memory.max((max as u64) * 65536);
-            }
-        }
-
         // Estimate CPU cycles and stack usage
-        for code_section in &module.code {
+        for code_section in code {
             let (cycles, stack_depth, call_depth) = self.analyze_function_complexity(&code_section.body);
             estimated_cpu_cycles += cycles;
+            if let Ok(instructions) = decoder::decode_function_body(&code_section.body) {
+                estimated_cpu_cycles = estimated_cpu_cycles.saturating_add(Self::counted_loop_cycles(&instructions));
+            }
             max_stack_depth = max_stack_depth.max(stack_depth);
             max_call_depth = max_call_depth.max(call_depth);
         }
 
         ResourceRequirements {
-            estimated_memory,
+            estimated_memory: 0,
             estimated_cpu_cycles,
             max_stack_depth,
             max_call_depth,
         }
     }
 
+    /// Estimated cycles per instruction.
+    fn instruction_cycles(opcode: u8) -> u64 {
+        match opcode {
+            // Control flow
+            0x02..=0x05 => 2,
+            // Simple operations
+            0x01..=0x11 => 1,
+            // Memory operations
+            0x28..=0x3E => 3,
+            // Arithmetic
+            0x6A..=0xC4 => 1,
+            _ => 1,
+        }
+    }
+
+    /// Cycles spent in the repeat iterations of loops with a constant trip
+    /// count, on top of the single pass `analyze_function_complexity` counts.
+    /// Nested counted loops multiply through.
+    fn counted_loop_cycles(instructions: &[Instruction]) -> u64 {
+        let mut constants: LocalConstants = HashMap::new();
+        // Open blocks: (instruction index, constants at a loop's entry)
+        let mut open: Vec<(usize, Option<LocalConstants>)> = Vec::new();
+        // Extra cycles of closed counted loops, by start index
+        let mut extra: Vec<(usize, u64)> = Vec::new();
+
+        for (i, instruction) in instructions.iter().enumerate() {
+            let index = match instruction.immediates.first() {
+                Some(Immediate::U32(index)) => *index,
+                _ => 0,
+            };
+            match instruction.opcode {
+                0x02 | 0x04 => open.push((i, None)),
+                0x03 => open.push((i, Some(constants.clone()))),
+                0x05 => {
+                    let depth = open.len();
+                    constants.retain(|_, (_, set_at)| *set_at < depth);
+                }
+                0x0B => {
+                    let (start, entry) = match open.pop() {
+                        Some(block) => block,
+                        None => continue,
+                    };
+                    let depth = open.len();
+                    constants.retain(|_, (_, set_at)| *set_at <= depth);
+                    let trip = match entry {
+                        Some(entry) => Self::constant_trip_count(&instructions[start..=i], &entry),
+                        None => None,
+                    };
+                    if let Some(trip) = trip {
+                        let nested: u64 = extra.iter()
+                            .filter(|(inner, _)| *inner > start)
+                            .map(|(_, cycles)| *cycles)
+                            .sum();
+                        let body: u64 = instructions[start + 1..i].iter()
+                            .map(|inner| Self::instruction_cycles(inner.opcode))
+                            .sum::<u64>() + nested;
+                        extra.retain(|(inner, _)| *inner < start);
+                        extra.push((start, body.saturating_mul(trip - 1).saturating_add(nested)));
+                    }
+                }
+                0x21 | 0x22 => {
+                    let value = match i.checked_sub(1).map(|prev| &instructions[prev]) {
+                        Some(Instruction { opcode: 0x41, immediates, .. }) => match immediates.first() {
+                            Some(Immediate::I32(value)) => Some(*value),
+                            _ => None,
+                        },
+                        _ => None,
+                    };
+                    match value {
+                        Some(value) => { constants.insert(index, (value, open.len())); }
+                        None => { constants.remove(&index); }
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        extra.iter().map(|(_, cycles)| *cycles).sum()
+    }
+
+    /// Trip count of a loop (from `loop` through its `end`) whose counter is
+    /// initialised to a constant, stepped by a constant and compared against a
+    /// constant bound at the back-edge:
+    /// `local.get i; i32.const step; i32.add; local.tee i; i32.const bound; i32.lt_s; br_if 0`.
+    fn constant_trip_count(body: &[Instruction], entry: &LocalConstants) -> Option<u64> {
+        if body.len() < 9 {
+            return None;
+        }
+        let tail = &body[body.len() - 8..body.len() - 1];
+        let immediate = |instruction: &Instruction| match instruction.immediates.first() {
+            Some(Immediate::U32(value)) => Some(*value as i64),
+            Some(Immediate::I32(value)) => Some(*value as i64),
+            _ => None,
+        };
+        let opcodes: Vec<u8> = tail.iter().map(|instruction| instruction.opcode).collect();
+        let comparison = opcodes[5];
+        if opcodes[..5] != [0x20, 0x41, 0x6A, 0x22, 0x41]
+            || !matches!(comparison, 0x47..=0x49) // ne, lt_s, lt_u
+            || opcodes[6] != 0x0D
+            || immediate(&tail[6]) != Some(0)
+        {
+            return None;
+        }
+
+        let counter = immediate(&tail[0])?;
+        if immediate(&tail[3]) != Some(counter) {
+            return None;
+        }
+        // The counter must only be written by the step
+        let rewritten = body[1..body.len() - 5].iter()
+            .any(|instruction| matches!(instruction.opcode, 0x21 | 0x22) && immediate(instruction) == Some(counter));
+        if rewritten {
+            return None;
+        }
+
+        let init = entry.get(&(counter as u32))?.0 as i64;
+        let step = immediate(&tail[1])?;
+        let bound = immediate(&tail[4])?;
+        if step <= 0 {
+            return None;
+        }
+        if bound <= init {
+            // The body runs once before the first comparison
+            return if comparison == 0x47 { None } else { Some(1) };
+        }
+        let distance = bound - init;
+        if comparison == 0x47 && distance % step != 0 {
+            return None;
+        }
+        Some(((distance + step - 1) / step) as u64)
+    }
+
     fn analyze_function_complexity(&self, bytecode: &[u8]) -> (u64, u32, u32) {
         let mut cycles = 0u64;
         let mut stack_depth = 0u32;
@@ -268,19 +982,12 @@ impl SecurityAnalyzer {
         let mut call_depth = 0u32;
         let mut max_call_depth = 0u32;
 
-        for &opcode in bytecode {
-            // Estimate cycles per instruction
-            cycles += match opcode {
-                // Simple operations
-                0x01..=0x11 => 1,
-                // Memory operations
-                0x28..=0x3E => 3,
-                // Control flow
-                0x02..=0x05 => 2,
-                // Arithmetic
-                0x6A..=0xC4 => 1,
-                _ => 1,
-            };
+        let mut i = 0;
+        while i < bytecode.len() {
+            let opcode = bytecode[i];
+            i += 1;
+
+            cycles += Self::instruction_cycles(opcode);
 
             // Track stack depth
             match opcode {
@@ -296,10 +1003,17 @@ impl SecurityAnalyzer {
                 0x24 => stack_depth = stack_depth.saturating_sub(1), // global.set
                 0x36..=0x3E => stack_depth = stack_depth.saturating_sub(2), // store operations
                 
-                // Function calls
+                // Function calls; skip the index operands so they aren't
+                // counted as opcodes
                 0x10 | 0x11 => {
                     call_depth += 1;
                     max_call_depth = max_call_depth.max(call_depth);
+                    let operands = if opcode == 0x11 { 2 } else { 1 };
+                    for _ in 0..operands {
+                        if let Some((_, len)) = decode_leb128_u32_at(bytecode, i) {
+                            i += len;
+                        }
+                    }
                 }
                 
                 // Return
@@ -344,6 +1058,7 @@ mod tests {
                 locals: vec![],
                 body: vec![0x40, 0x00], // memory.grow
             }],
+            ..Default::default()
         };
 
         let patterns = analyzer.analyze_memory_patterns(&module);
@@ -351,6 +1066,205 @@ mod tests {
         assert_eq!(patterns[0].pattern_type, "MemoryGrowth");
     }
 
+    #[test]
+    fn test_unaligned_findings_grouped() {
+        // 40 x (i32.const 0, i32.load align=0 offset=0, drop)
+        let body: Vec<u8> = [0x41, 0x00, 0x28, 0x00, 0x00, 0x1A].repeat(40);
+        let module = WasmModule {
+            code: vec![CodeSection { locals: vec![], body }],
+            ..Default::default()
+        };
+
+        let assessment = SecurityAnalyzer::new().analyze(&module).unwrap();
+        let unaligned: Vec<_> = assessment.suspicious_patterns.iter()
+            .filter(|p| p.pattern_name == "UnalignedAccess")
+            .collect();
+        assert_eq!(unaligned.len(), 1);
+        assert_eq!(unaligned[0].occurrences, 40);
+        assert_eq!(unaligned[0].instruction_offset, 2);
+        assert_eq!(unaligned[0].offsets.len(), MAX_GROUPED_OFFSETS);
+        assert_eq!(unaligned[0].offsets[1], 8);
+
+        let ungrouped = SecurityAnalyzer::new().with_finding_grouping(false).analyze(&module).unwrap();
+        assert_eq!(ungrouped.suspicious_patterns.iter()
+            .filter(|p| p.pattern_name == "UnalignedAccess")
+            .count(), 40);
+    }
+
+    #[test]
+    fn test_multibyte_call_index() {
+        let analyzer = SecurityAnalyzer::new();
+        // call 200 (0xC8 0x01), call 16, call 72, call_indirect 200 0
+        let body = vec![0x10, 0xC8, 0x01, 0x10, 0x10, 0x10, 0x48, 0x11, 0xC8, 0x01, 0x00, 0x0B];
+
+        assert_eq!(decode_leb128_u32_at(&body, 1), Some((200, 2)));
+        assert_eq!(analyzer.count_function_calls(&body, 200), 1);
+        // An operand byte equal to 0x10 is not another call
+        assert_eq!(analyzer.count_function_calls(&body, 16), 1);
+        assert_eq!(analyzer.count_function_calls(&body, 72), 1);
+        // 456 truncated to a byte is 0xC8
+        assert_eq!(analyzer.count_function_calls(&body, 456), 0);
+
+        // call_indirect type 16 table 0, then i32.const 16; drop
+        let indirect = vec![0x11, 0x10, 0x00, 0x41, 0x10, 0x1A, 0x0B];
+        assert_eq!(analyzer.count_function_calls(&indirect, 0), 0);
+        assert_eq!(analyzer.count_function_calls(&indirect, 16), 0);
+
+        let (cycles, _, call_depth) = analyzer.analyze_function_complexity(&body);
+        assert_eq!(cycles, 5);
+        assert_eq!(call_depth, 4);
+    }
+
+    #[test]
+    fn test_unlimited_imported_memory() {
+        use crate::parser::Import;
+
+        let import = |max| Import {
+            module: "env".to_string(),
+            name: "memory".to_string(),
+            kind: ImportKind::Memory(MemoryType { min: 1, max }),
+        };
+        let module = WasmModule { imports: vec![import(None)], ..Default::default() };
+
+        let assessment = SecurityAnalyzer::new().analyze(&module).unwrap();
+        let finding = assessment.suspicious_patterns.iter()
+            .find(|p| p.pattern_name == "UnlimitedImportedMemory")
+            .expect("finding for unbounded import");
+        assert_eq!(finding.risk_level, RiskLevel::Warning);
+        assert!(finding.description.contains("env.memory"));
+
+        let analysis = crate::static_analysis::StaticAnalyzer::new().analyze(&module).unwrap();
+        assert_eq!(analysis.risk_score.memory_risk, RiskLevel::Warning);
+
+        let bounded = WasmModule { imports: vec![import(Some(4))], ..Default::default() };
+        assert!(SecurityAnalyzer::new().analyze(&bounded).unwrap().suspicious_patterns.is_empty());
+    }
+
+    #[test]
+    fn test_mutual_recursion_cycle() {
+        let body = |callee: u8| CodeSection { locals: vec![], body: vec![0x10, callee, 0x0B] };
+        let module = WasmModule {
+            // 0 -> 1 -> 0, and 2 -> 0 outside the cycle
+            code: vec![body(1), body(0), body(0)],
+            ..Default::default()
+        };
+
+        let assessment = SecurityAnalyzer::new().analyze(&module).unwrap();
+        let cycles: Vec<_> = assessment.suspicious_patterns.iter()
+            .filter(|p| p.pattern_name == "Recursion")
+            .collect();
+        assert_eq!(cycles.len(), 1);
+        assert_eq!(cycles[0].function_index, 0);
+        assert_eq!(cycles[0].risk_level, RiskLevel::Warning);
+        assert!(cycles[0].description.contains("functions 0, 1"));
+
+        let direct = WasmModule { code: vec![body(0)], ..Default::default() };
+        let assessment = SecurityAnalyzer::new().analyze(&direct).unwrap();
+        assert!(assessment.suspicious_patterns.iter()
+            .any(|p| p.pattern_name == "Recursion" && p.description == "Function 0 calls itself"));
+    }
+
+    #[test]
+    fn test_stack_imbalance_drop_on_empty() {
+        use crate::parser::{Function, FunctionType};
+
+        let module = |body: Vec<u8>| WasmModule {
+            types: vec![FunctionType { params: vec![], results: vec![] }],
+            functions: vec![Function { type_idx: 0 }],
+            code: vec![CodeSection { locals: vec![], body }],
+            ..Default::default()
+        };
+        let imbalance = |body| SecurityAnalyzer::new().analyze(&module(body)).unwrap()
+            .suspicious_patterns.into_iter()
+            .find(|p| p.pattern_name == "StackImbalance");
+
+        // drop
+        let finding = imbalance(vec![0x1A, 0x0B]).expect("underflow finding");
+        assert_eq!(finding.function_index, 0);
+        assert_eq!(finding.instruction_offset, 0);
+        assert_eq!(finding.risk_level, RiskLevel::Warning);
+
+        // i32.const 1, i32.const 2, drop: one value left over for a () -> () function
+        let finding = imbalance(vec![0x41, 0x01, 0x41, 0x02, 0x1A, 0x0B]).expect("arity finding");
+        assert_eq!(finding.instruction_offset, 5);
+
+        // balanced, and drop after unreachable is valid
+        assert!(imbalance(vec![0x41, 0x01, 0x1A, 0x0B]).is_none());
+        assert!(imbalance(vec![0x00, 0x1A, 0x0B]).is_none());
+        // block (result i32) i32.const 1 end drop
+        assert!(imbalance(vec![0x02, 0x7F, 0x41, 0x01, 0x0B, 0x1A, 0x0B]).is_none());
+    }
+
+    #[test]
+    fn test_exported_mutable_global() {
+        use crate::parser::{Export, Global, ValueType};
+
+        let module = |mutable| WasmModule {
+            globals: vec![
+                Global { value_type: ValueType::I32, mutable: false, init_expr: vec![0x41, 0x00, 0x0B] },
+                Global { value_type: ValueType::I32, mutable, init_expr: vec![0x41, 0x00, 0x0B] },
+            ],
+            exports: vec![Export { name: "counter".to_string(), kind: ExportKind::Global, index: 1 }],
+            ..Default::default()
+        };
+
+        let findings = SecurityAnalyzer::new().analyze(&module(true)).unwrap().suspicious_patterns;
+        let finding = findings.iter().find(|p| p.pattern_name == "ExportedMutableGlobal").expect("mutable export finding");
+        assert_eq!(finding.risk_level, RiskLevel::Warning);
+        assert!(finding.description.contains("global 1"));
+
+        let findings = SecurityAnalyzer::new().analyze(&module(false)).unwrap().suspicious_patterns;
+        assert!(!findings.iter().any(|p| p.pattern_name == "ExportedMutableGlobal"));
+    }
+
+    #[test]
+    fn test_data_segment_payloads() {
+        use crate::parser::DataSegment;
+
+        let segment = |bytes: &[u8]| DataSegment { memory_index: 0, offset_expr: vec![0x41, 0x00, 0x0B], bytes: bytes.to_vec() };
+        let module = WasmModule {
+            data_segments: vec![segment(b"hello world"), segment(b"\0\0exec /bin/sh; curl https://x.io")],
+            ..Default::default()
+        };
+
+        let findings = SecurityAnalyzer::new().analyze(&module).unwrap().suspicious_patterns;
+        let shell = findings.iter().find(|p| p.pattern_name == "ShellcodeSignature").expect("shellcode finding");
+        assert_eq!(shell.risk_level, RiskLevel::Severe);
+        assert_eq!(shell.instruction_offset, 7);
+        assert!(shell.description.starts_with("Data segment 1"));
+        assert!(findings.iter().any(|p| p.pattern_name == "EmbeddedUrl"));
+        assert_eq!(findings.len(), 2);
+    }
+
+    #[test]
+    fn test_high_entropy_data_segment() {
+        use crate::parser::DataSegment;
+
+        // xorshift keeps the "random" bytes deterministic
+        let mut state = 0x2545_F491u32;
+        let random: Vec<u8> = (0..4096).map(|_| {
+            state ^= state << 13;
+            state ^= state >> 17;
+            state ^= state << 5;
+            state as u8
+        }).collect();
+        let repetitive = b"ABCD".repeat(1024);
+
+        let segment = |bytes: Vec<u8>| DataSegment { memory_index: 0, offset_expr: vec![0x41, 0x00, 0x0B], bytes };
+        let module = WasmModule {
+            data_segments: vec![segment(repetitive), segment(random)],
+            ..Default::default()
+        };
+
+        let findings: Vec<_> = SecurityAnalyzer::new().analyze(&module).unwrap().memory_patterns.into_iter()
+            .filter(|p| p.pattern_type == "HighEntropy")
+            .collect();
+        assert_eq!(findings.len(), 1);
+        assert_eq!(findings[0].locations, vec![1]);
+        assert_eq!(findings[0].risk_level, RiskLevel::Warning);
+        assert!(findings[0].description.starts_with("Data segment 1 (~4 KiB)"));
+    }
+
     #[test]
     fn test_resource_estimation() {
         let analyzer = SecurityAnalyzer::new();
@@ -363,6 +1277,7 @@ mod tests {
                 locals: vec![],
                 body: vec![0x41, 0x01, 0x10, 0x00], // i32.const 1, call 0
             }],
+            ..Default::default()
         };
 
         let requirements = analyzer.estimate_resource_requirements(&module);
@@ -371,6 +1286,39 @@ mod tests {
         assert!(requirements.max_call_depth > 0);
     }
 
+    #[test]
+    fn test_counted_loop_scales_estimate() {
+        let analyzer = SecurityAnalyzer::new();
+        let loop_module = |bound: &[u8]| {
+            let mut body = vec![
+                0x41, 0x00, 0x21, 0x00, // local0 = 0
+                0x03, 0x40, // loop
+                0x20, 0x01, 0x41, 0x01, 0x6A, 0x21, 0x01, // local1 += 1
+                0x20, 0x00, 0x41, 0x01, 0x6A, 0x22, 0x00, // local0 += 1 (tee)
+            ];
+            body.extend_from_slice(bound);
+            body.extend_from_slice(&[0x48, 0x0D, 0x00, 0x0B, 0x0B]); // lt_s; br_if 0; end
+            WasmModule {
+                code: vec![CodeSection { locals: vec![], body }],
+                ..Default::default()
+            }
+        };
+
+        let bounded = analyzer.estimate_resource_requirements(&loop_module(&[0x41, 0xE4, 0x00])); // i32.const 100
+        let unbounded = analyzer.estimate_resource_requirements(&loop_module(&[0x20, 0x02])); // local.get 2
+        let body = 11; // instructions per iteration, one cycle each
+        assert!(bounded.estimated_cpu_cycles >= unbounded.estimated_cpu_cycles + 99 * body);
+
+        let instructions = decoder::decode_function_body(&loop_module(&[0x41, 0xE4, 0x00]).code[0].body).unwrap();
+        assert_eq!(SecurityAnalyzer::counted_loop_cycles(&instructions), 99 * body);
+        let instructions = decoder::decode_function_body(&loop_module(&[0x20, 0x02]).code[0].body).unwrap();
+        assert_eq!(SecurityAnalyzer::counted_loop_cycles(&instructions), 0);
+
+        // Structured control flow costs more than a simple instruction
+        assert_eq!(SecurityAnalyzer::instruction_cycles(0x03), 2);
+        assert_eq!(SecurityAnalyzer::instruction_cycles(0x0D), 1);
+    }
+
     fn create_test_module() -> WasmModule {
         WasmModule {
             types: vec![],
@@ -381,6 +1329,7 @@ mod tests {
                 locals: vec![],
                 body: vec![0x41, 0x01], // i32.const 1
             }],
+            ..Default::default()
         }
     }
 }
\ No newline at end of file
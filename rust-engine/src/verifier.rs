@@ -0,0 +1,56 @@
+use crate::error::Result;
+
+/// Checks a module's provenance before it is parsed or instantiated.
+pub trait ModuleVerifier {
+    fn verify(&self, bytes: &[u8], signature: &[u8]) -> Result<()>;
+}
+
+#[cfg(feature = "ed25519")]
+pub use self::ed25519::Ed25519Verifier;
+
+#[cfg(feature = "ed25519")]
+mod ed25519 {
+    use super::ModuleVerifier;
+    use crate::error::{WasmError, Result};
+    use ed25519_dalek::{Signature, Verifier, VerifyingKey};
+
+    /// Verifies detached ed25519 signatures over the raw module bytes.
+    #[derive(Debug, Clone)]
+    pub struct Ed25519Verifier {
+        key: VerifyingKey,
+    }
+
+    impl Ed25519Verifier {
+        pub fn new(public_key: &[u8; 32]) -> Result<Self> {
+            let key = VerifyingKey::from_bytes(public_key)
+                .map_err(|e| WasmError::Runtime(format!("Invalid ed25519 public key: {}", e)))?;
+            Ok(Self { key })
+        }
+    }
+
+    impl ModuleVerifier for Ed25519Verifier {
+        fn verify(&self, bytes: &[u8], signature: &[u8]) -> Result<()> {
+            let signature = Signature::from_slice(signature)
+                .map_err(|e| WasmError::Runtime(format!("Malformed ed25519 signature: {}", e)))?;
+            self.key.verify(bytes, &signature)
+                .map_err(|e| WasmError::Runtime(format!("ed25519 verification failed: {}", e)))
+        }
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+        use ed25519_dalek::{Signer, SigningKey};
+
+        #[test]
+        fn test_ed25519_accept_and_reject() {
+            let signing_key = SigningKey::from_bytes(&[7u8; 32]);
+            let verifier = Ed25519Verifier::new(signing_key.verifying_key().as_bytes()).unwrap();
+            let module = b"\0asm\x01\0\0\0";
+            let signature = signing_key.sign(module).to_bytes();
+
+            assert!(verifier.verify(module, &signature).is_ok());
+            assert!(verifier.verify(b"\0asm\x01\0\0\x01", &signature).is_err());
+        }
+    }
+}
@@ -0,0 +1,467 @@
+use crate::error::{WasmError, Result};
+use crate::interpreter::{Interpreter, InterpreterConfig, StepResult, Value};
+use crate::memory::LinearMemory;
+use crate::parser::{ExportKind, ParseLimits, WasmParser};
+#[cfg(feature = "hashing")]
+use crate::registry::ModuleRegistry;
+use crate::sandbox::{ResourceLimits, Sandbox};
+use crate::verifier::ModuleVerifier;
+
+pub use crate::parser::WasmModule;
+
+/// Export names probed by `WasmInstance::health_check`, in order.
+pub const HEALTH_EXPORTS: &[&str] = &["health", "wasm_health"];
+
+/// Instructions a health check may execute before it is considered hung.
+pub const HEALTH_CHECK_BUDGET: u64 = 10_000;
+
+/// Result of a module's `health` export: 0 is healthy, 1 degraded, anything else unhealthy.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HealthStatus {
+    Healthy,
+    Degraded,
+    Unhealthy,
+}
+
+impl From<i32> for HealthStatus {
+    fn from(code: i32) -> Self {
+        match code {
+            0 => HealthStatus::Healthy,
+            1 => HealthStatus::Degraded,
+            _ => HealthStatus::Unhealthy,
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct WasmEngine {
+    parse_limits: ParseLimits,
+}
+
+impl WasmEngine {
+    pub fn new() -> Result<Self> {
+        Ok(Self::default())
+    }
+
+    pub fn with_parse_limits(mut self, limits: ParseLimits) -> Self {
+        self.parse_limits = limits;
+        self
+    }
+
+    pub fn parse_module(&self, bytes: &[u8]) -> Result<WasmModule> {
+        WasmParser::parse_with_limits(bytes, &self.parse_limits)
+    }
+
+    pub fn instantiate_with_sandbox(&self, module: WasmModule, mut sandbox: Sandbox) -> Result<WasmInstance> {
+        sandbox.check_module_limits(&module)?;
+
+        let memory = match &module.memory {
+            Some(mem) => Some(LinearMemory::new(mem.min, mem.max)?),
+            None => None,
+        };
+
+        let config = InterpreterConfig {
+            max_table_size: sandbox.limits().max_table_size,
+            ..Default::default()
+        };
+        Ok(WasmInstance {
+            interpreter: Interpreter::with_config(module, config)?,
+            memory,
+            sandbox,
+            paused: None,
+        })
+    }
+
+    /// Verify the module signature before parsing, then instantiate it.
+    pub fn instantiate_verified(
+        &self,
+        bytes: &[u8],
+        signature: &[u8],
+        verifier: &dyn ModuleVerifier,
+        limits: ResourceLimits,
+    ) -> Result<WasmInstance> {
+        if let Err(e) = verifier.verify(bytes, signature) {
+            log::warn!("Rejected module: {}", e);
+            return Err(WasmError::Runtime("signature verification failed".to_string()));
+        }
+
+        let module = self.parse_module(bytes)?;
+        self.instantiate_with_sandbox(module, Sandbox::new(limits))
+    }
+
+    /// Instantiate only if the module's content hash, computed while
+    /// parsing, passes `registry`.
+    #[cfg(feature = "hashing")]
+    pub fn instantiate_if_allowed(
+        &self,
+        bytes: &[u8],
+        registry: &ModuleRegistry,
+        limits: ResourceLimits,
+    ) -> Result<WasmInstance> {
+        // Parsing hashes the sections as it goes, so the bytes are read once
+        let module = self.parse_module(bytes)?;
+        let hash = module.content_hash().ok_or(WasmError::InvalidModule)?;
+        if let Err(e) = registry.check(hash) {
+            log::warn!("Rejected module: {}", e);
+            return Err(e);
+        }
+
+        self.instantiate_with_sandbox(module, Sandbox::new(limits))
+    }
+}
+
+/// Snapshot of a run paused by `WasmInstance::run_for`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PauseInfo {
+    pub stack: Vec<Value>,
+    /// Instructions executed by the run so far.
+    pub instructions: u64,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum RunState {
+    Paused { info: PauseInfo },
+    Done { result: Vec<Value> },
+}
+
+#[derive(Debug)]
+struct PausedRun {
+    export: String,
+    /// Interpreter instruction count when the run started.
+    started_at: u64,
+}
+
+/// An instantiated module. Globals and tables live in a single interpreter
+/// that persists across invocations.
+#[derive(Debug)]
+pub struct WasmInstance {
+    interpreter: Interpreter,
+    memory: Option<LinearMemory>,
+    sandbox: Sandbox,
+    paused: Option<PausedRun>,
+}
+
+impl WasmInstance {
+    pub fn module(&self) -> &WasmModule {
+        self.interpreter.module()
+    }
+
+    pub fn memory(&self) -> Option<&LinearMemory> {
+        self.memory.as_ref()
+    }
+
+    pub fn memory_mut(&mut self) -> Option<&mut LinearMemory> {
+        self.memory.as_mut()
+    }
+
+    pub fn sandbox(&self) -> &Sandbox {
+        &self.sandbox
+    }
+
+    pub fn sandbox_mut(&mut self) -> &mut Sandbox {
+        &mut self.sandbox
+    }
+
+    fn function_export(&self, name: &str) -> Option<u32> {
+        self.module().exports.iter()
+            .find(|e| e.name == name && matches!(e.kind, ExportKind::Function))
+            .map(|e| e.index)
+    }
+
+    /// Execute one instruction and charge it to the sandbox.
+    fn step(&mut self) -> Result<StepResult> {
+        let result = self.interpreter.step(self.memory.as_mut())?;
+        self.sandbox.consume_instructions(1)?;
+        Ok(result)
+    }
+
+    /// Invoke `export` under its own budget of `instructions`, on top of the
+    /// sandbox's limits. Running out returns `BudgetExhausted` and leaves
+    /// the instance usable. Discards any run paused by `run_for`.
+    pub fn invoke_with_budget(&mut self, export: &str, args: &[Value], instructions: u64) -> Result<Vec<Value>> {
+        let function_index = self.function_export(export)
+            .ok_or_else(|| WasmError::Runtime(format!("Export not found: {}", export)))?;
+        self.run_budgeted(function_index, args, instructions)
+    }
+
+    fn run_budgeted(&mut self, function_index: u32, args: &[Value], budget: u64) -> Result<Vec<Value>> {
+        self.paused = None;
+        self.interpreter.start(function_index, args)?;
+        let started_at = self.interpreter.instruction_count();
+        loop {
+            if self.interpreter.instruction_count() - started_at >= budget {
+                return Err(WasmError::BudgetExhausted(budget));
+            }
+            if let StepResult::Finished(results) = self.step()? {
+                return Ok(results);
+            }
+        }
+    }
+
+    /// Execute up to `steps` instructions of `export`. A paused run of the
+    /// same export resumes where it stopped and ignores `args`; anything else
+    /// starts a fresh call.
+    pub fn run_for(&mut self, export: &str, args: &[Value], steps: u64) -> Result<RunState> {
+        let started_at = match self.paused.take() {
+            Some(run) if run.export == export => run.started_at,
+            _ => {
+                let function_index = self.function_export(export)
+                    .ok_or_else(|| WasmError::Runtime(format!("Export not found: {}", export)))?;
+                self.interpreter.start(function_index, args)?;
+                self.interpreter.instruction_count()
+            }
+        };
+
+        for _ in 0..steps {
+            if let StepResult::Finished(result) = self.step()? {
+                return Ok(RunState::Done { result });
+            }
+        }
+
+        let info = PauseInfo {
+            stack: self.interpreter.stack().to_vec(),
+            instructions: self.interpreter.instruction_count() - started_at,
+        };
+        self.paused = Some(PausedRun { export: export.to_string(), started_at });
+        Ok(RunState::Paused { info })
+    }
+
+    /// Invoke the module's `health` export under `HEALTH_CHECK_BUDGET` instructions.
+    pub fn health_check(&mut self) -> Result<HealthStatus> {
+        let function_index = HEALTH_EXPORTS.iter()
+            .find_map(|name| self.function_export(name))
+            .ok_or_else(|| WasmError::Runtime("Module has no health export".to_string()))?;
+
+        let results = self.run_budgeted(function_index, &[], HEALTH_CHECK_BUDGET).map_err(|e| match e {
+            WasmError::BudgetExhausted(budget) => WasmError::Runtime(format!(
+                "Health check exceeded {} instructions", budget
+            )),
+            other => other,
+        })?;
+
+        match results.as_slice() {
+            [Value::I32(code)] => Ok(HealthStatus::from(*code)),
+            _ => Err(WasmError::TypeMismatch),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Accepts only a signature equal to the trailing 8 module bytes, reversed.
+    struct ReversedTailVerifier;
+
+    impl ModuleVerifier for ReversedTailVerifier {
+        fn verify(&self, bytes: &[u8], signature: &[u8]) -> Result<()> {
+            let expected: Vec<u8> = bytes.iter().rev().take(8).copied().collect();
+            if signature == expected.as_slice() {
+                Ok(())
+            } else {
+                Err(WasmError::Runtime("bad signature".to_string()))
+            }
+        }
+    }
+
+    fn module_with_globals(count: u8) -> Vec<u8> {
+        let mut bytes = vec![
+            0x00, 0x61, 0x73, 0x6d, // magic
+            0x01, 0x00, 0x00, 0x00, // version
+        ];
+
+        let mut section = vec![count];
+        for _ in 0..count {
+            section.extend_from_slice(&[0x7F, 0x00, 0x41, 0x00, 0x0B]); // i32 const = 0
+        }
+        bytes.push(0x06);
+        bytes.push(section.len() as u8);
+        bytes.extend(section);
+        bytes
+    }
+
+    #[test]
+    fn test_globals_over_limit_rejected() {
+        let engine = WasmEngine::new().unwrap();
+        let limits = ResourceLimits::strict();
+        let module = engine.parse_module(&module_with_globals(limits.max_globals as u8 + 1)).unwrap();
+
+        let result = engine.instantiate_with_sandbox(module, Sandbox::new(limits));
+        match result {
+            Err(WasmError::Runtime(msg)) => assert!(msg.contains("globals")),
+            other => panic!("expected global limit error, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_globals_within_limit_instantiate() {
+        let engine = WasmEngine::new().unwrap();
+        let limits = ResourceLimits::strict();
+        let module = engine.parse_module(&module_with_globals(limits.max_globals as u8)).unwrap();
+
+        let instance = engine.instantiate_with_sandbox(module, Sandbox::new(limits)).unwrap();
+        assert_eq!(instance.module().globals.len(), 10);
+        assert!(instance.sandbox().get_violations().is_empty());
+    }
+
+    #[test]
+    fn test_table_over_limit_rejected() {
+        let engine = WasmEngine::new().unwrap();
+        let bytes = [
+            0x00, 0x61, 0x73, 0x6d, // magic
+            0x01, 0x00, 0x00, 0x00, // version
+            0x04, 0x05, 0x01, 0x70, 0x00, 0xC8, 0x01, // table: funcref, min 200
+        ];
+        let module = engine.parse_module(&bytes).unwrap();
+
+        let result = engine.instantiate_with_sandbox(module, Sandbox::new(ResourceLimits::strict()));
+        assert!(matches!(result, Err(WasmError::Runtime(_))));
+    }
+
+    fn module_with_health(body: &[u8]) -> Vec<u8> {
+        let mut bytes = vec![
+            0x00, 0x61, 0x73, 0x6d, // magic
+            0x01, 0x00, 0x00, 0x00, // version
+            0x01, 0x05, 0x01, 0x60, 0x00, 0x01, 0x7F, // type: () -> i32
+            0x03, 0x02, 0x01, 0x00, // function 0 has type 0
+            0x07, 0x0A, 0x01, 0x06, b'h', b'e', b'a', b'l', b't', b'h', 0x00, 0x00, // export "health"
+        ];
+        let code_len = body.len() as u8 + 1;
+        bytes.extend_from_slice(&[0x0A, code_len + 2, 0x01, code_len, 0x00]);
+        bytes.extend_from_slice(body);
+        bytes
+    }
+
+    #[test]
+    fn test_health_check() {
+        let engine = WasmEngine::new().unwrap();
+        let module = engine.parse_module(&module_with_health(&[0x41, 0x00, 0x0B])).unwrap();
+        let mut instance = engine.instantiate_with_sandbox(module, Sandbox::new(ResourceLimits::default())).unwrap();
+        assert_eq!(instance.health_check().unwrap(), HealthStatus::Healthy);
+
+        // A hung health export runs out of budget instead of spinning forever
+        let module = engine.parse_module(&module_with_health(&[0x03, 0x40, 0x0C, 0x00, 0x0B, 0x41, 0x00, 0x0B])).unwrap();
+        let mut instance = engine.instantiate_with_sandbox(module, Sandbox::new(ResourceLimits::default())).unwrap();
+        assert!(matches!(instance.health_check(), Err(WasmError::Runtime(_))));
+
+        let module = engine.parse_module(&module_with_globals(1)).unwrap();
+        let mut instance = engine.instantiate_with_sandbox(module, Sandbox::new(ResourceLimits::default())).unwrap();
+        match instance.health_check() {
+            Err(WasmError::Runtime(msg)) => assert!(msg.contains("no health export")),
+            other => panic!("expected missing export error, got {:?}", other),
+        }
+    }
+
+    fn sum_module() -> Vec<u8> {
+        vec![
+            0x00, 0x61, 0x73, 0x6d, // magic
+            0x01, 0x00, 0x00, 0x00, // version
+            0x01, 0x06, 0x01, 0x60, 0x01, 0x7F, 0x01, 0x7F, // type: (i32) -> i32
+            0x03, 0x02, 0x01, 0x00, // function 0 has type 0
+            0x07, 0x07, 0x01, 0x03, b's', b'u', b'm', 0x00, 0x00, // export "sum"
+            0x0A, 0x1B, 0x01, 0x19, 0x01, 0x01, 0x7F, // code: one i32 local
+            0x03, 0x40, // loop
+            0x20, 0x01, 0x20, 0x00, 0x6A, 0x21, 0x01, // acc += n
+            0x20, 0x00, 0x41, 0x01, 0x6B, 0x22, 0x00, // n -= 1
+            0x0D, 0x00, 0x0B, // br_if 0, end
+            0x20, 0x01, 0x0B, // local.get acc, end
+        ]
+    }
+
+    #[test]
+    fn test_invoke_with_budget() {
+        let engine = WasmEngine::new().unwrap();
+        let module = engine.parse_module(&sum_module()).unwrap();
+        let mut instance = engine.instantiate_with_sandbox(module, Sandbox::new(ResourceLimits::default())).unwrap();
+
+        let result = instance.invoke_with_budget("sum", &[Value::I32(100)], 50);
+        assert!(matches!(result, Err(WasmError::BudgetExhausted(50))));
+
+        let results = instance.invoke_with_budget("sum", &[Value::I32(100)], 10_000).unwrap();
+        assert_eq!(results, vec![Value::I32(5050)]);
+        assert!(instance.invoke_with_budget("missing", &[], 10).is_err());
+    }
+
+    #[test]
+    fn test_run_for_pauses_and_resumes() {
+        let engine = WasmEngine::new().unwrap();
+        let module = engine.parse_module(&sum_module()).unwrap();
+        let mut instance = engine.instantiate_with_sandbox(module, Sandbox::new(ResourceLimits::default())).unwrap();
+
+        // loop, local.get acc, local.get n, i32.add
+        match instance.run_for("sum", &[Value::I32(3)], 4).unwrap() {
+            RunState::Paused { info } => {
+                assert_eq!(info.stack, vec![Value::I32(3)]);
+                assert_eq!(info.instructions, 4);
+            }
+            other => panic!("expected a paused run, got {:?}", other),
+        }
+
+        let state = instance.run_for("sum", &[], 1_000).unwrap();
+        assert_eq!(state, RunState::Done { result: vec![Value::I32(6)] });
+    }
+
+    #[test]
+    fn test_state_persists_across_invocations() {
+        let engine = WasmEngine::new().unwrap();
+        let bytes = [
+            0x00, 0x61, 0x73, 0x6d, // magic
+            0x01, 0x00, 0x00, 0x00, // version
+            0x01, 0x05, 0x01, 0x60, 0x00, 0x01, 0x7F, // type: () -> i32
+            0x03, 0x02, 0x01, 0x00, // function 0 has type 0
+            0x06, 0x06, 0x01, 0x7F, 0x01, 0x41, 0x00, 0x0B, // mut i32 global = 0
+            0x07, 0x08, 0x01, 0x04, b'b', b'u', b'm', b'p', 0x00, 0x00, // export "bump"
+            0x0A, 0x0D, 0x01, 0x0B, 0x00, // code: no locals
+            0x23, 0x00, 0x41, 0x01, 0x6A, 0x24, 0x00, // global += 1
+            0x23, 0x00, 0x0B, // global.get, end
+        ];
+        let module = engine.parse_module(&bytes).unwrap();
+        let mut instance = engine.instantiate_with_sandbox(module, Sandbox::new(ResourceLimits::default())).unwrap();
+
+        assert_eq!(instance.invoke_with_budget("bump", &[], 100).unwrap(), vec![Value::I32(1)]);
+        assert_eq!(instance.invoke_with_budget("bump", &[], 100).unwrap(), vec![Value::I32(2)]);
+        assert_eq!(instance.sandbox().get_usage().instruction_count, 12);
+    }
+
+    #[cfg(feature = "hashing")]
+    #[test]
+    fn test_instantiate_if_allowed() {
+        use crate::registry::{self, RegistryMode};
+
+        let engine = WasmEngine::new().unwrap();
+        let approved = module_with_globals(1);
+        let banned = module_with_globals(2);
+        let unknown = module_with_globals(3);
+
+        let mut registry = ModuleRegistry::new(RegistryMode::Allowlist);
+        registry.allow(&registry::content_hash(&approved));
+        registry.deny(&registry::content_hash(&banned));
+        assert!(engine.instantiate_if_allowed(&approved, &registry, ResourceLimits::default()).is_ok());
+        assert!(engine.instantiate_if_allowed(&banned, &registry, ResourceLimits::default()).is_err());
+        match engine.instantiate_if_allowed(&unknown, &registry, ResourceLimits::default()) {
+            Err(WasmError::Runtime(msg)) => assert!(msg.contains("not allowlisted")),
+            other => panic!("expected allowlist rejection, got {:?}", other),
+        }
+
+        let mut registry = ModuleRegistry::new(RegistryMode::Denylist);
+        registry.deny(&registry::content_hash(&banned));
+        assert!(engine.instantiate_if_allowed(&unknown, &registry, ResourceLimits::default()).is_ok());
+        assert!(engine.instantiate_if_allowed(&banned, &registry, ResourceLimits::default()).is_err());
+    }
+
+    #[test]
+    fn test_instantiate_verified() {
+        let engine = WasmEngine::new().unwrap();
+        let bytes = module_with_globals(1);
+        let valid: Vec<u8> = bytes.iter().rev().take(8).copied().collect();
+
+        let instance = engine.instantiate_verified(&bytes, &valid, &ReversedTailVerifier, ResourceLimits::default());
+        assert!(instance.is_ok());
+
+        let result = engine.instantiate_verified(&bytes, &[0u8; 8], &ReversedTailVerifier, ResourceLimits::default());
+        match result {
+            Err(WasmError::Runtime(msg)) => assert_eq!(msg, "signature verification failed"),
+            other => panic!("expected verification failure, got {:?}", other),
+        }
+    }
+}
@@ -172,6 +172,7 @@ fn test_debug_context() {
         memory: None,
         exports: vec![],
         code: vec![],
+        ..Default::default()
     };
     
     let mut context = core::DebugContext::new(module);
@@ -192,6 +193,7 @@ fn test_variable_inspector() {
         memory: None,
         exports: vec![],
         code: vec![],
+        ..Default::default()
     };
     
     let context = core::DebugContext::new(module);
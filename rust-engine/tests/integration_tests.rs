@@ -1,6 +1,7 @@
 use wasm_engine::*;
 use wasm_engine::sandbox::capabilities::{Capability, SensorType, AlertLevel};
 use wasm_engine::sandbox::policy::SecurityPolicy;
+use wasm_engine::abi::functions::AbiValue;
 
 #[test]
 fn test_sandbox_resource_limits() {
@@ -98,7 +99,7 @@ fn test_abi_integration() {
     let mut sandbox = Sandbox::new(limits);
     
     // Grant necessary capabilities
-    sandbox.grant_capability(Capability::GetTime);
+    sandbox.grant_capability(Capability::WallClock);
     sandbox.grant_capability(Capability::Random);
     sandbox.grant_capability(Capability::ReadSensor(SensorType::Any));
     
@@ -107,15 +108,14 @@ fn test_abi_integration() {
     
     // Test time function
     let result = abi.call_host_function("wasm_get_time", &[], &mut memory);
-    assert!(result.is_ok());
-    assert!(result.unwrap() > 0);
+    assert!(matches!(result, Ok(AbiValue::I64(seconds)) if seconds > 0));
     
     // Test random function
     let result = abi.call_host_function("wasm_random", &[], &mut memory);
     assert!(result.is_ok());
     
     // Test sensor function
-    let result = abi.call_host_function("wasm_read_sensor", &[0], &mut memory);
+    let result = abi.call_host_function("wasm_read_sensor", &[AbiValue::I32(0)], &mut memory);
     assert!(result.is_ok());
 }
 
@@ -211,6 +211,15 @@ fn test_real_world_module() {
     assert!(!analysis.recommendations.is_empty());
 }
 
+#[test]
+fn test_module_size_from_binary() {
+    let wasm_bytes = create_realistic_wasm_binary();
+    let module = WasmParser::parse(&wasm_bytes).unwrap();
+    
+    let analysis = StaticAnalyzer::new().analyze(&module).unwrap();
+    assert_eq!(analysis.module_info.size, wasm_bytes.len());
+}
+
 // Helper functions to create test modules
 
 fn create_basic_module() -> WasmModule {
@@ -227,6 +236,7 @@ fn create_basic_module() -> WasmModule {
             locals: vec![],
             body: vec![0x41, 0x01, 0x0B], // i32.const 1, end
         }],
+        ..Default::default()
     }
 }
 
@@ -240,6 +250,7 @@ fn create_suspicious_module() -> WasmModule {
             locals: vec![],
             body: vec![0x03, 0x40, 0x0C, 0x00, 0x0B], // loop, br 0, end (infinite loop)
         }],
+        ..Default::default()
     }
 }
 
@@ -264,6 +275,7 @@ fn create_module_with_syscalls() -> WasmModule {
             locals: vec![],
             body: vec![0x41, 0x01, 0x0B],
         }],
+        ..Default::default()
     }
 }
 
@@ -277,6 +289,7 @@ fn create_memory_intensive_module() -> WasmModule {
             locals: vec![],
             body: vec![0x40, 0x00, 0x0B], // memory.grow, end
         }],
+        ..Default::default()
     }
 }
 
@@ -298,6 +311,7 @@ fn create_risky_module() -> WasmModule {
                 0x11, 0x00, // Indirect call
             ],
         }],
+        ..Default::default()
     }
 }
 
@@ -318,6 +332,7 @@ fn create_module_with_patterns() -> WasmModule {
                 0x24, 0x00, // global.set
             ],
         }],
+        ..Default::default()
     }
 }
 
@@ -331,6 +346,7 @@ fn create_infinite_loop_module() -> WasmModule {
             locals: vec![],
             body: vec![0x03, 0x40, 0x0C, 0x00, 0x0B], // loop, br 0, end
         }],
+        ..Default::default()
     }
 }
 
@@ -351,6 +367,7 @@ fn create_memory_bomb_module() -> WasmModule {
                 0x0B, // end
             ],
         }],
+        ..Default::default()
     }
 }
 